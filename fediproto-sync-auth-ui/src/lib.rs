@@ -5,13 +5,24 @@ pub mod error;
 /// Endpoints for the FediProtoSync authentication web server.
 pub mod web;
 
+/// Tests for the `fediproto-sync-auth-ui` crate.
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests;
+
+use std::collections::HashMap;
+
 use async_session::MemoryStore;
 use axum::{Router, extract::FromRef, routing::get};
 use diesel::r2d2::{ConnectionManager, Pool};
 use fediproto_sync_db::AnyConnection;
 use fediproto_sync_lib::{config::FediProtoSyncConfig, error::FediProtoSyncError};
 use oauth2::basic::BasicClient;
-use web::{mastodon_auth, root};
+use web::{
+    mastodon_auth,
+    mastodon_webhook::{self, MastodonWebhook, MastodonWebhookState},
+    root,
+};
 
 /// Represents the "app state" for the FediProtoSync web server.
 #[derive(FromRef, Clone)]
@@ -22,8 +33,11 @@ pub struct FediProtoSyncWebServerAppState {
     /// The database connection pool.
     pub db_pool: Pool<ConnectionManager<AnyConnection>>,
 
-    /// The Mastodon OAuth2 client.
-    pub mastodon_oauth_client: BasicClient,
+    /// The configs of the account pairs to authorize.
+    pub account_pairs: Vec<FediProtoSyncConfig>,
+
+    /// The Mastodon OAuth2 clients, by account pair ID.
+    pub mastodon_oauth_clients: HashMap<String, BasicClient>,
 
     /// The memory store for storing session data.
     pub memory_store: MemoryStore,
@@ -34,8 +48,14 @@ pub struct FediProtoSyncWebServer {
     /// The FediProtoSync configuration.
     pub config: FediProtoSyncConfig,
 
+    /// The configs of the account pairs to authorize.
+    pub account_pairs: Vec<FediProtoSyncConfig>,
+
     /// The database connection pool.
     pub db_pool: Pool<ConnectionManager<AnyConnection>>,
+
+    /// The Mastodon webhooks to receive, by account pair ID.
+    pub mastodon_webhooks: HashMap<String, MastodonWebhook>,
 }
 
 impl FediProtoSyncWebServer {
@@ -44,14 +64,25 @@ impl FediProtoSyncWebServer {
     /// ## Arguments
     ///
     /// * `config` - The FediProtoSync configuration.
+    /// * `account_pairs` - The configs of the account pairs to authorize.
     /// * `db_pool` - The database connection pool.
+    /// * `mastodon_webhooks` - The Mastodon webhooks to receive, by account
+    ///   pair ID.
     pub fn new(
         config: &FediProtoSyncConfig,
+        account_pairs: &[FediProtoSyncConfig],
         db_pool: Pool<ConnectionManager<AnyConnection>>,
+        mastodon_webhooks: HashMap<String, MastodonWebhook>,
     ) -> Result<Self, FediProtoSyncError> {
         let config = config.clone();
-
-        Ok(Self { config, db_pool })
+        let account_pairs = account_pairs.to_vec();
+
+        Ok(Self {
+            config,
+            account_pairs,
+            db_pool,
+            mastodon_webhooks,
+        })
     }
 
     /// Run the web server.
@@ -62,7 +93,7 @@ impl FediProtoSyncWebServer {
             .auth_server_address
             .clone()
             .unwrap_or_else(|| "localhost".to_string());
-        let bind_port = self.config.auth_server_port.unwrap_or_else(|| 3000);
+        let bind_port = self.config.auth_server_port.unwrap_or(3000);
 
         let full_bind_address = format!("{}:{}", bind_address, bind_port);
 
@@ -78,21 +109,31 @@ impl FediProtoSyncWebServer {
         // Get the Mastodon redirect URI.
         let mastodon_redirect_uri = self.config.mastodon_redirect_uri.clone();
 
+        // Create a Mastodon OAuth2 client for each account pair.
+        let mut mastodon_oauth_clients = HashMap::new();
+        for account_pair in &self.account_pairs {
+            mastodon_oauth_clients.insert(
+                account_pair.account_pair_id.clone(),
+                auth::mastodon::get_mastodon_oauth_client(account_pair, &mastodon_redirect_uri)?,
+            );
+        }
+
         // Create the app state.
         let app_state = FediProtoSyncWebServerAppState {
             config: self.config.clone(),
             db_pool: self.db_pool.clone(),
-            mastodon_oauth_client: auth::mastodon::get_mastodon_oauth_client(
-                &self.config,
-                &mastodon_redirect_uri,
-            )?,
+            account_pairs: self.account_pairs.clone(),
+            mastodon_oauth_clients,
             memory_store,
         };
 
         // Create the router and define the routes.
         let router = Router::new()
             .route("/", get(root::root_endpoint))
-            .route("/auth/mastodon/login", get(mastodon_auth::login_endpoint))
+            .route(
+                "/auth/mastodon/login/{account_pair_id}",
+                get(mastodon_auth::login_endpoint),
+            )
             .route(
                 "/auth/mastodon/authorized",
                 get(mastodon_auth::authorized_endpoint),
@@ -102,6 +143,9 @@ impl FediProtoSyncWebServer {
                 get(mastodon_auth::already_authorized_endpoint),
             )
             .with_state(app_state)
+            .merge(mastodon_webhook::router(MastodonWebhookState {
+                webhooks: self.mastodon_webhooks.clone(),
+            }))
             .into_make_service();
 
         tracing::info!(
@@ -110,6 +154,16 @@ impl FediProtoSyncWebServer {
             bind_port
         );
 
+        for account_pair_id in self.mastodon_webhooks.keys() {
+            tracing::info!(
+                "Receiving Mastodon webhooks for '{}' at 'http://{}:{}/webhooks/mastodon/{}'.",
+                account_pair_id,
+                bind_address,
+                bind_port,
+                account_pair_id
+            );
+        }
+
         // Serve the web server.
         axum::serve(listener, router)
             .await
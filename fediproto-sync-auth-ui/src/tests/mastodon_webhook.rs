@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use rstest::*;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tower::ServiceExt;
+
+use crate::web::mastodon_webhook::{
+    MastodonWebhook, MastodonWebhookState, SIGNATURE_HEADER, WebhookStatus, router,
+};
+
+/// The ID of the account pair the webhook is for.
+const ACCOUNT_PAIR_ID: &str = "main";
+
+/// The secret the webhook is signed with.
+const WEBHOOK_SECRET: &str = "webhook-secret";
+
+/// Create a webhook payload for a status.
+///
+/// ## Arguments
+///
+/// * `event` - The webhook event.
+/// * `status_id` - The ID of the status.
+fn webhook_payload(
+    event: &str,
+    status_id: &str,
+) -> String {
+    serde_json::json!({
+        "event": event,
+        "created_at": "2026-10-18T19:00:00.000Z",
+        "object": {
+            "id": status_id,
+            "content": "<p>Hello world!</p>",
+            "account": {
+                "id": "109000000000000001",
+                "username": "test"
+            }
+        }
+    })
+    .to_string()
+}
+
+/// Sign a webhook payload like Mastodon does.
+///
+/// ## Arguments
+///
+/// * `secret` - The secret to sign the payload with.
+/// * `payload` - The payload to sign.
+fn sign_payload(
+    secret: &str,
+    payload: &str,
+) -> String {
+    let signature =
+        fediproto_sync_lib::crypto::hmac_sha256_hex(secret.as_bytes(), payload.as_bytes()).unwrap();
+
+    format!("sha256={}", signature)
+}
+
+/// Create the state for the webhook endpoint, with a webhook for the account
+/// pair.
+///
+/// ## Note
+///
+/// Returns the state and the queue the statuses are sent to.
+fn webhook_state() -> (MastodonWebhookState, UnboundedReceiver<WebhookStatus>) {
+    let (webhook_queue, webhook_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    let webhook_state = MastodonWebhookState {
+        webhooks: HashMap::from([(
+            ACCOUNT_PAIR_ID.to_string(),
+            MastodonWebhook {
+                secret: WEBHOOK_SECRET.to_string(),
+                queue: webhook_queue,
+            },
+        )]),
+    };
+
+    (webhook_state, webhook_receiver)
+}
+
+/// Send a webhook through the router for the webhook endpoint.
+///
+/// ## Arguments
+///
+/// * `webhook_state` - The state for the webhook endpoint.
+/// * `account_pair_id` - The ID of the account pair the webhook is for.
+/// * `signature` - The value of the signature header, if any.
+/// * `payload` - The body of the request.
+async fn send_webhook(
+    webhook_state: MastodonWebhookState,
+    account_pair_id: &str,
+    signature: Option<String>,
+    payload: String,
+) -> StatusCode {
+    let mut request = Request::post(format!("/webhooks/mastodon/{}", account_pair_id))
+        .header("Content-Type", "application/json");
+    if let Some(signature) = signature {
+        request = request.header(SIGNATURE_HEADER, signature);
+    }
+
+    router(webhook_state)
+        .oneshot(request.body(Body::from(payload)).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Tests to ensure signed statuses are queued for syncing.
+#[rstest]
+#[case::created("status.created")]
+#[case::updated("status.updated")]
+#[tokio::test]
+async fn webhook_endpoint__queues_signed_status(#[case] event: &str) {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = webhook_payload(event, "113000000000000001");
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(sign_payload(WEBHOOK_SECRET, &payload)),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(
+        webhook_receiver.try_recv().unwrap(),
+        WebhookStatus {
+            event: event.to_string(),
+            status_id: "113000000000000001".to_string(),
+            account_id: "109000000000000001".to_string(),
+        }
+    );
+}
+
+/// Tests to ensure webhooks without a valid signature are rejected with a
+/// `401`.
+#[rstest]
+#[case::not_hex("sha256=other")]
+#[case::missing_prefix("not-a-signature")]
+#[tokio::test]
+async fn webhook_endpoint__rejects_invalid_signature(#[case] signature: &str) {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = webhook_payload("status.created", "113000000000000001");
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(signature.to_string()),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure webhooks without the signature header are rejected with a
+/// `401`.
+#[rstest]
+#[tokio::test]
+async fn webhook_endpoint__rejects_missing_signature_header() {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = webhook_payload("status.created", "113000000000000001");
+
+    let status = send_webhook(webhook_state, ACCOUNT_PAIR_ID, None, payload).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure webhooks signed with another secret are rejected with a
+/// `401`.
+#[rstest]
+#[tokio::test]
+async fn webhook_endpoint__rejects_other_secret() {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = webhook_payload("status.created", "113000000000000001");
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(sign_payload("other-secret", &payload)),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure webhooks for account pairs without a webhook are rejected
+/// with a `404`.
+#[rstest]
+#[tokio::test]
+async fn webhook_endpoint__rejects_unknown_account_pair() {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = webhook_payload("status.created", "113000000000000001");
+
+    let status = send_webhook(
+        webhook_state,
+        "other",
+        Some(sign_payload(WEBHOOK_SECRET, &payload)),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure signed webhooks with a body that can't be parsed are
+/// rejected with a `400`.
+#[rstest]
+#[case::not_json("not json")]
+#[case::missing_event(r#"{"object":{"id":"113000000000000001"}}"#)]
+#[case::invalid_status(r#"{"event":"status.created","object":{"id":"113000000000000001"}}"#)]
+#[tokio::test]
+async fn webhook_endpoint__rejects_invalid_body(#[case] payload: &str) {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(sign_payload(WEBHOOK_SECRET, payload)),
+        payload.to_string(),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure events that aren't for statuses are accepted, but not
+/// queued.
+#[rstest]
+#[tokio::test]
+async fn webhook_endpoint__ignores_other_events() {
+    let (webhook_state, mut webhook_receiver) = webhook_state();
+    let payload = serde_json::json!({
+        "event": "account.created",
+        "object": {
+            "id": "109000000000000002"
+        }
+    })
+    .to_string();
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(sign_payload(WEBHOOK_SECRET, &payload)),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(webhook_receiver.try_recv().is_err());
+}
+
+/// Tests to ensure statuses are rejected with a `503` when the sync for the
+/// account pair isn't running.
+#[rstest]
+#[tokio::test]
+async fn webhook_endpoint__sync_not_running() {
+    let (webhook_state, webhook_receiver) = webhook_state();
+    drop(webhook_receiver);
+    let payload = webhook_payload("status.created", "113000000000000001");
+
+    let status = send_webhook(
+        webhook_state,
+        ACCOUNT_PAIR_ID,
+        Some(sign_payload(WEBHOOK_SECRET, &payload)),
+        payload,
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,2 @@
+/// Tests for `fediproto_sync_auth_ui::web::mastodon_webhook`.
+mod mastodon_webhook;
@@ -1,7 +1,7 @@
 use anyhow::Context;
 use async_session::{MemoryStore, Session, SessionStore};
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, header::SET_COOKIE},
     response::{Html, IntoResponse, Redirect},
 };
@@ -20,14 +20,17 @@ use crate::{
 static AUTH_SESSION: &str = "MASTODON_AUTH_SESSION";
 static CSRF_TOKEN: &str = "csrf_token";
 static PKCE_VERIFIER_SECRET: &str = "pkce_verifier_secret";
+static ACCOUNT_PAIR_ID: &str = "account_pair_id";
 
 /// The OAuth2 "login" endpoint for Mastodon.
 ///
 /// ## Arguments
 ///
+/// * `account_pair_id` - The ID of the account pair to authorize.
 /// * `app_state` - The application state.
 pub async fn login_endpoint(
-    State(app_state): State<FediProtoSyncWebServerAppState>
+    Path(account_pair_id): Path<String>,
+    State(app_state): State<FediProtoSyncWebServerAppState>,
 ) -> Result<impl IntoResponse, FediProtoSyncWebError> {
     // Get a database connection from the pool and check if a token already exists.
     let db_connection = &mut app_state
@@ -35,7 +38,7 @@ pub async fn login_endpoint(
         .get()
         .context("Failed to get the database connection.")?;
 
-    let token_exists = check_for_existing_token(db_connection, "mastodon")?;
+    let token_exists = check_for_existing_token(db_connection, &account_pair_id, "mastodon")?;
 
     if token_exists {
         let headers = HeaderMap::new();
@@ -43,16 +46,30 @@ pub async fn login_endpoint(
         return Ok((headers, Redirect::to("/auth/mastodon/already_authorized")));
     }
 
-    // Get the Mastodon OAuth client and the memory store.
-    let mastodon_client = app_state.mastodon_oauth_client.clone();
+    // Get the Mastodon OAuth client for the account pair and the memory store.
+    let mastodon_client = app_state
+        .mastodon_oauth_clients
+        .get(&account_pair_id)
+        .context("Unknown account pair.")?
+        .clone();
+    let source_platform = app_state
+        .account_pairs
+        .iter()
+        .find(|account_pair| account_pair.account_pair_id == account_pair_id)
+        .context("Unknown account pair.")?
+        .source_platform;
     let memory_store = app_state.memory_store.clone();
 
     // Generate the authorization URL, CSRF token, and PKCE code challenge.
     let (pkce_code_challenge, pkce_code_verifier) = oauth2::PkceCodeChallenge::new_random_sha256();
     let (auth_url, csrf_token) = mastodon_client
         .authorize_url(CsrfToken::new_random)
-        .add_scope(oauth2::Scope::new("read:statuses".to_string()))
-        .add_scope(oauth2::Scope::new("profile".to_string()))
+        .add_scopes(
+            source_platform
+                .oauth_scopes()
+                .iter()
+                .map(|scope| oauth2::Scope::new(scope.to_string())),
+        )
         .set_pkce_challenge(pkce_code_challenge)
         .url();
 
@@ -63,9 +80,13 @@ pub async fn login_endpoint(
         .context("Failed to insert CSRF token into the session.")?;
 
     session
-        .insert(PKCE_VERIFIER_SECRET, &pkce_code_verifier.secret())
+        .insert(PKCE_VERIFIER_SECRET, pkce_code_verifier.secret())
         .context("Failed to insert PKCE verifier secret into the session.")?;
 
+    session
+        .insert(ACCOUNT_PAIR_ID, &account_pair_id)
+        .context("Failed to insert the account pair ID into the session.")?;
+
     // Store the session in the memory store.
     let cookie = memory_store
         .store_session(session)
@@ -93,11 +114,15 @@ pub async fn login_endpoint(
 /// * `auth_request` - The authorization request.
 /// * `cookies` - The cookies.
 /// * `memory_store` - The memory store.
+///
+/// ## Note
+///
+/// Returns the PKCE verifier and the ID of the account pair being authorized.
 async fn validate_csrf(
     auth_request: &AuthRequest,
     cookies: &headers::Cookie,
     memory_store: &MemoryStore,
-) -> Result<PkceCodeVerifier, FediProtoSyncWebError> {
+) -> Result<(PkceCodeVerifier, String), FediProtoSyncWebError> {
     tracing::info!("Mastodon auth: Validating the CSRF token...");
 
     // Get the session cookie.
@@ -127,6 +152,11 @@ async fn validate_csrf(
         .context("Failed to get the PKCE verifier secret from the session.")?
         .to_owned();
 
+    let account_pair_id = session
+        .get::<String>(ACCOUNT_PAIR_ID)
+        .context("Failed to get the account pair ID from the session.")?
+        .to_owned();
+
     let pkce_verifier = PkceCodeVerifier::new(pkce_verifier_secret);
 
     // Destroy the session.
@@ -143,7 +173,7 @@ async fn validate_csrf(
 
     tracing::info!("Mastodon auth: CSRF token validated.");
 
-    Ok(pkce_verifier)
+    Ok((pkce_verifier, account_pair_id))
 }
 
 /// The OAuth2 "authorized" endpoint for Mastodon.
@@ -158,10 +188,15 @@ pub async fn authorized_endpoint(
     State(app_state): State<FediProtoSyncWebServerAppState>,
     TypedHeader(cookies): TypedHeader<headers::Cookie>,
 ) -> Result<impl IntoResponse, FediProtoSyncWebError> {
-    // Validate the CSRF token and get the PKCE verifier.
-    let pkce_verifier = validate_csrf(&query, &cookies, &app_state.memory_store).await?;
-
-    let mastodon_client = app_state.mastodon_oauth_client.clone();
+    // Validate the CSRF token and get the PKCE verifier and account pair ID.
+    let (pkce_verifier, account_pair_id) =
+        validate_csrf(&query, &cookies, &app_state.memory_store).await?;
+
+    let mastodon_client = app_state
+        .mastodon_oauth_clients
+        .get(&account_pair_id)
+        .context("Unknown account pair.")?
+        .clone();
     let config = app_state.config.clone();
 
     // Exchange the code for the token.
@@ -200,6 +235,7 @@ pub async fn authorized_endpoint(
     // Create a new token and insert it into the database.
     let new_token = NewCachedServiceToken::new(
         encryption_public_key,
+        &account_pair_id,
         "mastodon",
         &access_token,
         None,
@@ -223,7 +259,10 @@ pub async fn authorized_endpoint(
         }
     }
 
-    tracing::info!("Mastodon auth: Authorized!");
+    tracing::info!(
+        "Mastodon auth: Authorized account pair '{}'!",
+        account_pair_id
+    );
 
     Ok("Authorized!")
 }
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The header Mastodon puts the signature of a webhook in.
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature";
+
+/// The prefix of the signature in the signature header.
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// The webhook events for statuses that are queued for syncing.
+const STATUS_EVENTS: [&str; 2] = ["status.created", "status.updated"];
+
+/// A status sent by a Mastodon webhook, queued for its account pair to sync.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookStatus {
+    /// The webhook event, like `status.created`.
+    pub event: String,
+
+    /// The ID of the status.
+    pub status_id: String,
+
+    /// The ID of the account that posted the status.
+    pub account_id: String,
+}
+
+/// The webhook of an account pair.
+#[derive(Debug, Clone)]
+pub struct MastodonWebhook {
+    /// The secret the webhook is signed with.
+    pub secret: String,
+
+    /// The queue to send the statuses to.
+    pub queue: UnboundedSender<WebhookStatus>,
+}
+
+/// Represents the state for the Mastodon webhook endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct MastodonWebhookState {
+    /// The webhooks, by account pair ID.
+    pub webhooks: HashMap<String, MastodonWebhook>,
+}
+
+/// The payload sent by a Mastodon webhook.
+#[derive(Debug, Deserialize)]
+struct WebhookPayload {
+    /// The webhook event.
+    event: String,
+
+    /// The object the event is for.
+    object: serde_json::Value,
+}
+
+/// The status in the payload of a Mastodon webhook.
+#[derive(Debug, Deserialize)]
+struct WebhookPayloadStatus {
+    /// The ID of the status.
+    id: String,
+
+    /// The account that posted the status.
+    account: WebhookPayloadAccount,
+}
+
+/// The account in the payload of a Mastodon webhook.
+#[derive(Debug, Deserialize)]
+struct WebhookPayloadAccount {
+    /// The ID of the account.
+    id: String,
+}
+
+/// Create the router for the Mastodon webhook endpoint.
+///
+/// ## Arguments
+///
+/// * `webhook_state` - The state for the webhook endpoint.
+pub fn router(webhook_state: MastodonWebhookState) -> Router {
+    Router::new()
+        .route(
+            "/webhooks/mastodon/{account_pair_id}",
+            post(webhook_endpoint),
+        )
+        .with_state(webhook_state)
+}
+
+/// The endpoint Mastodon sends webhooks to.
+///
+/// ## Arguments
+///
+/// * `account_pair_id` - The ID of the account pair the webhook is for.
+/// * `webhook_state` - The state for the webhook endpoint.
+/// * `headers` - The headers of the request.
+/// * `body` - The body of the request.
+///
+/// ## Note
+///
+/// The body is only parsed once its signature is verified. Events that
+/// aren't for statuses are accepted, but not queued.
+pub async fn webhook_endpoint(
+    Path(account_pair_id): Path<String>,
+    State(webhook_state): State<MastodonWebhookState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let webhook = match webhook_state.webhooks.get(&account_pair_id) {
+        Some(webhook) => webhook,
+        None => return StatusCode::NOT_FOUND,
+    };
+
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|signature| signature.to_str().ok())
+        .and_then(|signature| signature.strip_prefix(SIGNATURE_PREFIX));
+
+    let is_signature_valid = signature
+        .map(|signature| {
+            fediproto_sync_lib::crypto::verify_hmac_sha256_hex(
+                webhook.secret.as_bytes(),
+                &body,
+                signature,
+            )
+        })
+        .unwrap_or(false);
+
+    if !is_signature_valid {
+        tracing::warn!(
+            "Rejected a webhook for '{}' with an invalid signature.",
+            account_pair_id
+        );
+
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload = match serde_json::from_slice::<WebhookPayload>(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to parse a webhook for '{}': {:#?}",
+                account_pair_id,
+                e
+            );
+
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    if !STATUS_EVENTS.contains(&payload.event.as_str()) {
+        tracing::debug!(
+            "Ignoring '{}' webhook for '{}'.",
+            payload.event,
+            account_pair_id
+        );
+
+        return StatusCode::ACCEPTED;
+    }
+
+    let payload_status = match serde_json::from_value::<WebhookPayloadStatus>(payload.object) {
+        Ok(payload_status) => payload_status,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to parse the status in a webhook for '{}': {:#?}",
+                account_pair_id,
+                e
+            );
+
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    let webhook_status = WebhookStatus {
+        event: payload.event,
+        status_id: payload_status.id,
+        account_id: payload_status.account.id,
+    };
+
+    tracing::debug!(
+        "Queueing status '{}' from a '{}' webhook for '{}'.",
+        webhook_status.status_id,
+        webhook_status.event,
+        account_pair_id
+    );
+
+    match webhook.queue.send(webhook_status) {
+        Ok(_) => StatusCode::ACCEPTED,
+        Err(_) => {
+            tracing::error!(
+                "The sync for '{}' isn't running, dropping the webhook.",
+                account_pair_id
+            );
+
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
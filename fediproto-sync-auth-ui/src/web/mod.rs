@@ -1,6 +1,9 @@
 /// Mastodon authentication endpoints.
 pub mod mastodon_auth;
 
+/// Mastodon webhook endpoints.
+pub mod mastodon_webhook;
+
 /// Root endpoints.
 pub mod root;
 
@@ -20,29 +23,33 @@ pub struct AuthRequest {
     pub state: String,
 }
 
-/// Check if a token already exists for the given service name.
+/// Check if a token already exists for the given account pair and service
+/// name.
 ///
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection.
+/// * `account_pair_id` - The ID of the account pair.
 /// * `service_name` - The name of the service.
 pub fn check_for_existing_token(
     db_connection: &mut PooledConnection<ConnectionManager<AnyConnection>>,
+    account_pair_id: &str,
     service_name: &str,
 ) -> Result<bool, FediProtoSyncWebError> {
     let result = fediproto_sync_db::operations::get_cached_service_token_by_service_name(
         db_connection,
+        account_pair_id,
         service_name,
     );
 
     match result {
         Ok(token) => {
             if token.is_some() {
-                return Ok(true);
+                Ok(true)
             } else {
-                return Ok(false);
+                Ok(false)
             }
         }
-        Err(e) => return Err(anyhow::anyhow!("Failed to get the token: {:?}", e).into()),
+        Err(e) => Err(anyhow::anyhow!("Failed to get the token: {:?}", e).into()),
     }
 }
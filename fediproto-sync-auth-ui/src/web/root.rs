@@ -15,17 +15,30 @@ pub async fn root_endpoint(
         .get()
         .context("Failed to get the database connection.")?;
 
-    let mastodon_token_exists = check_for_existing_token(db_connection, "mastodon")?;
-
     let mut html_output =
-        "<html>\n<head>\n<title>FediProtoSync</title>\n</head>\n<body>\n".to_string();
-
-    match mastodon_token_exists {
-        true => html_output.push_str(
-            "<h1>Mastodon</h1>\n<p><font style=\"font-style: bold;\">Configured</font></p>\n",
-        ),
-        false => html_output
-            .push_str("<h1>Mastodon</h1>\n<p><a href=\"/auth/mastodon/login\">Configure</a></p>\n"),
+        "<html>\n<head>\n<title>FediProtoSync</title>\n</head>\n<body>\n<h1>Mastodon</h1>\n"
+            .to_string();
+
+    for account_pair in &app_state.account_pairs {
+        let account_pair_id = &account_pair.account_pair_id;
+
+        let mastodon_token_exists =
+            check_for_existing_token(db_connection, account_pair_id, "mastodon")?;
+
+        html_output.push_str(&format!(
+            "<h2>{} ({})</h2>\n",
+            account_pair_id, account_pair.bluesky_handle
+        ));
+
+        match mastodon_token_exists {
+            true => {
+                html_output.push_str("<p><font style=\"font-style: bold;\">Configured</font></p>\n")
+            }
+            false => html_output.push_str(&format!(
+                "<p><a href=\"/auth/mastodon/login/{}\">Configure</a></p>\n",
+                account_pair_id
+            )),
+        }
     }
 
     html_output.push_str("</body>\n</html>");
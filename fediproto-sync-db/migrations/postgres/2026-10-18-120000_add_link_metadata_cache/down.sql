@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+
+
+DROP TABLE IF EXISTS "link_metadata_cache";
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE "link_metadata_cache"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"url" VARCHAR NOT NULL,
+	"title" VARCHAR NOT NULL,
+	"description" VARCHAR NOT NULL,
+	"thumbnail_url" VARCHAR,
+	"thumbnail_hash" VARCHAR,
+	"thumbnail_blob" VARCHAR,
+	"thumbnail_blob_did" VARCHAR NOT NULL,
+	"cached_at" TIMESTAMP NOT NULL,
+	"expires_at" TIMESTAMP NOT NULL,
+	UNIQUE("url", "thumbnail_blob_did")
+);
+
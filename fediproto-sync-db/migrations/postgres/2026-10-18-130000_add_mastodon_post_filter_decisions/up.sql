@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE "mastodon_post_filter_decisions"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"mastodon_post_id" VARCHAR NOT NULL UNIQUE,
+	"action" VARCHAR NOT NULL,
+	"rule_name" VARCHAR,
+	"decided_at" TIMESTAMP NOT NULL
+);
+
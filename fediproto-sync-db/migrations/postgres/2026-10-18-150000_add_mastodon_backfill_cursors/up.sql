@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE "mastodon_backfill_cursors"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"account_id" VARCHAR NOT NULL UNIQUE,
+	"range_start" TIMESTAMP NOT NULL,
+	"range_end" TIMESTAMP NOT NULL,
+	"last_post_id" VARCHAR NOT NULL,
+	"synced_count" BIGINT NOT NULL,
+	"updated_at" TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE "mastodon_profile_sync_states"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"account_id" VARCHAR NOT NULL UNIQUE,
+	"text_hash" VARCHAR NOT NULL,
+	"avatar_hash" VARCHAR NOT NULL,
+	"banner_hash" VARCHAR NOT NULL,
+	"updated_at" TIMESTAMP NOT NULL
+);
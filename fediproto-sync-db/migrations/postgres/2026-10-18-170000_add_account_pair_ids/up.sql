@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE "cached_service_tokens" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+ALTER TABLE "mastodon_posts" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+ALTER TABLE "mastodon_post_retry_queue" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+ALTER TABLE "mastodon_post_filter_decisions" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+ALTER TABLE "mastodon_pending_posts" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
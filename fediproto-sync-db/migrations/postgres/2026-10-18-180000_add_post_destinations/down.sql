@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+
+ALTER TABLE "mastodon_posts" DROP COLUMN "destination";
+ALTER TABLE "synced_posts_bluesky_data" DROP COLUMN "destination";
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+
+DELETE FROM "mastodon_post_retry_queue" WHERE "id" !~ '^[0-9]+$';
+ALTER TABLE "mastodon_post_retry_queue" ALTER COLUMN "id" TYPE BIGINT USING "id"::BIGINT;
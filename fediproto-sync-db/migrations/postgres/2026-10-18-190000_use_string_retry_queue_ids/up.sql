@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE "mastodon_post_retry_queue" ALTER COLUMN "id" TYPE VARCHAR USING "id"::VARCHAR;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE "sync_states"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"account_pair_id" VARCHAR NOT NULL UNIQUE,
+	"account_id" VARCHAR NOT NULL,
+	"last_seen_post_id" VARCHAR,
+	"last_run_at" TIMESTAMP NOT NULL,
+	"last_error" VARCHAR
+);
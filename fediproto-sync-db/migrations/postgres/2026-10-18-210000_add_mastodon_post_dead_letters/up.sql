@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE "mastodon_post_retry_queue" ADD COLUMN "next_attempt_at" TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+
+CREATE TABLE "mastodon_post_dead_letters"(
+	"id" VARCHAR NOT NULL PRIMARY KEY,
+	"failure_reason" VARCHAR NOT NULL,
+	"rendered_payload" VARCHAR,
+	"attempt_count" INTEGER NOT NULL,
+	"dead_lettered_at" TIMESTAMP NOT NULL,
+	"account_pair_id" VARCHAR NOT NULL
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+
+
+ALTER TABLE "mastodon_post_dead_letters" DROP COLUMN "failure_class";
+
+ALTER TABLE "mastodon_post_retry_queue" DROP COLUMN "failure_class";
@@ -0,0 +1,55 @@
+-- This file should undo anything in `up.sql`
+
+
+
+DELETE FROM "mastodon_profile_sync_states" AS "older"
+USING "mastodon_profile_sync_states" AS "newer"
+WHERE "older"."account_id" = "newer"."account_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_profile_sync_states" DROP CONSTRAINT "mastodon_profile_sync_states_account_pair_id_key";
+ALTER TABLE "mastodon_profile_sync_states" ADD CONSTRAINT "mastodon_profile_sync_states_account_id_key" UNIQUE ("account_id");
+ALTER TABLE "mastodon_profile_sync_states" DROP COLUMN "account_pair_id";
+
+DELETE FROM "mastodon_backfill_cursors" AS "older"
+USING "mastodon_backfill_cursors" AS "newer"
+WHERE "older"."account_id" = "newer"."account_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_backfill_cursors" DROP CONSTRAINT "mastodon_backfill_cursors_account_pair_id_key";
+ALTER TABLE "mastodon_backfill_cursors" ADD CONSTRAINT "mastodon_backfill_cursors_account_id_key" UNIQUE ("account_id");
+ALTER TABLE "mastodon_backfill_cursors" DROP COLUMN "account_pair_id";
+
+ALTER TABLE "synced_posts_bluesky_data" DROP COLUMN "account_pair_id";
+
+DELETE FROM "mastodon_post_dead_letters" AS "older"
+USING "mastodon_post_dead_letters" AS "newer"
+WHERE "older"."id" = "newer"."id"
+	AND "older"."dead_lettered_at" < "newer"."dead_lettered_at";
+
+ALTER TABLE "mastodon_post_dead_letters" DROP CONSTRAINT "mastodon_post_dead_letters_pkey";
+ALTER TABLE "mastodon_post_dead_letters" ADD PRIMARY KEY ("id");
+
+DELETE FROM "mastodon_post_retry_queue" AS "older"
+USING "mastodon_post_retry_queue" AS "newer"
+WHERE "older"."id" = "newer"."id"
+	AND "older"."last_retried_at" < "newer"."last_retried_at";
+
+ALTER TABLE "mastodon_post_retry_queue" DROP CONSTRAINT "mastodon_post_retry_queue_pkey";
+ALTER TABLE "mastodon_post_retry_queue" ADD PRIMARY KEY ("id");
+
+DELETE FROM "mastodon_pending_posts" AS "older"
+USING "mastodon_pending_posts" AS "newer"
+WHERE "older"."mastodon_post_id" = "newer"."mastodon_post_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_pending_posts" DROP CONSTRAINT "mastodon_pending_posts_account_pair_post_key";
+ALTER TABLE "mastodon_pending_posts" ADD CONSTRAINT "mastodon_pending_posts_mastodon_post_id_key" UNIQUE ("mastodon_post_id");
+
+DELETE FROM "mastodon_post_filter_decisions" AS "older"
+USING "mastodon_post_filter_decisions" AS "newer"
+WHERE "older"."mastodon_post_id" = "newer"."mastodon_post_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_post_filter_decisions" DROP CONSTRAINT "mastodon_post_filter_decisions_account_pair_post_key";
+ALTER TABLE "mastodon_post_filter_decisions" ADD CONSTRAINT "mastodon_post_filter_decisions_mastodon_post_id_key" UNIQUE ("mastodon_post_id");
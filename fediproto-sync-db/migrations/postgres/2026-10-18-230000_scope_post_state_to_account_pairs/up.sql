@@ -0,0 +1,55 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE "mastodon_post_filter_decisions" DROP CONSTRAINT "mastodon_post_filter_decisions_mastodon_post_id_key";
+ALTER TABLE "mastodon_post_filter_decisions" ADD CONSTRAINT "mastodon_post_filter_decisions_account_pair_post_key" UNIQUE ("account_pair_id", "mastodon_post_id");
+
+ALTER TABLE "mastodon_pending_posts" DROP CONSTRAINT "mastodon_pending_posts_mastodon_post_id_key";
+ALTER TABLE "mastodon_pending_posts" ADD CONSTRAINT "mastodon_pending_posts_account_pair_post_key" UNIQUE ("account_pair_id", "mastodon_post_id");
+
+ALTER TABLE "mastodon_post_retry_queue" DROP CONSTRAINT "mastodon_post_retry_queue_pkey";
+ALTER TABLE "mastodon_post_retry_queue" ADD PRIMARY KEY ("account_pair_id", "id");
+
+ALTER TABLE "mastodon_post_dead_letters" DROP CONSTRAINT "mastodon_post_dead_letters_pkey";
+ALTER TABLE "mastodon_post_dead_letters" ADD PRIMARY KEY ("account_pair_id", "id");
+
+ALTER TABLE "synced_posts_bluesky_data" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+
+UPDATE "synced_posts_bluesky_data"
+SET "account_pair_id" = "mastodon_posts"."account_pair_id"
+FROM "mastodon_posts"
+WHERE "mastodon_posts"."post_id" = "synced_posts_bluesky_data"."mastodon_post_id"
+	AND "mastodon_posts"."destination" = "synced_posts_bluesky_data"."destination";
+
+ALTER TABLE "mastodon_backfill_cursors" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+
+UPDATE "mastodon_backfill_cursors"
+SET "account_pair_id" = "sync_states"."account_pair_id"
+FROM "sync_states"
+WHERE "sync_states"."account_id" = "mastodon_backfill_cursors"."account_id";
+
+DELETE FROM "mastodon_backfill_cursors" AS "older"
+USING "mastodon_backfill_cursors" AS "newer"
+WHERE "older"."account_pair_id" = "newer"."account_pair_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_backfill_cursors" ALTER COLUMN "account_pair_id" DROP DEFAULT;
+ALTER TABLE "mastodon_backfill_cursors" DROP CONSTRAINT "mastodon_backfill_cursors_account_id_key";
+ALTER TABLE "mastodon_backfill_cursors" ADD CONSTRAINT "mastodon_backfill_cursors_account_pair_id_key" UNIQUE ("account_pair_id");
+
+ALTER TABLE "mastodon_profile_sync_states" ADD COLUMN "account_pair_id" VARCHAR NOT NULL DEFAULT 'default';
+
+UPDATE "mastodon_profile_sync_states"
+SET "account_pair_id" = "sync_states"."account_pair_id"
+FROM "sync_states"
+WHERE "sync_states"."account_id" = "mastodon_profile_sync_states"."account_id";
+
+DELETE FROM "mastodon_profile_sync_states" AS "older"
+USING "mastodon_profile_sync_states" AS "newer"
+WHERE "older"."account_pair_id" = "newer"."account_pair_id"
+	AND "older"."id" < "newer"."id";
+
+ALTER TABLE "mastodon_profile_sync_states" ALTER COLUMN "account_pair_id" DROP DEFAULT;
+ALTER TABLE "mastodon_profile_sync_states" DROP CONSTRAINT "mastodon_profile_sync_states_account_id_key";
+ALTER TABLE "mastodon_profile_sync_states" ADD CONSTRAINT "mastodon_profile_sync_states_account_pair_id_key" UNIQUE ("account_pair_id");
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `link_metadata_cache`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`url` TEXT NOT NULL,
+	`title` TEXT NOT NULL,
+	`description` TEXT NOT NULL,
+	`thumbnail_url` TEXT,
+	`thumbnail_hash` TEXT,
+	`thumbnail_blob` TEXT,
+	`thumbnail_blob_did` TEXT NOT NULL,
+	`cached_at` TIMESTAMP NOT NULL,
+	`expires_at` TIMESTAMP NOT NULL,
+	UNIQUE(`url`, `thumbnail_blob_did`)
+);
+
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `mastodon_post_filter_decisions`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`mastodon_post_id` TEXT NOT NULL UNIQUE,
+	`action` TEXT NOT NULL,
+	`rule_name` TEXT,
+	`decided_at` TIMESTAMP NOT NULL
+);
+
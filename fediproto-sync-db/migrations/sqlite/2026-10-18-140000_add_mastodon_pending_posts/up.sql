@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `mastodon_pending_posts`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`mastodon_post_id` TEXT NOT NULL UNIQUE,
+	`post_created_at` TIMESTAMP NOT NULL,
+	`publish_at` TIMESTAMP NOT NULL,
+	`queued_at` TIMESTAMP NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `mastodon_backfill_cursors`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`account_id` TEXT NOT NULL UNIQUE,
+	`range_start` TIMESTAMP NOT NULL,
+	`range_end` TIMESTAMP NOT NULL,
+	`last_post_id` TEXT NOT NULL,
+	`synced_count` BIGINT NOT NULL,
+	`updated_at` TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `mastodon_profile_sync_states`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`account_id` TEXT NOT NULL UNIQUE,
+	`text_hash` TEXT NOT NULL,
+	`avatar_hash` TEXT NOT NULL,
+	`banner_hash` TEXT NOT NULL,
+	`updated_at` TIMESTAMP NOT NULL
+);
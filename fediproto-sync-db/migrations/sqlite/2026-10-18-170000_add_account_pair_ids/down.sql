@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+
+
+ALTER TABLE `cached_service_tokens` DROP COLUMN `account_pair_id`;
+ALTER TABLE `mastodon_posts` DROP COLUMN `account_pair_id`;
+ALTER TABLE `mastodon_post_retry_queue` DROP COLUMN `account_pair_id`;
+ALTER TABLE `mastodon_post_filter_decisions` DROP COLUMN `account_pair_id`;
+ALTER TABLE `mastodon_pending_posts` DROP COLUMN `account_pair_id`;
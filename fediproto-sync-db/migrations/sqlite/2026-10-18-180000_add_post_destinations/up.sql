@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE `mastodon_posts` ADD COLUMN `destination` TEXT NOT NULL DEFAULT 'bluesky';
+ALTER TABLE `synced_posts_bluesky_data` ADD COLUMN `destination` TEXT NOT NULL DEFAULT 'bluesky';
@@ -0,0 +1,19 @@
+-- This file should undo anything in `up.sql`
+
+
+
+CREATE TABLE `mastodon_post_retry_queue_old`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`failure_reason` TEXT NOT NULL,
+	`last_retried_at` TIMESTAMP NOT NULL,
+	`retry_count` INTEGER NOT NULL,
+	`account_pair_id` TEXT NOT NULL DEFAULT 'default'
+);
+
+INSERT INTO `mastodon_post_retry_queue_old`
+SELECT CAST(`id` AS BIGINT), `failure_reason`, `last_retried_at`, `retry_count`, `account_pair_id`
+FROM `mastodon_post_retry_queue`
+WHERE `id` NOT GLOB '*[^0-9]*' AND `id` != '';
+
+DROP TABLE `mastodon_post_retry_queue`;
+ALTER TABLE `mastodon_post_retry_queue_old` RENAME TO `mastodon_post_retry_queue`;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `mastodon_post_retry_queue_new`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`failure_reason` TEXT NOT NULL,
+	`last_retried_at` TIMESTAMP NOT NULL,
+	`retry_count` INTEGER NOT NULL,
+	`account_pair_id` TEXT NOT NULL DEFAULT 'default'
+);
+
+INSERT INTO `mastodon_post_retry_queue_new`
+SELECT CAST(`id` AS TEXT), `failure_reason`, `last_retried_at`, `retry_count`, `account_pair_id`
+FROM `mastodon_post_retry_queue`;
+
+DROP TABLE `mastodon_post_retry_queue`;
+ALTER TABLE `mastodon_post_retry_queue_new` RENAME TO `mastodon_post_retry_queue`;
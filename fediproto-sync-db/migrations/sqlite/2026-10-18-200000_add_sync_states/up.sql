@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+
+
+CREATE TABLE `sync_states`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`account_pair_id` TEXT NOT NULL UNIQUE,
+	`account_id` TEXT NOT NULL,
+	`last_seen_post_id` TEXT,
+	`last_run_at` TIMESTAMP NOT NULL,
+	`last_error` TEXT
+);
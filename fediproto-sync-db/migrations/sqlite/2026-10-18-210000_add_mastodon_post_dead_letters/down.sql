@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+
+
+DROP TABLE IF EXISTS `mastodon_post_dead_letters`;
+
+ALTER TABLE `mastodon_post_retry_queue` DROP COLUMN `next_attempt_at`;
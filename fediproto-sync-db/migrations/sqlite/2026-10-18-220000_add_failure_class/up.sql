@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+
+
+ALTER TABLE `mastodon_post_retry_queue` ADD COLUMN `failure_class` TEXT NOT NULL DEFAULT 'transient';
+
+ALTER TABLE `mastodon_post_dead_letters` ADD COLUMN `failure_class` TEXT NOT NULL DEFAULT 'transient';
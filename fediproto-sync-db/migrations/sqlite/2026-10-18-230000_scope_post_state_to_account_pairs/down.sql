@@ -0,0 +1,106 @@
+-- This file should undo anything in `up.sql`
+
+
+
+CREATE TABLE `mastodon_profile_sync_states_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`account_id` TEXT NOT NULL UNIQUE,
+	`text_hash` TEXT NOT NULL,
+	`avatar_hash` TEXT NOT NULL,
+	`banner_hash` TEXT NOT NULL,
+	`updated_at` TIMESTAMP NOT NULL
+);
+
+INSERT OR IGNORE INTO `mastodon_profile_sync_states_old`
+SELECT `id`, `account_id`, `text_hash`, `avatar_hash`, `banner_hash`, `updated_at`
+FROM `mastodon_profile_sync_states`
+ORDER BY `updated_at` DESC;
+
+DROP TABLE `mastodon_profile_sync_states`;
+ALTER TABLE `mastodon_profile_sync_states_old` RENAME TO `mastodon_profile_sync_states`;
+
+CREATE TABLE `mastodon_backfill_cursors_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`account_id` TEXT NOT NULL UNIQUE,
+	`range_start` TIMESTAMP NOT NULL,
+	`range_end` TIMESTAMP NOT NULL,
+	`last_post_id` TEXT NOT NULL,
+	`synced_count` BIGINT NOT NULL,
+	`updated_at` TIMESTAMP NOT NULL
+);
+
+INSERT OR IGNORE INTO `mastodon_backfill_cursors_old`
+SELECT `id`, `account_id`, `range_start`, `range_end`, `last_post_id`, `synced_count`, `updated_at`
+FROM `mastodon_backfill_cursors`
+ORDER BY `updated_at` DESC;
+
+DROP TABLE `mastodon_backfill_cursors`;
+ALTER TABLE `mastodon_backfill_cursors_old` RENAME TO `mastodon_backfill_cursors`;
+
+ALTER TABLE `synced_posts_bluesky_data` DROP COLUMN `account_pair_id`;
+
+CREATE TABLE `mastodon_post_dead_letters_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`failure_reason` TEXT NOT NULL,
+	`rendered_payload` TEXT,
+	`attempt_count` INTEGER NOT NULL,
+	`dead_lettered_at` TIMESTAMP NOT NULL,
+	`account_pair_id` TEXT NOT NULL,
+	`failure_class` TEXT NOT NULL DEFAULT 'transient'
+);
+
+INSERT OR IGNORE INTO `mastodon_post_dead_letters_old`
+SELECT `id`, `failure_reason`, `rendered_payload`, `attempt_count`, `dead_lettered_at`, `account_pair_id`, `failure_class`
+FROM `mastodon_post_dead_letters`;
+
+DROP TABLE `mastodon_post_dead_letters`;
+ALTER TABLE `mastodon_post_dead_letters_old` RENAME TO `mastodon_post_dead_letters`;
+
+CREATE TABLE `mastodon_post_retry_queue_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`failure_reason` TEXT NOT NULL,
+	`last_retried_at` TIMESTAMP NOT NULL,
+	`retry_count` INTEGER NOT NULL,
+	`account_pair_id` TEXT NOT NULL DEFAULT 'default',
+	`next_attempt_at` TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+	`failure_class` TEXT NOT NULL DEFAULT 'transient'
+);
+
+INSERT OR IGNORE INTO `mastodon_post_retry_queue_old`
+SELECT `id`, `failure_reason`, `last_retried_at`, `retry_count`, `account_pair_id`, `next_attempt_at`, `failure_class`
+FROM `mastodon_post_retry_queue`;
+
+DROP TABLE `mastodon_post_retry_queue`;
+ALTER TABLE `mastodon_post_retry_queue_old` RENAME TO `mastodon_post_retry_queue`;
+
+CREATE TABLE `mastodon_pending_posts_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`mastodon_post_id` TEXT NOT NULL UNIQUE,
+	`post_created_at` TIMESTAMP NOT NULL,
+	`publish_at` TIMESTAMP NOT NULL,
+	`queued_at` TIMESTAMP NOT NULL,
+	`account_pair_id` TEXT NOT NULL DEFAULT 'default'
+);
+
+INSERT OR IGNORE INTO `mastodon_pending_posts_old`
+SELECT `id`, `mastodon_post_id`, `post_created_at`, `publish_at`, `queued_at`, `account_pair_id`
+FROM `mastodon_pending_posts`;
+
+DROP TABLE `mastodon_pending_posts`;
+ALTER TABLE `mastodon_pending_posts_old` RENAME TO `mastodon_pending_posts`;
+
+CREATE TABLE `mastodon_post_filter_decisions_old`(
+	`id` TEXT NOT NULL PRIMARY KEY,
+	`mastodon_post_id` TEXT NOT NULL UNIQUE,
+	`action` TEXT NOT NULL,
+	`rule_name` TEXT,
+	`decided_at` TIMESTAMP NOT NULL,
+	`account_pair_id` TEXT NOT NULL DEFAULT 'default'
+);
+
+INSERT OR IGNORE INTO `mastodon_post_filter_decisions_old`
+SELECT `id`, `mastodon_post_id`, `action`, `rule_name`, `decided_at`, `account_pair_id`
+FROM `mastodon_post_filter_decisions`;
+
+DROP TABLE `mastodon_post_filter_decisions`;
+ALTER TABLE `mastodon_post_filter_decisions_old` RENAME TO `mastodon_post_filter_decisions`;
@@ -41,8 +41,22 @@ pub enum AnyConnection {
     SQLite(diesel::SqliteConnection),
 }
 
+/// The number of connections each account pair's sync loop can hold at once.
+const CONNECTIONS_PER_ACCOUNT_PAIR: u32 = 3;
+
+/// The number of connections shared by the web server and the startup checks.
+const SHARED_CONNECTIONS: u32 = 4;
+
+/// Create a database connection pool sized for the account pairs being
+/// synced.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL of the database to connect to.
+/// * `account_pair_count` - The number of account pairs using the pool.
 pub fn create_database_connection(
-    database_url: &str
+    database_url: &str,
+    account_pair_count: usize,
 ) -> Result<Pool<ConnectionManager<AnyConnection>>> {
     tracing::debug!("Creating database connection pool.");
     let connection_manager = ConnectionManager::<AnyConnection>::new(database_url);
@@ -52,13 +66,26 @@ pub fn create_database_connection(
         .test_on_check_out(true)
         .connection_timeout(Duration::from_secs(15))
         .min_idle(Some(1))
-        .max_size(10)
+        .max_size(database_pool_size(account_pair_count))
         .build(connection_manager)
         .context("Failed to create database connection pool.")?;
 
     Ok(pool)
 }
 
+/// Get the maximum size of the database connection pool for a number of
+/// account pairs.
+///
+/// ## Arguments
+///
+/// * `account_pair_count` - The number of account pairs using the pool.
+fn database_pool_size(account_pair_count: usize) -> u32 {
+    let account_pair_count = u32::try_from(account_pair_count).unwrap_or(u32::MAX);
+
+    SHARED_CONNECTIONS
+        .saturating_add(CONNECTIONS_PER_ACCOUNT_PAIR.saturating_mul(account_pair_count))
+}
+
 // We have to implement `HasSqlType` for `MultiBackendUuid` since it's not a
 // built-in Diesel type.
 impl HasSqlType<MultiBackendUuid> for MultiBackend {
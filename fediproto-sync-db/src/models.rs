@@ -1,6 +1,6 @@
 use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
-use fediproto_sync_lib::error::FediProtoSyncError;
+use fediproto_sync_lib::error::{ErrorClass, FediProtoSyncError};
 use megalodon::entities::Status;
 
 use super::type_impls::UuidProxy;
@@ -36,6 +36,12 @@ pub struct MastodonPost {
 
     /// The root Mastodon post ID in the thread, if any.
     pub root_mastodon_post_id: Option<String>,
+
+    /// The ID of the account pair the post was synced by.
+    pub account_pair_id: String,
+
+    /// The name of the BlueSky destination the post was synced to.
+    pub destination: String,
 }
 
 /// Represents a new Mastodon post to insert into the `mastodon_posts` table.
@@ -68,6 +74,12 @@ pub struct NewMastodonPost {
 
     /// The root Mastodon post ID in the thread, if any.
     pub root_mastodon_post_id: Option<String>,
+
+    /// The ID of the account pair the post was synced by.
+    pub account_pair_id: String,
+
+    /// The name of the BlueSky destination the post was synced to.
+    pub destination: String,
 }
 
 impl NewMastodonPost {
@@ -75,11 +87,17 @@ impl NewMastodonPost {
     ///
     /// ## Arguments
     ///
+    /// * `account_pair_id` - The ID of the account pair the post was synced
+    ///   by.
+    /// * `destination` - The name of the BlueSky destination the post was
+    ///   synced to.
     /// * `post` - The Mastodon post to create a new post from.
     /// * `bsky_post_id` - The BlueSky post ID when the post was synced, if any.
     /// * `root_mastodon_post_id` - The root Mastodon post ID in the thread, if
     ///   any.
     pub fn new(
+        account_pair_id: &str,
+        destination: &str,
         post: &Status,
         bsky_post_id: Option<String>,
         root_mastodon_post_id: Option<String>,
@@ -122,6 +140,8 @@ impl NewMastodonPost {
             previous_post_id,
             bsky_post_id,
             root_mastodon_post_id,
+            account_pair_id: account_pair_id.to_string(),
+            destination: destination.to_string(),
         }
     }
 }
@@ -142,6 +162,12 @@ pub struct SyncedPostBlueSkyData {
 
     /// The URI of the BlueSky post.
     pub bsky_post_uri: String,
+
+    /// The name of the BlueSky destination the post was synced to.
+    pub destination: String,
+
+    /// The ID of the account pair the post was synced by.
+    pub account_pair_id: String,
 }
 
 /// Represents a new synced post to insert into the `synced_posts_bluesky_data`
@@ -160,6 +186,12 @@ pub struct NewSyncedPostBlueSkyData {
 
     /// The URI of the BlueSky post.
     pub bsky_post_uri: String,
+
+    /// The name of the BlueSky destination the post was synced to.
+    pub destination: String,
+
+    /// The ID of the account pair the post was synced by.
+    pub account_pair_id: String,
 }
 
 impl NewSyncedPostBlueSkyData {
@@ -167,10 +199,16 @@ impl NewSyncedPostBlueSkyData {
     ///
     /// ## Arguments
     ///
+    /// * `account_pair_id` - The ID of the account pair the post was synced
+    ///   by.
+    /// * `destination` - The name of the BlueSky destination the post was
+    ///   synced to.
     /// * `mastodon_post_id` - The Mastodon post ID.
     /// * `bsky_post_cid` - The CID of the BlueSky post.
     /// * `bsky_post_uri` - The URI of the BlueSky post.
     pub fn new(
+        account_pair_id: &str,
+        destination: &str,
         mastodon_post_id: &str,
         bsky_post_cid: &str,
         bsky_post_uri: &str,
@@ -183,6 +221,8 @@ impl NewSyncedPostBlueSkyData {
             mastodon_post_id: mastodon_post_id.to_string(),
             bsky_post_cid: bsky_post_cid.to_string(),
             bsky_post_uri: bsky_post_uri.to_string(),
+            destination: destination.to_string(),
+            account_pair_id: account_pair_id.to_string(),
         }
     }
 }
@@ -241,7 +281,7 @@ impl NewCachedFile {
     /// ## Arguments
     ///
     /// * `file_path` - The path to the cached file.
-    pub fn new(file_path: &std::path::PathBuf) -> Self {
+    pub fn new(file_path: &std::path::Path) -> Self {
         let time_context = uuid::ContextV7::new();
         let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
 
@@ -274,6 +314,9 @@ pub struct CachedServiceToken {
 
     /// The scopes the access token has, if any.
     pub scopes: Option<String>,
+
+    /// The ID of the account pair the token is for.
+    pub account_pair_id: String,
 }
 
 /// Trait for decrypting a cached service token's access and refresh tokens.
@@ -373,6 +416,9 @@ pub struct NewCachedServiceToken {
 
     /// The scopes the access token has, if any.
     pub scopes: Option<String>,
+
+    /// The ID of the account pair the token is for.
+    pub account_pair_id: String,
 }
 
 impl NewCachedServiceToken {
@@ -381,6 +427,7 @@ impl NewCachedServiceToken {
     /// ## Arguments
     ///
     /// * `encryption_public_key` - The public key to use for encryption.
+    /// * `account_pair_id` - The ID of the account pair the token is for.
     /// * `service_name` - The name of the service the token is for.
     /// * `access_token` - The access token to encrypt.
     /// * `refresh_token` - The refresh token to encrypt, if any.
@@ -389,6 +436,7 @@ impl NewCachedServiceToken {
     /// * `scopes` - The scopes the access token has, if any.
     pub fn new(
         encryption_public_key: &openssl::rsa::Rsa<openssl::pkey::Public>,
+        account_pair_id: &str,
         service_name: &str,
         access_token: &str,
         refresh_token: Option<String>,
@@ -423,6 +471,7 @@ impl NewCachedServiceToken {
             refresh_token: encrypted_refresh_token,
             expires_in,
             scopes,
+            account_pair_id: account_pair_id.to_string(),
         })
     }
 }
@@ -431,10 +480,10 @@ impl NewCachedServiceToken {
 #[derive(Queryable, Selectable, Identifiable, AsChangeset, Clone, PartialEq, Debug)]
 #[allow(dead_code)]
 #[diesel(table_name = crate::schema::mastodon_post_retry_queue)]
-#[diesel(primary_key(id))]
+#[diesel(primary_key(account_pair_id, id))]
 pub struct MastodonPostRetryQueueItem {
-    /// The unique Mastodon post ID.
-    pub id: i64,
+    /// The Mastodon post ID, unique per account pair.
+    pub id: String,
 
     /// The reason the post failed to sync.
     pub failure_reason: String,
@@ -444,6 +493,16 @@ pub struct MastodonPostRetryQueueItem {
 
     /// The amount of times retries have been attempted.
     pub retry_count: i32,
+
+    /// The ID of the account pair the post is being synced by.
+    pub account_pair_id: String,
+
+    /// The time the next retry can be attempted.
+    pub next_attempt_at: NaiveDateTime,
+
+    /// The class of the error the post failed to sync with, like
+    /// `transient`.
+    pub failure_class: String,
 }
 
 /// Represents a new Mastodon post to insert into the
@@ -451,8 +510,8 @@ pub struct MastodonPostRetryQueueItem {
 #[derive(Insertable)]
 #[diesel(table_name = crate::schema::mastodon_post_retry_queue)]
 pub struct NewMastodonPostRetryQueueItem {
-    /// A unique Mastodon post ID.
-    pub id: i64,
+    /// The Mastodon post ID, unique per account pair.
+    pub id: String,
 
     /// The reason the post failed to sync.
     pub failure_reason: String,
@@ -462,6 +521,16 @@ pub struct NewMastodonPostRetryQueueItem {
 
     /// The amount of times retries have been attempted.
     pub retry_count: i32,
+
+    /// The ID of the account pair the post is being synced by.
+    pub account_pair_id: String,
+
+    /// The time the next retry can be attempted.
+    pub next_attempt_at: NaiveDateTime,
+
+    /// The class of the error the post failed to sync with, like
+    /// `transient`.
+    pub failure_class: String,
 }
 
 impl NewMastodonPostRetryQueueItem {
@@ -469,17 +538,681 @@ impl NewMastodonPostRetryQueueItem {
     ///
     /// ## Arguments
     ///
+    /// * `account_pair_id` - The ID of the account pair the post is being
+    ///   synced by.
     /// * `mastodon_post_id` - The Mastodon post ID.
     /// * `failure_reason` - The reason the post failed to sync.
+    /// * `failure_class` - The class of the error the post failed to sync
+    ///   with.
+    /// * `next_attempt_at` - The time the first retry can be attempted.
     pub fn new(
-        mastodon_post_id: &i64,
+        account_pair_id: &str,
+        mastodon_post_id: &str,
         failure_reason: &str,
+        failure_class: ErrorClass,
+        next_attempt_at: NaiveDateTime,
     ) -> Self {
         Self {
-            id: mastodon_post_id.clone(),
+            id: mastodon_post_id.to_string(),
             failure_reason: failure_reason.to_string(),
             last_retried_at: Utc::now().naive_utc(),
             retry_count: 0,
+            account_pair_id: account_pair_id.to_string(),
+            next_attempt_at,
+            failure_class: failure_class.as_str().to_string(),
+        }
+    }
+}
+
+/// Represents a Mastodon post that ran out of retries in the
+/// `mastodon_post_dead_letters` table.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::mastodon_post_dead_letters)]
+pub struct MastodonPostDeadLetter {
+    /// The Mastodon post ID, unique per account pair.
+    pub id: String,
+
+    /// The reason the last attempt to sync the post failed.
+    pub failure_reason: String,
+
+    /// The text of the BlueSky post that failed to sync, if it could be
+    /// rendered.
+    pub rendered_payload: Option<String>,
+
+    /// The amount of times syncing the post was attempted.
+    pub attempt_count: i32,
+
+    /// The date and time the post was moved to the dead letters.
+    pub dead_lettered_at: NaiveDateTime,
+
+    /// The ID of the account pair the post was being synced by.
+    pub account_pair_id: String,
+
+    /// The class of the error the last attempt failed with, like
+    /// `permanent`.
+    pub failure_class: String,
+}
+
+/// Represents a new dead letter to insert into the
+/// `mastodon_post_dead_letters` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::mastodon_post_dead_letters)]
+pub struct NewMastodonPostDeadLetter {
+    /// The Mastodon post ID, unique per account pair.
+    pub id: String,
+
+    /// The reason the last attempt to sync the post failed.
+    pub failure_reason: String,
+
+    /// The text of the BlueSky post that failed to sync, if it could be
+    /// rendered.
+    pub rendered_payload: Option<String>,
+
+    /// The amount of times syncing the post was attempted.
+    pub attempt_count: i32,
+
+    /// The date and time the post was moved to the dead letters.
+    pub dead_lettered_at: NaiveDateTime,
+
+    /// The ID of the account pair the post was being synced by.
+    pub account_pair_id: String,
+
+    /// The class of the error the last attempt failed with, like
+    /// `permanent`.
+    pub failure_class: String,
+}
+
+impl NewMastodonPostDeadLetter {
+    /// Create a new instance of the `NewMastodonPostDeadLetter` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair the post was being
+    ///   synced by.
+    /// * `mastodon_post_id` - The Mastodon post ID.
+    /// * `failure_reason` - The reason the last attempt failed.
+    /// * `failure_class` - The class of the error the last attempt failed
+    ///   with.
+    /// * `rendered_payload` - The text of the BlueSky post, if it could be
+    ///   rendered.
+    /// * `attempt_count` - The amount of times syncing the post was attempted.
+    pub fn new(
+        account_pair_id: &str,
+        mastodon_post_id: &str,
+        failure_reason: &str,
+        failure_class: ErrorClass,
+        rendered_payload: Option<&str>,
+        attempt_count: i32,
+    ) -> Self {
+        Self {
+            id: mastodon_post_id.to_string(),
+            failure_reason: failure_reason.to_string(),
+            rendered_payload: rendered_payload.map(|payload| payload.to_string()),
+            attempt_count,
+            dead_lettered_at: Utc::now().naive_utc(),
+            account_pair_id: account_pair_id.to_string(),
+            failure_class: failure_class.as_str().to_string(),
+        }
+    }
+}
+
+/// Represents cached metadata for a link in the `link_metadata_cache` table.
+///
+/// ## Note
+///
+/// Blobs are stored per account, so links are cached separately for each
+/// BlueSky account the thumbnails are uploaded to.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::link_metadata_cache)]
+pub struct LinkMetadataCacheEntry {
+    /// A unique identifier for the cached link metadata in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The normalized URL of the link.
+    pub url: String,
+
+    /// The title of the link.
+    pub title: String,
+
+    /// The description of the link.
+    pub description: String,
+
+    /// The URL of the thumbnail image for the link, if any.
+    pub thumbnail_url: Option<String>,
+
+    /// The SHA-256 hash of the thumbnail image, if any.
+    pub thumbnail_hash: Option<String>,
+
+    /// The JSON serialized blob ref of the thumbnail uploaded to BlueSky, if
+    /// any.
+    pub thumbnail_blob: Option<String>,
+
+    /// The DID of the BlueSky account the link was cached for.
+    pub thumbnail_blob_did: String,
+
+    /// The date and time the link metadata was cached.
+    pub cached_at: NaiveDateTime,
+
+    /// The date and time the cached link metadata expires.
+    pub expires_at: NaiveDateTime,
+}
+
+/// Represents new cached metadata for a link to insert into the
+/// `link_metadata_cache` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::link_metadata_cache)]
+pub struct NewLinkMetadataCacheEntry {
+    /// A unique identifier for the cached link metadata in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The normalized URL of the link.
+    pub url: String,
+
+    /// The title of the link.
+    pub title: String,
+
+    /// The description of the link.
+    pub description: String,
+
+    /// The URL of the thumbnail image for the link, if any.
+    pub thumbnail_url: Option<String>,
+
+    /// The SHA-256 hash of the thumbnail image, if any.
+    pub thumbnail_hash: Option<String>,
+
+    /// The JSON serialized blob ref of the thumbnail uploaded to BlueSky, if
+    /// any.
+    pub thumbnail_blob: Option<String>,
+
+    /// The DID of the BlueSky account the link was cached for.
+    pub thumbnail_blob_did: String,
+
+    /// The date and time the link metadata was cached.
+    pub cached_at: NaiveDateTime,
+
+    /// The date and time the cached link metadata expires.
+    pub expires_at: NaiveDateTime,
+}
+
+impl NewLinkMetadataCacheEntry {
+    /// Create a new instance of the `NewLinkMetadataCacheEntry` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The normalized URL of the link.
+    /// * `title` - The title of the link.
+    /// * `description` - The description of the link.
+    /// * `thumbnail_url` - The URL of the thumbnail image for the link, if any.
+    /// * `thumbnail_blob_did` - The DID of the BlueSky account the link is
+    ///   cached for.
+    /// * `ttl` - How long the cached link metadata is valid for.
+    pub fn new(
+        url: &str,
+        title: &str,
+        description: &str,
+        thumbnail_url: Option<String>,
+        thumbnail_blob_did: &str,
+        ttl: std::time::Duration,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        let cached_at = Utc::now().naive_utc();
+        let expires_at = cached_at
+            + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(1));
+
+        Self {
+            id: UuidProxy(id),
+            url: url.to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+            thumbnail_url,
+            thumbnail_hash: None,
+            thumbnail_blob: None,
+            thumbnail_blob_did: thumbnail_blob_did.to_string(),
+            cached_at,
+            expires_at,
+        }
+    }
+
+    /// Set the thumbnail uploaded to BlueSky for the link.
+    ///
+    /// ## Arguments
+    ///
+    /// * `thumbnail_hash` - The SHA-256 hash of the thumbnail image.
+    /// * `thumbnail_blob` - The JSON serialized blob ref of the thumbnail.
+    pub fn with_thumbnail_blob(
+        mut self,
+        thumbnail_hash: &str,
+        thumbnail_blob: &str,
+    ) -> Self {
+        self.thumbnail_hash = Some(thumbnail_hash.to_string());
+        self.thumbnail_blob = Some(thumbnail_blob.to_string());
+
+        self
+    }
+}
+
+/// Represents a filter decision for a Mastodon post in the
+/// `mastodon_post_filter_decisions` table.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::mastodon_post_filter_decisions)]
+pub struct MastodonPostFilterDecision {
+    /// A unique identifier for the filter decision in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The Mastodon post ID.
+    pub mastodon_post_id: String,
+
+    /// The action decided for the post (`skip` or `hold`).
+    pub action: String,
+
+    /// The name of the rule that made the decision, if any.
+    pub rule_name: Option<String>,
+
+    /// The date and time the decision was made.
+    pub decided_at: NaiveDateTime,
+
+    /// The ID of the account pair the decision was made by.
+    pub account_pair_id: String,
+}
+
+/// Represents a new filter decision for a Mastodon post to insert into the
+/// `mastodon_post_filter_decisions` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::mastodon_post_filter_decisions)]
+pub struct NewMastodonPostFilterDecision {
+    /// A unique identifier for the filter decision in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The Mastodon post ID.
+    pub mastodon_post_id: String,
+
+    /// The action decided for the post (`skip` or `hold`).
+    pub action: String,
+
+    /// The name of the rule that made the decision, if any.
+    pub rule_name: Option<String>,
+
+    /// The date and time the decision was made.
+    pub decided_at: NaiveDateTime,
+
+    /// The ID of the account pair the decision was made by.
+    pub account_pair_id: String,
+}
+
+impl NewMastodonPostFilterDecision {
+    /// Create a new instance of the `NewMastodonPostFilterDecision` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair the decision was made
+    ///   by.
+    /// * `mastodon_post_id` - The Mastodon post ID.
+    /// * `action` - The action decided for the post.
+    /// * `rule_name` - The name of the rule that made the decision, if any.
+    pub fn new(
+        account_pair_id: &str,
+        mastodon_post_id: &str,
+        action: &str,
+        rule_name: Option<String>,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        Self {
+            id: UuidProxy(id),
+            mastodon_post_id: mastodon_post_id.to_string(),
+            action: action.to_string(),
+            rule_name,
+            decided_at: Utc::now().naive_utc(),
+            account_pair_id: account_pair_id.to_string(),
+        }
+    }
+}
+
+/// Represents a Mastodon post waiting for the publish delay to pass in the
+/// `mastodon_pending_posts` table.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::mastodon_pending_posts)]
+pub struct MastodonPendingPost {
+    /// A unique identifier for the pending post in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The Mastodon post ID.
+    pub mastodon_post_id: String,
+
+    /// The date and time the post was created on Mastodon.
+    pub post_created_at: NaiveDateTime,
+
+    /// The date and time the post can be published.
+    pub publish_at: NaiveDateTime,
+
+    /// The date and time the post was added to the pending posts.
+    pub queued_at: NaiveDateTime,
+
+    /// The ID of the account pair the post is pending for.
+    pub account_pair_id: String,
+}
+
+/// Represents a new pending Mastodon post to insert into the
+/// `mastodon_pending_posts` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::mastodon_pending_posts)]
+pub struct NewMastodonPendingPost {
+    /// A unique identifier for the pending post in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The Mastodon post ID.
+    pub mastodon_post_id: String,
+
+    /// The date and time the post was created on Mastodon.
+    pub post_created_at: NaiveDateTime,
+
+    /// The date and time the post can be published.
+    pub publish_at: NaiveDateTime,
+
+    /// The date and time the post was added to the pending posts.
+    pub queued_at: NaiveDateTime,
+
+    /// The ID of the account pair the post is pending for.
+    pub account_pair_id: String,
+}
+
+impl NewMastodonPendingPost {
+    /// Create a new instance of the `NewMastodonPendingPost` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair the status is pending
+    ///   for.
+    /// * `mastodon_status` - The Mastodon status that is pending.
+    /// * `publish_delay` - How long to wait after the status was created
+    ///   before publishing it.
+    pub fn new(
+        account_pair_id: &str,
+        mastodon_status: &Status,
+        publish_delay: std::time::Duration,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        let post_created_at = mastodon_status.created_at.naive_utc();
+        let publish_at = post_created_at
+            + chrono::Duration::from_std(publish_delay).unwrap_or(chrono::Duration::zero());
+
+        Self {
+            id: UuidProxy(id),
+            mastodon_post_id: mastodon_status.id.clone(),
+            post_created_at,
+            publish_at,
+            queued_at: Utc::now().naive_utc(),
+            account_pair_id: account_pair_id.to_string(),
+        }
+    }
+}
+
+/// Represents the progress of a backfill in the `mastodon_backfill_cursors`
+/// table.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::mastodon_backfill_cursors)]
+pub struct MastodonBackfillCursor {
+    /// A unique identifier for the cursor in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair being backfilled.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID being backfilled.
+    pub account_id: String,
+
+    /// The start of the date range being backfilled.
+    pub range_start: NaiveDateTime,
+
+    /// The end of the date range being backfilled.
+    pub range_end: NaiveDateTime,
+
+    /// The ID of the last Mastodon post processed by the backfill.
+    pub last_post_id: String,
+
+    /// The number of posts synced by the backfill.
+    pub synced_count: i64,
+
+    /// The date and time the cursor was last updated.
+    pub updated_at: NaiveDateTime,
+}
+
+/// Represents a new backfill cursor to insert into the
+/// `mastodon_backfill_cursors` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::mastodon_backfill_cursors)]
+pub struct NewMastodonBackfillCursor {
+    /// A unique identifier for the cursor in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair being backfilled.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID being backfilled.
+    pub account_id: String,
+
+    /// The start of the date range being backfilled.
+    pub range_start: NaiveDateTime,
+
+    /// The end of the date range being backfilled.
+    pub range_end: NaiveDateTime,
+
+    /// The ID of the last Mastodon post processed by the backfill.
+    pub last_post_id: String,
+
+    /// The number of posts synced by the backfill.
+    pub synced_count: i64,
+
+    /// The date and time the cursor was last updated.
+    pub updated_at: NaiveDateTime,
+}
+
+impl NewMastodonBackfillCursor {
+    /// Create a new instance of the `NewMastodonBackfillCursor` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair being backfilled.
+    /// * `account_id` - The Mastodon account ID being backfilled.
+    /// * `range_start` - The start of the date range being backfilled.
+    /// * `range_end` - The end of the date range being backfilled.
+    /// * `last_post_id` - The ID of the last Mastodon post processed.
+    /// * `synced_count` - The number of posts synced by the backfill.
+    pub fn new(
+        account_pair_id: &str,
+        account_id: &str,
+        range_start: NaiveDateTime,
+        range_end: NaiveDateTime,
+        last_post_id: &str,
+        synced_count: i64,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        Self {
+            id: UuidProxy(id),
+            account_pair_id: account_pair_id.to_string(),
+            account_id: account_id.to_string(),
+            range_start,
+            range_end,
+            last_post_id: last_post_id.to_string(),
+            synced_count,
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// Represents the state of the last profile sync for an account pair.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::mastodon_profile_sync_states)]
+pub struct MastodonProfileSyncState {
+    /// A unique identifier for the state in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair the profile is synced by.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID the profile belongs to.
+    pub account_id: String,
+
+    /// The hash of the synced display name and bio.
+    pub text_hash: String,
+
+    /// The hash of the synced avatar.
+    pub avatar_hash: String,
+
+    /// The hash of the synced banner.
+    pub banner_hash: String,
+
+    /// The date and time the profile was last synced.
+    pub updated_at: NaiveDateTime,
+}
+
+/// Represents a new profile sync state to insert into the
+/// `mastodon_profile_sync_states` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::mastodon_profile_sync_states)]
+pub struct NewMastodonProfileSyncState {
+    /// A unique identifier for the state in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair the profile is synced by.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID the profile belongs to.
+    pub account_id: String,
+
+    /// The hash of the synced display name and bio.
+    pub text_hash: String,
+
+    /// The hash of the synced avatar.
+    pub avatar_hash: String,
+
+    /// The hash of the synced banner.
+    pub banner_hash: String,
+
+    /// The date and time the profile was last synced.
+    pub updated_at: NaiveDateTime,
+}
+
+impl NewMastodonProfileSyncState {
+    /// Create a new instance of the `NewMastodonProfileSyncState` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair the profile is synced
+    ///   by.
+    /// * `account_id` - The Mastodon account ID the profile belongs to.
+    /// * `text_hash` - The hash of the synced display name and bio.
+    /// * `avatar_hash` - The hash of the synced avatar.
+    /// * `banner_hash` - The hash of the synced banner.
+    pub fn new(
+        account_pair_id: &str,
+        account_id: &str,
+        text_hash: &str,
+        avatar_hash: &str,
+        banner_hash: &str,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        Self {
+            id: UuidProxy(id),
+            account_pair_id: account_pair_id.to_string(),
+            account_id: account_id.to_string(),
+            text_hash: text_hash.to_string(),
+            avatar_hash: avatar_hash.to_string(),
+            banner_hash: banner_hash.to_string(),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// Represents the sync cursor of an account pair in the `sync_states` table.
+#[derive(Queryable, Selectable, Clone, PartialEq, Debug)]
+#[allow(dead_code)]
+#[diesel(table_name = crate::schema::sync_states)]
+pub struct SyncState {
+    /// A unique identifier for the state in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair the state is for.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID being synced.
+    pub account_id: String,
+
+    /// The ID of the newest Mastodon post seen by a sync. Posts after it are
+    /// fetched by the next sync.
+    pub last_seen_post_id: Option<String>,
+
+    /// The date and time of the last sync.
+    pub last_run_at: NaiveDateTime,
+
+    /// The error the last sync failed with, if it failed.
+    pub last_error: Option<String>,
+}
+
+/// Represents a new sync state to insert into the `sync_states` table.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::sync_states)]
+pub struct NewSyncState {
+    /// A unique identifier for the state in the database.
+    pub id: crate::type_impls::UuidProxy,
+
+    /// The ID of the account pair the state is for.
+    pub account_pair_id: String,
+
+    /// The Mastodon account ID being synced.
+    pub account_id: String,
+
+    /// The ID of the newest Mastodon post seen by a sync. Posts after it are
+    /// fetched by the next sync.
+    pub last_seen_post_id: Option<String>,
+
+    /// The date and time of the last sync.
+    pub last_run_at: NaiveDateTime,
+
+    /// The error the last sync failed with, if it failed.
+    pub last_error: Option<String>,
+}
+
+impl NewSyncState {
+    /// Create a new instance of the `NewSyncState` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_pair_id` - The ID of the account pair the state is for.
+    /// * `account_id` - The Mastodon account ID being synced.
+    /// * `last_seen_post_id` - The ID of the newest Mastodon post seen.
+    /// * `last_error` - The error the sync failed with, if it failed.
+    pub fn new(
+        account_pair_id: &str,
+        account_id: &str,
+        last_seen_post_id: Option<&str>,
+        last_error: Option<&str>,
+    ) -> Self {
+        let time_context = uuid::ContextV7::new();
+        let id = uuid::Uuid::new_v7(uuid::Timestamp::now(&time_context));
+
+        Self {
+            id: UuidProxy(id),
+            account_pair_id: account_pair_id.to_string(),
+            account_id: account_id.to_string(),
+            last_seen_post_id: last_seen_post_id.map(|post_id| post_id.to_string()),
+            last_run_at: Utc::now().naive_utc(),
+            last_error: last_error.map(|error| error.to_string()),
         }
     }
 }
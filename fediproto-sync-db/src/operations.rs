@@ -1,5 +1,6 @@
 use anyhow::Result;
 use diesel::prelude::*;
+use fediproto_sync_lib::error::ErrorClass;
 
 use crate::error::FediProtoSyncDbError;
 
@@ -8,15 +9,22 @@ use crate::error::FediProtoSyncDbError;
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `destination` - The name of the BlueSky destination the post was synced
+///   to.
 /// * `mastodon_post_id` - The Mastodon post ID to get.
 pub fn get_synced_mastodon_post_by_id(
     db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    destination: &str,
     mastodon_post_id: &str,
 ) -> Result<crate::models::MastodonPost, FediProtoSyncDbError> {
     let post = crate::schema::mastodon_posts::table
+        .filter(crate::schema::mastodon_posts::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_posts::destination.eq(destination))
         .filter(crate::schema::mastodon_posts::post_id.eq(mastodon_post_id))
         .first::<crate::models::MastodonPost>(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(post)
 }
@@ -26,19 +34,46 @@ pub fn get_synced_mastodon_post_by_id(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `destination` - The name of the BlueSky destination the post was synced
+///   to.
 /// * `mastodon_post_id` - The Mastodon post ID to check.
 pub fn check_synced_mastodon_post_exists(
     db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    destination: &str,
     mastodon_post_id: &str,
 ) -> bool {
-    let post = get_synced_mastodon_post_by_id(db_connection, &mastodon_post_id);
+    let post = get_synced_mastodon_post_by_id(
+        db_connection,
+        account_pair_id,
+        destination,
+        mastodon_post_id,
+    );
 
-    let post_exists = match post {
-        Ok(_) => true,
-        Err(_) => false,
-    };
+    post.is_ok()
+}
 
-    return post_exists;
+/// Get the names of the BlueSky destinations a Mastodon post was synced to.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `mastodon_post_id` - The Mastodon post ID to get the destinations for.
+pub fn get_synced_mastodon_post_destinations(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<Vec<String>, FediProtoSyncDbError> {
+    let destinations = crate::schema::synced_posts_bluesky_data::table
+        .filter(crate::schema::synced_posts_bluesky_data::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::synced_posts_bluesky_data::mastodon_post_id.eq(mastodon_post_id))
+        .select(crate::schema::synced_posts_bluesky_data::destination)
+        .load::<String>(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(destinations)
 }
 
 /// Get the last synced Mastodon post ID from the database.
@@ -46,33 +81,56 @@ pub fn check_synced_mastodon_post_exists(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the post for.
+///
+/// ## Note
+///
+/// Syncs resume from the cursor in the `sync_states` table. This is only used
+/// for account pairs that don't have a sync state yet, like after upgrading.
 pub fn get_last_synced_mastodon_post_id(
-    db_connection: &mut crate::AnyConnection
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
 ) -> Result<Option<String>, FediProtoSyncDbError> {
     let last_synced_post_id = crate::schema::mastodon_posts::table
+        .filter(crate::schema::mastodon_posts::account_pair_id.eq(account_pair_id))
         .order(crate::schema::mastodon_posts::created_at.desc())
         .select(crate::schema::mastodon_posts::post_id)
         .first::<String>(db_connection)
         .optional()
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(last_synced_post_id)
 }
 
-/// Insert a new synced Mastodon post into the database.
+/// Insert a new synced Mastodon post and the BlueSky post it was synced to
+/// into the database.
 ///
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
 /// * `new_post` - The new post to insert.
+/// * `synced_post_data` - The BlueSky data of the new post to insert.
+///
+/// ## Note
+///
+/// Both are inserted in a single transaction, so neither is inserted if the
+/// other fails.
 pub fn insert_new_synced_mastodon_post(
     db_connection: &mut crate::AnyConnection,
     new_post: &crate::models::NewMastodonPost,
+    synced_post_data: &crate::models::NewSyncedPostBlueSkyData,
 ) -> Result<(), FediProtoSyncDbError> {
-    diesel::insert_into(crate::schema::mastodon_posts::table)
-        .values(new_post)
-        .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+    db_connection
+        .transaction(|db_connection| {
+            diesel::insert_into(crate::schema::mastodon_posts::table)
+                .values(new_post)
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::synced_posts_bluesky_data::table)
+                .values(synced_post_data)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -82,35 +140,55 @@ pub fn insert_new_synced_mastodon_post(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `destination` - The name of the BlueSky destination the post was synced
+///   to.
 /// * `mastodon_post_id` - The Mastodon post ID to get.
 pub fn get_bluesky_data_by_mastodon_post_id(
     db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    destination: &str,
     mastodon_post_id: &str,
 ) -> Result<crate::models::SyncedPostBlueSkyData, FediProtoSyncDbError> {
     let synced_post = crate::schema::synced_posts_bluesky_data::table
+        .filter(crate::schema::synced_posts_bluesky_data::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::synced_posts_bluesky_data::destination.eq(destination))
         .filter(crate::schema::synced_posts_bluesky_data::mastodon_post_id.eq(mastodon_post_id))
         .first::<crate::models::SyncedPostBlueSkyData>(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(synced_post)
 }
 
-/// Insert new BlueSky data for a synced Mastodon post into the database.
+/// Find BlueSky data of a synced Mastodon post by its Mastodon post ID.
 ///
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
-/// * `synced_post_data` - The new synced post to insert.
-pub fn insert_new_bluesky_data_for_synced_mastodon_post(
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `destination` - The name of the BlueSky destination the post was synced
+///   to.
+/// * `mastodon_post_id` - The Mastodon post ID to find.
+///
+/// ## Note
+///
+/// Returns `None` if the post was never synced to the destination, and an
+/// error only if the database operation failed.
+pub fn find_bluesky_data_by_mastodon_post_id(
     db_connection: &mut crate::AnyConnection,
-    synced_post_data: &crate::models::NewSyncedPostBlueSkyData,
-) -> Result<(), FediProtoSyncDbError> {
-    diesel::insert_into(crate::schema::synced_posts_bluesky_data::table)
-        .values(synced_post_data)
-        .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+    account_pair_id: &str,
+    destination: &str,
+    mastodon_post_id: &str,
+) -> Result<Option<crate::models::SyncedPostBlueSkyData>, FediProtoSyncDbError> {
+    let synced_post = crate::schema::synced_posts_bluesky_data::table
+        .filter(crate::schema::synced_posts_bluesky_data::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::synced_posts_bluesky_data::destination.eq(destination))
+        .filter(crate::schema::synced_posts_bluesky_data::mastodon_post_id.eq(mastodon_post_id))
+        .first::<crate::models::SyncedPostBlueSkyData>(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
-    Ok(())
+    Ok(synced_post)
 }
 
 /// Get records of cached files from the database.
@@ -124,7 +202,7 @@ pub fn get_cached_file_records(
     let cached_files = crate::schema::cached_files::table
         .select(crate::models::CachedFile::as_select())
         .load(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(cached_files)
 }
@@ -142,7 +220,7 @@ pub fn insert_cached_file_record(
     diesel::insert_into(crate::schema::cached_files::table)
         .values(new_cached_file)
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -160,7 +238,7 @@ pub fn delete_cached_file_record(
     diesel::delete(crate::schema::cached_files::table)
         .filter(crate::schema::cached_files::id.eq(cached_file.id))
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -170,16 +248,19 @@ pub fn delete_cached_file_record(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the token for.
 /// * `service_name` - The service name to get.
 pub fn get_cached_service_token_by_service_name(
     db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
     service_name: &str,
 ) -> Result<Option<crate::models::CachedServiceToken>, FediProtoSyncDbError> {
     let token = crate::schema::cached_service_tokens::table
+        .filter(crate::schema::cached_service_tokens::account_pair_id.eq(account_pair_id))
         .filter(crate::schema::cached_service_tokens::service_name.eq(service_name))
         .first::<crate::models::CachedServiceToken>(db_connection)
         .optional()
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(token)
 }
@@ -197,7 +278,63 @@ pub fn insert_cached_service_token(
     diesel::insert_into(crate::schema::cached_service_tokens::table)
         .values(new_token)
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Replace the cached service token for a service in the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_token` - The new token to insert.
+///
+/// ## Note
+///
+/// Any existing token for the same account pair and service is deleted.
+pub fn replace_cached_service_token(
+    db_connection: &mut crate::AnyConnection,
+    new_token: &crate::models::NewCachedServiceToken,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::cached_service_tokens::table)
+                .filter(
+                    crate::schema::cached_service_tokens::account_pair_id
+                        .eq(&new_token.account_pair_id),
+                )
+                .filter(
+                    crate::schema::cached_service_tokens::service_name.eq(&new_token.service_name),
+                )
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::cached_service_tokens::table)
+                .values(new_token)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete the cached service token for a service from the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to delete the token for.
+/// * `service_name` - The service name to delete.
+pub fn delete_cached_service_token(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    service_name: &str,
+) -> Result<(), FediProtoSyncDbError> {
+    diesel::delete(crate::schema::cached_service_tokens::table)
+        .filter(crate::schema::cached_service_tokens::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::cached_service_tokens::service_name.eq(service_name))
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -207,13 +344,39 @@ pub fn insert_cached_service_token(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the items for.
 pub fn get_mastodon_post_retry_queue_items(
-    db_connection: &mut crate::AnyConnection
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
 ) -> Result<Vec<crate::models::MastodonPostRetryQueueItem>, FediProtoSyncDbError> {
     let items = crate::schema::mastodon_post_retry_queue::table
+        .filter(crate::schema::mastodon_post_retry_queue::account_pair_id.eq(account_pair_id))
         .select(crate::models::MastodonPostRetryQueueItem::as_select())
         .load(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(items)
+}
+
+/// Get the Mastodon post retry queue items that are due to be retried.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the items for.
+/// * `now` - The current time.
+pub fn get_due_mastodon_post_retry_queue_items(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    now: chrono::NaiveDateTime,
+) -> Result<Vec<crate::models::MastodonPostRetryQueueItem>, FediProtoSyncDbError> {
+    let items = crate::schema::mastodon_post_retry_queue::table
+        .filter(crate::schema::mastodon_post_retry_queue::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_post_retry_queue::next_attempt_at.le(now))
+        .order(crate::schema::mastodon_post_retry_queue::next_attempt_at.asc())
+        .select(crate::models::MastodonPostRetryQueueItem::as_select())
+        .load(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(items)
 }
@@ -223,17 +386,20 @@ pub fn get_mastodon_post_retry_queue_items(
 /// ## Arguments
 ///
 /// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the item for.
 /// * `mastodon_post_id` - The Mastodon post ID to get.
 pub fn get_mastodon_post_retry_queue_item_by_post_id(
     db_connection: &mut crate::AnyConnection,
-    mastodon_post_id: &i64,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
 ) -> Result<Option<crate::models::MastodonPostRetryQueueItem>, FediProtoSyncDbError> {
     let item = crate::schema::mastodon_post_retry_queue::table
+        .filter(crate::schema::mastodon_post_retry_queue::account_pair_id.eq(account_pair_id))
         .filter(crate::schema::mastodon_post_retry_queue::id.eq(mastodon_post_id))
         .select(crate::models::MastodonPostRetryQueueItem::as_select())
         .first::<crate::models::MastodonPostRetryQueueItem>(db_connection)
         .optional()
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(item)
 }
@@ -251,7 +417,7 @@ pub fn insert_mastodon_post_retry_queue_item(
     diesel::insert_into(crate::schema::mastodon_post_retry_queue::table)
         .values(new_item)
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -262,10 +428,15 @@ pub fn insert_mastodon_post_retry_queue_item(
 ///
 /// * `db_connection` - The database connection to use.
 /// * `item` - The item to update.
+/// * `new_reason` - The new reason the post failed to sync, if it changed.
+/// * `new_class` - The class of the error the post failed to sync with.
+/// * `new_next_attempt_at` - The time the next retry can be attempted.
 pub fn update_mastodon_post_retry_queue_item(
     db_connection: &mut crate::AnyConnection,
     item: &crate::models::MastodonPostRetryQueueItem,
     new_reason: Option<&str>,
+    new_class: ErrorClass,
+    new_next_attempt_at: chrono::NaiveDateTime,
 ) -> Result<(), FediProtoSyncDbError> {
     use crate::schema::mastodon_post_retry_queue::dsl::*;
     let updated_reason = match new_reason {
@@ -278,9 +449,11 @@ pub fn update_mastodon_post_retry_queue_item(
             failure_reason.eq(updated_reason),
             last_retried_at.eq(diesel::dsl::now),
             retry_count.eq(retry_count + 1),
+            next_attempt_at.eq(new_next_attempt_at),
+            failure_class.eq(new_class.as_str()),
         ))
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -295,10 +468,632 @@ pub fn delete_mastodon_post_retry_queue_item(
     db_connection: &mut crate::AnyConnection,
     item: &crate::models::MastodonPostRetryQueueItem,
 ) -> Result<(), FediProtoSyncDbError> {
-    diesel::delete(crate::schema::mastodon_post_retry_queue::table)
-        .filter(crate::schema::mastodon_post_retry_queue::id.eq(&item.id))
+    diesel::delete(item)
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Get the Mastodon posts that ran out of retries.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the dead letters
+///   for. Gets the dead letters of every account pair if `None`.
+pub fn get_mastodon_post_dead_letters(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: Option<&str>,
+) -> Result<Vec<crate::models::MastodonPostDeadLetter>, FediProtoSyncDbError> {
+    let dead_letters = match account_pair_id {
+        Some(account_pair_id) => crate::schema::mastodon_post_dead_letters::table
+            .filter(crate::schema::mastodon_post_dead_letters::account_pair_id.eq(account_pair_id))
+            .order(crate::schema::mastodon_post_dead_letters::dead_lettered_at.asc())
+            .select(crate::models::MastodonPostDeadLetter::as_select())
+            .load(db_connection),
+        None => crate::schema::mastodon_post_dead_letters::table
+            .order(crate::schema::mastodon_post_dead_letters::dead_lettered_at.asc())
+            .select(crate::models::MastodonPostDeadLetter::as_select())
+            .load(db_connection),
+    }
+    .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(dead_letters)
+}
+
+/// Get a Mastodon post that ran out of retries by the Mastodon post ID.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the dead letter
+///   for.
+/// * `mastodon_post_id` - The Mastodon post ID to get.
+pub fn get_mastodon_post_dead_letter_by_post_id(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<Option<crate::models::MastodonPostDeadLetter>, FediProtoSyncDbError> {
+    let dead_letter = crate::schema::mastodon_post_dead_letters::table
+        .filter(crate::schema::mastodon_post_dead_letters::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_post_dead_letters::id.eq(mastodon_post_id))
+        .select(crate::models::MastodonPostDeadLetter::as_select())
+        .first(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(dead_letter)
+}
+
+/// Move a Mastodon post to the dead letters, removing it from the retry
+/// queue.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_dead_letter` - The new dead letter to insert.
+pub fn insert_mastodon_post_dead_letter(
+    db_connection: &mut crate::AnyConnection,
+    new_dead_letter: &crate::models::NewMastodonPostDeadLetter,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::mastodon_post_retry_queue::table)
+                .filter(
+                    crate::schema::mastodon_post_retry_queue::account_pair_id
+                        .eq(&new_dead_letter.account_pair_id),
+                )
+                .filter(crate::schema::mastodon_post_retry_queue::id.eq(&new_dead_letter.id))
+                .execute(db_connection)?;
+
+            diesel::delete(crate::schema::mastodon_post_dead_letters::table)
+                .filter(
+                    crate::schema::mastodon_post_dead_letters::account_pair_id
+                        .eq(&new_dead_letter.account_pair_id),
+                )
+                .filter(crate::schema::mastodon_post_dead_letters::id.eq(&new_dead_letter.id))
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::mastodon_post_dead_letters::table)
+                .values(new_dead_letter)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Move a Mastodon post from the dead letters back to the retry queue, so
+/// it's retried on the next sync.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `dead_letter` - The dead letter to requeue.
+pub fn requeue_mastodon_post_dead_letter(
+    db_connection: &mut crate::AnyConnection,
+    dead_letter: &crate::models::MastodonPostDeadLetter,
+) -> Result<(), FediProtoSyncDbError> {
+    let mut new_retry_item = crate::models::NewMastodonPostRetryQueueItem::new(
+        &dead_letter.account_pair_id,
+        &dead_letter.id,
+        &dead_letter.failure_reason,
+        ErrorClass::Transient,
+        chrono::Utc::now().naive_utc(),
+    );
+    new_retry_item.failure_class = dead_letter.failure_class.clone();
+
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::mastodon_post_dead_letters::table)
+                .filter(
+                    crate::schema::mastodon_post_dead_letters::account_pair_id
+                        .eq(&dead_letter.account_pair_id),
+                )
+                .filter(crate::schema::mastodon_post_dead_letters::id.eq(&dead_letter.id))
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::mastodon_post_retry_queue::table)
+                .values(&new_retry_item)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete a Mastodon post from the dead letters.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to delete the dead letter
+///   for.
+/// * `mastodon_post_id` - The Mastodon post ID to delete.
+pub fn delete_mastodon_post_dead_letter(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<usize, FediProtoSyncDbError> {
+    let deleted_count = diesel::delete(crate::schema::mastodon_post_dead_letters::table)
+        .filter(crate::schema::mastodon_post_dead_letters::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_post_dead_letters::id.eq(mastodon_post_id))
         .execute(db_connection)
-        .map_err(|e| FediProtoSyncDbError::DatabaseOperationError(e))?;
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(deleted_count)
+}
+
+/// Get unexpired cached metadata for a link, cached for a BlueSky account,
+/// from the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `url` - The normalized URL of the link.
+/// * `thumbnail_blob_did` - The DID of the BlueSky account.
+pub fn get_link_metadata_cache_entry(
+    db_connection: &mut crate::AnyConnection,
+    url: &str,
+    thumbnail_blob_did: &str,
+) -> Result<Option<crate::models::LinkMetadataCacheEntry>, FediProtoSyncDbError> {
+    let entry = crate::schema::link_metadata_cache::table
+        .filter(crate::schema::link_metadata_cache::url.eq(url))
+        .filter(crate::schema::link_metadata_cache::thumbnail_blob_did.eq(thumbnail_blob_did))
+        .filter(crate::schema::link_metadata_cache::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .select(crate::models::LinkMetadataCacheEntry::as_select())
+        .first::<crate::models::LinkMetadataCacheEntry>(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(entry)
+}
+
+/// Get an unexpired cached thumbnail, uploaded to a BlueSky account, by the
+/// hash of the image.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `thumbnail_hash` - The SHA-256 hash of the thumbnail image.
+/// * `thumbnail_blob_did` - The DID of the BlueSky account.
+pub fn get_link_metadata_cache_entry_by_thumbnail_hash(
+    db_connection: &mut crate::AnyConnection,
+    thumbnail_hash: &str,
+    thumbnail_blob_did: &str,
+) -> Result<Option<crate::models::LinkMetadataCacheEntry>, FediProtoSyncDbError> {
+    let entry = crate::schema::link_metadata_cache::table
+        .filter(crate::schema::link_metadata_cache::thumbnail_hash.eq(thumbnail_hash))
+        .filter(crate::schema::link_metadata_cache::thumbnail_blob_did.eq(thumbnail_blob_did))
+        .filter(crate::schema::link_metadata_cache::thumbnail_blob.is_not_null())
+        .filter(crate::schema::link_metadata_cache::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .select(crate::models::LinkMetadataCacheEntry::as_select())
+        .first::<crate::models::LinkMetadataCacheEntry>(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(entry)
+}
+
+/// Insert cached metadata for a link into the database, replacing any
+/// existing entry for the same URL and BlueSky account.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_entry` - The new entry to insert.
+pub fn upsert_link_metadata_cache_entry(
+    db_connection: &mut crate::AnyConnection,
+    new_entry: &crate::models::NewLinkMetadataCacheEntry,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::link_metadata_cache::table)
+                .filter(crate::schema::link_metadata_cache::url.eq(&new_entry.url))
+                .filter(
+                    crate::schema::link_metadata_cache::thumbnail_blob_did
+                        .eq(&new_entry.thumbnail_blob_did),
+                )
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::link_metadata_cache::table)
+                .values(new_entry)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete expired cached link metadata from the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+pub fn delete_expired_link_metadata_cache_entries(
+    db_connection: &mut crate::AnyConnection
+) -> Result<usize, FediProtoSyncDbError> {
+    let deleted_count = diesel::delete(crate::schema::link_metadata_cache::table)
+        .filter(crate::schema::link_metadata_cache::expires_at.le(chrono::Utc::now().naive_utc()))
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(deleted_count)
+}
+
+/// Get the filter decision for a Mastodon post by its ID.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the decision for.
+/// * `mastodon_post_id` - The Mastodon post ID to get.
+pub fn get_mastodon_post_filter_decision_by_post_id(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<Option<crate::models::MastodonPostFilterDecision>, FediProtoSyncDbError> {
+    let decision = crate::schema::mastodon_post_filter_decisions::table
+        .filter(crate::schema::mastodon_post_filter_decisions::account_pair_id.eq(account_pair_id))
+        .filter(
+            crate::schema::mastodon_post_filter_decisions::mastodon_post_id.eq(mastodon_post_id),
+        )
+        .select(crate::models::MastodonPostFilterDecision::as_select())
+        .first::<crate::models::MastodonPostFilterDecision>(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(decision)
+}
+
+/// Get the filter decisions for Mastodon posts with an action.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get decisions for.
+/// * `action` - The action to get decisions for.
+pub fn get_mastodon_post_filter_decisions_by_action(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    action: &str,
+) -> Result<Vec<crate::models::MastodonPostFilterDecision>, FediProtoSyncDbError> {
+    let decisions = crate::schema::mastodon_post_filter_decisions::table
+        .filter(crate::schema::mastodon_post_filter_decisions::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_post_filter_decisions::action.eq(action))
+        .order(crate::schema::mastodon_post_filter_decisions::decided_at.asc())
+        .select(crate::models::MastodonPostFilterDecision::as_select())
+        .load(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(decisions)
+}
+
+/// Insert a filter decision for a Mastodon post into the database, replacing
+/// any existing decision for the same post and account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_decision` - The new decision to insert.
+pub fn upsert_mastodon_post_filter_decision(
+    db_connection: &mut crate::AnyConnection,
+    new_decision: &crate::models::NewMastodonPostFilterDecision,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::mastodon_post_filter_decisions::table)
+                .filter(
+                    crate::schema::mastodon_post_filter_decisions::account_pair_id
+                        .eq(&new_decision.account_pair_id),
+                )
+                .filter(
+                    crate::schema::mastodon_post_filter_decisions::mastodon_post_id
+                        .eq(&new_decision.mastodon_post_id),
+                )
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::mastodon_post_filter_decisions::table)
+                .values(new_decision)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete the filter decision for a Mastodon post from the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to delete the decision
+///   for.
+/// * `mastodon_post_id` - The Mastodon post ID to delete the decision for.
+pub fn delete_mastodon_post_filter_decision(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<(), FediProtoSyncDbError> {
+    diesel::delete(crate::schema::mastodon_post_filter_decisions::table)
+        .filter(crate::schema::mastodon_post_filter_decisions::account_pair_id.eq(account_pair_id))
+        .filter(
+            crate::schema::mastodon_post_filter_decisions::mastodon_post_id.eq(mastodon_post_id),
+        )
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Get the pending Mastodon posts, oldest first.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the posts for. All
+///   pending posts are returned if `None`.
+pub fn get_mastodon_pending_posts(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: Option<&str>,
+) -> Result<Vec<crate::models::MastodonPendingPost>, FediProtoSyncDbError> {
+    let pending_posts = match account_pair_id {
+        Some(account_pair_id) => crate::schema::mastodon_pending_posts::table
+            .filter(crate::schema::mastodon_pending_posts::account_pair_id.eq(account_pair_id))
+            .order(crate::schema::mastodon_pending_posts::post_created_at.asc())
+            .select(crate::models::MastodonPendingPost::as_select())
+            .load(db_connection),
+        None => crate::schema::mastodon_pending_posts::table
+            .order(crate::schema::mastodon_pending_posts::post_created_at.asc())
+            .select(crate::models::MastodonPendingPost::as_select())
+            .load(db_connection),
+    }
+    .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(pending_posts)
+}
+
+/// Get the pending Mastodon posts that can be published, oldest first.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the posts for.
+pub fn get_publishable_mastodon_pending_posts(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+) -> Result<Vec<crate::models::MastodonPendingPost>, FediProtoSyncDbError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let pending_posts = crate::schema::mastodon_pending_posts::table
+        .filter(crate::schema::mastodon_pending_posts::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_pending_posts::publish_at.le(now))
+        .order(crate::schema::mastodon_pending_posts::post_created_at.asc())
+        .select(crate::models::MastodonPendingPost::as_select())
+        .load(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(pending_posts)
+}
+
+/// Check if a Mastodon post is pending for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to check.
+/// * `mastodon_post_id` - The Mastodon post ID to check.
+pub fn get_mastodon_pending_post_exists(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<bool, FediProtoSyncDbError> {
+    let pending_post_exists = diesel::select(diesel::dsl::exists(
+        crate::schema::mastodon_pending_posts::table
+            .filter(crate::schema::mastodon_pending_posts::account_pair_id.eq(account_pair_id))
+            .filter(crate::schema::mastodon_pending_posts::mastodon_post_id.eq(mastodon_post_id)),
+    ))
+    .get_result::<bool>(db_connection)
+    .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(pending_post_exists)
+}
+
+/// Insert a pending Mastodon post into the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_pending_post` - The new pending post to insert.
+pub fn insert_mastodon_pending_post(
+    db_connection: &mut crate::AnyConnection,
+    new_pending_post: &crate::models::NewMastodonPendingPost,
+) -> Result<(), FediProtoSyncDbError> {
+    diesel::insert_into(crate::schema::mastodon_pending_posts::table)
+        .values(new_pending_post)
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete a pending Mastodon post from the database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to delete the post for.
+/// * `mastodon_post_id` - The Mastodon post ID to delete.
+pub fn delete_mastodon_pending_post(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<(), FediProtoSyncDbError> {
+    diesel::delete(crate::schema::mastodon_pending_posts::table)
+        .filter(crate::schema::mastodon_pending_posts::account_pair_id.eq(account_pair_id))
+        .filter(crate::schema::mastodon_pending_posts::mastodon_post_id.eq(mastodon_post_id))
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Get the backfill cursor for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the cursor for.
+pub fn get_mastodon_backfill_cursor(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+) -> Result<Option<crate::models::MastodonBackfillCursor>, FediProtoSyncDbError> {
+    let backfill_cursor = crate::schema::mastodon_backfill_cursors::table
+        .filter(crate::schema::mastodon_backfill_cursors::account_pair_id.eq(account_pair_id))
+        .select(crate::models::MastodonBackfillCursor::as_select())
+        .first(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(backfill_cursor)
+}
+
+/// Insert or replace the backfill cursor for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_cursor` - The new cursor to insert.
+pub fn upsert_mastodon_backfill_cursor(
+    db_connection: &mut crate::AnyConnection,
+    new_cursor: &crate::models::NewMastodonBackfillCursor,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::mastodon_backfill_cursors::table)
+                .filter(
+                    crate::schema::mastodon_backfill_cursors::account_pair_id
+                        .eq(&new_cursor.account_pair_id),
+                )
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::mastodon_backfill_cursors::table)
+                .values(new_cursor)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Delete the backfill cursor for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to delete the cursor for.
+pub fn delete_mastodon_backfill_cursor(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+) -> Result<(), FediProtoSyncDbError> {
+    diesel::delete(crate::schema::mastodon_backfill_cursors::table)
+        .filter(crate::schema::mastodon_backfill_cursors::account_pair_id.eq(account_pair_id))
+        .execute(db_connection)
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Get the profile sync state for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the state for.
+pub fn get_mastodon_profile_sync_state(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+) -> Result<Option<crate::models::MastodonProfileSyncState>, FediProtoSyncDbError> {
+    let profile_sync_state = crate::schema::mastodon_profile_sync_states::table
+        .filter(crate::schema::mastodon_profile_sync_states::account_pair_id.eq(account_pair_id))
+        .select(crate::models::MastodonProfileSyncState::as_select())
+        .first(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(profile_sync_state)
+}
+
+/// Insert or replace the profile sync state for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_state` - The new state to insert.
+pub fn upsert_mastodon_profile_sync_state(
+    db_connection: &mut crate::AnyConnection,
+    new_state: &crate::models::NewMastodonProfileSyncState,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::mastodon_profile_sync_states::table)
+                .filter(
+                    crate::schema::mastodon_profile_sync_states::account_pair_id
+                        .eq(&new_state.account_pair_id),
+                )
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::mastodon_profile_sync_states::table)
+                .values(new_state)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(())
+}
+
+/// Get the sync state for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair to get the state for.
+pub fn get_sync_state(
+    db_connection: &mut crate::AnyConnection,
+    account_pair_id: &str,
+) -> Result<Option<crate::models::SyncState>, FediProtoSyncDbError> {
+    let sync_state = crate::schema::sync_states::table
+        .filter(crate::schema::sync_states::account_pair_id.eq(account_pair_id))
+        .select(crate::models::SyncState::as_select())
+        .first(db_connection)
+        .optional()
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
+
+    Ok(sync_state)
+}
+
+/// Insert or replace the sync state for an account pair.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `new_state` - The new state to insert.
+pub fn upsert_sync_state(
+    db_connection: &mut crate::AnyConnection,
+    new_state: &crate::models::NewSyncState,
+) -> Result<(), FediProtoSyncDbError> {
+    db_connection
+        .transaction(|db_connection| {
+            diesel::delete(crate::schema::sync_states::table)
+                .filter(crate::schema::sync_states::account_pair_id.eq(&new_state.account_pair_id))
+                .execute(db_connection)?;
+
+            diesel::insert_into(crate::schema::sync_states::table)
+                .values(new_state)
+                .execute(db_connection)
+        })
+        .map_err(FediProtoSyncDbError::DatabaseOperationError)?;
 
     Ok(())
 }
@@ -11,6 +11,8 @@ diesel::table! {
         previous_post_id -> Nullable<VarChar>,
         bsky_post_id -> Nullable<VarChar>,
         root_mastodon_post_id -> Nullable<VarChar>,
+        account_pair_id -> VarChar,
+        destination -> VarChar,
     }
 }
 
@@ -20,6 +22,8 @@ diesel::table! {
         mastodon_post_id -> VarChar,
         bsky_post_cid -> VarChar,
         bsky_post_uri -> VarChar,
+        destination -> VarChar,
+        account_pair_id -> VarChar,
     }
 }
 
@@ -31,6 +35,7 @@ diesel::table! {
         refresh_token -> Nullable<VarChar>,
         expires_in -> Nullable<Integer>,
         scopes -> Nullable<VarChar>,
+        account_pair_id -> VarChar,
     }
 }
 
@@ -42,11 +47,98 @@ diesel::table! {
 }
 
 diesel::table! {
-    mastodon_post_retry_queue (id) {
-        id -> BigInt,
-        mastodon_post_id -> VarChar,
+    mastodon_post_retry_queue (account_pair_id, id) {
+        id -> VarChar,
         failure_reason -> VarChar,
         last_retried_at -> Timestamp,
-        retry_count -> Integer
+        retry_count -> Integer,
+        account_pair_id -> VarChar,
+        next_attempt_at -> Timestamp,
+        failure_class -> VarChar,
+    }
+}
+
+diesel::table! {
+    mastodon_post_dead_letters (account_pair_id, id) {
+        id -> VarChar,
+        failure_reason -> VarChar,
+        rendered_payload -> Nullable<VarChar>,
+        attempt_count -> Integer,
+        dead_lettered_at -> Timestamp,
+        account_pair_id -> VarChar,
+        failure_class -> VarChar,
+    }
+}
+
+diesel::table! {
+    link_metadata_cache (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        url -> VarChar,
+        title -> VarChar,
+        description -> VarChar,
+        thumbnail_url -> Nullable<VarChar>,
+        thumbnail_hash -> Nullable<VarChar>,
+        thumbnail_blob -> Nullable<VarChar>,
+        thumbnail_blob_did -> VarChar,
+        cached_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mastodon_backfill_cursors (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        account_pair_id -> VarChar,
+        account_id -> VarChar,
+        range_start -> Timestamp,
+        range_end -> Timestamp,
+        last_post_id -> VarChar,
+        synced_count -> BigInt,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mastodon_pending_posts (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        mastodon_post_id -> VarChar,
+        post_created_at -> Timestamp,
+        publish_at -> Timestamp,
+        queued_at -> Timestamp,
+        account_pair_id -> VarChar,
+    }
+}
+
+diesel::table! {
+    mastodon_post_filter_decisions (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        mastodon_post_id -> VarChar,
+        action -> VarChar,
+        rule_name -> Nullable<VarChar>,
+        decided_at -> Timestamp,
+        account_pair_id -> VarChar,
+    }
+}
+
+diesel::table! {
+    mastodon_profile_sync_states (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        account_pair_id -> VarChar,
+        account_id -> VarChar,
+        text_hash -> VarChar,
+        avatar_hash -> VarChar,
+        banner_hash -> VarChar,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sync_states (id) {
+        id -> crate::type_impls::MultiBackendUuid,
+        account_pair_id -> VarChar,
+        account_id -> VarChar,
+        last_seen_post_id -> Nullable<VarChar>,
+        last_run_at -> Timestamp,
+        last_error -> Nullable<VarChar>,
     }
 }
@@ -15,15 +15,81 @@ diesel::table! {
         refresh_token -> Nullable<Text>,
         expires_in -> Nullable<Integer>,
         scopes -> Nullable<Text>,
+        account_pair_id -> Text,
     }
 }
 
 diesel::table! {
-    mastodon_post_retry_queue (id) {
-        id -> BigInt,
+    link_metadata_cache (id) {
+        id -> Text,
+        url -> Text,
+        title -> Text,
+        description -> Text,
+        thumbnail_url -> Nullable<Text>,
+        thumbnail_hash -> Nullable<Text>,
+        thumbnail_blob -> Nullable<Text>,
+        thumbnail_blob_did -> Text,
+        cached_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mastodon_backfill_cursors (id) {
+        id -> Text,
+        account_pair_id -> Text,
+        account_id -> Text,
+        range_start -> Timestamp,
+        range_end -> Timestamp,
+        last_post_id -> Text,
+        synced_count -> BigInt,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mastodon_pending_posts (id) {
+        id -> Text,
+        mastodon_post_id -> Text,
+        post_created_at -> Timestamp,
+        publish_at -> Timestamp,
+        queued_at -> Timestamp,
+        account_pair_id -> Text,
+    }
+}
+
+diesel::table! {
+    mastodon_post_filter_decisions (id) {
+        id -> Text,
+        mastodon_post_id -> Text,
+        action -> Text,
+        rule_name -> Nullable<Text>,
+        decided_at -> Timestamp,
+        account_pair_id -> Text,
+    }
+}
+
+diesel::table! {
+    mastodon_post_retry_queue (account_pair_id, id) {
+        id -> Text,
         failure_reason -> Text,
         last_retried_at -> Timestamp,
         retry_count -> Integer,
+        account_pair_id -> Text,
+        next_attempt_at -> Timestamp,
+        failure_class -> Text,
+    }
+}
+
+diesel::table! {
+    mastodon_post_dead_letters (account_pair_id, id) {
+        id -> Text,
+        failure_reason -> Text,
+        rendered_payload -> Nullable<Text>,
+        attempt_count -> Integer,
+        dead_lettered_at -> Timestamp,
+        account_pair_id -> Text,
+        failure_class -> Text,
     }
 }
 
@@ -38,6 +104,20 @@ diesel::table! {
         previous_post_id -> Nullable<Text>,
         bsky_post_id -> Nullable<Text>,
         root_mastodon_post_id -> Nullable<Text>,
+        account_pair_id -> Text,
+        destination -> Text,
+    }
+}
+
+diesel::table! {
+    mastodon_profile_sync_states (id) {
+        id -> Text,
+        account_pair_id -> Text,
+        account_id -> Text,
+        text_hash -> Text,
+        avatar_hash -> Text,
+        banner_hash -> Text,
+        updated_at -> Timestamp,
     }
 }
 
@@ -47,13 +127,33 @@ diesel::table! {
         mastodon_post_id -> Text,
         bsky_post_cid -> Text,
         bsky_post_uri -> Text,
+        destination -> Text,
+        account_pair_id -> Text,
+    }
+}
+
+diesel::table! {
+    sync_states (id) {
+        id -> Text,
+        account_pair_id -> Text,
+        account_id -> Text,
+        last_seen_post_id -> Nullable<Text>,
+        last_run_at -> Timestamp,
+        last_error -> Nullable<Text>,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
     cached_files,
     cached_service_tokens,
+    link_metadata_cache,
+    mastodon_backfill_cursors,
+    mastodon_pending_posts,
+    mastodon_post_dead_letters,
+    mastodon_post_filter_decisions,
     mastodon_post_retry_queue,
     mastodon_posts,
+    mastodon_profile_sync_states,
+    sync_states,
     synced_posts_bluesky_data,
 );
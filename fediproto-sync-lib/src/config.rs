@@ -16,6 +16,7 @@ static DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
 static TOKEN_ENCRYPTION_PRIVATE_KEY_ENV_VAR: &str = "TOKEN_ENCRYPTION_PRIVATE_KEY";
 static TOKEN_ENCRYPTION_PUBLIC_KEY_ENV_VAR: &str = "TOKEN_ENCRYPTION_PUBLIC_KEY";
 static USER_AGENT_ENV_VAR: &str = "USER_AGENT";
+static SOURCE_PLATFORM_ENV_VAR: &str = "SOURCE_PLATFORM";
 static MASTODON_SERVER_ENV_VAR: &str = "MASTODON_SERVER";
 static MASTODON_CLIENT_ID_ENV_VAR: &str = "MASTODON_CLIENT_ID";
 static MASTODON_CLIENT_SECRET_ENV_VAR: &str = "MASTODON_CLIENT_SECRET";
@@ -24,10 +25,38 @@ static BLUESKY_PDS_SERVER_ENV_VAR: &str = "BLUESKY_PDS_SERVER";
 static BLUESKY_HANDLE_ENV_VAR: &str = "BLUESKY_HANDLE";
 static BLUESKY_APP_PASSWORD_ENV_VAR: &str = "BLUESKY_APP_PASSWORD";
 static SYNC_INTERVAL_SECONDS_ENV_VAR: &str = "SYNC_INTERVAL_SECONDS";
+static MASTODON_STREAMING_ENV_VAR: &str = "MASTODON_STREAMING";
+static STREAMING_MAX_RECONNECT_DELAY_SECONDS_ENV_VAR: &str = "STREAMING_MAX_RECONNECT_DELAY_SECONDS";
+static MASTODON_WEBHOOK_SECRET_ENV_VAR: &str = "MASTODON_WEBHOOK_SECRET";
+static CATCH_UP_MAX_POSTS_ENV_VAR: &str = "CATCH_UP_MAX_POSTS";
+static RETRY_MAX_ATTEMPTS_ENV_VAR: &str = "RETRY_MAX_ATTEMPTS";
+static RETRY_INITIAL_DELAY_SECONDS_ENV_VAR: &str = "RETRY_INITIAL_DELAY_SECONDS";
+static RETRY_MAX_DELAY_SECONDS_ENV_VAR: &str = "RETRY_MAX_DELAY_SECONDS";
+static RATE_LIMIT_MAX_WAIT_SECONDS_ENV_VAR: &str = "RATE_LIMIT_MAX_WAIT_SECONDS";
+static METRICS_ADDRESS_ENV_VAR: &str = "METRICS_ADDRESS";
 static BLUESKY_VIDEO_ALWAYS_FALLBACK_ENV_VAR: &str = "BLUESKY_VIDEO_ALWAYS_FALLBACK";
 static MASTODON_ALLOW_UNLISTED_POSTS_ENV_VAR: &str = "MASTODON_ALLOW_UNLISTED_POSTS";
+static LINK_METADATA_BACKEND_ENV_VAR: &str = "LINK_METADATA_BACKEND";
+static LINK_METADATA_TIMEOUT_SECONDS_ENV_VAR: &str = "LINK_METADATA_TIMEOUT_SECONDS";
+static LINK_METADATA_MAX_SIZE_ENV_VAR: &str = "LINK_METADATA_MAX_SIZE";
+static LINK_METADATA_CACHE_TTL_SECONDS_ENV_VAR: &str = "LINK_METADATA_CACHE_TTL_SECONDS";
+static LINK_CARD_PREFERENCE_ENV_VAR: &str = "LINK_CARD_PREFERENCE";
+static LINK_CARD_OVERRIDE_MARKER_ENV_VAR: &str = "LINK_CARD_OVERRIDE_MARKER";
+static LINK_CARD_BLOCKED_DOMAINS_ENV_VAR: &str = "LINK_CARD_BLOCKED_DOMAINS";
+static POST_RULES_FILE_ENV_VAR: &str = "POST_RULES_FILE";
+static PUBLISH_DELAY_MINUTES_ENV_VAR: &str = "PUBLISH_DELAY_MINUTES";
+static REPLY_POLICY_ENV_VAR: &str = "REPLY_POLICY";
+static REPLY_HASHTAG_ENV_VAR: &str = "REPLY_HASHTAG";
+static THREAD_REPAIR_MODE_ENV_VAR: &str = "THREAD_REPAIR_MODE";
+static PROFILE_SYNC_INTERVAL_SECONDS_ENV_VAR: &str = "PROFILE_SYNC_INTERVAL_SECONDS";
+static SYNC_PINNED_POST_ENV_VAR: &str = "SYNC_PINNED_POST";
+static SYNC_PROFILE_ENV_VAR: &str = "SYNC_PROFILE";
+static ACCOUNT_PAIRS_FILE_ENV_VAR: &str = "ACCOUNT_PAIRS_FILE";
 */
 
+/// The ID of the account pair configured by the environment variables.
+pub const DEFAULT_ACCOUNT_PAIR_ID: &str = "default";
+
 /// Config values for configuring the FediProtoSync
 /// application.
 #[derive(Debug, Clone)]
@@ -72,6 +101,11 @@ pub struct FediProtoSyncConfig {
     /// **Environment variable:** `USER_AGENT`
     pub user_agent: String,
 
+    /// The platform the source server runs.
+    ///
+    /// **Environment variable:** `SOURCE_PLATFORM`
+    pub source_platform: SourcePlatform,
+
     /// The Mastodon server URL to connect to.
     ///
     /// **Environment variable:** `MASTODON_SERVER`
@@ -112,6 +146,58 @@ pub struct FediProtoSyncConfig {
     /// **Environment variable:** `SYNC_INTERVAL_SECONDS`
     pub sync_interval: std::time::Duration,
 
+    /// Whether to sync posts as soon as they're posted with the Mastodon
+    /// streaming API, polling only to catch up after the stream disconnects.
+    ///
+    /// **Environment variable:** `MASTODON_STREAMING`
+    pub mastodon_streaming: bool,
+
+    /// The longest time, in seconds, to wait before reconnecting to the
+    /// Mastodon streaming API.
+    ///
+    /// **Environment variable:** `STREAMING_MAX_RECONNECT_DELAY_SECONDS`
+    pub streaming_max_reconnect_delay: std::time::Duration,
+
+    /// The secret for verifying the signatures of Mastodon webhooks. Webhooks
+    /// are only received if it's set.
+    ///
+    /// **Environment variable:** `MASTODON_WEBHOOK_SECRET`
+    pub mastodon_webhook_secret: Option<String>,
+
+    /// The maximum number of posts to sync at once when catching up on the
+    /// posts made since the last sync.
+    ///
+    /// **Environment variable:** `CATCH_UP_MAX_POSTS`
+    pub catch_up_max_posts: usize,
+
+    /// The maximum number of times to try syncing a post before moving it to
+    /// the dead letters.
+    ///
+    /// **Environment variable:** `RETRY_MAX_ATTEMPTS`
+    pub retry_max_attempts: u32,
+
+    /// How long to wait before retrying a post that failed to sync for the
+    /// first time. The delay doubles after every failed retry.
+    ///
+    /// **Environment variable:** `RETRY_INITIAL_DELAY_SECONDS`
+    pub retry_initial_delay: std::time::Duration,
+
+    /// The longest time to wait before retrying a post.
+    ///
+    /// **Environment variable:** `RETRY_MAX_DELAY_SECONDS`
+    pub retry_max_delay: std::time::Duration,
+
+    /// The longest time to wait for a rate limit to reset before a request is
+    /// deferred.
+    ///
+    /// **Environment variable:** `RATE_LIMIT_MAX_WAIT_SECONDS`
+    pub rate_limit_max_wait: std::time::Duration,
+
+    /// The address to serve the Prometheus metrics on, if any.
+    ///
+    /// **Environment variable:** `METRICS_ADDRESS`
+    pub metrics_address: Option<String>,
+
     /// Whether to always fallback to the video URL for BlueSky posts.
     ///
     /// **Environment variable:** `BLUESKY_VIDEO_ALWAYS_FALLBACK`
@@ -121,6 +207,95 @@ pub struct FediProtoSyncConfig {
     ///
     /// **Environment variable:** `MASTODON_ALLOW_UNLISTED_POSTS`
     pub mastodon_allow_unlisted_posts: bool,
+
+    /// The backend to use for getting link metadata for link cards.
+    ///
+    /// **Environment variable:** `LINK_METADATA_BACKEND`
+    pub link_metadata_backend: LinkMetadataBackend,
+
+    /// The timeout, in seconds, for requests made to get link metadata.
+    ///
+    /// **Environment variable:** `LINK_METADATA_TIMEOUT_SECONDS`
+    pub link_metadata_timeout: std::time::Duration,
+
+    /// The maximum size, in bytes, of a page to read when getting link
+    /// metadata.
+    ///
+    /// **Environment variable:** `LINK_METADATA_MAX_SIZE`
+    pub link_metadata_max_size: u64,
+
+    /// How long, in seconds, link metadata and uploaded thumbnails are cached
+    /// for.
+    ///
+    /// **Environment variable:** `LINK_METADATA_CACHE_TTL_SECONDS`
+    pub link_metadata_cache_ttl: std::time::Duration,
+
+    /// Which link in a post to prefer for the link card.
+    ///
+    /// **Environment variable:** `LINK_CARD_PREFERENCE`
+    pub link_card_preference: LinkCardPreference,
+
+    /// The marker to put before a link in a post to use it for the link card.
+    ///
+    /// **Environment variable:** `LINK_CARD_OVERRIDE_MARKER`
+    pub link_card_override_marker: String,
+
+    /// Domains that should never be used for a link card.
+    ///
+    /// **Environment variable:** `LINK_CARD_BLOCKED_DOMAINS`
+    pub link_card_blocked_domains: Vec<String>,
+
+    /// The path to the file with rules and templates for processing posts, if
+    /// any.
+    ///
+    /// **Environment variable:** `POST_RULES_FILE`
+    pub post_rules_file: Option<std::path::PathBuf>,
+
+    /// How long, in minutes, to wait after a post is created before syncing
+    /// it. Posts are held until then so edits and deletes can be caught.
+    ///
+    /// **Environment variable:** `PUBLISH_DELAY_MINUTES`
+    pub publish_delay: std::time::Duration,
+
+    /// How replies to other accounts are synced.
+    ///
+    /// **Environment variable:** `REPLY_POLICY`
+    pub reply_policy: ReplyPolicy,
+
+    /// A hashtag that makes a reply to another account eligible for syncing,
+    /// even if it mentions someone.
+    ///
+    /// **Environment variable:** `REPLY_HASHTAG`
+    pub reply_hashtag: Option<String>,
+
+    /// How to thread a reply when the post it replies to was never synced.
+    ///
+    /// **Environment variable:** `THREAD_REPAIR_MODE`
+    pub thread_repair_mode: ThreadRepairMode,
+
+    /// The interval, in seconds, to sync the profile.
+    ///
+    /// **Environment variable:** `PROFILE_SYNC_INTERVAL_SECONDS`
+    pub profile_sync_interval: std::time::Duration,
+
+    /// Whether to mirror the pinned post on Mastodon to the BlueSky profile.
+    ///
+    /// **Environment variable:** `SYNC_PINNED_POST`
+    pub sync_pinned_post: bool,
+
+    /// Whether to sync the display name, bio, avatar, and banner from Mastodon
+    /// to the BlueSky profile.
+    ///
+    /// **Environment variable:** `SYNC_PROFILE`
+    pub sync_profile: bool,
+
+    /// The path to the file with more account pairs to sync, if any.
+    ///
+    /// **Environment variable:** `ACCOUNT_PAIRS_FILE`
+    pub account_pairs_file: Option<std::path::PathBuf>,
+
+    /// The ID of the account pair the config is for.
+    pub account_pair_id: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -132,6 +307,71 @@ pub enum FediProtoSyncMode {
     Normal,
 }
 
+/// The fediverse platform the source server runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SourcePlatform {
+    /// Detect the platform with the server's nodeinfo.
+    #[value(name = "auto")]
+    Auto,
+
+    /// Mastodon, or a fork of it like glitch-soc or Hometown.
+    #[value(name = "mastodon")]
+    Mastodon,
+
+    /// Pleroma, or a fork of it like Akkoma.
+    #[value(name = "pleroma")]
+    Pleroma,
+
+    /// Friendica.
+    #[value(name = "friendica")]
+    Friendica,
+
+    /// Firefish, or a fork of it like Iceshrimp.
+    #[value(name = "firefish")]
+    Firefish,
+
+    /// GoToSocial.
+    #[value(name = "gotosocial")]
+    GoToSocial,
+}
+
+impl SourcePlatform {
+    /// Get the name of the platform.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Mastodon => "mastodon",
+            Self::Pleroma => "pleroma",
+            Self::Friendica => "friendica",
+            Self::Firefish => "firefish",
+            Self::GoToSocial => "gotosocial",
+        }
+    }
+
+    /// Get the OAuth scopes to request from the server.
+    ///
+    /// ## Note
+    ///
+    /// Only Mastodon supports the `profile` scope, and GoToSocial and
+    /// Friendica only support the top-level `read` scope.
+    pub fn oauth_scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Auto | Self::Mastodon => &["read:statuses", "profile"],
+            Self::Pleroma | Self::Firefish => &["read:statuses", "read:accounts"],
+            Self::Friendica | Self::GoToSocial => &["read"],
+        }
+    }
+}
+
+impl std::fmt::Display for SourcePlatform {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// The type of database to use.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum DatabaseType {
@@ -144,6 +384,65 @@ pub enum DatabaseType {
     SQLite,
 }
 
+/// Which link in a post to prefer for the link card.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LinkCardPreference {
+    /// Use the first eligible link in the post.
+    #[value(name = "first")]
+    First,
+
+    /// Use the last eligible link in the post.
+    #[value(name = "last")]
+    Last,
+}
+
+/// How replies to other accounts are synced.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ReplyPolicy {
+    /// Don't sync replies to other accounts.
+    #[value(name = "skip")]
+    Skip,
+
+    /// Sync the reply as a standalone post with a link card to the post it
+    /// replies to.
+    #[value(name = "standalone")]
+    Standalone,
+
+    /// Quote the post it replies to if it's bridged to BlueSky. Otherwise,
+    /// sync it as a standalone post.
+    #[value(name = "quote")]
+    Quote,
+}
+
+/// How to thread a reply when the post it replies to was never synced.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ThreadRepairMode {
+    /// Sync the reply as a new post.
+    #[value(name = "off")]
+    Off,
+
+    /// Reply to the nearest ancestor in the thread that was synced.
+    #[value(name = "nearest")]
+    Nearest,
+
+    /// Sync the missing ancestors in the thread first, then reply to the
+    /// post it replies to.
+    #[value(name = "sync-ancestors")]
+    SyncAncestors,
+}
+
+/// The backend to use for getting link metadata.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LinkMetadataBackend {
+    /// Fetch the page and extract the metadata locally.
+    #[value(name = "local")]
+    Local,
+
+    /// Use BlueSky's "CardyB" metadata service.
+    #[value(name = "cardyb")]
+    CardyB,
+}
+
 /// Decode a Base64 string into a private key.
 ///
 /// ## Arguments
@@ -164,7 +463,7 @@ pub fn get_token_encryption_private_key(key: &str) -> Result<Rsa<Private>, FediP
 /// * `key` - A Base64 encoded string of a public key.
 pub fn get_token_encryption_public_key(key: &str) -> Result<Rsa<Public>, FediProtoSyncError> {
     let token_encryption_public_key =
-        openssl::base64::decode_block(&key).map_err(|_| FediProtoSyncError::KeyLoadError)?;
+        openssl::base64::decode_block(key).map_err(|_| FediProtoSyncError::KeyLoadError)?;
 
     openssl::rsa::Rsa::public_key_from_pem(&token_encryption_public_key)
         .map_err(|_| FediProtoSyncError::KeyLoadError)
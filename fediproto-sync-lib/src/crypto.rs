@@ -1,27 +1,67 @@
 use crate::error::FediProtoSyncError;
 
+/// The prefix of strings encrypted with a wrapped AES-256-GCM key.
+///
+/// Strings without a prefix were encrypted directly with the RSA key, which
+/// only fits short values.
+const ENCRYPTED_STRING_V2_PREFIX: &str = "v2:";
+
+/// The size of the AES-256-GCM key in bytes.
+const AES_KEY_SIZE: usize = 32;
+
+/// The size of the AES-256-GCM nonce in bytes.
+const AES_NONCE_SIZE: usize = 12;
+
+/// The size of the AES-256-GCM authentication tag in bytes.
+const AES_TAG_SIZE: usize = 16;
+
 /// Encrypts a string using the provided public key.
 ///
 /// ## Arguments
 ///
 /// * `public_key` - The public key to use for encryption.
+///
+/// ## Note
+///
+/// The string is encrypted with a random AES-256-GCM key, which is wrapped
+/// with the public key using RSA-OAEP, so strings of any length can be
+/// encrypted and the whole value is authenticated.
 pub fn encrypt_string(
     public_key: &openssl::rsa::Rsa<openssl::pkey::Public>,
     input_string: &str,
 ) -> Result<String, FediProtoSyncError> {
-    let mut encrypted_string = vec![0; public_key.size() as usize];
+    let mut aes_key = [0; AES_KEY_SIZE];
+    let mut nonce = [0; AES_NONCE_SIZE];
+    openssl::rand::rand_bytes(&mut aes_key).map_err(|_| FediProtoSyncError::EncryptionError)?;
+    openssl::rand::rand_bytes(&mut nonce).map_err(|_| FediProtoSyncError::EncryptionError)?;
 
+    let mut wrapped_key = vec![0; public_key.size() as usize];
     public_key
         .public_encrypt(
-            input_string.as_bytes(),
-            &mut encrypted_string,
-            openssl::rsa::Padding::PKCS1,
+            &aes_key,
+            &mut wrapped_key,
+            openssl::rsa::Padding::PKCS1_OAEP,
         )
         .map_err(|_| FediProtoSyncError::EncryptionError)?;
 
-    let encoded_string = openssl::base64::encode_block(&encrypted_string);
+    let mut tag = [0; AES_TAG_SIZE];
+    let ciphertext = openssl::symm::encrypt_aead(
+        openssl::symm::Cipher::aes_256_gcm(),
+        &aes_key,
+        Some(&nonce),
+        ENCRYPTED_STRING_V2_PREFIX.as_bytes(),
+        input_string.as_bytes(),
+        &mut tag,
+    )
+    .map_err(|_| FediProtoSyncError::EncryptionError)?;
 
-    Ok(encoded_string)
+    let encrypted_string = [wrapped_key.as_slice(), &nonce, &tag, &ciphertext].concat();
+
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_STRING_V2_PREFIX,
+        openssl::base64::encode_block(&encrypted_string)
+    ))
 }
 
 /// Decrypts a string using the provided private key.
@@ -29,27 +69,96 @@ pub fn encrypt_string(
 /// ## Arguments
 ///
 /// * `private_key` - The private key to use for decryption.
+///
+/// ## Note
+///
+/// Strings encrypted directly with the RSA key, before the AES-256-GCM
+/// format was added, are still decrypted.
 pub fn decrypt_string(
     private_key: &openssl::rsa::Rsa<openssl::pkey::Private>,
     input_string: &str,
 ) -> Result<String, FediProtoSyncError> {
-    let mut decrypted_string = vec![0; private_key.size() as usize];
+    let decrypted_bytes = match input_string.strip_prefix(ENCRYPTED_STRING_V2_PREFIX) {
+        Some(input_string) => decrypt_v2_bytes(private_key, input_string)?,
+        None => decrypt_v1_bytes(private_key, input_string)?,
+    };
+
+    let decrypted_string =
+        String::from_utf8(decrypted_bytes).map_err(|_| FediProtoSyncError::DecryptionError)?;
 
-    let encrypted_string = openssl::base64::decode_block(input_string)
+    Ok(decrypted_string)
+}
+
+/// Decrypts a string that was encrypted directly with the RSA key.
+///
+/// ## Arguments
+///
+/// * `private_key` - The private key to use for decryption.
+/// * `input_string` - The Base64 encoded encrypted string.
+fn decrypt_v1_bytes(
+    private_key: &openssl::rsa::Rsa<openssl::pkey::Private>,
+    input_string: &str,
+) -> Result<Vec<u8>, FediProtoSyncError> {
+    let mut decrypted_bytes = vec![0; private_key.size() as usize];
+
+    let encrypted_bytes = openssl::base64::decode_block(input_string)
         .map_err(|_| FediProtoSyncError::DecryptionError)?;
 
     let decrypted_length = private_key
         .private_decrypt(
-            &encrypted_string,
-            &mut decrypted_string,
+            &encrypted_bytes,
+            &mut decrypted_bytes,
             openssl::rsa::Padding::PKCS1,
         )
         .map_err(|_| FediProtoSyncError::DecryptionError)?;
 
-    let decrypted_string = String::from_utf8(decrypted_string[..decrypted_length].to_vec())
+    decrypted_bytes.truncate(decrypted_length);
+
+    Ok(decrypted_bytes)
+}
+
+/// Decrypts a string that was encrypted with a wrapped AES-256-GCM key.
+///
+/// ## Arguments
+///
+/// * `private_key` - The private key to use for decryption.
+/// * `input_string` - The Base64 encoded encrypted string, without the
+///   prefix.
+fn decrypt_v2_bytes(
+    private_key: &openssl::rsa::Rsa<openssl::pkey::Private>,
+    input_string: &str,
+) -> Result<Vec<u8>, FediProtoSyncError> {
+    let key_size = private_key.size() as usize;
+
+    let encrypted_bytes = openssl::base64::decode_block(input_string)
         .map_err(|_| FediProtoSyncError::DecryptionError)?;
 
-    Ok(decrypted_string)
+    if encrypted_bytes.len() < key_size + AES_NONCE_SIZE + AES_TAG_SIZE {
+        return Err(FediProtoSyncError::DecryptionError);
+    }
+
+    let (wrapped_key, encrypted_bytes) = encrypted_bytes.split_at(key_size);
+    let (nonce, encrypted_bytes) = encrypted_bytes.split_at(AES_NONCE_SIZE);
+    let (tag, ciphertext) = encrypted_bytes.split_at(AES_TAG_SIZE);
+
+    let mut aes_key = vec![0; key_size];
+    let aes_key_length = private_key
+        .private_decrypt(wrapped_key, &mut aes_key, openssl::rsa::Padding::PKCS1_OAEP)
+        .map_err(|_| FediProtoSyncError::DecryptionError)?;
+
+    if aes_key_length != AES_KEY_SIZE {
+        return Err(FediProtoSyncError::DecryptionError);
+    }
+
+    openssl::symm::decrypt_aead(
+        openssl::symm::Cipher::aes_256_gcm(),
+        &aes_key[..AES_KEY_SIZE],
+        Some(nonce),
+        ENCRYPTED_STRING_V2_PREFIX.as_bytes(),
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| FediProtoSyncError::DecryptionError)
 }
 
 /// Generate a new token encryption key pair.
@@ -74,3 +183,67 @@ pub fn generate_token_encryption_key() -> Result<String, FediProtoSyncError> {
 
     Ok(output_string)
 }
+
+/// Generate a hex encoded SHA-256 hash of some data.
+///
+/// ## Arguments
+///
+/// * `data` - The data to hash.
+pub fn sha256_hex(data: &[u8]) -> String {
+    openssl::sha::sha256(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Generate a hex encoded HMAC-SHA256 signature of some data.
+///
+/// ## Arguments
+///
+/// * `key` - The secret key to sign with.
+/// * `data` - The data to sign.
+pub fn hmac_sha256_hex(
+    key: &[u8],
+    data: &[u8],
+) -> Result<String, FediProtoSyncError> {
+    let key = openssl::pkey::PKey::hmac(key).map_err(|_| FediProtoSyncError::SigningError)?;
+
+    let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &key)
+        .map_err(|_| FediProtoSyncError::SigningError)?;
+
+    let signature = signer
+        .sign_oneshot_to_vec(data)
+        .map_err(|_| FediProtoSyncError::SigningError)?;
+
+    Ok(signature
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Verify a hex encoded HMAC-SHA256 signature of some data.
+///
+/// ## Arguments
+///
+/// * `key` - The secret key the data was signed with.
+/// * `data` - The data that was signed.
+/// * `signature` - The hex encoded signature to verify.
+///
+/// ## Note
+///
+/// The signatures are compared in constant time.
+pub fn verify_hmac_sha256_hex(
+    key: &[u8],
+    data: &[u8],
+    signature: &str,
+) -> bool {
+    let expected_signature = match hmac_sha256_hex(key, data) {
+        Ok(expected_signature) => expected_signature,
+        Err(_) => return false,
+    };
+
+    let signature = signature.to_lowercase();
+
+    expected_signature.len() == signature.len()
+        && openssl::memcmp::eq(expected_signature.as_bytes(), signature.as_bytes())
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use thiserror::Error;
 
 /// Error value for FediProtoSync.
@@ -20,6 +22,14 @@ pub enum FediProtoSyncError {
     #[error("DB connection pool error.")]
     DatabaseConnectionPoolError,
 
+    /// A database operation failed.
+    #[error("Database operation failed.")]
+    DatabaseError {
+        /// The error returned by the database.
+        #[source]
+        source: Arc<dyn std::error::Error + Send + Sync>,
+    },
+
     /// An invalid database type was specified.
     #[error("Invalid database type.")]
     InvalidDatabaseType,
@@ -38,6 +48,10 @@ pub enum FediProtoSyncError {
     #[error("Failed to decrypt value.")]
     DecryptionError,
 
+    /// An error occurred while signing a value.
+    #[error("Failed to sign value.")]
+    SigningError,
+
     /// An error occurred while creating a HTTP client.
     #[error("Failed to create HTTP client.")]
     HttpClientCreationError,
@@ -57,6 +71,178 @@ pub enum FediProtoSyncError {
     /// An error occurred while trying to compress an image.
     #[error("Failed to compress image.")]
     ImageCompressionError,
+
+    /// A HTTP request to a service failed.
+    #[error("HTTP request to {service} failed{}.", format_status(.status))]
+    HttpError {
+        /// The service the request was sent to.
+        service: AuthenticationSource,
+
+        /// The HTTP status of the response, if a response was received.
+        status: Option<u16>,
+
+        /// The error returned by the HTTP client.
+        #[source]
+        source: Arc<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// An XRPC request to BlueSky failed.
+    #[error(
+        "XRPC request to BlueSky failed{}{}.",
+        format_status(.status),
+        format_xrpc_error(.error_name, .message)
+    )]
+    XrpcError {
+        /// The HTTP status of the response, if a response was received.
+        status: Option<u16>,
+
+        /// The name of the XRPC error, like `InvalidRequest`.
+        error_name: Option<String>,
+
+        /// The message of the XRPC error.
+        message: Option<String>,
+
+        /// The error returned by the XRPC client.
+        #[source]
+        source: Arc<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A service returned a response that can't be used, like a response
+    /// missing the result of a write.
+    #[error("Unexpected response from {service}: {message}")]
+    UnexpectedResponseError {
+        /// The service the response was returned by.
+        service: AuthenticationSource,
+
+        /// What was unexpected about the response.
+        message: String,
+    },
+
+    /// The rate limit for a host is exhausted, and it resets later than the
+    /// longest time to wait for it.
+    #[error("Rate limit for '{host}' is exhausted, it resets in {retry_after_seconds} seconds.")]
+    RateLimitError {
+        /// The host the rate limit is for.
+        host: String,
+
+        /// The number of seconds until the rate limit resets.
+        retry_after_seconds: u64,
+    },
+}
+
+impl FediProtoSyncError {
+    /// Classify the error, to decide if the action that failed should be
+    /// tried again.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            FediProtoSyncError::AuthenticationError(_) => ErrorClass::Auth,
+
+            FediProtoSyncError::ImageCompressionError => ErrorClass::Permanent,
+
+            FediProtoSyncError::UnexpectedResponseError { .. } => ErrorClass::Permanent,
+
+            FediProtoSyncError::RateLimitError { .. } => ErrorClass::Transient,
+
+            FediProtoSyncError::HttpError { status, .. } => ErrorClass::from_http_status(*status),
+
+            FediProtoSyncError::XrpcError {
+                status, error_name, ..
+            } => match error_name.as_deref() {
+                Some(
+                    "AuthRequired"
+                    | "AuthenticationRequired"
+                    | "ExpiredToken"
+                    | "InvalidToken"
+                    | "AccountTakedown"
+                    | "AccountDeactivated",
+                ) => ErrorClass::Auth,
+                Some("RateLimitExceeded") => ErrorClass::Transient,
+                _ => ErrorClass::from_http_status(*status),
+            },
+
+            _ => ErrorClass::Transient,
+        }
+    }
+}
+
+/// The class of an error, used to decide if the action that failed should be
+/// tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The error is expected to go away on its own, like a timeout or a `502`.
+    Transient,
+
+    /// The error will happen again no matter how often it's retried, like an
+    /// invalid record.
+    Permanent,
+
+    /// The credentials for a service are invalid or expired.
+    Auth,
+}
+
+impl ErrorClass {
+    /// Get the name of the class, as it's stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Transient => "transient",
+            ErrorClass::Permanent => "permanent",
+            ErrorClass::Auth => "auth",
+        }
+    }
+
+    /// Classify a failed HTTP request by the status of its response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The HTTP status of the response, if a response was
+    ///   received.
+    pub fn from_http_status(status: Option<u16>) -> Self {
+        match status {
+            Some(401 | 403) => ErrorClass::Auth,
+            Some(408 | 429) => ErrorClass::Transient,
+            Some(400..=499) => ErrorClass::Permanent,
+            _ => ErrorClass::Transient,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorClass {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Format the HTTP status of a failed request for an error message.
+///
+/// ## Arguments
+///
+/// * `status` - The HTTP status of the response, if a response was received.
+fn format_status(status: &Option<u16>) -> String {
+    match status {
+        Some(status) => format!(" with status {}", status),
+        None => String::new(),
+    }
+}
+
+/// Format the name and message of an XRPC error for an error message.
+///
+/// ## Arguments
+///
+/// * `error_name` - The name of the XRPC error.
+/// * `message` - The message of the XRPC error.
+fn format_xrpc_error(
+    error_name: &Option<String>,
+    message: &Option<String>,
+) -> String {
+    match (error_name, message) {
+        (Some(error_name), Some(message)) => format!(": {} ({})", error_name, message),
+        (Some(error_name), None) => format!(": {}", error_name),
+        (None, Some(message)) => format!(": {}", message),
+        (None, None) => String::new(),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use rstest::*;
+
+use crate::error::*;
+
+/// Create an XRPC error.
+///
+/// ## Arguments
+///
+/// * `status` - The HTTP status of the response.
+/// * `error_name` - The name of the XRPC error.
+fn xrpc_error(
+    status: Option<u16>,
+    error_name: Option<&str>,
+) -> FediProtoSyncError {
+    FediProtoSyncError::XrpcError {
+        status,
+        error_name: error_name.map(|error_name| error_name.to_string()),
+        message: None,
+        source: Arc::new(std::io::Error::other("XRPC error")),
+    }
+}
+
+/// Tests to ensure failed HTTP requests are classified by their status.
+#[rstest]
+#[case(None, ErrorClass::Transient)]
+#[case(Some(400), ErrorClass::Permanent)]
+#[case(Some(401), ErrorClass::Auth)]
+#[case(Some(403), ErrorClass::Auth)]
+#[case(Some(404), ErrorClass::Permanent)]
+#[case(Some(408), ErrorClass::Transient)]
+#[case(Some(429), ErrorClass::Transient)]
+#[case(Some(500), ErrorClass::Transient)]
+#[case(Some(502), ErrorClass::Transient)]
+fn http_error__class(
+    #[case] status: Option<u16>,
+    #[case] expected_class: ErrorClass,
+) {
+    let error = FediProtoSyncError::HttpError {
+        service: AuthenticationSource::BlueSky,
+        status,
+        source: Arc::new(std::io::Error::other("HTTP error")),
+    };
+
+    assert_eq!(error.class(), expected_class);
+}
+
+/// Tests to ensure XRPC errors are classified by their name, and by their
+/// status when the name isn't known.
+#[rstest]
+#[case(Some(400), Some("InvalidRequest"), ErrorClass::Permanent)]
+#[case(Some(400), Some("ExpiredToken"), ErrorClass::Auth)]
+#[case(Some(400), Some("AccountTakedown"), ErrorClass::Auth)]
+#[case(Some(429), Some("RateLimitExceeded"), ErrorClass::Transient)]
+#[case(Some(502), None, ErrorClass::Transient)]
+#[case(None, None, ErrorClass::Transient)]
+fn xrpc_error__class(
+    #[case] status: Option<u16>,
+    #[case] error_name: Option<&str>,
+    #[case] expected_class: ErrorClass,
+) {
+    assert_eq!(xrpc_error(status, error_name).class(), expected_class);
+}
+
+/// Tests to ensure XRPC errors include the status and the name of the error
+/// in their message.
+#[rstest]
+fn xrpc_error__message() {
+    let error = FediProtoSyncError::XrpcError {
+        status: Some(400),
+        error_name: Some("InvalidRequest".to_string()),
+        message: Some("Record is invalid".to_string()),
+        source: Arc::new(std::io::Error::other("XRPC error")),
+    };
+
+    assert_eq!(
+        error.to_string(),
+        "XRPC request to BlueSky failed with status 400: InvalidRequest (Record is invalid)."
+    );
+}
+
+/// Tests to ensure other errors are classified.
+#[rstest]
+#[case(
+    FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon),
+    ErrorClass::Auth
+)]
+#[case(FediProtoSyncError::ImageCompressionError, ErrorClass::Permanent)]
+#[case(
+    FediProtoSyncError::UnexpectedResponseError {
+        service: AuthenticationSource::BlueSky,
+        message: "No results".to_string(),
+    },
+    ErrorClass::Permanent
+)]
+#[case(FediProtoSyncError::VideoUploadError, ErrorClass::Transient)]
+#[case(FediProtoSyncError::DatabaseConnectionPoolError, ErrorClass::Transient)]
+fn other_error__class(
+    #[case] error: FediProtoSyncError,
+    #[case] expected_class: ErrorClass,
+) {
+    assert_eq!(error.class(), expected_class);
+}
@@ -1,5 +1,8 @@
 /// Tests for `fediproto_sync_lib::crypto`.
 mod crypto;
 
+/// Tests for `fediproto_sync_lib::error`.
+mod error;
+
 /// Tests for `fediproto_sync_lib::utils`.
 mod utils;
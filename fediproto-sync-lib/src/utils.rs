@@ -14,9 +14,9 @@ pub fn new_random_file_name(
 
     let random_string = rand::distr::Alphanumeric.sample_string(rng, length);
 
-    return match file_extension {
+    match file_extension {
         Some(ext) => format!("{}.{}", random_string, ext.trim_start_matches('.')),
 
         None => random_string,
-    };
+    }
 }
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use fediproto_sync_lib::config::{DEFAULT_ACCOUNT_PAIR_ID, FediProtoSyncConfig, SourcePlatform};
+use serde::Deserialize;
+
+/// The account pairs to sync, loaded from the account pairs file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AccountPairs {
+    /// The account pairs.
+    #[serde(rename = "pair")]
+    pub pairs: Vec<AccountPair>,
+}
+
+/// A Mastodon account and the BlueSky account it's synced to.
+///
+/// Values that aren't set are taken from the environment variables.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AccountPair {
+    /// The unique ID of the account pair.
+    pub id: String,
+
+    /// The platform the source server runs, like `pleroma` or `auto`.
+    #[serde(default, deserialize_with = "deserialize_source_platform")]
+    pub source_platform: Option<SourcePlatform>,
+
+    /// The Mastodon server URL to connect to.
+    pub mastodon_server: Option<String>,
+
+    /// The client ID for the Mastodon application.
+    pub mastodon_client_id: Option<String>,
+
+    /// The client secret for the Mastodon application.
+    pub mastodon_client_secret: Option<String>,
+
+    /// The BlueSky PDS URL to connect to.
+    pub bluesky_pds_server: Option<String>,
+
+    /// The BlueSky handle to use for authentication.
+    pub bluesky_handle: String,
+
+    /// The BlueSky app password to use for authentication.
+    pub bluesky_app_password: String,
+
+    /// The interval, in seconds, to sync posts.
+    pub sync_interval_seconds: Option<u64>,
+
+    /// Whether to sync posts as soon as they're posted with the Mastodon
+    /// streaming API.
+    pub mastodon_streaming: Option<bool>,
+
+    /// The secret for verifying the signatures of Mastodon webhooks.
+    pub mastodon_webhook_secret: Option<String>,
+
+    /// The maximum number of posts to sync at once when catching up on the
+    /// posts made since the last sync.
+    pub catch_up_max_posts: Option<usize>,
+
+    /// Whether to allow unlisted posts from Mastodon to sync to BlueSky.
+    pub mastodon_allow_unlisted_posts: Option<bool>,
+
+    /// The path to the file with rules and templates for processing posts.
+    pub post_rules_file: Option<std::path::PathBuf>,
+
+    /// How long, in minutes, to wait after a post is created before syncing
+    /// it.
+    pub publish_delay_minutes: Option<u64>,
+}
+
+impl AccountPairs {
+    /// Load the account pairs from a TOML file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the account pairs file, if any.
+    ///
+    /// ## Note
+    ///
+    /// If no path is provided, no account pairs are returned.
+    pub fn load(path: Option<&std::path::Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+
+        let pairs_file = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read account pairs file '{}'.", path.display()))?;
+
+        let account_pairs = Self::from_toml_str(&pairs_file)
+            .with_context(|| format!("Failed to parse account pairs file '{}'.", path.display()))?;
+
+        tracing::info!(
+            "Loaded '{}' account pairs from '{}'.",
+            account_pairs.pairs.len(),
+            path.display()
+        );
+
+        Ok(account_pairs)
+    }
+
+    /// Parse the account pairs from a TOML string.
+    ///
+    /// ## Arguments
+    ///
+    /// * `pairs` - The TOML string to parse.
+    ///
+    /// ## Note
+    ///
+    /// Every pair needs a unique ID, and `default` is reserved for the pair
+    /// configured by the environment variables.
+    pub fn from_toml_str(pairs: &str) -> Result<Self> {
+        let account_pairs = toml::from_str::<Self>(pairs)?;
+
+        let mut pair_ids = HashSet::from([DEFAULT_ACCOUNT_PAIR_ID]);
+
+        for pair in &account_pairs.pairs {
+            if pair.id.trim().is_empty() {
+                return Err(anyhow::anyhow!("Account pair IDs can't be empty."));
+            }
+
+            if !pair_ids.insert(pair.id.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Account pair ID '{}' is used more than once.",
+                    pair.id
+                ));
+            }
+        }
+
+        Ok(account_pairs)
+    }
+
+    /// Get the config for every account pair to sync.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config from the environment variables.
+    ///
+    /// ## Note
+    ///
+    /// The pair configured by the environment variables is always first.
+    pub fn configs(
+        &self,
+        config: &FediProtoSyncConfig,
+    ) -> Vec<FediProtoSyncConfig> {
+        std::iter::once(config.clone())
+            .chain(self.pairs.iter().map(|pair| pair.config(config)))
+            .collect()
+    }
+}
+
+impl AccountPair {
+    /// Get the config for the account pair.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config from the environment variables.
+    pub fn config(
+        &self,
+        config: &FediProtoSyncConfig,
+    ) -> FediProtoSyncConfig {
+        let mut pair_config = config.clone();
+
+        pair_config.account_pair_id = self.id.clone();
+        pair_config.bluesky_handle = self.bluesky_handle.clone();
+        pair_config.bluesky_app_password = self.bluesky_app_password.clone();
+
+        if let Some(source_platform) = self.source_platform {
+            pair_config.source_platform = source_platform;
+        }
+
+        if let Some(mastodon_server) = &self.mastodon_server {
+            pair_config.mastodon_server = mastodon_server.clone();
+        }
+
+        if let Some(mastodon_client_id) = &self.mastodon_client_id {
+            pair_config.mastodon_client_id = mastodon_client_id.clone();
+        }
+
+        if let Some(mastodon_client_secret) = &self.mastodon_client_secret {
+            pair_config.mastodon_client_secret = mastodon_client_secret.clone();
+        }
+
+        if let Some(bluesky_pds_server) = &self.bluesky_pds_server {
+            pair_config.bluesky_pds_server = bluesky_pds_server.clone();
+        }
+
+        if let Some(sync_interval_seconds) = self.sync_interval_seconds {
+            pair_config.sync_interval = std::time::Duration::from_secs(sync_interval_seconds);
+        }
+
+        if let Some(mastodon_streaming) = self.mastodon_streaming {
+            pair_config.mastodon_streaming = mastodon_streaming;
+        }
+
+        if let Some(mastodon_webhook_secret) = &self.mastodon_webhook_secret {
+            pair_config.mastodon_webhook_secret = Some(mastodon_webhook_secret.clone());
+        }
+
+        if let Some(catch_up_max_posts) = self.catch_up_max_posts {
+            pair_config.catch_up_max_posts = catch_up_max_posts;
+        }
+
+        if let Some(mastodon_allow_unlisted_posts) = self.mastodon_allow_unlisted_posts {
+            pair_config.mastodon_allow_unlisted_posts = mastodon_allow_unlisted_posts;
+        }
+
+        if let Some(post_rules_file) = &self.post_rules_file {
+            pair_config.post_rules_file = Some(post_rules_file.clone());
+        }
+
+        if let Some(publish_delay_minutes) = self.publish_delay_minutes {
+            pair_config.publish_delay = std::time::Duration::from_secs(publish_delay_minutes * 60);
+        }
+
+        pair_config
+    }
+}
+
+/// Deserialize the name of a source platform.
+///
+/// ## Arguments
+///
+/// * `deserializer` - The deserializer.
+fn deserialize_source_platform<'de, D>(deserializer: D) -> Result<Option<SourcePlatform>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let source_platform = Option::<String>::deserialize(deserializer)?;
+
+    match source_platform {
+        Some(source_platform) => SourcePlatform::from_str(&source_platform, true)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+/// Load the config for every account pair to sync.
+///
+/// ## Arguments
+///
+/// * `config` - The config from the environment variables.
+pub fn load_account_pair_configs(config: &FediProtoSyncConfig) -> Result<Vec<FediProtoSyncConfig>> {
+    let account_pairs = AccountPairs::load(config.account_pairs_file.as_deref())?;
+
+    Ok(account_pairs.configs(config))
+}
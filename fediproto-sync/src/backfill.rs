@@ -0,0 +1,302 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::r2d2::{ConnectionManager, Pool};
+use fediproto_sync_db::{AnyConnection, models::NewMastodonBackfillCursor};
+use fediproto_sync_lib::{
+    config::{FediProtoSyncConfig, ReplyPolicy},
+    error::{AuthenticationSource, FediProtoSyncError},
+};
+
+use crate::{
+    core::{
+        SocialMediaClients, apply_filter_decision, decide_filter_action, sync_post_or_queue_retry,
+    },
+    mastodon::MastodonApiExtensions,
+    rules::{PostRules, filter::FilterAction},
+};
+
+/// Options for backfilling older posts to BlueSky.
+#[derive(Debug, Clone)]
+pub struct BackfillOptions {
+    /// The start of the date range to backfill.
+    pub since: DateTime<Utc>,
+
+    /// The end of the date range to backfill. Posts created at this time are
+    /// not included.
+    pub until: DateTime<Utc>,
+
+    /// The maximum number of posts to sync per hour. `0` disables the limit.
+    pub max_posts_per_hour: u32,
+
+    /// Whether to only list the posts that would be synced.
+    pub dry_run: bool,
+}
+
+impl BackfillOptions {
+    /// Get how long to wait between synced posts to stay under the hourly
+    /// limit.
+    pub fn post_interval(&self) -> std::time::Duration {
+        match self.max_posts_per_hour {
+            0 => std::time::Duration::ZERO,
+            max_posts_per_hour => {
+                std::time::Duration::from_secs_f64(3600.0 / max_posts_per_hour as f64)
+            }
+        }
+    }
+}
+
+/// Backfill the posts from a date range to BlueSky, oldest first.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `options` - The options for the backfill.
+///
+/// ## Note
+///
+/// Progress is saved to a cursor after each post, so running the backfill
+/// again with the same date range resumes where it stopped. Posts that were
+/// already synced are skipped.
+pub async fn run_backfill(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    options: &BackfillOptions,
+) -> Result<()> {
+    let post_rules = Arc::new(PostRules::load(config.post_rules_file.as_deref())?);
+
+    let social_media_clients =
+        SocialMediaClients::new(config, &db_connection_pool, &post_rules).await?;
+    let social_media_clients = Arc::new(social_media_clients);
+
+    let mastodon_account = social_media_clients
+        .mastodon_rate_limiter
+        .send(
+            social_media_clients
+                .mastodon_client
+                .verify_account_credentials(),
+        )
+        .await
+        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?
+        .json;
+
+    let range_start = options.since.naive_utc();
+    let range_end = options.until.naive_utc();
+
+    // Resume from the cursor if the previous backfill was for the same
+    // account and date range.
+    let backfill_cursor = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_mastodon_backfill_cursor(
+            db_connection,
+            &config.account_pair_id,
+        )?
+    };
+    let backfill_cursor = backfill_cursor.filter(|backfill_cursor| {
+        let is_same_range = backfill_cursor.account_id == mastodon_account.id
+            && backfill_cursor.range_start == range_start
+            && backfill_cursor.range_end == range_end;
+
+        if !is_same_range {
+            tracing::info!(
+                "Previous backfill was for a different account or date range, starting over."
+            );
+        }
+
+        is_same_range
+    });
+
+    let mut synced_count = backfill_cursor
+        .as_ref()
+        .map(|backfill_cursor| backfill_cursor.synced_count)
+        .unwrap_or(0);
+
+    if let Some(backfill_cursor) = &backfill_cursor {
+        tracing::info!(
+            "Resuming backfill after post '{}', '{}' posts synced so far.",
+            backfill_cursor.last_post_id,
+            backfill_cursor.synced_count
+        );
+    }
+
+    tracing::info!(
+        "Getting posts from '{}' to '{}' from Mastodon...",
+        options.since,
+        options.until
+    );
+
+    let posts_in_range = social_media_clients
+        .mastodon_client
+        .get_posts_in_range(
+            &social_media_clients.mastodon_rate_limiter,
+            &mastodon_account.id,
+            options.since,
+            options.until,
+            config.mastodon_allow_unlisted_posts,
+            config.reply_policy != ReplyPolicy::Skip,
+        )
+        .await?;
+
+    let posts_to_backfill = posts_after_cursor(
+        posts_in_range,
+        backfill_cursor
+            .as_ref()
+            .map(|backfill_cursor| backfill_cursor.last_post_id.as_str()),
+    );
+
+    tracing::info!("Found '{}' posts to backfill.", posts_to_backfill.len());
+
+    let post_interval = options.post_interval();
+    let mut synced_this_run = 0;
+
+    for post_item in &posts_to_backfill {
+        // Connections are only taken for each query, since syncing the posts
+        // takes connections of its own.
+        let is_synced = {
+            let db_connection = &mut db_connection_pool.get()?;
+
+            !fediproto_sync_db::operations::get_synced_mastodon_post_destinations(
+                db_connection,
+                &config.account_pair_id,
+                &post_item.id,
+            )?
+            .is_empty()
+        };
+
+        if is_synced {
+            tracing::info!("Post '{}' was already synced, skipping.", post_item.id);
+        } else if options.dry_run {
+            let filter_decision =
+                decide_filter_action(config, &post_rules, post_item, &mastodon_account);
+
+            println!(
+                "{} {} {:<5} {}",
+                post_item.created_at.format("%Y-%m-%d %H:%M:%S"),
+                post_item.id,
+                filter_decision.action.as_str(),
+                post_item.url.as_deref().unwrap_or(&post_item.uri)
+            );
+
+            if filter_decision.action == FilterAction::Sync {
+                synced_this_run += 1;
+            }
+        } else {
+            let filter_decision =
+                decide_filter_action(config, &post_rules, post_item, &mastodon_account);
+            let should_sync = {
+                let db_connection = &mut db_connection_pool.get()?;
+
+                apply_filter_decision(
+                    db_connection,
+                    &config.account_pair_id,
+                    post_item,
+                    &filter_decision,
+                )?
+            };
+
+            if should_sync {
+                // Wait between posts to stay under the hourly limit.
+                if synced_this_run > 0 && !post_interval.is_zero() {
+                    tracing::info!(
+                        "Waiting '{}' seconds before syncing the next post.",
+                        post_interval.as_secs()
+                    );
+
+                    tokio::time::sleep(post_interval).await;
+                }
+
+                tracing::info!("Backfilling post '{}'", post_item.id);
+
+                sync_post_or_queue_retry(
+                    post_item,
+                    config,
+                    &db_connection_pool,
+                    social_media_clients.clone(),
+                    &mastodon_account,
+                    post_rules.clone(),
+                )
+                .await?;
+
+                synced_count += 1;
+                synced_this_run += 1;
+            }
+        }
+
+        if !options.dry_run {
+            let new_backfill_cursor = NewMastodonBackfillCursor::new(
+                &config.account_pair_id,
+                &mastodon_account.id,
+                range_start,
+                range_end,
+                &post_item.id,
+                synced_count,
+            );
+
+            let db_connection = &mut db_connection_pool.get()?;
+
+            fediproto_sync_db::operations::upsert_mastodon_backfill_cursor(
+                db_connection,
+                &new_backfill_cursor,
+            )?;
+        }
+    }
+
+    match options.dry_run {
+        true => println!(
+            "\n{} of {} post(s) would be synced.",
+            synced_this_run,
+            posts_to_backfill.len()
+        ),
+        false => tracing::info!(
+            "Backfill completed, '{}' posts synced in total.",
+            synced_count
+        ),
+    }
+
+    Ok(())
+}
+
+/// Get the posts that come after the post a backfill cursor is at.
+///
+/// ## Arguments
+///
+/// * `posts` - The posts to backfill, oldest first.
+/// * `last_post_id` - The ID of the last post processed by the backfill, if
+///   any.
+pub fn posts_after_cursor(
+    posts: Vec<megalodon::entities::Status>,
+    last_post_id: Option<&str>,
+) -> Vec<megalodon::entities::Status> {
+    let last_post_id = match last_post_id {
+        Some(last_post_id) => last_post_id,
+        None => return posts,
+    };
+
+    posts
+        .into_iter()
+        .filter(|post| compare_post_ids(&post.id, last_post_id) == Ordering::Greater)
+        .collect()
+}
+
+/// Compare two Mastodon post IDs.
+///
+/// ## Arguments
+///
+/// * `a` - The first post ID.
+/// * `b` - The second post ID.
+///
+/// ## Note
+///
+/// Mastodon and Friendica post IDs are numeric strings that increase over
+/// time, so longer IDs are always newer. The IDs of Pleroma, Firefish and
+/// GoToSocial posts have a fixed length and sort by the time they were
+/// created, so they're compared as strings.
+pub fn compare_post_ids(
+    a: &str,
+    b: &str,
+) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
@@ -1,8 +1,8 @@
-use std::{num::NonZero, str::FromStr};
+use std::{num::NonZero, pin::Pin, str::FromStr, sync::Arc};
 
 use anyhow::Result;
 use atrium_api::{
-    agent::atp_agent::{AtpAgent, store::MemorySessionStore},
+    agent::atp_agent::AtpAgent,
     app::{
         self,
         bsky::{
@@ -13,17 +13,19 @@ use atrium_api::{
     com,
     types::{
         CidLink, Object, TryIntoUnknown, Union,
-        string::{Cid, Datetime, Did, Nsid},
+        string::{AtIdentifier, Cid, Datetime, Did, Nsid, RecordKey},
     },
 };
-use atrium_xrpc_client::reqwest::ReqwestClient;
 use diesel::r2d2::{ConnectionManager, Pool};
 use fediproto_sync_db::{
     AnyConnection,
-    models::{NewCachedFile, NewMastodonPost, NewSyncedPostBlueSkyData},
+    models::{NewCachedFile, NewLinkMetadataCacheEntry, NewMastodonPost, NewSyncedPostBlueSkyData},
 };
 use fediproto_sync_lib::{
-    config::FediProtoSyncConfig, error::FediProtoSyncError, utils::new_random_file_name,
+    config::{FediProtoSyncConfig, ReplyPolicy, SourcePlatform, ThreadRepairMode},
+    crypto::sha256_hex,
+    error::{AuthenticationSource, FediProtoSyncError},
+    utils::new_random_file_name,
 };
 use ipld_core::ipld::Ipld;
 use reqwest::header::CONTENT_TYPE;
@@ -31,9 +33,28 @@ use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 
 use crate::{
-    core::create_http_client, img_utils::ImageAttachmentData, mastodon::ParsedMastodonPost,
+    bsky_session::DbSessionStore,
+    core::create_http_client,
+    img_utils::ImageAttachmentData,
+    link_metadata::{
+        LinkMetadata, LinkMetadataClient, LinkMetadataProvider, card_matches_url, normalize_url,
+        selection::LinkSelectionPolicy,
+    },
+    mastodon::{ParsedMastodonPost, is_syncable_visibility},
+    rate_limit::{MastodonRateLimiter, RateLimitedClient, send_rate_limited},
+    replies::{ReplyFilter, ThreadRepairPlan, bridged_handle, is_bridged_copy_of},
+    rules::{
+        PostRules,
+        filter::{FilterAction, FilterDecision},
+        route::RouteDecision,
+    },
+    sync_error::from_xrpc_error,
+    templates::{TemplateContext, TemplateSet},
 };
 
+/// The characters of the base32 alphabet used by TIDs.
+const TID_ALPHABET: &[u8; 32] = b"234567abcdefghijklmnopqrstuvwxyz";
+
 /// The maximum duration for a BlueSky video in seconds.
 ///
 /// (Currently `60` seconds)
@@ -50,11 +71,18 @@ pub const MAX_IMAGE_SIZE: u64 = 950_000;
 /// (Currently `50 MB`)
 pub const MAX_VIDEO_SIZE: u64 = 50_000_000;
 
+/// The name of the destination for the BlueSky account of the account pair,
+/// used to select transform and route rules.
+pub const BLUESKY_DESTINATION: &str = "bluesky";
+
 /// Holds config data for syncing a single post.
 pub struct BlueSkyPostSyncConfig {
     /// The environment variables for the FediProto Sync application.
     pub config: FediProtoSyncConfig,
 
+    /// The name of the BlueSky destination the post is synced to.
+    pub destination: String,
+
     /// The DID of the BlueSky session.
     pub did: atrium_api::types::string::Did,
 
@@ -64,8 +92,106 @@ pub struct BlueSkyPostSyncConfig {
     /// The Mastodon account that posted the status.
     pub mastodon_account: megalodon::entities::account::Account,
 
+    /// The Mastodon client for the FediProto Sync application.
+    pub mastodon_client: Arc<Box<dyn megalodon::Megalodon + Send + Sync>>,
+
+    /// The rate limiter for the requests sent with the Mastodon client.
+    pub mastodon_rate_limiter: MastodonRateLimiter,
+
     /// The database connection for the FediProto Sync application.
     pub db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+
+    /// The rules for processing posts.
+    pub post_rules: Arc<PostRules>,
+}
+
+/// Delete a synced post from BlueSky.
+///
+/// ## Arguments
+///
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `did` - The DID of the BlueSky account the post is in.
+/// * `bsky_post_uri` - The URI of the BlueSky post.
+pub async fn delete_post(
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    did: &Did,
+    bsky_post_uri: &str,
+) -> Result<()> {
+    let (collection, rkey) = parse_post_uri(bsky_post_uri)?;
+
+    atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .delete_record(
+            com::atproto::repo::delete_record::InputData {
+                collection,
+                repo: AtIdentifier::Did(did.clone()),
+                rkey,
+                swap_commit: None,
+                swap_record: None,
+            }
+            .into(),
+        )
+        .await
+        .map_err(from_xrpc_error)?;
+
+    Ok(())
+}
+
+/// Get the collection and record key from the URI of a BlueSky post.
+///
+/// ## Arguments
+///
+/// * `bsky_post_uri` - The URI of the BlueSky post, like
+///   `at://did:plc:abc/app.bsky.feed.post/3kabc`.
+pub fn parse_post_uri(bsky_post_uri: &str) -> Result<(Nsid, RecordKey)> {
+    let invalid_uri_error = || anyhow::anyhow!("Invalid BlueSky post URI '{}'", bsky_post_uri);
+
+    let uri_path = bsky_post_uri
+        .strip_prefix("at://")
+        .ok_or_else(invalid_uri_error)?;
+
+    let (collection, rkey) = match uri_path.split('/').collect::<Vec<&str>>().as_slice() {
+        [_, collection, rkey] => (collection.to_string(), rkey.to_string()),
+        _ => return Err(invalid_uri_error()),
+    };
+
+    let collection = Nsid::new(collection).map_err(|_| invalid_uri_error())?;
+    let rkey = RecordKey::new(rkey).map_err(|_| invalid_uri_error())?;
+
+    Ok((collection, rkey))
+}
+
+/// Get the record key of the BlueSky post for a Mastodon post.
+///
+/// ## Arguments
+///
+/// * `mastodon_status` - The Mastodon status.
+///
+/// ## Note
+///
+/// The record key is a TID derived from the time the status was created and
+/// its ID, so retrying a post that was already published finds the existing
+/// record instead of publishing it twice.
+pub fn post_record_key(mastodon_status: &megalodon::entities::Status) -> Result<RecordKey> {
+    // TIDs are 53 bits of microseconds since the UNIX epoch, followed by a
+    // 10 bit clock ID. The clock ID is taken from the hash of the status ID,
+    // so statuses created in the same millisecond get different keys.
+    let timestamp_micros =
+        (mastodon_status.created_at.timestamp_micros().max(0) as u64) & ((1 << 53) - 1);
+
+    let status_id_hash = sha256_hex(mastodon_status.id.as_bytes());
+    let clock_id = u64::from_str_radix(&status_id_hash[..4], 16)? & 0x3FF;
+
+    let tid = (timestamp_micros << 10) | clock_id;
+    let tid = (0..13)
+        .rev()
+        .map(|index| TID_ALPHABET[((tid >> (index * 5)) & 0x1F) as usize] as char)
+        .collect::<String>();
+
+    RecordKey::new(tid.clone()).map_err(|_| anyhow::anyhow!("Error creating record key '{}'", tid))
 }
 
 /// Sync a Mastodon post to BlueSky.
@@ -77,13 +203,36 @@ pub struct BlueSkyPostSyncConfig {
 /// * `sync_config` - Config for the sync.
 pub async fn sync_post(
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<()> {
-    let db_connection = &mut sync_config.db_connection_pool.get()?;
-
     let collection = Nsid::new("app.bsky.feed.post".to_string())
         .map_err(|_| anyhow::anyhow!("Error creating NSID for collection 'app.bsky.feed.post'"))?;
+    let rkey = post_record_key(mastodon_status)?;
+
+    // A record with the key means the post was published by an attempt that
+    // failed before it was recorded, so only the records are added.
+    if let Some((post_cid, post_uri)) =
+        get_existing_post(&collection, &rkey, atp_client, sync_config).await?
+    {
+        tracing::info!(
+            "Post '{}' was already published to BlueSky destination '{}', recording it.",
+            &mastodon_status.id,
+            &sync_config.destination
+        );
+
+        let root_mastodon_post_id = resolve_root_post_id(mastodon_status, sync_config).await?;
+        let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+        return record_synced_post(
+            db_connection,
+            mastodon_status,
+            sync_config,
+            &post_cid,
+            &post_uri,
+            root_mastodon_post_id,
+        );
+    }
 
     let mut previous_post_id = None;
     let post_item = match mastodon_status.reblog.is_some() {
@@ -107,65 +256,235 @@ pub async fn sync_post(
             com::atproto::repo::apply_writes::InputData {
                 repo: atrium_api::types::string::AtIdentifier::Did(sync_config.did.clone()),
                 writes: vec![com::atproto::repo::apply_writes::InputWritesItem::Create(
-                    Box::new(
-                        com::atproto::repo::apply_writes::Create {
-                            data: com::atproto::repo::apply_writes::CreateData {
-                                collection: collection,
-                                rkey: None,
-                                value: post_item.try_into_unknown()?,
-                            },
-                            extra_data: Ipld::Null,
-                        }
-                        .into(),
-                    ),
+                    Box::new(com::atproto::repo::apply_writes::Create {
+                        data: com::atproto::repo::apply_writes::CreateData {
+                            collection,
+                            rkey: Some(rkey),
+                            value: post_item.try_into_unknown()?,
+                        },
+                        extra_data: Ipld::Null,
+                    }),
                 )],
                 swap_commit: None,
                 validate: Some(true),
             }
             .into(),
         )
-        .await?;
+        .await
+        .map_err(from_xrpc_error)?;
 
     // If no HTTP errors occurred, get the results from the response.
     // We need the CID and URI of the post that was created from it.
-    let post_result = apply_writes_result.results.clone().unwrap();
-    let post_result = match post_result.first().unwrap() {
+    let post_result = get_create_result(&apply_writes_result)?;
+    let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+    record_synced_post(
+        db_connection,
+        mastodon_status,
+        sync_config,
+        &post_result.cid.as_ref().to_string(),
+        &post_result.uri,
+        previous_post_id,
+    )?;
+
+    tracing::info!(
+        "Synced post '{}' to BlueSky destination '{}'.",
+        &mastodon_status.id,
+        &sync_config.destination
+    );
+
+    Ok(())
+}
+
+/// Get the result of creating a post from the response of an `applyWrites`
+/// request.
+///
+/// ## Arguments
+///
+/// * `apply_writes_result` - The response of the `applyWrites` request.
+pub fn get_create_result(
+    apply_writes_result: &com::atproto::repo::apply_writes::OutputData
+) -> Result<&com::atproto::repo::apply_writes::CreateResult, FediProtoSyncError> {
+    let unexpected_response = |message: &str| FediProtoSyncError::UnexpectedResponseError {
+        service: AuthenticationSource::BlueSky,
+        message: message.to_string(),
+    };
+
+    let post_result = apply_writes_result
+        .results
+        .as_ref()
+        .and_then(|results| results.first())
+        .ok_or_else(|| unexpected_response("The post was created without a result."))?;
+
+    match post_result {
         com::atproto::repo::apply_writes::OutputResultsItem::CreateResult(create_result) => {
-            create_result
+            Ok(create_result)
         }
 
-        _ => panic!("Unexpected response from Bluesky"),
-    };
+        _ => Err(unexpected_response(
+            "The result of creating the post isn't a create result.",
+        )),
+    }
+}
+
+/// Get the CID and URI of a BlueSky post that was already published with a
+/// record key.
+///
+/// ## Arguments
+///
+/// * `collection` - The collection of the post.
+/// * `rkey` - The record key of the post.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+async fn get_existing_post(
+    collection: &Nsid,
+    rkey: &RecordKey,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<(String, String)>> {
+    let get_record_result = atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .get_record(
+            com::atproto::repo::get_record::ParametersData {
+                cid: None,
+                collection: collection.clone(),
+                repo: AtIdentifier::Did(sync_config.did.clone()),
+                rkey: rkey.clone(),
+            }
+            .into(),
+        )
+        .await;
+
+    match get_record_result {
+        Ok(post_record) => {
+            let post_cid = post_record
+                .cid
+                .as_ref()
+                .map(|cid| cid.as_ref().to_string())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("BlueSky post '{}' doesn't have a CID.", post_record.uri)
+                })?;
+
+            Ok(Some((post_cid, post_record.uri.clone())))
+        }
 
+        Err(atrium_api::xrpc::Error::XrpcResponse(atrium_api::xrpc::error::XrpcError {
+            error:
+                Some(atrium_api::xrpc::error::XrpcErrorKind::Custom(
+                    com::atproto::repo::get_record::Error::RecordNotFound(_),
+                )),
+            ..
+        })) => Ok(None),
+
+        Err(e) => Err(from_xrpc_error(e).into()),
+    }
+}
+
+/// Get the ID of the root Mastodon post in the thread of a post from the
+/// posts that were already synced.
+///
+/// ## Arguments
+///
+/// * `mastodon_status` - The Mastodon status.
+/// * `sync_config` - Config for the sync.
+async fn resolve_root_post_id(
+    mastodon_status: &megalodon::entities::Status,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<String>> {
+    if mastodon_status.reblog.is_some() {
+        return Ok(None);
+    }
+
+    match (
+        &mastodon_status.in_reply_to_id,
+        &mastodon_status.in_reply_to_account_id,
+    ) {
+        (Some(reply_to_id), Some(reply_to_account_id))
+            if *reply_to_account_id == sync_config.mastodon_account.id =>
+        {
+            let previous_post = resolve_previous_post(reply_to_id, sync_config).await?;
+
+            Ok(previous_post.map(|previous_post| previous_post.1))
+        }
+
+        _ => Ok(None),
+    }
+}
+
+/// Record a Mastodon post and the BlueSky post it was synced to in the
+/// database.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `mastodon_status` - The Mastodon status.
+/// * `sync_config` - Config for the sync.
+/// * `bsky_post_cid` - The CID of the BlueSky post.
+/// * `bsky_post_uri` - The URI of the BlueSky post.
+/// * `root_mastodon_post_id` - The root Mastodon post ID in the thread, if
+///   any.
+fn record_synced_post(
+    db_connection: &mut AnyConnection,
+    mastodon_status: &megalodon::entities::Status,
+    sync_config: &BlueSkyPostSyncConfig,
+    bsky_post_cid: &str,
+    bsky_post_uri: &str,
+    root_mastodon_post_id: Option<String>,
+) -> Result<()> {
     let new_mastodon_post = NewMastodonPost::new(
-        &mastodon_status,
-        Some(post_result.cid.clone().as_ref().to_string()),
-        previous_post_id.clone(),
+        &sync_config.config.account_pair_id,
+        &sync_config.destination,
+        mastodon_status,
+        Some(bsky_post_cid.to_string()),
+        root_mastodon_post_id,
     );
 
     let new_synced_post = NewSyncedPostBlueSkyData::new(
+        &sync_config.config.account_pair_id,
+        &sync_config.destination,
         &mastodon_status.id,
-        &post_result.cid.clone().as_ref().to_string(),
-        &post_result.uri,
+        bsky_post_cid,
+        bsky_post_uri,
     );
 
-    // Insert the synced Mastodon post into the database for future tracking.
+    // Insert both records at once, so a post is never recorded without the
+    // BlueSky post it was synced to.
     fediproto_sync_db::operations::insert_new_synced_mastodon_post(
         db_connection,
         &new_mastodon_post,
-    )?;
-
-    // Insert the synced BlueSky post into the database for future tracking.
-    fediproto_sync_db::operations::insert_new_bluesky_data_for_synced_mastodon_post(
-        db_connection,
         &new_synced_post,
     )?;
 
-    tracing::info!("Synced post '{}' to BlueSky.", &mastodon_status.id);
-
     Ok(())
 }
 
+/// Parse a Mastodon post, and apply the transforms and templates for a
+/// BlueSky destination to it.
+///
+/// ## Arguments
+///
+/// * `mastodon_status` - The Mastodon status.
+/// * `source_platform` - The platform the status was posted on.
+/// * `post_rules` - The rules for processing posts.
+/// * `destination` - The name of the BlueSky destination.
+pub(crate) fn parse_post_for_destination(
+    mastodon_status: &megalodon::entities::Status,
+    source_platform: SourcePlatform,
+    post_rules: &PostRules,
+    destination: &str,
+) -> Result<ParsedMastodonPost> {
+    let templates = post_rules
+        .templates
+        .for_language(mastodon_status.language.as_deref());
+
+    ParsedMastodonPost::from_mastodon_status(mastodon_status, source_platform)?
+        .apply_transforms(&post_rules.transforms, destination)?
+        .truncate_post_content(&templates)
+}
+
 /// Process a regular Mastodon post and generate a BlueSky post for it.
 ///
 /// ## Arguments
@@ -175,7 +494,7 @@ pub async fn sync_post(
 /// * `sync_config` - Config for the sync.
 async fn process_post(
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<(
     atrium_api::app::bsky::feed::post::RecordData,
@@ -188,13 +507,24 @@ async fn process_post(
         let reply_to_account_id = mastodon_status
             .in_reply_to_account_id
             .clone()
-            .unwrap_or_else(|| "".to_string());
+            .unwrap_or_default();
 
         if reply_to_account_id == sync_config.mastodon_account.id {
-            if let Some(previous_post) = resolve_previous_post(&reply_to_id, sync_config).await? {
+            let mut previous_post = resolve_previous_post(reply_to_id, sync_config).await?;
+
+            if previous_post.is_none()
+                && sync_config.config.thread_repair_mode != ThreadRepairMode::Off
+            {
+                previous_post = repair_thread(mastodon_status, atp_client, sync_config).await?;
+            }
+
+            if let Some(previous_post) = previous_post {
                 post_item.reply = Some(previous_post.0.into());
                 previous_post_id = Some(previous_post.1);
             }
+        } else {
+            post_item.embed =
+                generate_reply_embed(reply_to_id, post_item.embed, atp_client, sync_config).await?;
         }
     }
 
@@ -210,7 +540,7 @@ async fn process_post(
 /// * `sync_config` - Config for the sync.
 async fn process_boosted_post(
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<atrium_api::app::bsky::feed::post::RecordData> {
     let mut post_item = atrium_api::app::bsky::feed::post::RecordData {
@@ -227,10 +557,20 @@ async fn process_boosted_post(
 
     let reblogged_status = mastodon_status.reblog.clone().unwrap();
 
-    let parsed_status =
-        ParsedMastodonPost::from_mastodon_status(&reblogged_status)?.truncate_post_content()?;
+    let templates = sync_config
+        .post_rules
+        .templates
+        .for_language(reblogged_status.language.as_deref());
+
+    let parsed_status = ParsedMastodonPost::from_mastodon_status(
+        &reblogged_status,
+        sync_config.config.source_platform,
+    )?
+    .apply_transforms(&sync_config.post_rules.transforms, &sync_config.destination)?
+    .truncate_post_content(&templates)?;
 
-    post_item.embed = generate_boost_link_embed(&parsed_status, atp_client, sync_config).await?;
+    post_item.embed =
+        generate_boost_link_embed(&parsed_status, &templates, atp_client, sync_config).await?;
 
     Ok(post_item)
 }
@@ -244,12 +584,16 @@ async fn process_boosted_post(
 /// * `sync_config` - Config for the sync.
 async fn generate_post_item(
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<atrium_api::app::bsky::feed::post::RecordData> {
     // Parse the Mastodon post.
-    let parsed_status =
-        ParsedMastodonPost::from_mastodon_status(mastodon_status)?.truncate_post_content()?;
+    let parsed_status = parse_post_for_destination(
+        mastodon_status,
+        sync_config.config.source_platform,
+        &sync_config.post_rules,
+        &sync_config.destination,
+    )?;
 
     // Create the BlueSky post item.
     let mut post_item = atrium_api::app::bsky::feed::post::RecordData {
@@ -265,7 +609,7 @@ async fn generate_post_item(
     };
 
     // Add media attachments.
-    if parsed_status.mastodon_status.media_attachments.len() > 0 {
+    if !parsed_status.mastodon_status.media_attachments.is_empty() {
         tracing::info!(
             "Found '{}' media attachments in post '{}'",
             parsed_status.mastodon_status.media_attachments.len(),
@@ -315,30 +659,40 @@ async fn generate_post_item(
     let mut richtext_facets = Vec::<Object<app::bsky::richtext::facet::MainData>>::new();
 
     // Add hashtags to richtext facets.
-    if parsed_status.found_tags.len() > 0 {
+    if !parsed_status.found_tags.is_empty() {
         richtext_facets.extend(generate_rich_text_tags(&parsed_status)?);
     }
 
     // Add links to richtext facets.
-    if parsed_status.found_links.len() > 0 {
+    if !parsed_status.found_links.is_empty() {
         richtext_facets.extend(generate_rich_text_links(&parsed_status)?);
+    }
 
-        // Check if the post has an embed and add an external embed for the first link
-        // if it doesn't.
-        if post_item.embed.is_none() {
-            // Get the first link found in the post.
-            let first_link = parsed_status.found_links[0].clone();
+    // Check if the post has an embed and add an external embed for the
+    // selected link if it doesn't.
+    if post_item.embed.is_none() {
+        let link_selection_policy = LinkSelectionPolicy::new(&sync_config.config);
 
+        if let Some(card_link) = link_selection_policy.select_link(&parsed_status.mastodon_status) {
             tracing::info!(
                 "Post has no embeds, adding external embed for link '{}'",
-                first_link
+                card_link
             );
 
-            post_item.embed = generate_link_embed(&first_link, atp_client, sync_config).await?;
+            // Use the preview card Mastodon generated for the link, if it has
+            // one, so the link card matches what Mastodon users see.
+            let status_card = parsed_status
+                .mastodon_status
+                .card
+                .as_ref()
+                .filter(|card| card_matches_url(card, &card_link));
+
+            post_item.embed =
+                generate_link_embed(&card_link, status_card, atp_client, sync_config).await?;
         }
     }
 
-    post_item.facets = match richtext_facets.len() != 0 {
+    post_item.facets = match !richtext_facets.is_empty() {
         true => Some(richtext_facets),
         false => None,
     };
@@ -355,14 +709,14 @@ async fn generate_post_item(
 /// * `sync_config` - Config for the sync.
 async fn generate_image_embed(
     media_attachments: &Vec<megalodon::entities::attachment::Attachment>,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<RecordEmbedRefs>>> {
     let mut image_attachments = Vec::<Object<ImageData>>::new();
 
     for image_attachment in media_attachments {
         // Download the media attachment from the Mastodon server.
-        let temp_file_path = download_file_to_temp(&image_attachment.url, &sync_config).await?;
+        let temp_file_path = download_file_to_temp(&image_attachment.url, sync_config).await?;
 
         let media_attachment = ImageAttachmentData::new(
             tokio::fs::read(temp_file_path).await?.into(),
@@ -387,7 +741,8 @@ async fn generate_image_embed(
             .atproto
             .repo
             .upload_blob(media_attachment.image_bytes.into())
-            .await?;
+            .await
+            .map_err(from_xrpc_error)?;
 
         // Create an image embed and add it to the list of image attachments.
         image_attachments.push(
@@ -429,7 +784,7 @@ async fn generate_image_embed(
 async fn generate_video_embed(
     media_attachment: &megalodon::entities::attachment::Attachment,
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<RecordEmbedRefs>>> {
     #[allow(unused_assignments)]
@@ -454,13 +809,13 @@ async fn generate_video_embed(
     let post_embed = match sync_config.config.bluesky_video_always_fallback || should_fallback {
         // Add a video link embed.
         true => {
-            generate_video_link_embed(&media_attachment, mastodon_status, atp_client, sync_config)
+            generate_video_link_embed(media_attachment, mastodon_status, atp_client, sync_config)
                 .await?
         }
 
         // Upload the video to BlueSky.
         false => {
-            upload_video_to_bluesky(&media_attachment, &temp_file_path, atp_client, sync_config)
+            upload_video_to_bluesky(media_attachment, &temp_file_path, atp_client, sync_config)
                 .await?
         }
     };
@@ -479,7 +834,7 @@ async fn generate_video_embed(
 async fn generate_video_link_embed(
     media_attachment: &megalodon::entities::attachment::Attachment,
     mastodon_status: &megalodon::entities::Status,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<RecordEmbedRefs>>> {
     let temp_file_path =
@@ -489,7 +844,13 @@ async fn generate_video_link_embed(
     let video_link_thumbnail =
         ImageAttachmentData::new(video_link_thumbnail, &media_attachment.url)?;
 
-    let blob_item = match video_link_thumbnail.image_bytes.len() > 0 {
+    let templates = sync_config
+        .post_rules
+        .templates
+        .for_language(mastodon_status.language.as_deref());
+    let template_context = TemplateContext::from_mastodon_status(mastodon_status);
+
+    let blob_item = match !video_link_thumbnail.image_bytes.is_empty() {
         true => Some(
             atp_client
                 .api
@@ -497,7 +858,8 @@ async fn generate_video_link_embed(
                 .atproto
                 .repo
                 .upload_blob(video_link_thumbnail.image_bytes.into())
-                .await?
+                .await
+                .map_err(from_xrpc_error)?
                 .blob
                 .clone(),
         ),
@@ -510,11 +872,8 @@ async fn generate_video_link_embed(
             app::bsky::embed::external::MainData {
                 external: app::bsky::embed::external::ExternalData {
                     uri: mastodon_status.uri.clone(),
-                    title: "View video on Mastodon".to_string(),
-                    description: format!(
-                        "Check out this video posted by @{}!",
-                        mastodon_status.account.username.clone()
-                    ),
+                    title: template_context.render(&templates.video_link_title),
+                    description: template_context.render(&templates.video_link_description),
                     thumb: blob_item,
                 }
                 .into(),
@@ -535,7 +894,7 @@ async fn generate_video_link_embed(
 async fn upload_video_to_bluesky(
     media_attachment: &megalodon::entities::attachment::Attachment,
     temp_path: &std::path::PathBuf,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<RecordEmbedRefs>>> {
     tracing::info!("Creating video upload service auth token");
@@ -558,7 +917,8 @@ async fn upload_video_to_bluesky(
             }
             .into(),
         )
-        .await?;
+        .await
+        .map_err(from_xrpc_error)?;
 
     let random_video_name = new_random_file_name(14, Some(".mp4"));
 
@@ -569,22 +929,25 @@ async fn upload_video_to_bluesky(
         random_video_name
     );
 
-    let temp_file = tokio::fs::File::open(temp_path).await?;
+    let video_bytes = tokio::fs::read(temp_path).await?;
 
     let video_upload_client = create_http_client(&sync_config.config)?;
-    let upload_video_job_response = video_upload_client
-        .post("https://video.bsky.app/xrpc/app.bsky.video.uploadVideo")
-        .query(&[
-            ("did", sync_config.did.as_str()),
-            ("name", &random_video_name),
-        ])
-        .bearer_auth(&service_auth_response.token)
-        .header(CONTENT_TYPE, "video/mp4")
-        .body(temp_file)
-        .send()
-        .await?
-        .json::<JobStatus>()
-        .await?;
+    let upload_video_job_response = send_rate_limited(
+        AuthenticationSource::BlueSky,
+        video_upload_client
+            .post("https://video.bsky.app/xrpc/app.bsky.video.uploadVideo")
+            .query(&[
+                ("did", sync_config.did.as_str()),
+                ("name", &random_video_name),
+            ])
+            .bearer_auth(&service_auth_response.token)
+            .header(CONTENT_TYPE, "video/mp4")
+            .body(video_bytes),
+        sync_config.config.rate_limit_max_wait,
+    )
+    .await?
+    .json::<JobStatus>()
+    .await?;
 
     tracing::info!(
         "Waiting for video upload job '{}' to complete",
@@ -594,15 +957,18 @@ async fn upload_video_to_bluesky(
     let mut job_status = upload_video_job_response.clone();
 
     while job_status.state != "JOB_STATE_FAILED" {
-        job_status = video_upload_client
-            .get("https://video.bsky.app/xrpc/app.bsky.video.getJobStatus")
-            .query(&[("jobId", &job_status.job_id)])
-            .send()
-            .await?
-            .json::<JobStatusResponse>()
-            .await?
-            .job_status
-            .clone();
+        job_status = send_rate_limited(
+            AuthenticationSource::BlueSky,
+            video_upload_client
+                .get("https://video.bsky.app/xrpc/app.bsky.video.getJobStatus")
+                .query(&[("jobId", &job_status.job_id)]),
+            sync_config.config.rate_limit_max_wait,
+        )
+        .await?
+        .json::<JobStatusResponse>()
+        .await?
+        .job_status
+        .clone();
 
         if job_status.state == "JOB_STATE_COMPLETED" {
             break;
@@ -622,7 +988,7 @@ async fn upload_video_to_bluesky(
                     .unwrap_or_else(|| "N/A".to_string())
             );
 
-            return Err(FediProtoSyncError::VideoUploadError.into());
+            Err(FediProtoSyncError::VideoUploadError.into())
         }
 
         _ => {
@@ -633,7 +999,7 @@ async fn upload_video_to_bluesky(
 
             let blob = job_status.blob.clone().unwrap();
 
-            return Ok(Some(Union::Refs(RecordEmbedRefs::AppBskyEmbedVideoMain(
+            Ok(Some(Union::Refs(RecordEmbedRefs::AppBskyEmbedVideoMain(
                 Box::new(
                     app::bsky::embed::video::MainData {
                         video: atrium_api::types::BlobRef::Typed(
@@ -645,18 +1011,13 @@ async fn upload_video_to_bluesky(
                                 size: blob.size as usize,
                             }),
                         ),
-                        alt: Some(
-                            media_attachment
-                                .description
-                                .clone()
-                                .unwrap_or_else(|| "".to_string()),
-                        ),
+                        alt: Some(media_attachment.description.clone().unwrap_or_default()),
                         aspect_ratio: None,
                         captions: None,
                     }
                     .into(),
                 ),
-            ))));
+            ))))
         }
     }
 }
@@ -671,10 +1032,13 @@ async fn download_file_to_temp(
     url: &str,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<std::path::PathBuf> {
-    let db_connection = &mut sync_config.db_connection_pool.get()?;
-
     let file_download_client = crate::core::create_http_client(&sync_config.config)?;
-    let mut file_download_response = file_download_client.get(url).send().await?;
+    let mut file_download_response = send_rate_limited(
+        AuthenticationSource::Mastodon,
+        file_download_client.get(url),
+        sync_config.config.rate_limit_max_wait,
+    )
+    .await?;
 
     let temp_path = std::env::temp_dir().join(new_random_file_name(14, None));
     let mut temp_file = tokio::fs::File::create(&temp_path).await?;
@@ -685,6 +1049,7 @@ async fn download_file_to_temp(
 
     temp_file.flush().await?;
 
+    let db_connection = &mut sync_config.db_connection_pool.get()?;
     let new_cached_file_record = NewCachedFile::new(&temp_path);
     fediproto_sync_db::operations::insert_cached_file_record(
         db_connection,
@@ -754,12 +1119,12 @@ fn generate_rich_text_links(
             .filter(|(index, _)| {
                 richtext_facets
                     .iter()
-                    .any(|facet| facet.index.byte_start as usize != *index)
+                    .any(|facet| facet.index.byte_start != *index)
             })
             .map(|(index, _)| index)
             .collect::<Vec<usize>>();
 
-        let link_start_index = match link_start_index_filter.len() > 0 {
+        let link_start_index = match !link_start_index_filter.is_empty() {
             true => link_start_index_filter[0],
             false => match parsed_status.stripped_html.find(&link) {
                 Some(index) => index,
@@ -767,7 +1132,7 @@ fn generate_rich_text_links(
             },
         };
 
-        let link_end_index = link_start_index + &link.len();
+        let link_end_index = link_start_index + link.len();
 
         let richtext_facet_link = app::bsky::richtext::facet::MainData {
             index: app::bsky::richtext::facet::ByteSliceData {
@@ -796,44 +1161,27 @@ fn generate_rich_text_links(
 /// ## Arguments
 ///
 /// * `url` - The URL for the link embed.
+/// * `status_card` - The preview card Mastodon generated for the link, if any.
 /// * `atp_client` - The client/agent for interacting with the AT Protocol.
 /// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// The link metadata is only fetched if Mastodon didn't generate a preview
+/// card for the link and it isn't cached.
 async fn generate_link_embed(
     url: &str,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    status_card: Option<&megalodon::entities::Card>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<app::bsky::feed::post::RecordEmbedRefs>>> {
-    // Get metadata for the link.
-    let link_metadata = get_link_metadata(url, sync_config).await?;
-
-    // Get the thumbnail for the link if it has one and upload it to BlueSky.
-    let link_thumbnail_url = link_metadata["image"].as_str().unwrap_or_else(|| "");
-    let link_thumbnail = match link_thumbnail_url == "" {
-        true => None,
-        false => {
-            let temp_file_path = download_file_to_temp(link_thumbnail_url, sync_config).await?;
-
-            Some(ImageAttachmentData::new(
-                tokio::fs::read(temp_file_path).await?.into(),
-                link_thumbnail_url,
-            )?)
-        }
-    };
-
-    let blob_item = match link_thumbnail {
-        Some(thumbnail) => Some(
-            atp_client
-                .api
-                .com
-                .atproto
-                .repo
-                .upload_blob(thumbnail.image_bytes.into())
-                .await?
-                .blob
-                .clone(),
-        ),
-        _ => None,
-    };
+    // Get the metadata and thumbnail for the link. If the metadata can't be
+    // retrieved, the post is synced without a link card.
+    let (link_metadata, blob_item) =
+        match get_link_card(url, status_card, atp_client, sync_config).await? {
+            Some(link_card) => link_card,
+            None => return Ok(None),
+        };
 
     let link_embed = Some(Union::Refs(
         app::bsky::feed::post::RecordEmbedRefs::AppBskyEmbedExternalMain(Box::new(
@@ -841,22 +1189,148 @@ async fn generate_link_embed(
                 data: app::bsky::embed::external::MainData {
                     external: app::bsky::embed::external::ExternalData {
                         uri: url.to_string(),
-                        title: link_metadata["title"].as_str().unwrap().to_string(),
-                        description: link_metadata["description"].as_str().unwrap().to_string(),
+                        title: link_metadata.title,
+                        description: link_metadata.description,
                         thumb: blob_item,
                     }
                     .into(),
                 },
                 extra_data: Ipld::Null,
-            }
-            .into(),
+            },
         )),
     ));
 
     Ok(link_embed)
 }
 
-/// Get link metadata using BlueSky's "CardyB" metadata service.
+/// Get the metadata for a link and its thumbnail uploaded to BlueSky, reusing
+/// cached metadata and thumbnails where possible.
+///
+/// ## Arguments
+///
+/// * `url` - The URL of the link.
+/// * `status_card` - The preview card Mastodon generated for the link, if any.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// Returns `None` if the metadata for the link couldn't be retrieved.
+async fn get_link_card(
+    url: &str,
+    status_card: Option<&megalodon::entities::Card>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<(LinkMetadata, Option<atrium_api::types::BlobRef>)>> {
+    let normalized_url = normalize_url(url);
+
+    let cache_entry = {
+        let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_link_metadata_cache_entry(
+            db_connection,
+            &normalized_url,
+            sync_config.did.as_str(),
+        )?
+    };
+
+    // Whether anything new was retrieved that should be cached.
+    let mut is_cache_stale = false;
+
+    let link_metadata = match (status_card, &cache_entry) {
+        (Some(status_card), _) => {
+            tracing::info!("Using Mastodon's preview card for link '{}'", url);
+
+            LinkMetadata::from(status_card)
+        }
+
+        (None, Some(cache_entry)) => {
+            tracing::info!("Using cached link metadata for '{}'", url);
+
+            LinkMetadata::from(cache_entry)
+        }
+
+        (None, None) => match get_link_metadata(url, sync_config).await {
+            Ok(link_metadata) => {
+                is_cache_stale = true;
+
+                link_metadata
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to get link metadata for '{}', skipping link card: {}",
+                    url,
+                    e
+                );
+
+                return Ok(None);
+            }
+        },
+    };
+
+    let mut new_cache_entry = NewLinkMetadataCacheEntry::new(
+        &normalized_url,
+        &link_metadata.title,
+        &link_metadata.description,
+        link_metadata.image.clone(),
+        sync_config.did.as_str(),
+        sync_config.config.link_metadata_cache_ttl,
+    );
+
+    // Reuse the cached thumbnail if it's for the same image. Otherwise, upload
+    // the thumbnail.
+    let cached_thumbnail = cache_entry
+        .as_ref()
+        .filter(|cache_entry| cache_entry.thumbnail_url == link_metadata.image)
+        .and_then(|cache_entry| {
+            let thumbnail_blob = cache_entry.thumbnail_blob.clone()?;
+            let thumbnail_hash = cache_entry.thumbnail_hash.clone()?;
+
+            Some((thumbnail_hash, thumbnail_blob))
+        });
+
+    let blob_item = match cached_thumbnail {
+        Some((thumbnail_hash, thumbnail_blob)) => {
+            tracing::info!("Using cached thumbnail for link '{}'", url);
+
+            new_cache_entry = new_cache_entry.with_thumbnail_blob(&thumbnail_hash, &thumbnail_blob);
+
+            serde_json::from_str::<atrium_api::types::BlobRef>(&thumbnail_blob).ok()
+        }
+
+        None => {
+            let uploaded_thumbnail =
+                upload_link_thumbnail(link_metadata.image.as_deref(), atp_client, sync_config)
+                    .await?;
+
+            match uploaded_thumbnail {
+                Some((thumbnail_hash, blob_item)) => {
+                    is_cache_stale = true;
+
+                    new_cache_entry = new_cache_entry
+                        .with_thumbnail_blob(&thumbnail_hash, &serde_json::to_string(&blob_item)?);
+
+                    Some(blob_item)
+                }
+
+                None => None,
+            }
+        }
+    };
+
+    if is_cache_stale {
+        let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::upsert_link_metadata_cache_entry(
+            db_connection,
+            &new_cache_entry,
+        )?;
+    }
+
+    Ok(Some((link_metadata, blob_item)))
+}
+
+/// Get link metadata using the configured link metadata backend.
 ///
 /// ## Arguments
 ///
@@ -865,21 +1339,96 @@ async fn generate_link_embed(
 async fn get_link_metadata(
     url: &str,
     sync_config: &BlueSkyPostSyncConfig,
-) -> Result<serde_json::Value> {
-    tracing::info!("Getting link metadata for '{}'.", url);
-    let query_params = vec![("url", url)];
+) -> Result<LinkMetadata> {
+    let link_metadata_client = LinkMetadataClient::new(&sync_config.config)?;
 
-    let link_info_client = crate::core::create_http_client(&sync_config.config)?;
+    link_metadata_client.get_link_metadata(url).await
+}
 
-    let link_info_response = link_info_client
-        .get("https://cardyb.bsky.app/v1/extract")
-        .query(&query_params)
-        .send()
-        .await?;
+/// Download a link's thumbnail and upload it to BlueSky.
+///
+/// ## Arguments
+///
+/// * `thumbnail_url` - The URL of the thumbnail, if any.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// Returns the SHA-256 hash of the thumbnail and its blob ref. If the same
+/// image was already uploaded for another link, its blob ref is reused instead
+/// of uploading it again. If the thumbnail can't be downloaded or decoded, the
+/// link card is created without a thumbnail.
+async fn upload_link_thumbnail(
+    thumbnail_url: Option<&str>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<(String, atrium_api::types::BlobRef)>> {
+    let thumbnail_url = match thumbnail_url {
+        Some(thumbnail_url) if !thumbnail_url.is_empty() => thumbnail_url,
+        _ => return Ok(None),
+    };
+
+    let link_thumbnail = match download_file_to_temp(thumbnail_url, sync_config).await {
+        Ok(temp_file_path) => {
+            ImageAttachmentData::new(tokio::fs::read(temp_file_path).await?.into(), thumbnail_url)
+        }
+        Err(e) => Err(e),
+    };
+
+    let link_thumbnail = match link_thumbnail {
+        Ok(link_thumbnail) => link_thumbnail,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to get thumbnail '{}', skipping thumbnail: {}",
+                thumbnail_url,
+                e
+            );
+
+            return Ok(None);
+        }
+    };
+
+    let thumbnail_hash = sha256_hex(&link_thumbnail.image_bytes);
+
+    let cache_entry = {
+        let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_link_metadata_cache_entry_by_thumbnail_hash(
+            db_connection,
+            &thumbnail_hash,
+            sync_config.did.as_str(),
+        )?
+    };
 
-    let link_info_json = link_info_response.json::<serde_json::Value>().await?;
+    let cached_blob_item = cache_entry
+        .as_ref()
+        .and_then(|cache_entry| cache_entry.thumbnail_blob.as_deref())
+        .and_then(|thumbnail_blob| {
+            serde_json::from_str::<atrium_api::types::BlobRef>(thumbnail_blob).ok()
+        });
 
-    Ok(link_info_json)
+    if let Some(cached_blob_item) = cached_blob_item {
+        tracing::info!(
+            "Thumbnail '{}' was already uploaded, reusing it.",
+            thumbnail_url
+        );
+
+        return Ok(Some((thumbnail_hash, cached_blob_item)));
+    }
+
+    let blob_item = atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .upload_blob(link_thumbnail.image_bytes.into())
+        .await
+        .map_err(from_xrpc_error)?
+        .blob
+        .clone();
+
+    Ok(Some((thumbnail_hash, blob_item)))
 }
 
 /// Generate a link embed to a boosted Mastodon post.
@@ -887,69 +1436,498 @@ async fn get_link_metadata(
 /// ## Arguments
 ///
 /// * `status` - The Mastodon post that has been boosted.
+/// * `templates` - The templates for the language of the boosted post.
 /// * `atp_client` - The client/agent for interacting with the AT Protocol.
 /// * `sync_config` - Config for the sync.
 async fn generate_boost_link_embed(
     status: &ParsedMastodonPost,
-    atp_client: &AtpAgent<MemorySessionStore, ReqwestClient>,
+    templates: &TemplateSet,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
     sync_config: &BlueSkyPostSyncConfig,
 ) -> Result<Option<Union<app::bsky::feed::post::RecordEmbedRefs>>> {
-    // Get metadata for the link.
-    let link_metadata = get_link_metadata(&status.mastodon_status.uri, sync_config).await?;
+    // Get the metadata and thumbnail for the link. If the metadata can't be
+    // retrieved, fallback to the details of the boosted post.
+    let link_card =
+        get_link_card(&status.mastodon_status.uri, None, atp_client, sync_config).await?;
+
+    let (link_metadata, blob_item) = match link_card {
+        Some(link_card) => link_card,
+        None => (
+            LinkMetadata {
+                url: status
+                    .mastodon_status
+                    .url
+                    .clone()
+                    .unwrap_or_else(|| status.mastodon_status.uri.clone()),
+                title: format!("@{}", status.mastodon_status.account.acct),
+                description: "".to_string(),
+                image: None,
+            },
+            None,
+        ),
+    };
 
-    // Get the thumbnail for the link if it has one and upload it to BlueSky.
-    let link_thumbnail_url = link_metadata["image"].as_str().unwrap_or_else(|| "");
-    let link_thumbnail = match link_thumbnail_url == "" {
-        true => None,
-        false => {
-            let temp_file_path = download_file_to_temp(link_thumbnail_url, sync_config).await?;
+    let link_title = TemplateContext::from_mastodon_status(&status.mastodon_status)
+        .with_title(&link_metadata.title)
+        .render(&templates.boost_title);
+
+    Ok(Some(Union::Refs(
+        app::bsky::feed::post::RecordEmbedRefs::AppBskyEmbedExternalMain(Box::new(
+            app::bsky::embed::external::Main {
+                data: app::bsky::embed::external::MainData {
+                    external: app::bsky::embed::external::ExternalData {
+                        uri: link_metadata.url,
+                        title: link_title,
+                        description: status.stripped_html.clone(),
+                        thumb: blob_item,
+                    }
+                    .into(),
+                },
+                extra_data: Ipld::Null,
+            },
+        )),
+    )))
+}
+
+/// Generate the embed for a reply to another account, using the reply policy.
+///
+/// ## Arguments
+///
+/// * `in_reply_to_id` - The ID of the Mastodon post being replied to.
+/// * `post_embed` - The embed already generated for the reply, if any.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// With the `quote` policy, the post being replied to is quoted if it's
+/// bridged to BlueSky, keeping any media in the reply. Otherwise, a link card
+/// to it is added unless the reply already has an embed, like media or a link
+/// card. The post being replied to gets the same transforms as the reply.
+async fn generate_reply_embed(
+    in_reply_to_id: &str,
+    post_embed: Option<Union<RecordEmbedRefs>>,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<Union<RecordEmbedRefs>>> {
+    if sync_config.config.reply_policy == ReplyPolicy::Skip {
+        return Ok(post_embed);
+    }
+
+    let parent_status = match sync_config
+        .mastodon_rate_limiter
+        .send(
+            sync_config
+                .mastodon_client
+                .get_status(in_reply_to_id.to_string()),
+        )
+        .await
+    {
+        Ok(parent_status) => parent_status.json,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to fetch post '{}' being replied to, syncing the reply as is: {:#?}",
+                in_reply_to_id,
+                e
+            );
 
-            Some(ImageAttachmentData::new(
-                tokio::fs::read(temp_file_path).await?.into(),
-                link_thumbnail_url,
-            )?)
+            return Ok(post_embed);
         }
     };
 
-    let blob_item = match link_thumbnail {
-        Some(thumbnail) => Some(
-            atp_client
-                .api
-                .com
-                .atproto
-                .repo
-                .upload_blob(thumbnail.image_bytes.into())
-                .await?
-                .blob
-                .clone(),
-        ),
+    if sync_config.config.reply_policy == ReplyPolicy::Quote {
+        match find_bridged_post(&parent_status, atp_client, sync_config).await? {
+            Some(bridged_post) => {
+                tracing::info!(
+                    "Quoting bridged post '{}' for reply to '{}'.",
+                    bridged_post.uri,
+                    parent_status.id
+                );
+
+                return Ok(Some(generate_quote_embed(bridged_post, post_embed)));
+            }
+
+            None => {
+                tracing::info!(
+                    "Post '{}' isn't bridged to BlueSky, adding a link card to it instead.",
+                    parent_status.id
+                );
+            }
+        }
+    }
+
+    // A post can only have one embed, so the media or link card of the reply
+    // is kept.
+    if post_embed.is_some() {
+        return Ok(post_embed);
+    }
+
+    let templates = sync_config
+        .post_rules
+        .templates
+        .for_language(parent_status.language.as_deref());
+
+    let parsed_parent_status = parse_post_for_destination(
+        &parent_status,
+        sync_config.config.source_platform,
+        &sync_config.post_rules,
+        &sync_config.destination,
+    )?;
+
+    generate_reply_link_embed(&parsed_parent_status, &templates, atp_client, sync_config).await
+}
+
+/// Find the copy of a Mastodon post that Bridgy Fed bridged to BlueSky.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon post to find.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// Only the most recent posts of the bridged account are checked. Returns
+/// `None` if the account or the post isn't bridged, or if the posts of the
+/// bridged account couldn't be loaded.
+async fn find_bridged_post(
+    status: &megalodon::entities::Status,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<com::atproto::repo::strong_ref::MainData>> {
+    let handle = bridged_handle(&status.account.acct, &sync_config.config.mastodon_server);
+
+    let handle = match atrium_api::types::string::Handle::new(handle.clone()) {
+        Ok(handle) => handle,
+        Err(_) => return Ok(None),
+    };
+
+    let resolved_handle = atp_client
+        .api
+        .com
+        .atproto
+        .identity
+        .resolve_handle(
+            com::atproto::identity::resolve_handle::ParametersData {
+                handle: handle.clone(),
+            }
+            .into(),
+        )
+        .await;
+
+    let bridged_did = match resolved_handle {
+        Ok(resolved_handle) => resolved_handle.did.clone(),
+        Err(_) => {
+            tracing::debug!("Account '{}' isn't bridged to BlueSky.", handle.as_str());
+
+            return Ok(None);
+        }
+    };
+
+    let author_feed = atp_client
+        .api
+        .app
+        .bsky
+        .feed
+        .get_author_feed(
+            app::bsky::feed::get_author_feed::ParametersData {
+                actor: atrium_api::types::string::AtIdentifier::Did(bridged_did),
+                cursor: None,
+                filter: None,
+                include_pins: None,
+                limit: atrium_api::types::LimitedNonZeroU8::try_from(100).ok(),
+            }
+            .into(),
+        )
+        .await
+        .map_err(from_xrpc_error);
+
+    let author_feed = match author_feed {
+        Ok(author_feed) => author_feed,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to get the posts of the bridged account '{}': {}",
+                handle.as_str(),
+                e
+            );
+
+            return Ok(None);
+        }
+    };
+
+    for feed_item in &author_feed.feed {
+        let record = serde_json::to_value(&feed_item.post.record)?;
+
+        if is_bridged_copy_of(&record, status) {
+            return Ok(Some(com::atproto::repo::strong_ref::MainData {
+                cid: feed_item.post.cid.clone(),
+                uri: feed_item.post.uri.clone(),
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Generate an embed quoting a BlueSky post, keeping any media already
+/// embedded.
+///
+/// ## Arguments
+///
+/// * `quoted_post` - The BlueSky post to quote.
+/// * `post_embed` - The embed already generated for the post, if any.
+fn generate_quote_embed(
+    quoted_post: com::atproto::repo::strong_ref::MainData,
+    post_embed: Option<Union<RecordEmbedRefs>>,
+) -> Union<RecordEmbedRefs> {
+    let record_embed: app::bsky::embed::record::Main = app::bsky::embed::record::MainData {
+        record: quoted_post.into(),
+    }
+    .into();
+
+    let media = match post_embed {
+        Some(Union::Refs(RecordEmbedRefs::AppBskyEmbedImagesMain(images))) => {
+            Some(app::bsky::embed::record_with_media::MainMediaRefs::AppBskyEmbedImagesMain(images))
+        }
+        Some(Union::Refs(RecordEmbedRefs::AppBskyEmbedVideoMain(video))) => {
+            Some(app::bsky::embed::record_with_media::MainMediaRefs::AppBskyEmbedVideoMain(video))
+        }
         _ => None,
     };
 
-    let link_title = format!(
-        "{} / 🚀 Boost",
-        link_metadata["title"].as_str().unwrap().to_string()
-    );
+    match media {
+        Some(media) => Union::Refs(RecordEmbedRefs::AppBskyEmbedRecordWithMediaMain(Box::new(
+            app::bsky::embed::record_with_media::MainData {
+                media: Union::Refs(media),
+                record: record_embed,
+            }
+            .into(),
+        ))),
+
+        None => Union::Refs(RecordEmbedRefs::AppBskyEmbedRecordMain(Box::new(
+            record_embed,
+        ))),
+    }
+}
+
+/// Generate a link embed to the Mastodon post a reply is replying to.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon post being replied to.
+/// * `templates` - The templates for the language of the post being replied
+///   to.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+async fn generate_reply_link_embed(
+    status: &ParsedMastodonPost,
+    templates: &TemplateSet,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<Union<app::bsky::feed::post::RecordEmbedRefs>>> {
+    let template_context = TemplateContext::from_mastodon_status(&status.mastodon_status);
+
+    // Use the thumbnail from the link card of the post being replied to, if it
+    // can be retrieved.
+    let blob_item =
+        match get_link_card(&template_context.url, None, atp_client, sync_config).await? {
+            Some((_, blob_item)) => blob_item,
+            None => None,
+        };
 
     Ok(Some(Union::Refs(
         app::bsky::feed::post::RecordEmbedRefs::AppBskyEmbedExternalMain(Box::new(
             app::bsky::embed::external::Main {
                 data: app::bsky::embed::external::MainData {
                     external: app::bsky::embed::external::ExternalData {
-                        uri: link_metadata["url"].as_str().unwrap().to_string(),
-                        title: link_title,
+                        uri: template_context.url.clone(),
+                        title: template_context.render(&templates.reply_title),
                         description: status.stripped_html.clone(),
                         thumb: blob_item,
                     }
                     .into(),
                 },
                 extra_data: Ipld::Null,
-            }
-            .into(),
+            },
         )),
     )))
 }
 
+/// Find the post to reply to for a reply whose parent was never synced, by
+/// walking up its thread on Mastodon.
+///
+/// ## Arguments
+///
+/// * `mastodon_status` - The Mastodon reply.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// With the `sync-ancestors` mode, the missing ancestors in the thread are
+/// synced first, oldest first, so the thread on BlueSky matches the one on
+/// Mastodon. Ancestors that the filter rules or the reply policy wouldn't
+/// sync are left out. Otherwise, the reply is threaded to the nearest synced
+/// ancestor.
+async fn repair_thread(
+    mastodon_status: &megalodon::entities::Status,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<Option<(app::bsky::feed::post::ReplyRefData, String)>> {
+    let status_context = match sync_config
+        .mastodon_rate_limiter
+        .send(
+            sync_config
+                .mastodon_client
+                .get_status_context(mastodon_status.id.clone(), None),
+        )
+        .await
+    {
+        Ok(status_context) => status_context.json,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to get the thread for post '{}', syncing it as a new post: {:#?}",
+                mastodon_status.id,
+                e
+            );
+
+            return Ok(None);
+        }
+    };
+
+    let thread_repair_plan = {
+        let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+        ThreadRepairPlan::new(&status_context.ancestors, |post_id| {
+            fediproto_sync_db::operations::check_synced_mastodon_post_exists(
+                db_connection,
+                &sync_config.config.account_pair_id,
+                &sync_config.destination,
+                post_id,
+            )
+        })
+    };
+
+    let mut nearest_synced_ancestor_id = thread_repair_plan.nearest_synced_ancestor_id.clone();
+
+    if sync_config.config.thread_repair_mode == ThreadRepairMode::SyncAncestors {
+        for ancestor in &thread_repair_plan.missing_ancestors {
+            if !should_sync_ancestor(ancestor, sync_config)? {
+                continue;
+            }
+
+            tracing::info!(
+                "Syncing missing ancestor '{}' of post '{}' first.",
+                ancestor.id,
+                mastodon_status.id
+            );
+
+            match sync_ancestor_post(ancestor, atp_client, sync_config).await {
+                Ok(_) => nearest_synced_ancestor_id = Some(ancestor.id.clone()),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to sync missing ancestor '{}', replying to the nearest synced ancestor instead: {:#?}",
+                        ancestor.id,
+                        e
+                    );
+
+                    break;
+                }
+            }
+        }
+    }
+
+    match nearest_synced_ancestor_id {
+        Some(nearest_synced_ancestor_id) => {
+            tracing::info!(
+                "Threading post '{}' to its nearest synced ancestor '{}'.",
+                mastodon_status.id,
+                nearest_synced_ancestor_id
+            );
+
+            resolve_previous_post(&nearest_synced_ancestor_id, sync_config).await
+        }
+
+        None => {
+            tracing::info!(
+                "No ancestors of post '{}' were synced, syncing it as a new post.",
+                mastodon_status.id
+            );
+
+            Ok(None)
+        }
+    }
+}
+
+/// Sync a missing ancestor in a thread to BlueSky.
+///
+/// ## Arguments
+///
+/// * `ancestor` - The Mastodon post in the thread.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// The future is boxed since `sync_post` is called recursively while
+/// repairing a thread.
+fn sync_ancestor_post<'a>(
+    ancestor: &'a megalodon::entities::Status,
+    atp_client: &'a AtpAgent<DbSessionStore, RateLimitedClient>,
+    sync_config: &'a BlueSkyPostSyncConfig,
+) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+    Box::pin(sync_post(ancestor, atp_client, sync_config))
+}
+
+/// Check if a missing ancestor in a thread should be synced.
+///
+/// ## Arguments
+///
+/// * `ancestor` - The Mastodon post in the thread.
+/// * `sync_config` - Config for the sync.
+///
+/// ## Note
+///
+/// Only public, or unlisted if allowed, posts by the account being synced are
+/// synced. Posts skipped or held by the filter rules or the reply policy, or
+/// not routed to the destination being synced to, are left out.
+fn should_sync_ancestor(
+    ancestor: &megalodon::entities::Status,
+    sync_config: &BlueSkyPostSyncConfig,
+) -> Result<bool> {
+    if ancestor.account.id != sync_config.mastodon_account.id {
+        return Ok(false);
+    }
+
+    if !is_syncable_visibility(ancestor, sync_config.config.mastodon_allow_unlisted_posts) {
+        return Ok(false);
+    }
+
+    let db_connection = &mut sync_config.db_connection_pool.get()?;
+
+    let previous_filter_decision =
+        fediproto_sync_db::operations::get_mastodon_post_filter_decision_by_post_id(
+            db_connection,
+            &sync_config.config.account_pair_id,
+            &ancestor.id,
+        )?;
+
+    if previous_filter_decision.is_some() {
+        return Ok(false);
+    }
+
+    let is_allowed_reply =
+        ReplyFilter::new(&sync_config.config).allows(ancestor, &sync_config.mastodon_account.id);
+    let filter_decision = FilterDecision::evaluate(&sync_config.post_rules.filters, ancestor);
+    let route_decision = RouteDecision::evaluate(&sync_config.post_rules.routes, ancestor);
+
+    Ok(is_allowed_reply
+        && filter_decision.action == FilterAction::Sync
+        && route_decision
+            .destinations
+            .contains(&sync_config.destination))
+}
+
 /// Resolve previously synced Mastodon posts.
 ///
 /// ## Arguments
@@ -964,6 +1942,8 @@ async fn resolve_previous_post(
 
     if !fediproto_sync_db::operations::check_synced_mastodon_post_exists(
         db_connection,
+        &sync_config.config.account_pair_id,
+        &sync_config.destination,
         in_reply_to_id,
     ) {
         return Ok(None);
@@ -971,12 +1951,16 @@ async fn resolve_previous_post(
 
     let previous_mastodon_post = fediproto_sync_db::operations::get_synced_mastodon_post_by_id(
         db_connection,
-        &in_reply_to_id,
+        &sync_config.config.account_pair_id,
+        &sync_config.destination,
+        in_reply_to_id,
     )?;
 
     let previous_synced_post = fediproto_sync_db::operations::get_bluesky_data_by_mastodon_post_id(
         db_connection,
-        &in_reply_to_id,
+        &sync_config.config.account_pair_id,
+        &sync_config.destination,
+        in_reply_to_id,
     )?;
 
     let (previous_synced_post_root, previous_post_id) =
@@ -986,6 +1970,8 @@ async fn resolve_previous_post(
                 (
                     fediproto_sync_db::operations::get_bluesky_data_by_mastodon_post_id(
                         db_connection,
+                        &sync_config.config.account_pair_id,
+                        &sync_config.destination,
                         &root_mastodon_post_id,
                     )?,
                     root_mastodon_post_id.clone(),
@@ -1074,11 +2060,3 @@ struct JobStatusResponse {
     #[serde(rename = "jobStatus")]
     pub job_status: JobStatus,
 }
-
-/// The response to a request to upload a video.
-#[derive(Serialize, Deserialize, Debug)]
-struct UploadVideoResponse {
-    /// The status of the job.
-    #[serde(rename = "jobStatus")]
-    pub job_status: JobStatus,
-}
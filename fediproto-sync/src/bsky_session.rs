@@ -0,0 +1,463 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use atrium_api::{
+    agent::{
+        AuthorizationProvider,
+        atp_agent::{AtpAgent, AtpSession, store::AtpSessionStore},
+    },
+    com::atproto::server::create_session,
+    types::string::{Did, Handle},
+    xrpc::types::AuthorizationToken,
+};
+use atrium_common::store::Store;
+use chrono::{DateTime, Utc};
+use diesel::r2d2::{ConnectionManager, Pool};
+use fediproto_sync_db::{
+    AnyConnection,
+    models::{CachedServiceTokenDecrypt, NewCachedServiceToken},
+};
+use fediproto_sync_lib::{
+    config::FediProtoSyncConfig,
+    error::{AuthenticationSource, FediProtoSyncError},
+};
+use serde::Deserialize;
+
+use crate::{rate_limit::RateLimitedClient, sync_error::from_xrpc_error};
+
+/// How long before the access token of a session expires to refresh it.
+pub const SESSION_REFRESH_MARGIN: Duration = Duration::from_secs(15 * 60);
+
+/// The claims read from the JWTs of a BlueSky session.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SessionTokenClaims {
+    /// The DID of the account the token is for.
+    pub sub: Option<String>,
+
+    /// The UNIX timestamp the token expires at.
+    pub exp: Option<i64>,
+}
+
+impl SessionTokenClaims {
+    /// Read the claims from a JWT.
+    ///
+    /// ## Arguments
+    ///
+    /// * `jwt` - The JWT to read the claims from.
+    ///
+    /// ## Note
+    ///
+    /// The signature isn't verified, since the PDS verifies it whenever the
+    /// token is used. Returns `None` if the JWT can't be read.
+    pub fn from_jwt(jwt: &str) -> Option<Self> {
+        let payload = jwt.split('.').nth(1)?;
+
+        // JWTs are encoded with the URL safe alphabet and without padding.
+        let mut payload = payload.replace('-', "+").replace('_', "/");
+        while payload.len() % 4 != 0 {
+            payload.push('=');
+        }
+
+        let payload = openssl::base64::decode_block(&payload).ok()?;
+
+        serde_json::from_slice(&payload).ok()
+    }
+
+    /// Get the time the token expires at.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.exp
+            .and_then(|expires_at| DateTime::from_timestamp(expires_at, 0))
+    }
+}
+
+/// Get the service name the session of a BlueSky account is cached under.
+///
+/// ## Arguments
+///
+/// * `bluesky_handle` - The handle the account logs in with.
+pub fn session_service_name(bluesky_handle: &str) -> String {
+    format!("bluesky:{}", bluesky_handle.to_lowercase())
+}
+
+/// Check if a session should be refreshed.
+///
+/// ## Arguments
+///
+/// * `expires_at` - The time the access token of the session expires at, if
+///   known.
+/// * `now` - The current time.
+/// * `margin` - How long before the access token expires to refresh it.
+///
+/// ## Note
+///
+/// Sessions without a known expiry aren't refreshed ahead of time, and are
+/// only refreshed once the PDS says the access token expired.
+pub fn is_refresh_due(
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    margin: Duration,
+) -> bool {
+    let margin = chrono::Duration::from_std(margin).unwrap_or(chrono::Duration::MAX);
+
+    match expires_at {
+        Some(expires_at) => expires_at - now <= margin,
+        None => false,
+    }
+}
+
+/// A session store that keeps the BlueSky session encrypted in the
+/// `cached_service_tokens` table, so it can be resumed after a restart.
+#[derive(Clone)]
+pub struct DbSessionStore {
+    /// The database connection pool.
+    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+
+    /// The ID of the account pair the session is for.
+    account_pair_id: String,
+
+    /// The service name the session is cached under.
+    service_name: String,
+
+    /// The public key to encrypt the tokens with.
+    token_encryption_public_key: openssl::rsa::Rsa<openssl::pkey::Public>,
+
+    /// The private key to decrypt the tokens with.
+    token_encryption_private_key: openssl::rsa::Rsa<openssl::pkey::Private>,
+
+    /// The current session.
+    session: Arc<Mutex<Option<AtpSession>>>,
+}
+
+impl DbSessionStore {
+    /// Create a new instance of the `DbSessionStore` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config with the BlueSky account the session is for.
+    /// * `db_connection_pool` - The database connection pool.
+    pub fn new(
+        config: &FediProtoSyncConfig,
+        db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    ) -> Self {
+        Self {
+            db_connection_pool,
+            account_pair_id: config.account_pair_id.clone(),
+            service_name: session_service_name(&config.bluesky_handle),
+            token_encryption_public_key: config.token_encryption_public_key.clone(),
+            token_encryption_private_key: config.token_encryption_private_key.clone(),
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Load the cached session from the database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `bluesky_handle` - The handle the account logs in with.
+    ///
+    /// ## Note
+    ///
+    /// Only the JWTs are cached, so the DID is read from the access token.
+    /// The handle and DID document are filled in from the PDS when the
+    /// session is resumed.
+    pub fn load_cached_session(
+        &self,
+        bluesky_handle: &str,
+    ) -> Result<Option<AtpSession>> {
+        let db_connection = &mut self.db_connection_pool.get()?;
+
+        let cached_token = fediproto_sync_db::operations::get_cached_service_token_by_service_name(
+            db_connection,
+            &self.account_pair_id,
+            &self.service_name,
+        )?;
+
+        let Some(cached_token) = cached_token else {
+            return Ok(None);
+        };
+
+        let access_jwt = cached_token.decrypt_access_token(&self.token_encryption_private_key)?;
+        let Some(refresh_jwt) =
+            cached_token.decrypt_refresh_token(&self.token_encryption_private_key)?
+        else {
+            return Ok(None);
+        };
+
+        let did = SessionTokenClaims::from_jwt(&access_jwt)
+            .and_then(|claims| claims.sub)
+            .and_then(|sub| Did::new(sub).ok());
+        let handle = Handle::new(bluesky_handle.trim_start_matches('@').to_string()).ok();
+
+        let (Some(did), Some(handle)) = (did, handle) else {
+            return Ok(None);
+        };
+
+        let session = create_session::OutputData {
+            access_jwt,
+            active: None,
+            did,
+            did_doc: None,
+            email: None,
+            email_auth_factor: None,
+            email_confirmed: None,
+            handle,
+            refresh_jwt,
+            status: None,
+        };
+
+        Ok(Some(session.into()))
+    }
+
+    /// Save a session to the database.
+    ///
+    /// ## Arguments
+    ///
+    /// * `session` - The session to save.
+    fn save_session(
+        &self,
+        session: &AtpSession,
+    ) -> Result<(), FediProtoSyncError> {
+        let expires_in = SessionTokenClaims::from_jwt(&session.access_jwt)
+            .and_then(|claims| claims.expires_at())
+            .map(|expires_at| {
+                (expires_at - Utc::now())
+                    .num_seconds()
+                    .clamp(0, i32::MAX as i64) as i32
+            });
+
+        let new_token = NewCachedServiceToken::new(
+            &self.token_encryption_public_key,
+            &self.account_pair_id,
+            &self.service_name,
+            &session.access_jwt,
+            Some(session.refresh_jwt.clone()),
+            expires_in,
+            None,
+        )?;
+
+        let db_connection = &mut self
+            .db_connection_pool
+            .get()
+            .map_err(|_| FediProtoSyncError::DatabaseConnectionPoolError)?;
+        fediproto_sync_db::operations::replace_cached_service_token(db_connection, &new_token)
+            .map_err(|e| FediProtoSyncError::DatabaseError {
+                source: Arc::new(e),
+            })?;
+
+        Ok(())
+    }
+
+    /// Delete the session from the database.
+    fn delete_session(&self) -> Result<(), FediProtoSyncError> {
+        let db_connection = &mut self
+            .db_connection_pool
+            .get()
+            .map_err(|_| FediProtoSyncError::DatabaseConnectionPoolError)?;
+        fediproto_sync_db::operations::delete_cached_service_token(
+            db_connection,
+            &self.account_pair_id,
+            &self.service_name,
+        )
+        .map_err(|e| FediProtoSyncError::DatabaseError {
+            source: Arc::new(e),
+        })?;
+
+        Ok(())
+    }
+
+    /// Get the current session.
+    fn current_session(&self) -> Option<AtpSession> {
+        self.session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replace the current session.
+    ///
+    /// ## Arguments
+    ///
+    /// * `session` - The new session, or `None` to remove it.
+    fn replace_current_session(
+        &self,
+        session: Option<AtpSession>,
+    ) {
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = session;
+    }
+}
+
+impl Store<(), AtpSession> for DbSessionStore {
+    type Error = FediProtoSyncError;
+
+    async fn get(
+        &self,
+        _key: &(),
+    ) -> Result<Option<AtpSession>, Self::Error> {
+        Ok(self.current_session())
+    }
+
+    async fn set(
+        &self,
+        _key: (),
+        value: AtpSession,
+    ) -> Result<(), Self::Error> {
+        // The agent ignores errors from the store, so the session is still
+        // used if it can't be cached. It just can't be resumed after a
+        // restart.
+        let save_result = self.save_session(&value);
+        if let Err(e) = &save_result {
+            tracing::warn!(
+                "Failed to cache the BlueSky session for '{}': {:#}",
+                self.service_name,
+                e
+            );
+        }
+
+        self.replace_current_session(Some(value));
+
+        save_result
+    }
+
+    async fn del(
+        &self,
+        _key: &(),
+    ) -> Result<(), Self::Error> {
+        self.replace_current_session(None);
+
+        let delete_result = self.delete_session();
+        if let Err(e) = &delete_result {
+            tracing::warn!(
+                "Failed to delete the cached BlueSky session for '{}': {:#}",
+                self.service_name,
+                e
+            );
+        }
+
+        delete_result
+    }
+
+    async fn clear(&self) -> Result<(), Self::Error> {
+        self.del(&()).await
+    }
+}
+
+impl AuthorizationProvider for DbSessionStore {
+    async fn authorization_token(
+        &self,
+        is_refresh: bool,
+    ) -> Option<AuthorizationToken> {
+        // `is_refresh` is only set for `com.atproto.server.refreshSession`,
+        // which takes the refresh token instead of the access token.
+        self.current_session().map(|session| match is_refresh {
+            true => AuthorizationToken::Bearer(session.refresh_jwt.clone()),
+            false => AuthorizationToken::Bearer(session.access_jwt.clone()),
+        })
+    }
+}
+
+impl AtpSessionStore for DbSessionStore {}
+
+/// Refresh a BlueSky session if its access token expires soon.
+///
+/// ## Arguments
+///
+/// * `atp_agent` - The ATProto agent for the session.
+/// * `bluesky_handle` - The handle to log in with if the refresh fails.
+/// * `bluesky_app_password` - The app password to log in with if the refresh
+///   fails.
+/// * `margin` - How long before the access token expires to refresh it.
+///
+/// ## Note
+///
+/// Only logs in with the app password again if there's no session or the
+/// refresh token was rejected, to stay clear of the `createSession` rate
+/// limit.
+pub async fn refresh_session_if_expiring(
+    atp_agent: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    bluesky_handle: &str,
+    bluesky_app_password: &str,
+    margin: Duration,
+) -> Result<()> {
+    if let Some(session) = atp_agent.get_session().await {
+        let expires_at = SessionTokenClaims::from_jwt(&session.access_jwt)
+            .and_then(|claims| claims.expires_at());
+
+        if !is_refresh_due(expires_at, Utc::now(), margin) {
+            return Ok(());
+        }
+
+        match refresh_session(atp_agent, session).await {
+            Ok(_) => {
+                tracing::info!("Refreshed the BlueSky session for '{}'.", bluesky_handle);
+
+                return Ok(());
+            }
+
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to refresh the BlueSky session for '{}', logging in again: {:#}",
+                    bluesky_handle,
+                    e
+                );
+            }
+        }
+    }
+
+    atp_agent
+        .login(bluesky_handle, bluesky_app_password)
+        .await
+        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::BlueSky))?;
+
+    tracing::info!("Logged in to BlueSky again as '{}'.", bluesky_handle);
+
+    Ok(())
+}
+
+/// Refresh a BlueSky session with its refresh token.
+///
+/// ## Arguments
+///
+/// * `atp_agent` - The ATProto agent for the session.
+/// * `session` - The current session.
+///
+/// ## Note
+///
+/// The refreshed session is resumed on the agent, so it's saved to the
+/// agent's session store.
+async fn refresh_session(
+    atp_agent: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    session: AtpSession,
+) -> Result<()> {
+    let refreshed_session = atp_agent
+        .api
+        .com
+        .atproto
+        .server
+        .refresh_session()
+        .await
+        .map_err(from_xrpc_error)?
+        .data;
+
+    let new_session = create_session::OutputData {
+        access_jwt: refreshed_session.access_jwt,
+        active: refreshed_session.active,
+        did: refreshed_session.did,
+        did_doc: refreshed_session.did_doc,
+        email: session.email.clone(),
+        email_auth_factor: session.email_auth_factor,
+        email_confirmed: session.email_confirmed,
+        handle: refreshed_session.handle,
+        refresh_jwt: refreshed_session.refresh_jwt,
+        status: refreshed_session.status,
+    };
+
+    atp_agent
+        .resume_session(new_session.into())
+        .await
+        .map_err(from_xrpc_error)?;
+
+    Ok(())
+}
@@ -1,6 +1,12 @@
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::{Args, Parser, Subcommand};
-use fediproto_sync_lib::config::{DatabaseType, FediProtoSyncConfig, FediProtoSyncMode};
+use fediproto_sync_lib::config::{
+    DEFAULT_ACCOUNT_PAIR_ID, DatabaseType, FediProtoSyncConfig, FediProtoSyncMode,
+    LinkCardPreference, LinkMetadataBackend, ReplyPolicy, SourcePlatform, ThreadRepairMode,
+};
+
+use crate::backfill::BackfillOptions;
 
 #[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
@@ -16,6 +22,25 @@ pub enum CliSubcommands {
 
     /// Generate keypair for token encryption.
     GenerateTokenEncryptionKey,
+
+    /// List the posts waiting for the publish delay to pass.
+    ListPendingPosts(ListPendingPostsArgs),
+
+    /// Sync older posts from a date range to BlueSky.
+    Backfill(BackfillArgs),
+
+    /// List the posts that ran out of retries.
+    ListDeadLetters(ListDeadLettersArgs),
+
+    /// Show the last error and rendered post of a post that ran out of
+    /// retries.
+    InspectDeadLetter(DeadLetterArgs),
+
+    /// Move a post that ran out of retries back to the retry queue.
+    RequeueDeadLetter(DeadLetterArgs),
+
+    /// Discard a post that ran out of retries, so it's never synced.
+    DiscardDeadLetter(DeadLetterArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -84,6 +109,15 @@ pub struct RunArgs {
     )]
     pub user_agent: String,
 
+    /// The platform the source server runs.
+    #[arg(
+        long = "source-platform",
+        env = "SOURCE_PLATFORM",
+        default_value_t = SourcePlatform::Auto,
+        value_enum
+    )]
+    pub source_platform: SourcePlatform,
+
     /// The Mastodon server URL to connect to.
     #[arg(long = "mastodon-server", env = "MASTODON_SERVER", required = true)]
     pub mastodon_server: String,
@@ -141,6 +175,80 @@ pub struct RunArgs {
     )]
     pub sync_interval: std::time::Duration,
 
+    /// Whether to sync posts as soon as they're posted with the Mastodon
+    /// streaming API.
+    #[arg(
+        long = "mastodon-streaming",
+        env = "MASTODON_STREAMING",
+        default_value_t = false
+    )]
+    pub mastodon_streaming: bool,
+
+    /// The longest time, in seconds, to wait before reconnecting to the
+    /// Mastodon streaming API.
+    #[arg(
+        long = "streaming-max-reconnect-delay",
+        env = "STREAMING_MAX_RECONNECT_DELAY_SECONDS",
+        default_value = "300",
+        value_parser = sync_interval_parser
+    )]
+    pub streaming_max_reconnect_delay: std::time::Duration,
+
+    /// The secret for verifying the signatures of Mastodon webhooks.
+    #[arg(long = "mastodon-webhook-secret", env = "MASTODON_WEBHOOK_SECRET")]
+    pub mastodon_webhook_secret: Option<String>,
+
+    /// The maximum number of posts to sync at once when catching up on the
+    /// posts made since the last sync.
+    #[arg(
+        long = "catch-up-max-posts",
+        env = "CATCH_UP_MAX_POSTS",
+        default_value_t = 400
+    )]
+    pub catch_up_max_posts: usize,
+
+    /// The maximum number of times to try syncing a post before moving it to
+    /// the dead letters.
+    #[arg(
+        long = "retry-max-attempts",
+        env = "RETRY_MAX_ATTEMPTS",
+        default_value_t = 8
+    )]
+    pub retry_max_attempts: u32,
+
+    /// The time, in seconds, to wait before retrying a post that failed to
+    /// sync for the first time.
+    #[arg(
+        long = "retry-initial-delay",
+        env = "RETRY_INITIAL_DELAY_SECONDS",
+        default_value = "300",
+        value_parser = sync_interval_parser
+    )]
+    pub retry_initial_delay: std::time::Duration,
+
+    /// The longest time, in seconds, to wait before retrying a post.
+    #[arg(
+        long = "retry-max-delay",
+        env = "RETRY_MAX_DELAY_SECONDS",
+        default_value = "21600",
+        value_parser = sync_interval_parser
+    )]
+    pub retry_max_delay: std::time::Duration,
+
+    /// The longest time, in seconds, to wait for a rate limit to reset before
+    /// a request is deferred.
+    #[arg(
+        long = "rate-limit-max-wait",
+        env = "RATE_LIMIT_MAX_WAIT_SECONDS",
+        default_value = "60",
+        value_parser = sync_interval_parser
+    )]
+    pub rate_limit_max_wait: std::time::Duration,
+
+    /// The address to serve the Prometheus metrics on, like `0.0.0.0:9100`.
+    #[arg(long = "metrics-address", env = "METRICS_ADDRESS")]
+    pub metrics_address: Option<String>,
+
     /// Whether to always fallback to the video URL for BlueSky posts.
     #[arg(
         long = "bluesky-video-always-fallback",
@@ -156,29 +264,254 @@ pub struct RunArgs {
         default_value_t = false
     )]
     pub mastodon_allow_unlisted_posts: bool,
+
+    /// The backend to use for getting link metadata for link cards.
+    #[arg(
+        long = "link-metadata-backend",
+        env = "LINK_METADATA_BACKEND",
+        default_value_t = LinkMetadataBackend::Local,
+        value_enum
+    )]
+    pub link_metadata_backend: LinkMetadataBackend,
+
+    /// The timeout, in seconds, for requests made to get link metadata.
+    #[arg(
+        long = "link-metadata-timeout",
+        env = "LINK_METADATA_TIMEOUT_SECONDS",
+        default_value = "10",
+        value_parser = sync_interval_parser
+    )]
+    pub link_metadata_timeout: std::time::Duration,
+
+    /// The maximum size, in bytes, of a page to read when getting link
+    /// metadata.
+    #[arg(
+        long = "link-metadata-max-size",
+        env = "LINK_METADATA_MAX_SIZE",
+        default_value_t = 1_048_576
+    )]
+    pub link_metadata_max_size: u64,
+
+    /// How long, in seconds, link metadata and uploaded thumbnails are cached
+    /// for.
+    #[arg(
+        long = "link-metadata-cache-ttl",
+        env = "LINK_METADATA_CACHE_TTL_SECONDS",
+        default_value = "86400",
+        value_parser = sync_interval_parser
+    )]
+    pub link_metadata_cache_ttl: std::time::Duration,
+
+    /// Which link in a post to prefer for the link card.
+    #[arg(
+        long = "link-card-preference",
+        env = "LINK_CARD_PREFERENCE",
+        default_value_t = LinkCardPreference::Last,
+        value_enum
+    )]
+    pub link_card_preference: LinkCardPreference,
+
+    /// The marker to put before a link in a post to use it for the link card.
+    #[arg(
+        long = "link-card-override-marker",
+        env = "LINK_CARD_OVERRIDE_MARKER",
+        default_value = "🔗"
+    )]
+    pub link_card_override_marker: String,
+
+    /// Domains that should never be used for a link card.
+    #[arg(
+        long = "link-card-blocked-domains",
+        env = "LINK_CARD_BLOCKED_DOMAINS",
+        value_delimiter = ','
+    )]
+    pub link_card_blocked_domains: Vec<String>,
+
+    /// The path to the file with rules for filtering posts.
+    #[arg(long = "post-rules-file", env = "POST_RULES_FILE")]
+    pub post_rules_file: Option<std::path::PathBuf>,
+
+    /// How long, in minutes, to wait after a post is created before syncing
+    /// it.
+    #[arg(
+        long = "publish-delay",
+        env = "PUBLISH_DELAY_MINUTES",
+        default_value = "0",
+        value_parser = publish_delay_parser
+    )]
+    pub publish_delay: std::time::Duration,
+
+    /// How replies to other accounts are synced.
+    #[arg(
+        long = "reply-policy",
+        env = "REPLY_POLICY",
+        default_value_t = ReplyPolicy::Skip,
+        value_enum
+    )]
+    pub reply_policy: ReplyPolicy,
+
+    /// A hashtag that makes a reply to another account eligible for syncing,
+    /// even if it mentions someone.
+    #[arg(long = "reply-hashtag", env = "REPLY_HASHTAG")]
+    pub reply_hashtag: Option<String>,
+
+    /// How to thread a reply when the post it replies to was never synced.
+    #[arg(
+        long = "thread-repair-mode",
+        env = "THREAD_REPAIR_MODE",
+        default_value_t = ThreadRepairMode::Off,
+        value_enum
+    )]
+    pub thread_repair_mode: ThreadRepairMode,
+
+    /// The interval, in seconds, to sync the profile.
+    #[arg(
+        long = "profile-sync-interval",
+        env = "PROFILE_SYNC_INTERVAL_SECONDS",
+        default_value = "3600",
+        value_parser = sync_interval_parser
+    )]
+    pub profile_sync_interval: std::time::Duration,
+
+    /// Whether to mirror the pinned post on Mastodon to the BlueSky profile.
+    #[arg(
+        long = "sync-pinned-post",
+        env = "SYNC_PINNED_POST",
+        default_value_t = false
+    )]
+    pub sync_pinned_post: bool,
+
+    /// Whether to sync the display name, bio, avatar, and banner from Mastodon
+    /// to the BlueSky profile.
+    #[arg(long = "sync-profile", env = "SYNC_PROFILE", default_value_t = false)]
+    pub sync_profile: bool,
+
+    /// The path to the file with more account pairs to sync.
+    #[arg(long = "account-pairs-file", env = "ACCOUNT_PAIRS_FILE")]
+    pub account_pairs_file: Option<std::path::PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ListPendingPostsArgs {
+    /// The URL/path to the database.
+    #[arg(long = "database-url", env = "DATABASE_URL", required = true)]
+    pub database_url: String,
 }
 
-impl Into<FediProtoSyncConfig> for RunArgs {
-    fn into(self) -> FediProtoSyncConfig {
+#[derive(Args, Debug, Clone)]
+pub struct ListDeadLettersArgs {
+    /// The URL/path to the database.
+    #[arg(long = "database-url", env = "DATABASE_URL", required = true)]
+    pub database_url: String,
+
+    /// Only list the dead letters of this account pair.
+    #[arg(long = "account-pair")]
+    pub account_pair: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DeadLetterArgs {
+    /// The URL/path to the database.
+    #[arg(long = "database-url", env = "DATABASE_URL", required = true)]
+    pub database_url: String,
+
+    /// The ID of the Mastodon post.
+    pub post_id: String,
+
+    /// The ID of the account pair the post was synced by. Defaults to the
+    /// pair configured by the environment variables.
+    #[arg(long = "account-pair")]
+    pub account_pair: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackfillArgs {
+    #[command(flatten)]
+    pub run_args: RunArgs,
+
+    /// The date to start backfilling from, like `2024-01-01` or
+    /// `2024-01-01T12:00:00Z`.
+    #[arg(long = "since", required = true, value_parser = backfill_date_parser)]
+    pub since: DateTime<Utc>,
+
+    /// The date to backfill up to, but not including. Defaults to now.
+    #[arg(long = "until", value_parser = backfill_date_parser)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// The maximum number of posts to sync per hour. Set to `0` to disable
+    /// the limit.
+    #[arg(long = "max-posts-per-hour", default_value_t = 30)]
+    pub max_posts_per_hour: u32,
+
+    /// Only list the posts that would be synced.
+    #[arg(long = "dry-run", default_value_t = false)]
+    pub dry_run: bool,
+
+    /// The ID of the account pair to backfill. Defaults to the pair configured
+    /// by the environment variables.
+    #[arg(long = "account-pair")]
+    pub account_pair: Option<String>,
+}
+
+impl From<&BackfillArgs> for BackfillOptions {
+    fn from(args: &BackfillArgs) -> Self {
+        BackfillOptions {
+            since: args.since.to_owned(),
+            until: args.until.unwrap_or_else(Utc::now),
+            max_posts_per_hour: args.max_posts_per_hour.to_owned(),
+            dry_run: args.dry_run.to_owned(),
+        }
+    }
+}
+
+impl From<RunArgs> for FediProtoSyncConfig {
+    fn from(args: RunArgs) -> Self {
         FediProtoSyncConfig {
-            mode: self.mode.to_owned(),
-            auth_server_address: Some(self.auth_server_address.to_owned()),
-            auth_server_port: Some(self.auth_server_port.to_owned()),
-            database_type: self.database_type.to_owned(),
-            database_url: self.database_url.to_owned(),
-            token_encryption_private_key: self.token_encryption_private_key.to_owned(),
-            token_encryption_public_key: self.token_encryption_public_key.to_owned(),
-            user_agent: self.user_agent.to_owned(),
-            mastodon_server: self.mastodon_server.to_owned(),
-            mastodon_client_id: self.mastodon_client_id.to_owned(),
-            mastodon_client_secret: self.mastodon_client_secret.to_owned(),
-            mastodon_redirect_uri: self.mastodon_redirect_uri.to_owned(),
-            bluesky_pds_server: self.bluesky_pds_server.to_owned(),
-            bluesky_handle: self.bluesky_handle.to_owned(),
-            bluesky_app_password: self.bluesky_app_password.to_owned(),
-            sync_interval: self.sync_interval.to_owned(),
-            bluesky_video_always_fallback: self.bluesky_video_always_fallback.to_owned(),
-            mastodon_allow_unlisted_posts: self.mastodon_allow_unlisted_posts.to_owned(),
+            mode: args.mode.to_owned(),
+            auth_server_address: Some(args.auth_server_address.to_owned()),
+            auth_server_port: Some(args.auth_server_port.to_owned()),
+            database_type: args.database_type.to_owned(),
+            database_url: args.database_url.to_owned(),
+            token_encryption_private_key: args.token_encryption_private_key.to_owned(),
+            token_encryption_public_key: args.token_encryption_public_key.to_owned(),
+            user_agent: args.user_agent.to_owned(),
+            source_platform: args.source_platform,
+            mastodon_server: args.mastodon_server.to_owned(),
+            mastodon_client_id: args.mastodon_client_id.to_owned(),
+            mastodon_client_secret: args.mastodon_client_secret.to_owned(),
+            mastodon_redirect_uri: args.mastodon_redirect_uri.to_owned(),
+            bluesky_pds_server: args.bluesky_pds_server.to_owned(),
+            bluesky_handle: args.bluesky_handle.to_owned(),
+            bluesky_app_password: args.bluesky_app_password.to_owned(),
+            sync_interval: args.sync_interval.to_owned(),
+            mastodon_streaming: args.mastodon_streaming.to_owned(),
+            streaming_max_reconnect_delay: args.streaming_max_reconnect_delay.to_owned(),
+            mastodon_webhook_secret: args.mastodon_webhook_secret.to_owned(),
+            catch_up_max_posts: args.catch_up_max_posts.to_owned(),
+            retry_max_attempts: args.retry_max_attempts.to_owned(),
+            retry_initial_delay: args.retry_initial_delay.to_owned(),
+            retry_max_delay: args.retry_max_delay.to_owned(),
+            rate_limit_max_wait: args.rate_limit_max_wait.to_owned(),
+            metrics_address: args.metrics_address.to_owned(),
+            bluesky_video_always_fallback: args.bluesky_video_always_fallback.to_owned(),
+            mastodon_allow_unlisted_posts: args.mastodon_allow_unlisted_posts.to_owned(),
+            link_metadata_backend: args.link_metadata_backend.to_owned(),
+            link_metadata_timeout: args.link_metadata_timeout.to_owned(),
+            link_metadata_max_size: args.link_metadata_max_size.to_owned(),
+            link_metadata_cache_ttl: args.link_metadata_cache_ttl.to_owned(),
+            link_card_preference: args.link_card_preference.to_owned(),
+            link_card_override_marker: args.link_card_override_marker.to_owned(),
+            link_card_blocked_domains: args.link_card_blocked_domains.to_owned(),
+            post_rules_file: args.post_rules_file.to_owned(),
+            publish_delay: args.publish_delay.to_owned(),
+            reply_policy: args.reply_policy.to_owned(),
+            reply_hashtag: args.reply_hashtag.to_owned(),
+            thread_repair_mode: args.thread_repair_mode.to_owned(),
+            profile_sync_interval: args.profile_sync_interval.to_owned(),
+            sync_pinned_post: args.sync_pinned_post.to_owned(),
+            sync_profile: args.sync_profile.to_owned(),
+            account_pairs_file: args.account_pairs_file.to_owned(),
+            account_pair_id: DEFAULT_ACCOUNT_PAIR_ID.to_string(),
         }
     }
 }
@@ -188,3 +521,19 @@ fn sync_interval_parser(value: &str) -> Result<std::time::Duration> {
 
     Ok(std::time::Duration::from_secs(value))
 }
+
+fn publish_delay_parser(value: &str) -> Result<std::time::Duration> {
+    let value = value.parse::<u64>()?;
+
+    Ok(std::time::Duration::from_secs(value * 60))
+}
+
+fn backfill_date_parser(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date_time.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")?;
+
+    Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+}
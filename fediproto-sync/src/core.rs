@@ -1,320 +1,1775 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Result;
-use atrium_api::{
-    agent::{atp_agent::AtpAgent, atp_agent::store::MemorySessionStore},
-    client::AtpServiceClient,
-    types::string::Did,
-};
-use atrium_xrpc_client::reqwest::{ReqwestClient, ReqwestClientBuilder};
+use atrium_api::{agent::atp_agent::AtpAgent, client::AtpServiceClient, types::string::Did};
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use fediproto_sync_auth_ui::web::mastodon_webhook::WebhookStatus;
 use fediproto_sync_db::{
     AnyConnection,
-    models::{self, CachedServiceTokenDecrypt, NewMastodonPostRetryQueueItem},
+    models::{self, CachedServiceTokenDecrypt},
 };
 use fediproto_sync_lib::{
-    config::FediProtoSyncConfig,
-    error::{AuthenticationSource, FediProtoSyncError},
+    config::{FediProtoSyncConfig, ReplyPolicy},
+    error::{AuthenticationSource, ErrorClass, FediProtoSyncError},
 };
 use megalodon::{Megalodon, entities::Account};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{
+    bsky_post_sync::BLUESKY_DESTINATION,
+    bsky_session::{self, DbSessionStore},
+    mastodon::{MastodonApiExtensions, MastodonHttpClient},
+    profile,
+    rate_limit::{self, MastodonRateLimiter, RateLimitedClient},
+    replies::ReplyFilter,
+    retry, routing,
+    rules::{
+        PostRules,
+        filter::{FilterAction, FilterDecision},
+    },
+    streaming::{self, StreamMessage, StreamingEvent},
+    sync_error::{classify_error, from_xrpc_error},
+};
+
+/// The rule name recorded for posts skipped by the reply policy.
+const REPLY_POLICY_RULE_NAME: &str = "reply-policy";
+
+/// A BlueSky session to sync posts to.
+pub struct BlueSkySession {
+    /// The ATProto agent for the session.
+    pub atp_agent: AtpAgent<DbSessionStore, RateLimitedClient>,
+
+    /// The PDS service endpoint for the session.
+    pub pds_service_endpoint: String,
+
+    /// The DID of the session.
+    pub did: Did,
+
+    /// The handle to log in again with if the session can't be refreshed.
+    bluesky_handle: String,
+
+    /// The app password to log in again with if the session can't be
+    /// refreshed.
+    bluesky_app_password: String,
+}
+
+impl BlueSkySession {
+    /// Resume the cached session of the account in the config, or log in to
+    /// BlueSky if it can't be resumed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config with the BlueSky account to log in with.
+    /// * `db_connection_pool` - The database connection pool.
+    pub(crate) async fn new(
+        config: &FediProtoSyncConfig,
+        db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    ) -> Result<BlueSkySession> {
+        let session_store = DbSessionStore::new(config, db_connection_pool.clone());
+        let atp_auth_data = create_atp_agent(config, session_store).await?;
+
+        Ok(BlueSkySession {
+            atp_agent: atp_auth_data.0,
+            pds_service_endpoint: atp_auth_data.1.replace("https://", ""),
+            did: atp_auth_data.2,
+            bluesky_handle: config.bluesky_handle.clone(),
+            bluesky_app_password: config.bluesky_app_password.clone(),
+        })
+    }
+
+    /// Refresh the session if its access token expires soon.
+    ///
+    /// ## Arguments
+    ///
+    /// * `margin` - How long before the access token expires to refresh it.
+    pub async fn refresh_if_expiring(
+        &self,
+        margin: std::time::Duration,
+    ) -> Result<()> {
+        bsky_session::refresh_session_if_expiring(
+            &self.atp_agent,
+            &self.bluesky_handle,
+            &self.bluesky_app_password,
+            margin,
+        )
+        .await
+    }
+}
+
+pub struct SocialMediaClients {
+    /// The BlueSky session for the account pair.
+    pub bluesky_session: BlueSkySession,
+
+    /// The BlueSky sessions for the other destinations posts can be routed
+    /// to, by name.
+    pub bluesky_destinations: HashMap<String, BlueSkySession>,
+
+    /// The Mastodon client for the FediProto Sync application.
+    pub mastodon_client: Arc<Box<dyn Megalodon + Send + Sync>>,
+
+    /// The rate limiter for the requests sent with the Mastodon client.
+    pub mastodon_rate_limiter: MastodonRateLimiter,
+
+    /// The client for the Mastodon API requests the Mastodon client can't
+    /// send.
+    pub mastodon_http_client: MastodonHttpClient,
+}
+
+impl SocialMediaClients {
+    pub(crate) async fn new(
+        config: &FediProtoSyncConfig,
+        db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+        post_rules: &PostRules,
+    ) -> Result<SocialMediaClients> {
+        let bluesky_session = BlueSkySession::new(config, db_connection_pool).await?;
+
+        let mut bluesky_destinations = HashMap::new();
+        for destination in &post_rules.destinations {
+            let mut destination_config = config.clone();
+            destination_config.bluesky_handle = destination.bluesky_handle.clone();
+            destination_config.bluesky_app_password = destination.bluesky_app_password.clone();
+
+            if let Some(bluesky_pds_server) = &destination.bluesky_pds_server {
+                destination_config.bluesky_pds_server = bluesky_pds_server.clone();
+            }
+
+            tracing::info!(
+                "Logging in to BlueSky destination '{}'...",
+                destination.name
+            );
+
+            bluesky_destinations.insert(
+                destination.name.to_lowercase(),
+                BlueSkySession::new(&destination_config, db_connection_pool).await?,
+            );
+        }
+
+        let db_connection = &mut db_connection_pool.get()?;
+        let mastodon_client = Arc::new(create_mastodon_client(config, db_connection).await?);
+        let mastodon_http_client = MastodonHttpClient::new(
+            create_http_client(config)?,
+            &format!("https://{}", config.mastodon_server),
+            &get_mastodon_access_token(config, db_connection)?,
+        );
+
+        Ok(SocialMediaClients {
+            bluesky_session,
+            bluesky_destinations,
+            mastodon_client,
+            mastodon_rate_limiter: MastodonRateLimiter::new(
+                &config.mastodon_server,
+                config.rate_limit_max_wait,
+            ),
+            mastodon_http_client,
+        })
+    }
+
+    /// Get the BlueSky session for a destination.
+    ///
+    /// ## Arguments
+    ///
+    /// * `destination` - The name of the destination.
+    pub fn bluesky_session_for(
+        &self,
+        destination: &str,
+    ) -> Option<&BlueSkySession> {
+        match destination.eq_ignore_ascii_case(BLUESKY_DESTINATION) {
+            true => Some(&self.bluesky_session),
+            false => self.bluesky_destinations.get(&destination.to_lowercase()),
+        }
+    }
+
+    /// Refresh every BlueSky session whose access token expires before the
+    /// next sync.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config for the app.
+    pub async fn refresh_bluesky_sessions(
+        &self,
+        config: &FediProtoSyncConfig,
+    ) {
+        let margin = config.sync_interval + bsky_session::SESSION_REFRESH_MARGIN;
+
+        let bluesky_sessions = std::iter::once((BLUESKY_DESTINATION, &self.bluesky_session)).chain(
+            self.bluesky_destinations
+                .iter()
+                .map(|(name, session)| (name.as_str(), session)),
+        );
+
+        for (destination, bluesky_session) in bluesky_sessions {
+            if let Err(e) = bluesky_session.refresh_if_expiring(margin).await {
+                tracing::error!(
+                    "Failed to refresh the BlueSky session for '{}': {:#}",
+                    destination,
+                    e
+                );
+            }
+        }
+    }
+}
+
+pub async fn run_sync_loop(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    mut webhook_receiver: Option<&mut UnboundedReceiver<WebhookStatus>>,
+) -> Result<()> {
+    let post_rules = Arc::new(PostRules::load(config.post_rules_file.as_deref())?);
+
+    let social_media_clients =
+        SocialMediaClients::new(config, &db_connection_pool, &post_rules).await?;
+    let social_media_clients = Arc::new(social_media_clients);
+
+    let mastodon_account = social_media_clients
+        .mastodon_rate_limiter
+        .send(
+            social_media_clients
+                .mastodon_client
+                .verify_account_credentials(),
+        )
+        .await
+        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?
+        .json;
+
+    let mut last_profile_sync: Option<tokio::time::Instant> = None;
+
+    // Posts are synced as soon as the stream sends them, and the interval
+    // only polls while the stream is disconnected.
+    let mut stream_receiver = match streaming::is_streaming_enabled(config) {
+        true => {
+            let access_token = {
+                let db_connection = &mut db_connection_pool.get()?;
+
+                get_mastodon_access_token(config, db_connection)?
+            };
+
+            Some(streaming::spawn_user_stream(config, access_token)?)
+        }
+
+        false => None,
+    };
+    let mut is_stream_connected = false;
+
+    let mut interval = tokio::time::interval(config.sync_interval);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                social_media_clients.refresh_bluesky_sessions(config).await;
+
+                let is_poll_due = match is_stream_connected {
+                    true => {
+                        let db_connection = &mut db_connection_pool.get()?;
+
+                        has_queued_posts(config, db_connection)?
+                    }
+
+                    false => true,
+                };
+
+                if is_poll_due {
+                    sync_posts_and_log(
+                        config,
+                        &db_connection_pool,
+                        social_media_clients.clone(),
+                        &mastodon_account,
+                        post_rules.clone(),
+                    )
+                    .await;
+                }
+
+                let is_profile_sync_due = last_profile_sync
+                    .map(|last_profile_sync| {
+                        last_profile_sync.elapsed() >= config.profile_sync_interval
+                    })
+                    .unwrap_or(true);
+
+                if profile::is_profile_sync_enabled(config) && is_profile_sync_due {
+                    last_profile_sync = Some(tokio::time::Instant::now());
+
+                    tracing::info!("Syncing profile...");
+
+                    let profile_sync_result = profile::sync_profile(
+                        config,
+                        &db_connection_pool,
+                        social_media_clients.clone(),
+                        &mastodon_account,
+                    )
+                    .await;
+
+                    if let Err(e) = profile_sync_result {
+                        tracing::error!("Profile sync failed: {:#?}", e);
+                    }
+                }
+            }
+
+            stream_message = streaming::next_stream_message(&mut stream_receiver) => {
+                match stream_message {
+                    // Catch up on anything posted while the stream was
+                    // disconnected.
+                    Some(StreamMessage::Connected) => {
+                        is_stream_connected = true;
+
+                        sync_posts_and_log(
+                            config,
+                            &db_connection_pool,
+                            social_media_clients.clone(),
+                            &mastodon_account,
+                            post_rules.clone(),
+                        )
+                        .await;
+                    }
+
+                    Some(StreamMessage::Disconnected) => {
+                        is_stream_connected = false;
+
+                        tracing::warn!("Polling for posts until the stream reconnects.");
+                    }
+
+                    Some(StreamMessage::Event(streaming_event)) => {
+                        let handle_result = handle_streaming_event(
+                            streaming_event,
+                            config,
+                            &db_connection_pool,
+                            social_media_clients.clone(),
+                            &mastodon_account,
+                            post_rules.clone(),
+                        )
+                        .await;
+
+                        if let Err(e) = handle_result {
+                            tracing::error!("Failed to handle streaming event: {:#?}", e);
+                        }
+                    }
+
+                    None => {
+                        tracing::warn!("The stream stopped, polling for posts from now on.");
+
+                        stream_receiver = None;
+                        is_stream_connected = false;
+                    }
+                }
+            }
+
+            webhook_status = next_webhook_status(&mut webhook_receiver) => {
+                match webhook_status {
+                    Some(webhook_status) => {
+                        let handle_result = handle_webhook_status(
+                            webhook_status,
+                            config,
+                            &db_connection_pool,
+                            social_media_clients.clone(),
+                            &mastodon_account,
+                            post_rules.clone(),
+                        )
+                        .await;
+
+                        if let Err(e) = handle_result {
+                            tracing::error!("Failed to handle webhook: {:#?}", e);
+                        }
+                    }
+
+                    None => {
+                        tracing::warn!("The webhook queue closed, no longer receiving webhooks.");
+
+                        webhook_receiver = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the next status sent by a Mastodon webhook.
+///
+/// ## Arguments
+///
+/// * `receiver` - The queue of statuses, if the account pair receives
+///   webhooks.
+///
+/// ## Note
+///
+/// Never completes if the account pair doesn't receive webhooks, so it can be
+/// used in `tokio::select!` next to the sync interval.
+async fn next_webhook_status(
+    receiver: &mut Option<&mut UnboundedReceiver<WebhookStatus>>
+) -> Option<WebhookStatus> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Handle a status sent by a Mastodon webhook.
+///
+/// ## Arguments
+///
+/// * `webhook_status` - The status to handle.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+///
+/// ## Note
+///
+/// Admin webhooks are sent for every status on the server, so only statuses
+/// posted by the account are synced.
+///
+/// New statuses aren't synced by their ID. The webhook runs a sync like the
+/// sync interval does, so the sync cursor moves past the new posts in order
+/// and a post isn't synced twice if the poll also picks it up.
+async fn handle_webhook_status(
+    webhook_status: WebhookStatus,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    if webhook_status.account_id != mastodon_account.id {
+        return Ok(());
+    }
+
+    match webhook_status.event.as_str() {
+        "status.updated" => {
+            sync_edited_post(
+                &webhook_status.status_id,
+                config,
+                db_connection_pool,
+                social_media_clients,
+                mastodon_account,
+                post_rules,
+            )
+            .await?;
+        }
+
+        _ => {
+            tracing::info!(
+                "Post '{}' was sent by a webhook, syncing now.",
+                webhook_status.status_id
+            );
+
+            sync_posts_and_log(
+                config,
+                db_connection_pool,
+                social_media_clients,
+                mastodon_account,
+                post_rules,
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sync a post that was edited, unless it was already synced.
+///
+/// ## Arguments
+///
+/// * `mastodon_post_id` - The ID of the edited post.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+async fn sync_edited_post(
+    mastodon_post_id: &str,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    let synced_destinations = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_synced_mastodon_post_destinations(
+            db_connection,
+            &config.account_pair_id,
+            mastodon_post_id,
+        )?
+    };
+
+    if !synced_destinations.is_empty() {
+        tracing::warn!(
+            "Post '{}' was edited after it was synced. BlueSky posts can't be edited, so the edit isn't synced.",
+            mastodon_post_id
+        );
+
+        return Ok(());
+    }
+
+    // Pending and held posts are fetched again when they're synced, so they
+    // pick up the edit.
+    tracing::info!("Post '{}' was edited, syncing now.", mastodon_post_id);
+
+    sync_posts_and_log(
+        config,
+        db_connection_pool,
+        social_media_clients,
+        mastodon_account,
+        post_rules,
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Sync the latest posts, logging the result.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+async fn sync_posts_and_log(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) {
+    tracing::info!("Running sync...");
+
+    let sync_result = sync_posts(
+        config,
+        db_connection_pool,
+        social_media_clients,
+        mastodon_account,
+        post_rules,
+    )
+    .await;
+
+    rate_limit::log_rate_limit_budgets();
+
+    match sync_result {
+        Ok(_) => {
+            tracing::info!("Sync completed successfully.");
+        }
+        Err(e) => {
+            let error_class = classify_error(&e);
+
+            tracing::error!("Sync failed with a {} error: {:#?}", error_class, e);
+
+            if error_class == ErrorClass::Auth {
+                tracing::error!(
+                    "Check the credentials of the account pair '{}'.",
+                    config.account_pair_id
+                );
+            }
+        }
+    }
+}
+
+/// Check if there are pending, held or failed posts waiting to be synced
+/// again. Failed posts only count once their backoff has passed.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection` - The database connection to use.
+///
+/// ## Note
+///
+/// While the stream is connected, new posts come from the stream, so the
+/// interval only has to sync when posts are waiting in the database.
+fn has_queued_posts(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+) -> Result<bool> {
+    let pending_posts = fediproto_sync_db::operations::get_mastodon_pending_posts(
+        db_connection,
+        Some(&config.account_pair_id),
+    )?;
+
+    let held_posts = fediproto_sync_db::operations::get_mastodon_post_filter_decisions_by_action(
+        db_connection,
+        &config.account_pair_id,
+        FilterAction::Hold.as_str(),
+    )?;
+
+    let posts_to_retry = fediproto_sync_db::operations::get_due_mastodon_post_retry_queue_items(
+        db_connection,
+        &config.account_pair_id,
+        chrono::Utc::now().naive_utc(),
+    )?;
+
+    Ok(!pending_posts.is_empty() || !held_posts.is_empty() || !posts_to_retry.is_empty())
+}
+
+/// Handle an event from the Mastodon streaming API.
+///
+/// ## Arguments
+///
+/// * `streaming_event` - The event to handle.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+///
+/// ## Note
+///
+/// The user stream includes posts from followed accounts, so only events for
+/// the account's own posts are handled.
+async fn handle_streaming_event(
+    streaming_event: StreamingEvent,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    match streaming_event {
+        StreamingEvent::Update(status) => {
+            if status.account.id != mastodon_account.id {
+                return Ok(());
+            }
+
+            tracing::info!("Post '{}' was posted, syncing now.", status.id);
+
+            sync_posts_and_log(
+                config,
+                db_connection_pool,
+                social_media_clients,
+                mastodon_account,
+                post_rules,
+            )
+            .await;
+        }
+
+        StreamingEvent::StatusUpdate(status) => {
+            if status.account.id != mastodon_account.id {
+                return Ok(());
+            }
+
+            sync_edited_post(
+                &status.id,
+                config,
+                db_connection_pool,
+                social_media_clients,
+                mastodon_account,
+                post_rules,
+            )
+            .await?;
+        }
+
+        StreamingEvent::Delete(mastodon_post_id) => {
+            // The connection is released before deleting the post from the
+            // destinations, since that takes connections of its own.
+            {
+                let db_connection = &mut db_connection_pool.get()?;
+
+                if fediproto_sync_db::operations::get_mastodon_pending_post_exists(
+                    db_connection,
+                    &config.account_pair_id,
+                    &mastodon_post_id,
+                )? {
+                    fediproto_sync_db::operations::delete_mastodon_pending_post(
+                        db_connection,
+                        &config.account_pair_id,
+                        &mastodon_post_id,
+                    )?;
+
+                    tracing::info!(
+                        "Pending post '{}' was deleted, dropping it.",
+                        mastodon_post_id
+                    );
+                }
+
+                let retry_item =
+                    fediproto_sync_db::operations::get_mastodon_post_retry_queue_item_by_post_id(
+                        db_connection,
+                        &config.account_pair_id,
+                        &mastodon_post_id,
+                    )?;
 
-use crate::{bsky_post_sync, mastodon::MastodonApiExtensions};
+                if let Some(retry_item) = retry_item {
+                    fediproto_sync_db::operations::delete_mastodon_post_retry_queue_item(
+                        db_connection,
+                        &retry_item,
+                    )?;
+
+                    tracing::info!(
+                        "Post '{}' was deleted, removing it from the retry queue.",
+                        mastodon_post_id
+                    );
+                }
+
+                if fediproto_sync_db::operations::delete_mastodon_post_dead_letter(
+                    db_connection,
+                    &config.account_pair_id,
+                    &mastodon_post_id,
+                )? > 0
+                {
+                    tracing::info!(
+                        "Post '{}' was deleted, removing it from the dead letters.",
+                        mastodon_post_id
+                    );
+                }
+            }
+
+            routing::delete_post_from_destinations(
+                &mastodon_post_id,
+                &config.account_pair_id,
+                db_connection_pool,
+                social_media_clients,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sync the posts made since the last sync, and record the sync state for
+/// the account pair.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+///
+/// ## Note
+///
+/// The sync state is recorded even if the sync fails, so the next sync
+/// resumes after the posts that were processed.
+async fn sync_posts(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    // Get the sync cursor, if any.
+    tracing::info!("Getting sync cursor...");
+    let sync_cursor = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        get_sync_cursor(config, db_connection)?
+    };
+
+    let mut last_seen_post_id = sync_cursor.clone();
+
+    let sync_result = sync_posts_since_cursor(
+        config,
+        db_connection_pool,
+        social_media_clients,
+        mastodon_account,
+        post_rules,
+        sync_cursor,
+        &mut last_seen_post_id,
+    )
+    .await;
+
+    let last_error = sync_result.as_ref().err().map(|e| format!("{:#}", e));
+
+    let new_sync_state = models::NewSyncState::new(
+        &config.account_pair_id,
+        &mastodon_account.id,
+        last_seen_post_id.as_deref(),
+        last_error.as_deref(),
+    );
+
+    {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::upsert_sync_state(db_connection, &new_sync_state)?;
+    }
+
+    sync_result
+}
+
+/// Get the ID of the newest post seen by the last sync of the account pair.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection` - The database connection to use.
+///
+/// ## Note
+///
+/// Account pairs without a sync state, like after upgrading, resume from the
+/// newest synced post.
+fn get_sync_cursor(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+) -> Result<Option<String>> {
+    let sync_state =
+        fediproto_sync_db::operations::get_sync_state(db_connection, &config.account_pair_id)?;
+
+    match sync_state {
+        Some(sync_state) => Ok(sync_state.last_seen_post_id),
+        None => Ok(
+            fediproto_sync_db::operations::get_last_synced_mastodon_post_id(
+                db_connection,
+                &config.account_pair_id,
+            )?,
+        ),
+    }
+}
+
+/// Sync the posts made since the sync cursor.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the posts of.
+/// * `post_rules` - The rules for processing posts.
+/// * `sync_cursor` - The ID of the newest post seen by the last sync.
+/// * `last_seen_post_id` - Set to the ID of each post once it's processed.
+async fn sync_posts_since_cursor(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+    sync_cursor: Option<String>,
+    last_seen_post_id: &mut Option<String>,
+) -> Result<()> {
+    // Sync the pending posts that have waited out the publish delay first, so
+    // they aren't fetched again as new posts.
+    process_pending_posts(
+        config,
+        db_connection_pool,
+        social_media_clients.clone(),
+        mastodon_account,
+        post_rules.clone(),
+    )
+    .await?;
+
+    // Get the latest posts from Mastodon, oldest first.
+    // If there is no sync cursor, we will only get the latest post.
+    // Otherwise, we will get all posts since the cursor, up to the catch-up
+    // limit. Posts over the limit are newer than the ones returned, so the
+    // cursor stays before them and they're synced on the next sync. The end
+    // of the limit is found by paging forward from the cursor, so the posts
+    // over it aren't fetched.
+    tracing::info!("Getting latest posts from Mastodon...");
+    let first_post_id_over_limit = match &sync_cursor {
+        Some(sync_cursor) => {
+            social_media_clients
+                .mastodon_http_client
+                .get_first_post_id_over_limit(
+                    &social_media_clients.mastodon_rate_limiter,
+                    &mastodon_account.id,
+                    sync_cursor,
+                    config.reply_policy != ReplyPolicy::Skip,
+                    config.catch_up_max_posts,
+                )
+                .await?
+        }
+
+        None => None,
+    };
+
+    let latest_posts = social_media_clients
+        .mastodon_client
+        .get_latest_posts(
+            &social_media_clients.mastodon_rate_limiter,
+            &mastodon_account.id,
+            sync_cursor.clone(),
+            first_post_id_over_limit,
+            config.mastodon_allow_unlisted_posts,
+            config.reply_policy != ReplyPolicy::Skip,
+        )
+        .await?;
+
+    // If there is no sync cursor, we start it at the latest post. This is so
+    // we have a starting point for future syncs.
+    //
+    // Note: The initial post **is not synced** to BlueSky.
+    if sync_cursor.is_none() {
+        if let Some(initial_post) = latest_posts.last() {
+            *last_seen_post_id = Some(initial_post.id.clone());
+
+            tracing::info!("Started the sync cursor at the latest post for future syncs.");
+        }
+
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Retrieved '{}' new posts from Mastodon.",
+        latest_posts.len()
+    );
+
+    // The connection is released before syncing, since syncing the posts
+    // takes connections of its own.
+    let (retry_post_ids, posts_to_retry) = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        // Keep track of the posts in the retry queue and the dead letters so
+        // we don't try to process them twice.
+        let retry_post_ids = fediproto_sync_db::operations::get_mastodon_post_retry_queue_items(
+            db_connection,
+            &config.account_pair_id,
+        )?
+        .into_iter()
+        .map(|retry_item| retry_item.id)
+        .chain(
+            fediproto_sync_db::operations::get_mastodon_post_dead_letters(
+                db_connection,
+                Some(&config.account_pair_id),
+            )?
+            .into_iter()
+            .map(|dead_letter| dead_letter.id),
+        )
+        .collect::<Vec<String>>();
+
+        // Only retry the posts whose backoff has passed.
+        let posts_to_retry =
+            fediproto_sync_db::operations::get_due_mastodon_post_retry_queue_items(
+                db_connection,
+                &config.account_pair_id,
+                chrono::Utc::now().naive_utc(),
+            )?;
+
+        (retry_post_ids, posts_to_retry)
+    };
+
+    if !posts_to_retry.is_empty() {
+        tracing::info!(
+            "Retrying '{}' posts that failed to sync previously.",
+            posts_to_retry.len()
+        );
+
+        for retry_item in posts_to_retry {
+            let fetched_post = social_media_clients
+                .mastodon_rate_limiter
+                .send(
+                    social_media_clients
+                        .mastodon_client
+                        .get_status(retry_item.id.clone()),
+                )
+                .await;
+
+            match fetched_post {
+                Ok(post) => {
+                    tracing::info!("Retrying sync for post '{}'", retry_item.id);
+                    let post = &post.json;
+
+                    let sync_result = routing::sync_post_to_destinations(
+                        post,
+                        config,
+                        db_connection_pool,
+                        social_media_clients.clone(),
+                        mastodon_account,
+                        post_rules.clone(),
+                    )
+                    .await;
+
+                    match sync_result {
+                        Ok(_) => {
+                            tracing::info!("Post '{}' processed successfully.", retry_item.id);
+                            let db_connection = &mut db_connection_pool.get()?;
+
+                            fediproto_sync_db::operations::delete_mastodon_post_retry_queue_item(
+                                db_connection,
+                                &retry_item,
+                            )?;
+                        }
+
+                        Err(e) => {
+                            let db_connection = &mut db_connection_pool.get()?;
+
+                            record_failed_sync(
+                                config,
+                                db_connection,
+                                post,
+                                Some(&retry_item),
+                                &e,
+                                &post_rules,
+                            )?;
+                        }
+                    }
+                }
+
+                Err(e) if is_deleted_status_error(&e) => {
+                    tracing::info!(
+                        "Post '{}' in the retry queue was deleted. Removing it from the retry queue.",
+                        retry_item.id
+                    );
+
+                    let db_connection = &mut db_connection_pool.get()?;
+
+                    fediproto_sync_db::operations::delete_mastodon_post_retry_queue_item(
+                        db_connection,
+                        &retry_item,
+                    )?;
+                }
+
+                Err(e) => {
+                    tracing::warn!(
+                        "Post '{}' in the retry queue could not be fetched: {}",
+                        retry_item.id,
+                        e
+                    );
+
+                    let db_connection = &mut db_connection_pool.get()?;
+
+                    retry::record_failed_fetch(
+                        config,
+                        db_connection,
+                        &retry_item,
+                        &anyhow::Error::new(e),
+                    )?;
+                }
+            }
+        }
+    }
+
+    // Check the posts held by the filter rules again.
+    process_held_posts(
+        config,
+        db_connection_pool,
+        social_media_clients.clone(),
+        mastodon_account,
+        post_rules.clone(),
+    )
+    .await?;
+
+    // Process each new post and sync it to BlueSky, moving the cursor past it
+    // once it's done.
+    for post_item in latest_posts {
+        // Posts in the retry queue were already retried above.
+        if !retry_post_ids.contains(&post_item.id) {
+            process_new_post(
+                &post_item,
+                config,
+                db_connection_pool,
+                social_media_clients.clone(),
+                mastodon_account,
+                post_rules.clone(),
+            )
+            .await?;
+        }
+
+        *last_seen_post_id = Some(post_item.id.clone());
+    }
+
+    let db_connection = &mut db_connection_pool.get()?;
+
+    let cached_files_to_delete =
+        fediproto_sync_db::operations::get_cached_file_records(db_connection)?;
+
+    if !cached_files_to_delete.is_empty() {
+        tracing::info!("Deleting cached files during sync...");
+
+        for cached_file in cached_files_to_delete {
+            tracing::info!("Deleting cached file '{}'.", cached_file.file_path);
+            cached_file.remove_file(db_connection).await?;
+        }
+    }
+
+    let expired_link_metadata_count =
+        fediproto_sync_db::operations::delete_expired_link_metadata_cache_entries(db_connection)?;
+
+    if expired_link_metadata_count > 0 {
+        tracing::info!(
+            "Deleted '{}' expired link metadata cache entries.",
+            expired_link_metadata_count
+        );
+    }
+
+    Ok(())
+}
+
+/// Process a new Mastodon post, syncing it to BlueSky unless it's pending or
+/// filtered.
+///
+/// ## Arguments
+///
+/// * `post_item` - The Mastodon post to process.
+/// * `config` - The config for the app.
+/// * `db_connection` - The database connection to use.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account that posted the status.
+/// * `post_rules` - The rules for processing posts.
+async fn process_new_post(
+    post_item: &megalodon::entities::Status,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    tracing::info!("Processing post '{}'", post_item.id);
+
+    let db_connection = &mut db_connection_pool.get()?;
+
+    if !decide_new_post(
+        post_item,
+        config,
+        db_connection,
+        mastodon_account,
+        &post_rules,
+    )? {
+        return Ok(());
+    }
+
+    sync_post_or_queue_retry(
+        post_item,
+        config,
+        db_connection_pool,
+        social_media_clients,
+        mastodon_account,
+        post_rules,
+    )
+    .await
+}
+
+/// Check whether a new Mastodon post should be synced now, making it pending
+/// or saving its filter decision if it shouldn't.
+///
+/// ## Arguments
+///
+/// * `post_item` - The Mastodon post to check.
+/// * `config` - The config for the app.
+/// * `db_connection` - The database connection to use.
+/// * `mastodon_account` - The Mastodon account that posted the status.
+/// * `post_rules` - The rules for processing posts.
+///
+/// ## Note
+///
+/// Returns `true` if the post should be synced.
+fn decide_new_post(
+    post_item: &megalodon::entities::Status,
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+    mastodon_account: &Account,
+    post_rules: &PostRules,
+) -> Result<bool> {
+    // Posts that are already pending will be synced once the publish delay
+    // has passed.
+    if fediproto_sync_db::operations::get_mastodon_pending_post_exists(
+        db_connection,
+        &config.account_pair_id,
+        &post_item.id,
+    )? {
+        tracing::info!("Post '{}' is still pending.", post_item.id);
+
+        return Ok(false);
+    }
+
+    if is_within_publish_delay(post_item, config.publish_delay, chrono::Utc::now()) {
+        let new_pending_post = models::NewMastodonPendingPost::new(
+            &config.account_pair_id,
+            post_item,
+            config.publish_delay,
+        );
+
+        fediproto_sync_db::operations::insert_mastodon_pending_post(
+            db_connection,
+            &new_pending_post,
+        )?;
+
+        tracing::info!(
+            "Post '{}' is newer than the publish delay, pending until '{}'.",
+            post_item.id,
+            new_pending_post.publish_at
+        );
+
+        return Ok(false);
+    }
+
+    // Posts with a previous filter decision have already been skipped or
+    // are being held, so they don't need to be evaluated again.
+    let previous_filter_decision =
+        fediproto_sync_db::operations::get_mastodon_post_filter_decision_by_post_id(
+            db_connection,
+            &config.account_pair_id,
+            &post_item.id,
+        )?;
+
+    if let Some(previous_filter_decision) = previous_filter_decision {
+        tracing::info!(
+            "Ignoring post '{}', it was previously decided to '{}'.",
+            post_item.id,
+            previous_filter_decision.action
+        );
+
+        return Ok(false);
+    }
+
+    let filter_decision = decide_filter_action(config, post_rules, post_item, mastodon_account);
+
+    apply_filter_decision(
+        db_connection,
+        &config.account_pair_id,
+        post_item,
+        &filter_decision,
+    )
+}
+
+/// Sync a Mastodon post to its BlueSky destinations, adding it to the retry
+/// queue if it fails.
+///
+/// ## Arguments
+///
+/// * `post_item` - The Mastodon post to sync.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account that posted the status.
+/// * `post_rules` - The rules for processing posts.
+pub(crate) async fn sync_post_or_queue_retry(
+    post_item: &megalodon::entities::Status,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    let sync_result = routing::sync_post_to_destinations(
+        post_item,
+        config,
+        db_connection_pool,
+        social_media_clients,
+        mastodon_account,
+        post_rules.clone(),
+    )
+    .await;
+
+    match sync_result {
+        Ok(_) => {
+            tracing::info!("Post '{}' processed successfully.", post_item.id);
+        }
+        Err(error) => {
+            let db_connection = &mut db_connection_pool.get()?;
+
+            record_failed_sync(config, db_connection, post_item, None, &error, &post_rules)?;
+        }
+    }
 
-pub struct SocialMediaClients {
-    /// The ATProto agent for the FediProto Sync application.
-    pub atp_agent: AtpAgent<MemorySessionStore, ReqwestClient>,
+    Ok(())
+}
 
-    pub atp_pds_service_endpoint: String,
+/// Log the error a post failed to sync with and record the failed attempt, so
+/// the post is retried with a backoff or moved to the dead letters.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection` - The database connection to use.
+/// * `post_item` - The Mastodon post that failed to sync.
+/// * `retry_item` - The post's item in the retry queue, if it was a retry.
+/// * `error` - The error the post failed to sync with.
+/// * `post_rules` - The rules for processing posts.
+fn record_failed_sync(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+    post_item: &megalodon::entities::Status,
+    retry_item: Option<&models::MastodonPostRetryQueueItem>,
+    error: &anyhow::Error,
+    post_rules: &PostRules,
+) -> Result<()> {
+    tracing::error!(
+        "Failed to process post '{}' with a {} error: {:#?}",
+        post_item.id,
+        classify_error(error),
+        error
+    );
 
-    pub atp_did: Did,
+    if let Some(source_error) = error.source() {
+        tracing::error!("Source error: {:#?}", source_error);
+    }
 
-    /// The Mastodon client for the FediProto Sync application.
-    pub mastodon_client: Box<dyn Megalodon + Send + Sync>,
+    retry::record_failed_attempt(
+        config,
+        db_connection,
+        post_item,
+        retry_item,
+        error,
+        post_rules,
+    )
 }
 
-impl SocialMediaClients {
-    async fn new(
-        config: &FediProtoSyncConfig,
-        db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
-    ) -> Result<SocialMediaClients> {
-        let atp_auth_data = create_atp_agent(config).await?;
-        let atp_agent = atp_auth_data.0;
-        let atp_pds_service_endpoint = atp_auth_data.1.replace("https://", "");
-        let atp_did = atp_auth_data.2;
+/// Decide what to do with a post using the reply policy and the filter rules.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `post_rules` - The rules for processing posts.
+/// * `post_item` - The Mastodon post to decide for.
+/// * `mastodon_account` - The Mastodon account that posted the status.
+///
+/// ## Note
+///
+/// Replies to other accounts that aren't allowed by the reply policy are
+/// skipped before the filter rules are evaluated.
+pub(crate) fn decide_filter_action(
+    config: &FediProtoSyncConfig,
+    post_rules: &PostRules,
+    post_item: &megalodon::entities::Status,
+    mastodon_account: &Account,
+) -> FilterDecision {
+    if !ReplyFilter::new(config).allows(post_item, &mastodon_account.id) {
+        return FilterDecision {
+            action: FilterAction::Skip,
+            rule_name: Some(REPLY_POLICY_RULE_NAME.to_string()),
+        };
+    }
 
-        let db_connection = &mut db_connection_pool.get()?;
-        let mastodon_client = create_mastodon_client(config, db_connection).await?;
+    FilterDecision::evaluate(&post_rules.filters, post_item)
+}
 
-        Ok(SocialMediaClients {
-            atp_agent,
-            atp_pds_service_endpoint,
-            atp_did,
-            mastodon_client,
-        })
+/// Log the filter decision for a post and save it if the post isn't being
+/// synced.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair syncing the post.
+/// * `post_item` - The Mastodon post the decision is for.
+/// * `filter_decision` - The filter decision for the post.
+///
+/// ## Note
+///
+/// Returns `true` if the post should be synced.
+pub(crate) fn apply_filter_decision(
+    db_connection: &mut AnyConnection,
+    account_pair_id: &str,
+    post_item: &megalodon::entities::Status,
+    filter_decision: &FilterDecision,
+) -> Result<bool> {
+    let rule_name = filter_decision
+        .rule_name
+        .clone()
+        .unwrap_or_else(|| "(default)".to_string());
+
+    if filter_decision.action == FilterAction::Sync {
+        if filter_decision.rule_name.is_some() {
+            tracing::info!(
+                "Post '{}' matched filter rule '{}', syncing.",
+                post_item.id,
+                rule_name
+            );
+        }
+
+        return Ok(true);
     }
+
+    tracing::info!(
+        "Post '{}' matched filter rule '{}', decided to '{}'.",
+        post_item.id,
+        rule_name,
+        filter_decision.action
+    );
+
+    let new_filter_decision = models::NewMastodonPostFilterDecision::new(
+        account_pair_id,
+        &post_item.id,
+        filter_decision.action.as_str(),
+        filter_decision.rule_name.clone(),
+    );
+
+    fediproto_sync_db::operations::upsert_mastodon_post_filter_decision(
+        db_connection,
+        &new_filter_decision,
+    )?;
+
+    Ok(false)
 }
 
-pub async fn run_sync_loop(
+/// Check posts held by the filter rules again and sync or skip them if the
+/// decision has changed.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account that posted the statuses.
+/// * `post_rules` - The rules for processing posts.
+async fn process_held_posts(
     config: &FediProtoSyncConfig,
-    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
 ) -> Result<()> {
-    let social_media_clients = SocialMediaClients::new(config, &db_connection_pool).await?;
-    let social_media_clients = Arc::new(social_media_clients);
+    let db_connection = &mut db_connection_pool.get()?;
 
-    let mastodon_account = social_media_clients
-        .mastodon_client
-        .verify_account_credentials()
-        .await
-        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?
-        .json;
+    let held_posts = fediproto_sync_db::operations::get_mastodon_post_filter_decisions_by_action(
+        db_connection,
+        &config.account_pair_id,
+        FilterAction::Hold.as_str(),
+    )?;
 
-    let mut interval = tokio::time::interval(config.sync_interval);
-    loop {
-        interval.tick().await;
+    if held_posts.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("Checking '{}' held posts again.", held_posts.len());
+
+    for held_post in held_posts {
+        // The connection is released before syncing the post, since syncing
+        // takes connections of its own.
+        let post_item = {
+            let db_connection = &mut db_connection_pool.get()?;
+
+            let post_item = match fetch_held_post(
+                db_connection,
+                social_media_clients.mastodon_client.as_ref().as_ref(),
+                &social_media_clients.mastodon_rate_limiter,
+                &config.account_pair_id,
+                &held_post.mastodon_post_id,
+            )
+            .await?
+            {
+                Some(post_item) => post_item,
+                None => continue,
+            };
+
+            let filter_decision =
+                decide_filter_action(config, &post_rules, &post_item, mastodon_account);
+
+            if filter_decision.action == FilterAction::Hold {
+                tracing::info!("Post '{}' is still being held.", post_item.id);
+
+                continue;
+            }
+
+            if !apply_filter_decision(
+                db_connection,
+                &config.account_pair_id,
+                &post_item,
+                &filter_decision,
+            )? {
+                continue;
+            }
+
+            fediproto_sync_db::operations::delete_mastodon_post_filter_decision(
+                db_connection,
+                &config.account_pair_id,
+                &held_post.mastodon_post_id,
+            )?;
 
-        tracing::info!("Running sync...");
+            post_item
+        };
 
-        let sync_result = sync_posts(
+        sync_post_or_queue_retry(
+            &post_item,
             config,
-            &db_connection_pool,
+            db_connection_pool,
             social_media_clients.clone(),
-            &mastodon_account,
+            mastodon_account,
+            post_rules.clone(),
         )
-        .await;
+        .await?;
+    }
 
-        match sync_result {
-            Ok(_) => {
-                tracing::info!("Sync completed successfully.");
-            }
-            Err(e) => {
-                tracing::error!("Sync failed: {:#?}", e);
-            }
+    Ok(())
+}
+
+/// Fetch a post held by the filter rules. Held posts that were deleted are
+/// dropped, held posts that could not be fetched for any other reason stay
+/// held until the next run.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection.
+/// * `mastodon_client` - The Mastodon client.
+/// * `mastodon_rate_limiter` - The rate limiter for the Mastodon server.
+/// * `account_pair_id` - The ID of the account pair the post is held for.
+/// * `mastodon_post_id` - The ID of the held post.
+pub async fn fetch_held_post(
+    db_connection: &mut AnyConnection,
+    mastodon_client: &(dyn Megalodon + Send + Sync),
+    mastodon_rate_limiter: &MastodonRateLimiter,
+    account_pair_id: &str,
+    mastodon_post_id: &str,
+) -> Result<Option<megalodon::entities::Status>> {
+    match mastodon_rate_limiter
+        .send(mastodon_client.get_status(mastodon_post_id.to_string()))
+        .await
+    {
+        Ok(fetched_post) => Ok(Some(fetched_post.json)),
+        Err(e) if is_deleted_status_error(&e) => {
+            tracing::info!("Held post '{}' was deleted. Dropping it.", mastodon_post_id);
+
+            fediproto_sync_db::operations::delete_mastodon_post_filter_decision(
+                db_connection,
+                account_pair_id,
+                mastodon_post_id,
+            )?;
+
+            Ok(None)
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Held post '{}' could not be fetched, trying again on the next run: {}",
+                mastodon_post_id,
+                e
+            );
+
+            Ok(None)
         }
     }
 }
 
-async fn sync_posts(
+/// Check if fetching a Mastodon status failed because the status no longer
+/// exists.
+///
+/// ## Arguments
+///
+/// * `error` - The error returned while fetching the status.
+pub fn is_deleted_status_error(error: &megalodon::error::Error) -> bool {
+    let status = match error {
+        megalodon::error::Error::OwnError(own_error) => own_error.status,
+        megalodon::error::Error::RequestError(request_error) => {
+            request_error.status().map(|status| status.as_u16())
+        }
+        _ => None,
+    };
+
+    matches!(status, Some(404) | Some(410))
+}
+
+/// Check if a post was created within the publish delay.
+///
+/// ## Arguments
+///
+/// * `post_item` - The Mastodon post to check.
+/// * `publish_delay` - How long to wait after a post is created before syncing
+///   it.
+/// * `now` - The current date and time.
+pub fn is_within_publish_delay(
+    post_item: &megalodon::entities::Status,
+    publish_delay: std::time::Duration,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if publish_delay.is_zero() {
+        return false;
+    }
+
+    let publish_delay = match chrono::Duration::from_std(publish_delay) {
+        Ok(publish_delay) => publish_delay,
+        Err(_) => return true,
+    };
+
+    post_item.created_at + publish_delay > now
+}
+
+/// Fetch the pending posts that have waited out the publish delay and sync
+/// them in their current form. Posts that were deleted in the meantime are
+/// dropped, posts that could not be fetched for any other reason stay pending
+/// until the next run.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account that posted the statuses.
+/// * `post_rules` - The rules for processing posts.
+async fn process_pending_posts(
     config: &FediProtoSyncConfig,
     db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
     social_media_clients: Arc<SocialMediaClients>,
     mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
 ) -> Result<()> {
-    let db_connection = &mut db_connection_pool.get()?;
-
-    // Get the last synced post ID, if any.
-    tracing::info!("Getting last synced post...");
-    let last_synced_post_id =
-        fediproto_sync_db::operations::get_last_synced_mastodon_post_id(db_connection)?;
-
-    // Get the latest posts from Mastodon.
-    // If there is no last synced post ID, we will only get the latest post.
-    // Otherwise, we will get all posts since the last synced post.
-    tracing::info!("Getting latest posts from Mastodon...");
-    let mut latest_posts = social_media_clients
-        .mastodon_client
-        .get_latest_posts(
-            &mastodon_account.id,
-            last_synced_post_id.clone(),
-            config.mastodon_allow_unlisted_posts,
-        )
-        .await?;
-
-    // Reverse the posts so we process them in ascending order.
-    latest_posts.reverse();
-
-    // If there is no last synced post ID, we need to add the initial post to the
-    // database. This is so we have a starting point for future syncs.
-    //
-    // Note: The initial post **is not synced** to BlueSky.
-    if last_synced_post_id.is_none() && latest_posts.len() > 0 {
-        let initial_post = latest_posts[0].clone();
+    let (pending_posts, publishable_posts) = {
+        let db_connection = &mut db_connection_pool.get()?;
 
-        let new_mastodon_post = models::NewMastodonPost::new(&initial_post, None, None);
-        fediproto_sync_db::operations::insert_new_synced_mastodon_post(
+        let pending_posts = fediproto_sync_db::operations::get_mastodon_pending_posts(
             db_connection,
-            &new_mastodon_post,
+            Some(&config.account_pair_id),
         )?;
 
-        tracing::info!("Added initial post to database for future syncs.");
+        if pending_posts.is_empty() {
+            return Ok(());
+        }
 
-        return Ok(());
-    }
+        let publishable_posts =
+            fediproto_sync_db::operations::get_publishable_mastodon_pending_posts(
+                db_connection,
+                &config.account_pair_id,
+            )?;
+
+        (pending_posts, publishable_posts)
+    };
 
     tracing::info!(
-        "Retrieved '{}' new posts from Mastodon.",
-        latest_posts.len()
+        "'{}' posts are pending, '{}' are ready to sync.",
+        pending_posts.len(),
+        publishable_posts.len()
     );
 
-    let posts_to_retry =
-        fediproto_sync_db::operations::get_mastodon_post_retry_queue_items(db_connection)?;
-
-    // Filter out any posts that are in the retry queue so we don't try to process
-    // them twice.
-    let latest_posts = latest_posts
-        .iter()
-        .filter(|post| {
-            let post_id = post.id.clone();
-            let post_id = post_id.parse::<i64>().unwrap_or(0);
-
-            let retry_post_exists = posts_to_retry.iter().any(|retry_item| {
-                let retry_post_id = retry_item.id;
-                retry_post_id == post_id
-            });
-
-            !retry_post_exists
-        })
-        .cloned()
-        .collect::<Vec<megalodon::entities::Status>>();
-
-    if posts_to_retry.len() > 0 {
-        tracing::info!(
-            "Retrying '{}' posts that failed to sync previously.",
-            posts_to_retry.len()
+    for pending_post in &pending_posts {
+        tracing::debug!(
+            "Post '{}' is pending until '{}'.",
+            pending_post.mastodon_post_id,
+            pending_post.publish_at
         );
+    }
 
-        for retry_item in posts_to_retry {
-            let fetched_post = social_media_clients
-                .mastodon_client
-                .get_status(retry_item.id.to_string())
-                .await;
+    for pending_post in publishable_posts {
+        let fetched_post = social_media_clients
+            .mastodon_rate_limiter
+            .send(
+                social_media_clients
+                    .mastodon_client
+                    .get_status(pending_post.mastodon_post_id.clone()),
+            )
+            .await;
+
+        let post_item = match fetched_post {
+            Ok(fetched_post) => fetched_post.json,
+            Err(e) if is_deleted_status_error(&e) => {
+                tracing::info!(
+                    "Pending post '{}' was deleted. Dropping it.",
+                    pending_post.mastodon_post_id
+                );
 
-            match fetched_post {
-                Ok(post) => {
-                    tracing::info!("Retrying sync for post '{}'", retry_item.id);
-                    let post = &post.json;
+                let db_connection = &mut db_connection_pool.get()?;
 
-                    let sync_config = bsky_post_sync::BlueSkyPostSyncConfig {
-                        config: config.clone(),
-                        did: social_media_clients.atp_did.clone(),
-                        pds_service_endpoint: social_media_clients.atp_pds_service_endpoint.clone(),
-                        mastodon_account: mastodon_account.clone(),
-                        db_connection_pool: db_connection_pool.clone(),
-                    };
-
-                    let sync_result = bsky_post_sync::sync_post(
-                        &post,
-                        &social_media_clients.atp_agent,
-                        &sync_config,
-                    )
-                    .await;
+                fediproto_sync_db::operations::delete_mastodon_pending_post(
+                    db_connection,
+                    &config.account_pair_id,
+                    &pending_post.mastodon_post_id,
+                )?;
 
-                    match sync_result {
-                        Ok(_) => {
-                            tracing::info!("Post '{}' processed successfully.", retry_item.id);
-                            fediproto_sync_db::operations::delete_mastodon_post_retry_queue_item(
-                                db_connection,
-                                &retry_item,
-                            )?;
-                        }
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Pending post '{}' could not be fetched, trying again on the next run: {}",
+                    pending_post.mastodon_post_id,
+                    e
+                );
 
-                        Err(e) => {
-                            tracing::error!("Failed to process post '{}': {:#?}", retry_item.id, e);
+                continue;
+            }
+        };
 
-                            let source_error = e.source();
+        if post_item.edited_at.is_some() {
+            tracing::info!(
+                "Pending post '{}' was edited, syncing the latest version.",
+                post_item.id
+            );
+        }
 
-                            if let Some(source_error) = source_error {
-                                tracing::error!("Source error: {:#?}", source_error);
-                            }
+        tracing::info!("Processing pending post '{}'", post_item.id);
 
-                            fediproto_sync_db::operations::update_mastodon_post_retry_queue_item(
-                                db_connection,
-                                &retry_item,
-                                None,
-                            )?;
-                        }
-                    }
-                }
+        let filter_decision =
+            decide_filter_action(config, &post_rules, &post_item, mastodon_account);
 
-                Err(e) => {
-                    tracing::warn!("Failed to fetch post '{}': {:#?}", retry_item.id, e);
-                    tracing::warn!("Removing post from retry queue.");
+        // The connection is released before syncing the post, since syncing
+        // takes connections of its own.
+        let should_sync = {
+            let db_connection = &mut db_connection_pool.get()?;
 
-                    fediproto_sync_db::operations::delete_mastodon_post_retry_queue_item(
-                        db_connection,
-                        &retry_item,
-                    )?;
+            fediproto_sync_db::operations::delete_mastodon_pending_post(
+                db_connection,
+                &config.account_pair_id,
+                &pending_post.mastodon_post_id,
+            )?;
 
-                    continue;
-                }
-            }
+            apply_filter_decision(
+                db_connection,
+                &config.account_pair_id,
+                &post_item,
+                &filter_decision,
+            )?
+        };
+
+        if !should_sync {
+            continue;
         }
+
+        sync_post_or_queue_retry(
+            &post_item,
+            config,
+            db_connection_pool,
+            social_media_clients.clone(),
+            mastodon_account,
+            post_rules.clone(),
+        )
+        .await?;
     }
 
-    // Process each new post and sync it to BlueSky.
-    for post_item in latest_posts {
-        tracing::info!("Processing post '{}'", post_item.id);
-
-        let sync_config = bsky_post_sync::BlueSkyPostSyncConfig {
-            config: config.clone(),
-            did: social_media_clients.atp_did.clone(),
-            pds_service_endpoint: social_media_clients.atp_pds_service_endpoint.clone(),
-            mastodon_account: mastodon_account.clone(),
-            db_connection_pool: db_connection_pool.clone(),
-        };
+    Ok(())
+}
 
-        let sync_result =
-            bsky_post_sync::sync_post(&post_item, &social_media_clients.atp_agent, &sync_config)
-                .await;
+pub async fn create_atp_agent(
+    config: &FediProtoSyncConfig,
+    session_store: DbSessionStore,
+) -> Result<(AtpAgent<DbSessionStore, RateLimitedClient>, String, Did), FediProtoSyncError> {
+    let client = RateLimitedClient::new(
+        &format!("https://{}", &config.bluesky_pds_server),
+        create_http_client(config)?,
+        config.rate_limit_max_wait,
+    );
 
-        match sync_result {
-            Ok(_) => {
-                tracing::info!("Post '{}' processed successfully.", post_item.id);
-            }
-            Err(error) => {
-                tracing::error!("Failed to process post '{}': {:#?}", post_item.id, error);
+    let atp_agent = AtpAgent::new(client, session_store.clone());
 
-                let source_error = error.source();
+    let cached_session = match session_store.load_cached_session(&config.bluesky_handle) {
+        Ok(cached_session) => cached_session,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to load the cached BlueSky session for '{}': {:#}",
+                config.bluesky_handle,
+                e
+            );
 
-                if let Some(source_error) = source_error {
-                    tracing::error!("Source error: {:#?}", source_error);
-                }
+            None
+        }
+    };
 
-                let new_retry_item = NewMastodonPostRetryQueueItem::new(
-                    &post_item.id.parse::<i64>()?,
-                    error.to_string().as_str(),
+    // Resuming the session refreshes it if the access token expired, so the
+    // app password is only used if the refresh token was rejected too.
+    let resumed_session = match cached_session {
+        Some(cached_session) => match atp_agent.resume_session(cached_session).await {
+            Ok(_) => atp_agent.get_session().await,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to resume the cached BlueSky session for '{}', logging in again: {}",
+                    config.bluesky_handle,
+                    from_xrpc_error(e)
                 );
 
-                fediproto_sync_db::operations::insert_mastodon_post_retry_queue_item(
-                    db_connection,
-                    &new_retry_item,
-                )?;
+                None
             }
-        }
-    }
+        },
 
-    let cached_files_to_delete =
-        fediproto_sync_db::operations::get_cached_file_records(db_connection)?;
+        None => None,
+    };
 
-    if cached_files_to_delete.len() > 0 {
-        tracing::info!("Deleting cached files during sync...");
+    let auth_result = match resumed_session {
+        Some(session) => {
+            tracing::info!(
+                "Resumed the BlueSky session for '{}'",
+                session.handle.as_str()
+            );
 
-        for cached_file in cached_files_to_delete {
-            tracing::info!("Deleting cached file '{}'.", cached_file.file_path);
-            cached_file.remove_file(db_connection).await?;
+            session
         }
-    }
-
-    Ok(())
-}
-
-pub async fn create_atp_agent(
-    config: &FediProtoSyncConfig
-) -> Result<(AtpAgent<MemorySessionStore, ReqwestClient>, String, Did), FediProtoSyncError> {
-    let client = ReqwestClientBuilder::new(format!("https://{}", &config.bluesky_pds_server))
-        .client(create_http_client(config)?)
-        .build();
 
-    let atp_agent = AtpAgent::new(client, MemorySessionStore::default());
+        None => {
+            let session = atp_agent
+                .login(&config.bluesky_handle, &config.bluesky_app_password)
+                .await
+                .map_err(|_| {
+                    FediProtoSyncError::AuthenticationError(AuthenticationSource::BlueSky)
+                })?;
 
-    let auth_result = atp_agent
-        .login(&config.bluesky_handle, &config.bluesky_app_password)
-        .await
-        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::BlueSky))?;
+            tracing::info!("Authenticated to BlueSky as '{}'", session.handle.as_str());
 
-    tracing::info!(
-        "Authenticated to BlueSky as '{}'",
-        auth_result.handle.as_str()
-    );
+            session
+        }
+    };
 
     let pds_endpoint = atp_agent.get_endpoint().await;
 
@@ -326,7 +1781,7 @@ pub fn create_atp_service_client(
     hostname: &str,
     auth_token: Option<&str>,
     config: &FediProtoSyncConfig,
-) -> Result<AtpServiceClient<ReqwestClient>, FediProtoSyncError> {
+) -> Result<AtpServiceClient<RateLimitedClient>, FediProtoSyncError> {
     let mut headers = reqwest::header::HeaderMap::new();
     if let Some(auth_token) = auth_token {
         headers.insert(
@@ -336,16 +1791,16 @@ pub fn create_atp_service_client(
         );
     }
 
-    let client = ReqwestClientBuilder::new(format!("https://{}", hostname))
-        .client(
-            reqwest::Client::builder()
-                .user_agent(config.user_agent.clone())
-                .use_rustls_tls()
-                .default_headers(headers)
-                .build()
-                .map_err(|_| FediProtoSyncError::HttpClientCreationError)?,
-        )
-        .build();
+    let client = RateLimitedClient::new(
+        &format!("https://{}", hostname),
+        reqwest::Client::builder()
+            .user_agent(config.user_agent.clone())
+            .use_rustls_tls()
+            .default_headers(headers)
+            .build()
+            .map_err(|_| FediProtoSyncError::HttpClientCreationError)?,
+        config.rate_limit_max_wait,
+    );
 
     let service_client = AtpServiceClient::new(client);
 
@@ -362,9 +1817,34 @@ async fn create_mastodon_client(
     config: &FediProtoSyncConfig,
     db_connection: &mut PooledConnection<ConnectionManager<AnyConnection>>,
 ) -> Result<Box<dyn Megalodon + Send + Sync>, FediProtoSyncError> {
+    let decrypted_mastodon_token = get_mastodon_access_token(config, db_connection)?;
+
+    // Create the Mastodon client and authenticate.
+    let mastodon_client = megalodon::generator(
+        crate::source_platform::megalodon_sns(config.source_platform),
+        format!("https://{}", config.mastodon_server.clone()),
+        Some(decrypted_mastodon_token),
+        Some(config.user_agent.clone()),
+    )
+    .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?;
+
+    Ok(mastodon_client)
+}
+
+/// Get the decrypted Mastodon access token for the account pair.
+///
+/// ## Arguments
+///
+/// * `config` - The environment variables for the FediProto Sync application.
+/// * `db_connection` - The database connection to use.
+fn get_mastodon_access_token(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+) -> Result<String, FediProtoSyncError> {
     let cached_mastodon_token =
         fediproto_sync_db::operations::get_cached_service_token_by_service_name(
             db_connection,
+            &config.account_pair_id,
             "mastodon",
         )
         .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?;
@@ -372,25 +1852,16 @@ async fn create_mastodon_client(
     let cached_mastodon_token = match cached_mastodon_token {
         Some(token) => token,
         None => {
-            return Err(
-                FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon).into(),
-            );
+            return Err(FediProtoSyncError::AuthenticationError(
+                AuthenticationSource::Mastodon,
+            ));
         }
     };
 
     let decrypted_mastodon_token =
         cached_mastodon_token.decrypt_access_token(&config.token_encryption_private_key)?;
 
-    // Create the Mastodon client and authenticate.
-    let mastodon_client = megalodon::generator(
-        megalodon::SNS::Mastodon,
-        format!("https://{}", config.mastodon_server.clone()),
-        Some(decrypted_mastodon_token),
-        Some(config.user_agent.clone()),
-    )
-    .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?;
-
-    Ok(mastodon_client)
+    Ok(decrypted_mastodon_token)
 }
 
 /// Create a new HTTP client for the FediProto Sync application.
@@ -198,7 +198,7 @@ trait ImageUtils {
     fn resize_image(self) -> Self;
 }
 
-impl<'a> ImageUtils for DynamicImage {
+impl ImageUtils for DynamicImage {
     /// Resize an image to a maximum of 1080 pixels in either dimension.
     fn resize_image(self) -> DynamicImage {
         let dimensions = self.dimensions();
@@ -235,7 +235,7 @@ impl<'a> ImageUtils for DynamicImage {
             new_height
         );
 
-        return self.resize(new_width, new_height, FilterType::Lanczos3);
+        self.resize(new_width, new_height, FilterType::Lanczos3)
     }
 }
 
@@ -263,9 +263,7 @@ fn greatest_common_divisor(
     };
 
     while b != 0 {
-        let temp = a;
-        a = b;
-        b = temp;
+        std::mem::swap(&mut a, &mut b);
 
         b %= a;
     }
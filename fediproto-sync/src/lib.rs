@@ -1,19 +1,67 @@
+/// Loading the account pairs to sync.
+pub mod account_pairs;
+/// Backfilling older posts to BlueSky.
+pub mod backfill;
 /// BlueSky operations for syncing posts.
 pub mod bsky_post_sync;
+/// Persisting and refreshing BlueSky sessions.
+pub mod bsky_session;
 /// CLI operations for the application.
 pub mod cli;
 /// Core operations for the application.
 pub mod core;
 /// Utilities for working with images.
 pub mod img_utils;
+/// Link metadata for generating link cards.
+pub mod link_metadata;
 /// Mastodon operations.
 pub mod mastodon;
+/// Syncing the Mastodon profile to BlueSky.
+pub mod profile;
+/// Tracking the rate limits of the servers requests are sent to.
+pub mod rate_limit;
+/// Policies for syncing replies and repairing threads.
+pub mod replies;
+/// Retrying posts that failed to sync.
+pub mod retry;
+/// Routing posts to BlueSky destinations.
+pub mod routing;
+/// Rules for filtering and transforming posts.
+pub mod rules;
+/// Detecting the platform of the source server.
+pub mod source_platform;
+/// Syncing posts in real time with the Mastodon streaming API.
+pub mod streaming;
+/// Converting and classifying the errors that happen while syncing.
+pub mod sync_error;
+/// Templates for the strings added to synced posts.
+pub mod templates;
+
+/// Tests for the `fediproto-sync` crate.
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests;
+
+use std::collections::HashMap;
 
 use anyhow::Result;
+use diesel::r2d2::{ConnectionManager, Pool};
+use fediproto_sync_auth_ui::web::mastodon_webhook::{MastodonWebhook, WebhookStatus};
+use fediproto_sync_db::AnyConnection;
 use fediproto_sync_lib::{
     GIT_VERSION,
-    config::{FediProtoSyncConfig, FediProtoSyncMode},
+    config::{DEFAULT_ACCOUNT_PAIR_ID, FediProtoSyncConfig, FediProtoSyncMode},
 };
+use tracing::Instrument;
+
+/// How long to wait before restarting the sync loop of an account pair that
+/// failed.
+const ACCOUNT_PAIR_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often to check if an account pair that isn't logged in to Mastodon has
+/// been authorized.
+const MASTODON_AUTHORIZATION_POLL_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(10);
 
 /// Handles the `run` command.
 ///
@@ -39,70 +87,120 @@ pub async fn handle_run_command(config: FediProtoSyncConfig) -> Result<()> {
     tracing::info!("FediProto Sync - v{}", GIT_VERSION);
     tracing::info!("Press Ctrl+C to shutdown at any time...");
 
+    if let Some(metrics_address) = &config.metrics_address {
+        rate_limit::serve_metrics(metrics_address)?;
+    }
+
     let database_url = config.database_url.clone();
 
-    let db_connection_pool = fediproto_sync_db::create_database_connection(&database_url)?;
+    let mut account_pair_configs = account_pairs::load_account_pair_configs(&config)?;
+
+    let db_connection_pool =
+        fediproto_sync_db::create_database_connection(&database_url, account_pair_configs.len())?;
     tracing::info!("Connected to database.");
 
-    let db_connection_main = &mut db_connection_pool.get()?;
+    // The connection is only held for the startup checks, so the pool is left
+    // for the account pairs once they start syncing.
+    let mut unauthorized_account_pair_ids = Vec::new();
+    {
+        let db_connection_main = &mut db_connection_pool.get()?;
 
-    fediproto_sync_db::core::run_migrations(db_connection_main)?;
+        fediproto_sync_db::core::run_migrations(db_connection_main)?;
 
-    let cached_tokens_exist =
-        fediproto_sync_db::operations::get_cached_service_token_by_service_name(
-            db_connection_main,
-            "mastodon",
-        )?
-        .is_some();
+        for account_pair_config in &account_pair_configs {
+            let cached_token_exists =
+                fediproto_sync_db::operations::get_cached_service_token_by_service_name(
+                    db_connection_main,
+                    &account_pair_config.account_pair_id,
+                    "mastodon",
+                )?
+                .is_some();
 
-    match config.mode == FediProtoSyncMode::Auth || !cached_tokens_exist {
-        true => {
-            let config_auth = config.clone();
-            let db_connection_pool_auth = db_connection_pool.clone();
+            if !cached_token_exists {
+                unauthorized_account_pair_ids.push(account_pair_config.account_pair_id.clone());
+            }
+        }
+    }
 
-            // Spawn the auth web server.
-            tokio::spawn(async move {
-                let fediproto_auth_web_server =
-                    fediproto_sync_auth_ui::FediProtoSyncWebServer::new(
-                        &config_auth,
-                        db_connection_pool_auth,
-                    )
-                    .unwrap();
+    source_platform::resolve_account_pair_source_platforms(&mut account_pair_configs).await;
 
-                let result = fediproto_auth_web_server.run().await;
+    if !unauthorized_account_pair_ids.is_empty() {
+        tracing::warn!(
+            "Account pairs '{}' aren't logged in to Mastodon.",
+            unauthorized_account_pair_ids.join("', '")
+        );
+    }
 
-                match result {
-                    Ok(_) => {
-                        tracing::info!("Auth server completed successfully.");
-                    }
-                    Err(e) => {
-                        tracing::error!("Auth server failed: {}", e);
+    // Create a queue for the webhooks of each account pair that receives
+    // them.
+    let mut mastodon_webhooks = HashMap::new();
+    let mut webhook_receivers = HashMap::new();
+    for account_pair_config in &account_pair_configs {
+        if let Some(mastodon_webhook_secret) = &account_pair_config.mastodon_webhook_secret {
+            let (webhook_queue, webhook_receiver) = tokio::sync::mpsc::unbounded_channel();
 
-                        core_sig_error_send.send(()).unwrap();
-                    }
-                }
-            });
+            mastodon_webhooks.insert(
+                account_pair_config.account_pair_id.clone(),
+                MastodonWebhook {
+                    secret: mastodon_webhook_secret.clone(),
+                    queue: webhook_queue,
+                },
+            );
+            webhook_receivers.insert(
+                account_pair_config.account_pair_id.clone(),
+                webhook_receiver,
+            );
         }
+    }
 
-        false => {
-            let config_core = config.clone();
-            let db_connection_pool_core = db_connection_pool.clone();
+    match config.mode {
+        FediProtoSyncMode::Auth => {
+            // Spawn the auth web server.
+            spawn_web_server(
+                &config,
+                &account_pair_configs,
+                db_connection_pool.clone(),
+                HashMap::new(),
+                core_sig_error_send,
+            );
+        }
 
-            // Spawn the core loop for running the syncs.
-            tokio::spawn(async move {
-                let result = core::run_sync_loop(&config_core, db_connection_pool_core).await;
+        FediProtoSyncMode::Normal => {
+            tracing::info!("Syncing '{}' account pairs.", account_pair_configs.len());
 
-                match result {
-                    Ok(_) => {
-                        tracing::info!("FediProto Sync completed successfully.");
-                    }
-                    Err(e) => {
-                        tracing::error!("FediProto Sync failed: {}", e);
+            // Spawn the web server to receive webhooks and to log in the
+            // account pairs that aren't authorized yet.
+            if !mastodon_webhooks.is_empty() || !unauthorized_account_pair_ids.is_empty() {
+                spawn_web_server(
+                    &config,
+                    &account_pair_configs,
+                    db_connection_pool.clone(),
+                    mastodon_webhooks,
+                    core_sig_error_send,
+                );
+            }
 
-                        core_sig_error_send.send(()).unwrap();
-                    }
-                }
-            });
+            // Spawn a supervised core loop for running the syncs of each account
+            // pair. The account pairs that aren't authorized yet start syncing
+            // once they're logged in.
+            for account_pair_config in account_pair_configs {
+                let db_connection_pool_core = db_connection_pool.clone();
+                let webhook_receiver =
+                    webhook_receivers.remove(&account_pair_config.account_pair_id);
+                let account_pair_span = tracing::info_span!(
+                    "account_pair",
+                    id = %account_pair_config.account_pair_id
+                );
+
+                tokio::spawn(
+                    supervise_sync_loop(
+                        account_pair_config,
+                        db_connection_pool_core,
+                        webhook_receiver,
+                    )
+                    .instrument(account_pair_span),
+                );
+            }
         }
     };
 
@@ -138,6 +236,150 @@ pub async fn handle_run_command(config: FediProtoSyncConfig) -> Result<()> {
     Ok(())
 }
 
+/// Spawn the web server for authorizing account pairs and receiving
+/// webhooks.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `account_pair_configs` - The configs of the account pairs.
+/// * `db_connection_pool` - The database connection pool.
+/// * `mastodon_webhooks` - The Mastodon webhooks to receive, by account pair
+///   ID.
+/// * `core_sig_error_send` - The channel to signal an error on.
+fn spawn_web_server(
+    config: &FediProtoSyncConfig,
+    account_pair_configs: &[FediProtoSyncConfig],
+    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    mastodon_webhooks: HashMap<String, MastodonWebhook>,
+    core_sig_error_send: tokio::sync::mpsc::UnboundedSender<()>,
+) {
+    let config = config.clone();
+    let account_pair_configs = account_pair_configs.to_vec();
+
+    tokio::spawn(async move {
+        let fediproto_auth_web_server = fediproto_sync_auth_ui::FediProtoSyncWebServer::new(
+            &config,
+            &account_pair_configs,
+            db_connection_pool,
+            mastodon_webhooks,
+        )
+        .unwrap();
+
+        let result = fediproto_auth_web_server.run().await;
+
+        match result {
+            Ok(_) => {
+                tracing::info!("Auth server completed successfully.");
+            }
+            Err(e) => {
+                tracing::error!("Auth server failed: {}", e);
+
+                core_sig_error_send.send(()).unwrap();
+            }
+        }
+    });
+}
+
+/// Run the sync loop for an account pair, restarting it if it fails.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `db_connection_pool` - The database connection pool.
+/// * `webhook_receiver` - The queue of statuses sent by Mastodon webhooks, if
+///   the account pair receives them.
+///
+/// ## Note
+///
+/// A failing account pair doesn't stop the syncs of the other account pairs.
+/// An account pair that isn't logged in to Mastodon yet waits until it's
+/// authorized through the auth server.
+async fn supervise_sync_loop(
+    config: FediProtoSyncConfig,
+    db_connection_pool: Pool<ConnectionManager<AnyConnection>>,
+    mut webhook_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<WebhookStatus>>,
+) {
+    wait_for_mastodon_authorization(&config, &db_connection_pool).await;
+
+    loop {
+        let result = core::run_sync_loop(
+            &config,
+            db_connection_pool.clone(),
+            webhook_receiver.as_mut(),
+        )
+        .await;
+
+        match result {
+            Ok(_) => {
+                tracing::info!("FediProto Sync completed successfully.");
+
+                return;
+            }
+            Err(e) => {
+                tracing::error!(
+                    "FediProto Sync failed, restarting in '{}' seconds: {}",
+                    ACCOUNT_PAIR_RESTART_DELAY.as_secs(),
+                    e
+                );
+
+                tokio::time::sleep(ACCOUNT_PAIR_RESTART_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Wait until the account pair has a cached Mastodon token.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `db_connection_pool` - The database connection pool.
+async fn wait_for_mastodon_authorization(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+) {
+    let mut logged_waiting = false;
+
+    loop {
+        let cached_token_exists = db_connection_pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut db_connection| {
+                Ok(
+                    fediproto_sync_db::operations::get_cached_service_token_by_service_name(
+                        &mut db_connection,
+                        &config.account_pair_id,
+                        "mastodon",
+                    )?
+                    .is_some(),
+                )
+            });
+
+        match cached_token_exists {
+            Ok(true) => {
+                if logged_waiting {
+                    tracing::info!("Logged in to Mastodon, starting the sync.");
+                }
+
+                return;
+            }
+            Ok(false) => {
+                if !logged_waiting {
+                    tracing::info!("Waiting for the account pair to be logged in to Mastodon.");
+
+                    logged_waiting = true;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to check the Mastodon authorization: {}", e);
+            }
+        }
+
+        tokio::time::sleep(MASTODON_AUTHORIZATION_POLL_INTERVAL).await;
+    }
+}
+
 /// Handles the `generate-token-encryption-key` command.
 pub fn handle_generate_token_key_command() -> Result<()> {
     let encryption_keys = fediproto_sync_lib::crypto::generate_token_encryption_key()?;
@@ -146,3 +388,274 @@ pub fn handle_generate_token_key_command() -> Result<()> {
 
     Ok(())
 }
+
+/// Handles the `backfill` command.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `account_pair_id` - The ID of the account pair to backfill, if not the
+///   one configured by the environment variables.
+/// * `options` - The options for the backfill.
+pub async fn handle_backfill_command(
+    config: FediProtoSyncConfig,
+    account_pair_id: Option<&str>,
+    options: backfill::BackfillOptions,
+) -> Result<()> {
+    tracing::info!("FediProto Sync - v{}", GIT_VERSION);
+
+    let account_pair_id = account_pair_id.unwrap_or(DEFAULT_ACCOUNT_PAIR_ID);
+
+    let mut config = account_pairs::load_account_pair_configs(&config)?
+        .into_iter()
+        .find(|account_pair_config| account_pair_config.account_pair_id == account_pair_id)
+        .ok_or_else(|| anyhow::anyhow!("Account pair '{}' doesn't exist.", account_pair_id))?;
+    config.source_platform = source_platform::resolve_source_platform(&config).await;
+
+    let db_connection_pool =
+        fediproto_sync_db::create_database_connection(&config.database_url, 1)?;
+    tracing::info!("Connected to database.");
+
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let cached_tokens_exist =
+        fediproto_sync_db::operations::get_cached_service_token_by_service_name(
+            db_connection,
+            &config.account_pair_id,
+            "mastodon",
+        )?
+        .is_some();
+
+    if !cached_tokens_exist {
+        return Err(anyhow::anyhow!(
+            "Not logged in to Mastodon. Run the daemon to log in before backfilling."
+        ));
+    }
+
+    backfill::run_backfill(&config, db_connection_pool.clone(), &options).await
+}
+
+/// Handles the `list-pending-posts` command.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL/path to the database.
+pub fn handle_list_pending_posts_command(database_url: &str) -> Result<()> {
+    let db_connection_pool = fediproto_sync_db::create_database_connection(database_url, 1)?;
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let pending_posts =
+        fediproto_sync_db::operations::get_mastodon_pending_posts(db_connection, None)?;
+
+    if pending_posts.is_empty() {
+        println!("No posts are pending.");
+
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<24} {:<20} {:<20}",
+        "ACCOUNT PAIR", "POST ID", "CREATED AT (UTC)", "PUBLISH AT (UTC)"
+    );
+
+    for pending_post in &pending_posts {
+        println!(
+            "{:<16} {:<24} {:<20} {:<20}",
+            pending_post.account_pair_id,
+            pending_post.mastodon_post_id,
+            pending_post.post_created_at.format("%Y-%m-%d %H:%M:%S"),
+            pending_post.publish_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+
+    println!();
+    println!("{} post(s) pending.", pending_posts.len());
+
+    Ok(())
+}
+
+/// Handles the `list-dead-letters` command.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL/path to the database.
+/// * `account_pair_id` - The ID of the account pair to list the dead letters
+///   for. Lists the dead letters of every account pair if `None`.
+pub fn handle_list_dead_letters_command(
+    database_url: &str,
+    account_pair_id: Option<&str>,
+) -> Result<()> {
+    let db_connection_pool = fediproto_sync_db::create_database_connection(database_url, 1)?;
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let dead_letters = fediproto_sync_db::operations::get_mastodon_post_dead_letters(
+        db_connection,
+        account_pair_id,
+    )?;
+
+    if dead_letters.is_empty() {
+        println!("No posts are in the dead letters.");
+
+        return Ok(());
+    }
+
+    println!(
+        "{:<16} {:<24} {:<8} {:<10} {:<20} REASON",
+        "ACCOUNT PAIR", "POST ID", "ATTEMPTS", "CLASS", "FAILED AT (UTC)"
+    );
+
+    for dead_letter in &dead_letters {
+        let failure_reason = dead_letter
+            .failure_reason
+            .lines()
+            .next()
+            .unwrap_or_default();
+
+        println!(
+            "{:<16} {:<24} {:<8} {:<10} {:<20} {}",
+            dead_letter.account_pair_id,
+            dead_letter.id,
+            dead_letter.attempt_count,
+            dead_letter.failure_class,
+            dead_letter.dead_lettered_at.format("%Y-%m-%d %H:%M:%S"),
+            failure_reason
+        );
+    }
+
+    println!();
+    println!("{} post(s) in the dead letters.", dead_letters.len());
+
+    Ok(())
+}
+
+/// Handles the `inspect-dead-letter` command.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL/path to the database.
+/// * `account_pair_id` - The ID of the account pair the post was synced by, if
+///   not the one configured by the environment variables.
+/// * `mastodon_post_id` - The Mastodon post ID of the dead letter.
+pub fn handle_inspect_dead_letter_command(
+    database_url: &str,
+    account_pair_id: Option<&str>,
+    mastodon_post_id: &str,
+) -> Result<()> {
+    let account_pair_id = account_pair_id.unwrap_or(DEFAULT_ACCOUNT_PAIR_ID);
+
+    let db_connection_pool = fediproto_sync_db::create_database_connection(database_url, 1)?;
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let dead_letter = fediproto_sync_db::operations::get_mastodon_post_dead_letter_by_post_id(
+        db_connection,
+        account_pair_id,
+        mastodon_post_id,
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Post '{}' isn't in the dead letters.", mastodon_post_id))?;
+
+    println!("Post ID:         {}", dead_letter.id);
+    println!("Account pair:    {}", dead_letter.account_pair_id);
+    println!("Attempts:        {}", dead_letter.attempt_count);
+    println!("Error class:     {}", dead_letter.failure_class);
+    println!(
+        "Failed at (UTC): {}",
+        dead_letter.dead_lettered_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    println!();
+    println!("Last error:");
+    println!("{}", dead_letter.failure_reason);
+    println!();
+    println!("Rendered post:");
+    println!(
+        "{}",
+        dead_letter
+            .rendered_payload
+            .as_deref()
+            .unwrap_or("(The post couldn't be rendered.)")
+    );
+
+    Ok(())
+}
+
+/// Handles the `requeue-dead-letter` command.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL/path to the database.
+/// * `account_pair_id` - The ID of the account pair the post was synced by, if
+///   not the one configured by the environment variables.
+/// * `mastodon_post_id` - The Mastodon post ID of the dead letter.
+pub fn handle_requeue_dead_letter_command(
+    database_url: &str,
+    account_pair_id: Option<&str>,
+    mastodon_post_id: &str,
+) -> Result<()> {
+    let account_pair_id = account_pair_id.unwrap_or(DEFAULT_ACCOUNT_PAIR_ID);
+
+    let db_connection_pool = fediproto_sync_db::create_database_connection(database_url, 1)?;
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let dead_letter = fediproto_sync_db::operations::get_mastodon_post_dead_letter_by_post_id(
+        db_connection,
+        account_pair_id,
+        mastodon_post_id,
+    )?
+    .ok_or_else(|| anyhow::anyhow!("Post '{}' isn't in the dead letters.", mastodon_post_id))?;
+
+    fediproto_sync_db::operations::requeue_mastodon_post_dead_letter(db_connection, &dead_letter)?;
+
+    println!(
+        "Post '{}' was moved back to the retry queue, and will be retried on the next sync.",
+        mastodon_post_id
+    );
+
+    Ok(())
+}
+
+/// Handles the `discard-dead-letter` command.
+///
+/// ## Arguments
+///
+/// * `database_url` - The URL/path to the database.
+/// * `account_pair_id` - The ID of the account pair the post was synced by, if
+///   not the one configured by the environment variables.
+/// * `mastodon_post_id` - The Mastodon post ID of the dead letter.
+pub fn handle_discard_dead_letter_command(
+    database_url: &str,
+    account_pair_id: Option<&str>,
+    mastodon_post_id: &str,
+) -> Result<()> {
+    let account_pair_id = account_pair_id.unwrap_or(DEFAULT_ACCOUNT_PAIR_ID);
+
+    let db_connection_pool = fediproto_sync_db::create_database_connection(database_url, 1)?;
+    let db_connection = &mut db_connection_pool.get()?;
+
+    fediproto_sync_db::core::run_migrations(db_connection)?;
+
+    let deleted_count = fediproto_sync_db::operations::delete_mastodon_post_dead_letter(
+        db_connection,
+        account_pair_id,
+        mastodon_post_id,
+    )?;
+
+    if deleted_count == 0 {
+        return Err(anyhow::anyhow!(
+            "Post '{}' isn't in the dead letters.",
+            mastodon_post_id
+        ));
+    }
+
+    println!("Post '{}' was discarded.", mastodon_post_id);
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{LinkMetadata, LinkMetadataProvider};
+
+/// The URL for the "extract" endpoint of BlueSky's "CardyB" metadata service.
+pub const CARDYB_EXTRACT_URL: &str = "https://cardyb.bsky.app/v1/extract";
+
+/// Client for getting link metadata from BlueSky's "CardyB" metadata service.
+pub struct CardyBClient {
+    /// The HTTP client to use for requests.
+    http_client: reqwest::Client,
+
+    /// The URL for the "extract" endpoint.
+    extract_url: String,
+
+    /// The timeout for requests.
+    timeout: std::time::Duration,
+}
+
+impl CardyBClient {
+    /// Create a new instance of the `CardyBClient` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `http_client` - The HTTP client to use for requests.
+    /// * `extract_url` - The URL for the "extract" endpoint.
+    /// * `timeout` - The timeout for requests.
+    pub fn new(
+        http_client: reqwest::Client,
+        extract_url: &str,
+        timeout: std::time::Duration,
+    ) -> Self {
+        Self {
+            http_client,
+            extract_url: extract_url.to_string(),
+            timeout,
+        }
+    }
+}
+
+impl LinkMetadataProvider for CardyBClient {
+    /// Get link metadata using BlueSky's "CardyB" metadata service.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to get metadata for.
+    async fn get_link_metadata(
+        &self,
+        url: &str,
+    ) -> Result<LinkMetadata> {
+        tracing::info!("Getting link metadata for '{}' from CardyB.", url);
+
+        let response = self
+            .http_client
+            .get(&self.extract_url)
+            .query(&[("url", url)])
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CardyBResponse>()
+            .await?;
+
+        if let Some(error) = response.error.filter(|error| !error.is_empty()) {
+            return Err(anyhow::anyhow!(
+                "CardyB failed to get metadata for '{}': {}",
+                url,
+                error
+            ));
+        }
+
+        Ok(LinkMetadata {
+            url: response
+                .url
+                .filter(|response_url| !response_url.is_empty())
+                .unwrap_or_else(|| url.to_string()),
+            title: response
+                .title
+                .filter(|title| !title.is_empty())
+                .unwrap_or_else(|| url.to_string()),
+            description: response.description.unwrap_or_default(),
+            image: response.image.filter(|image| !image.is_empty()),
+        })
+    }
+}
+
+/// The response from the "CardyB" extract endpoint.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CardyBResponse {
+    /// The error message, if the extraction failed.
+    pub error: Option<String>,
+
+    /// The URL of the link.
+    pub url: Option<String>,
+
+    /// The title of the link.
+    pub title: Option<String>,
+
+    /// The description of the link.
+    pub description: Option<String>,
+
+    /// The URL of the thumbnail image for the link.
+    pub image: Option<String>,
+}
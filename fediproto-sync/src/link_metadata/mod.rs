@@ -0,0 +1,219 @@
+/// Link metadata from BlueSky's "CardyB" metadata service.
+pub mod cardyb;
+/// Link metadata extracted locally from a page's HTML.
+pub mod opengraph;
+/// Choosing which link in a post becomes the link card.
+pub mod selection;
+
+use anyhow::Result;
+use fediproto_sync_lib::config::{FediProtoSyncConfig, LinkMetadataBackend};
+
+use self::{cardyb::CardyBClient, opengraph::OpenGraphExtractor};
+use crate::core::create_http_client;
+
+/// Metadata for a link to use when generating a link card.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LinkMetadata {
+    /// The canonical URL of the link.
+    pub url: String,
+
+    /// The title of the link.
+    pub title: String,
+
+    /// The description of the link.
+    pub description: String,
+
+    /// The URL of the thumbnail image for the link, if any.
+    pub image: Option<String>,
+}
+
+impl From<&megalodon::entities::Card> for LinkMetadata {
+    /// Convert a preview card generated by Mastodon into `LinkMetadata`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `card` - The preview card attached to a Mastodon status.
+    fn from(card: &megalodon::entities::Card) -> Self {
+        let title = match card.title.trim().is_empty() {
+            true => card.url.clone(),
+            false => card.title.trim().to_string(),
+        };
+
+        Self {
+            url: card.url.clone(),
+            title,
+            description: card.description.trim().to_string(),
+            image: card.image.clone().filter(|image| !image.is_empty()),
+        }
+    }
+}
+
+impl From<&fediproto_sync_db::models::LinkMetadataCacheEntry> for LinkMetadata {
+    /// Convert cached link metadata into `LinkMetadata`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `cache_entry` - The cached link metadata.
+    fn from(cache_entry: &fediproto_sync_db::models::LinkMetadataCacheEntry) -> Self {
+        Self {
+            url: cache_entry.url.clone(),
+            title: cache_entry.title.clone(),
+            description: cache_entry.description.clone(),
+            image: cache_entry.thumbnail_url.clone(),
+        }
+    }
+}
+
+/// Query parameters used for tracking that are removed when normalizing a
+/// URL.
+const TRACKING_QUERY_PARAMS: [&str; 5] = ["fbclid", "gclid", "mc_cid", "mc_eid", "igshid"];
+
+/// Normalize a URL so the same link is cached under the same key.
+///
+/// ## Arguments
+///
+/// * `url` - The URL to normalize.
+///
+/// ## Note
+///
+/// The scheme and host are lowercased, default ports and the fragment are
+/// removed and tracking query parameters (like `utm_source`) are removed. If
+/// the URL can't be parsed, it's returned trimmed.
+pub fn normalize_url(url: &str) -> String {
+    let mut parsed_url = match reqwest::Url::parse(url.trim()) {
+        Ok(parsed_url) => parsed_url,
+        Err(_) => return url.trim().to_string(),
+    };
+
+    parsed_url.set_fragment(None);
+
+    let query_pairs = parsed_url
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_lowercase();
+
+            !key.starts_with("utm_") && !TRACKING_QUERY_PARAMS.contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<(String, String)>>();
+
+    match query_pairs.is_empty() {
+        true => parsed_url.set_query(None),
+        false => {
+            parsed_url
+                .query_pairs_mut()
+                .clear()
+                .extend_pairs(query_pairs);
+        }
+    }
+
+    parsed_url.to_string()
+}
+
+/// Check if a preview card generated by Mastodon is for a link.
+///
+/// ## Arguments
+///
+/// * `card` - The preview card attached to a Mastodon status.
+/// * `url` - The URL of the link.
+///
+/// ## Note
+///
+/// Mastodon may store the canonical URL of a page in the card, so the scheme,
+/// a leading `www.`, a trailing `/` and the fragment are ignored when
+/// comparing the URLs.
+pub fn card_matches_url(
+    card: &megalodon::entities::Card,
+    url: &str,
+) -> bool {
+    match (comparable_url(&card.url), comparable_url(url)) {
+        (Some(card_url), Some(url)) => card_url == url,
+        _ => false,
+    }
+}
+
+/// Get a version of a URL that can be loosely compared with other URLs.
+///
+/// ## Arguments
+///
+/// * `url` - The URL to convert.
+fn comparable_url(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url.trim()).ok()?;
+    let host = url.host_str()?;
+
+    Some(format!(
+        "{}{}{}",
+        host.trim_start_matches("www."),
+        url.path().trim_end_matches('/'),
+        url.query()
+            .map(|query| format!("?{}", query))
+            .unwrap_or_default()
+    ))
+}
+
+/// Trait for a backend that can get metadata for a link.
+pub trait LinkMetadataProvider {
+    /// Get metadata for a link.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to get metadata for.
+    #[allow(async_fn_in_trait)]
+    async fn get_link_metadata(
+        &self,
+        url: &str,
+    ) -> Result<LinkMetadata>;
+}
+
+/// The link metadata backend configured for the application.
+pub enum LinkMetadataClient {
+    /// Extract the metadata locally.
+    Local(OpenGraphExtractor),
+
+    /// Use BlueSky's "CardyB" metadata service.
+    CardyB(CardyBClient),
+}
+
+impl LinkMetadataClient {
+    /// Create a new link metadata client for the configured backend.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config for the app.
+    pub fn new(config: &FediProtoSyncConfig) -> Result<Self> {
+        let http_client = create_http_client(config)?;
+
+        let client = match config.link_metadata_backend {
+            LinkMetadataBackend::Local => Self::Local(OpenGraphExtractor::new(
+                http_client,
+                config.link_metadata_timeout,
+                config.link_metadata_max_size,
+            )),
+
+            LinkMetadataBackend::CardyB => Self::CardyB(CardyBClient::new(
+                http_client,
+                cardyb::CARDYB_EXTRACT_URL,
+                config.link_metadata_timeout,
+            )),
+        };
+
+        Ok(client)
+    }
+}
+
+impl LinkMetadataProvider for LinkMetadataClient {
+    /// Get metadata for a link with the configured backend.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to get metadata for.
+    async fn get_link_metadata(
+        &self,
+        url: &str,
+    ) -> Result<LinkMetadata> {
+        match self {
+            Self::Local(extractor) => extractor.get_link_metadata(url).await,
+            Self::CardyB(client) => client.get_link_metadata(url).await,
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use anyhow::Result;
+use reqwest::{
+    Url,
+    header::{ACCEPT, CONTENT_TYPE},
+};
+use serde::Deserialize;
+
+use super::{LinkMetadata, LinkMetadataProvider};
+
+/// Extracts link metadata locally by fetching a page and reading its
+/// OpenGraph, Twitter card, oEmbed and `<title>`/`<meta>` tags.
+pub struct OpenGraphExtractor {
+    /// The HTTP client to use for requests.
+    http_client: reqwest::Client,
+
+    /// The timeout for requests.
+    timeout: std::time::Duration,
+
+    /// The maximum amount of bytes to read from a response.
+    max_size: u64,
+}
+
+impl OpenGraphExtractor {
+    /// Create a new instance of the `OpenGraphExtractor` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `http_client` - The HTTP client to use for requests.
+    /// * `timeout` - The timeout for requests.
+    /// * `max_size` - The maximum amount of bytes to read from a response.
+    pub fn new(
+        http_client: reqwest::Client,
+        timeout: std::time::Duration,
+        max_size: u64,
+    ) -> Self {
+        Self {
+            http_client,
+            timeout,
+            max_size,
+        }
+    }
+
+    /// Fetch a URL and read the body, up to the maximum size, as a string.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to fetch.
+    /// * `accept` - The value for the `Accept` header.
+    ///
+    /// ## Note
+    ///
+    /// Returns the final URL after any redirects, the `Content-Type` of the
+    /// response and the body.
+    async fn fetch(
+        &self,
+        url: &Url,
+        accept: &str,
+    ) -> Result<(Url, String, String)> {
+        let mut response = self
+            .http_client
+            .get(url.clone())
+            .header(ACCEPT, accept)
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        // Read the body in chunks so we stop downloading once the maximum size
+        // has been reached. The metadata we need is in the `<head>` of the
+        // page, so a truncated body is still useful.
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            let remaining = self.max_size as usize - body.len();
+
+            if chunk.len() >= remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                tracing::debug!(
+                    "Stopped reading '{}' after '{}' bytes.",
+                    final_url,
+                    self.max_size
+                );
+                break;
+            }
+
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok((
+            final_url,
+            content_type,
+            String::from_utf8_lossy(&body).to_string(),
+        ))
+    }
+
+    /// Get the metadata from an oEmbed endpoint.
+    ///
+    /// ## Arguments
+    ///
+    /// * `oembed_url` - The URL of the oEmbed endpoint.
+    async fn get_oembed_metadata(
+        &self,
+        oembed_url: &Url,
+    ) -> Result<OEmbedResponse> {
+        let (_, _, body) = self.fetch(oembed_url, "application/json").await?;
+
+        Ok(serde_json::from_str::<OEmbedResponse>(&body)?)
+    }
+}
+
+impl LinkMetadataProvider for OpenGraphExtractor {
+    /// Get link metadata by fetching the page and parsing its HTML.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to get metadata for.
+    async fn get_link_metadata(
+        &self,
+        url: &str,
+    ) -> Result<LinkMetadata> {
+        tracing::info!("Getting link metadata for '{}'.", url);
+
+        let page_url = Url::parse(url)?;
+        let (final_url, content_type, body) = self
+            .fetch(&page_url, "text/html,application/xhtml+xml;q=0.9,*/*;q=0.8")
+            .await?;
+
+        // Links directly to an image can use the image as the thumbnail.
+        if content_type.starts_with("image/") {
+            return Ok(LinkMetadata {
+                url: url.to_string(),
+                title: url.to_string(),
+                description: "".to_string(),
+                image: Some(final_url.to_string()),
+            });
+        }
+
+        let mut html_metadata = HtmlMetadata::parse(&body, &final_url);
+
+        // Only call the oEmbed endpoint if the page didn't have the title or
+        // thumbnail we need.
+        let needs_oembed = html_metadata.title.is_none() || html_metadata.image.is_none();
+        if let Some(oembed_url) = html_metadata.oembed_url.clone().filter(|_| needs_oembed) {
+            match self.get_oembed_metadata(&oembed_url).await {
+                Ok(oembed) => html_metadata.apply_oembed(oembed, &final_url),
+                Err(e) => tracing::warn!("Failed to get oEmbed data from '{}': {}", oembed_url, e),
+            }
+        }
+
+        Ok(html_metadata.into_link_metadata(url))
+    }
+}
+
+/// Metadata parsed from a page's HTML.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HtmlMetadata {
+    /// The canonical URL of the page, if any.
+    pub url: Option<String>,
+
+    /// The title of the page, if any.
+    pub title: Option<String>,
+
+    /// The description of the page, if any.
+    pub description: Option<String>,
+
+    /// The URL of the thumbnail image for the page, if any.
+    pub image: Option<String>,
+
+    /// The URL of the page's JSON oEmbed endpoint, if any.
+    pub oembed_url: Option<Url>,
+}
+
+impl HtmlMetadata {
+    /// Parse the metadata from a page's HTML.
+    ///
+    /// ## Arguments
+    ///
+    /// * `html` - The HTML of the page.
+    /// * `page_url` - The URL of the page, used to resolve relative URLs.
+    ///
+    /// ## Note
+    ///
+    /// Values are taken in order of preference from OpenGraph tags, Twitter
+    /// card tags and then the standard `<title>`, `<meta>` and `<link>` tags.
+    pub fn parse(
+        html: &str,
+        page_url: &Url,
+    ) -> Self {
+        let document = dom_query::Document::from(html);
+
+        let title = first_value(&[
+            meta_content(&document, "property", "og:title"),
+            meta_content(&document, "name", "twitter:title"),
+            non_empty(document.select("head title").first().text().to_string()),
+        ]);
+
+        let description = first_value(&[
+            meta_content(&document, "property", "og:description"),
+            meta_content(&document, "name", "twitter:description"),
+            meta_content(&document, "name", "description"),
+        ]);
+
+        let image = first_value(&[
+            meta_content(&document, "property", "og:image:secure_url"),
+            meta_content(&document, "property", "og:image"),
+            meta_content(&document, "name", "twitter:image"),
+            meta_content(&document, "name", "twitter:image:src"),
+        ])
+        .and_then(|image| resolve_url(page_url, &image));
+
+        let url = first_value(&[
+            meta_content(&document, "property", "og:url"),
+            link_href(&document, "canonical"),
+        ])
+        .and_then(|url| resolve_url(page_url, &url));
+
+        let oembed_url = document
+            .select("link[rel='alternate'][type='application/json+oembed']")
+            .first()
+            .attr("href")
+            .and_then(|href| page_url.join(href.trim()).ok());
+
+        Self {
+            url,
+            title,
+            description,
+            image,
+            oembed_url,
+        }
+    }
+
+    /// Fill in any missing values with data from an oEmbed response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `oembed` - The oEmbed response.
+    /// * `page_url` - The URL of the page, used to resolve relative URLs.
+    fn apply_oembed(
+        &mut self,
+        oembed: OEmbedResponse,
+        page_url: &Url,
+    ) {
+        if self.title.is_none() {
+            self.title = oembed.title.and_then(non_empty);
+        }
+
+        if self.image.is_none() {
+            self.image = oembed
+                .thumbnail_url
+                .and_then(|thumbnail_url| resolve_url(page_url, &thumbnail_url));
+        }
+    }
+
+    /// Convert the parsed metadata into `LinkMetadata`, falling back to the
+    /// requested URL for any missing values.
+    ///
+    /// ## Arguments
+    ///
+    /// * `requested_url` - The URL that metadata was requested for.
+    pub fn into_link_metadata(
+        self,
+        requested_url: &str,
+    ) -> LinkMetadata {
+        LinkMetadata {
+            url: self.url.unwrap_or_else(|| requested_url.to_string()),
+            title: self.title.unwrap_or_else(|| requested_url.to_string()),
+            description: self.description.unwrap_or_default(),
+            image: self.image,
+        }
+    }
+}
+
+/// The response from an oEmbed endpoint.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct OEmbedResponse {
+    /// The title of the resource.
+    pub title: Option<String>,
+
+    /// The URL of the thumbnail image for the resource.
+    pub thumbnail_url: Option<String>,
+}
+
+/// Get the `content` attribute of a `<meta>` tag.
+///
+/// ## Arguments
+///
+/// * `document` - The HTML document.
+/// * `attribute` - The attribute used to identify the tag (`property` or
+///   `name`).
+/// * `value` - The value of the identifying attribute.
+fn meta_content(
+    document: &dom_query::Document,
+    attribute: &str,
+    value: &str,
+) -> Option<String> {
+    document
+        .select(&format!("meta[{}='{}']", attribute, value))
+        .first()
+        .attr("content")
+        .and_then(|content| non_empty(content.to_string()))
+}
+
+/// Get the `href` attribute of a `<link>` tag.
+///
+/// ## Arguments
+///
+/// * `document` - The HTML document.
+/// * `rel` - The `rel` attribute of the tag.
+fn link_href(
+    document: &dom_query::Document,
+    rel: &str,
+) -> Option<String> {
+    document
+        .select(&format!("link[rel='{}']", rel))
+        .first()
+        .attr("href")
+        .and_then(|href| non_empty(href.to_string()))
+}
+
+/// Get the first value that is set.
+///
+/// ## Arguments
+///
+/// * `values` - The values in order of preference.
+fn first_value(values: &[Option<String>]) -> Option<String> {
+    values.iter().flatten().next().cloned()
+}
+
+/// Trim a string and return `None` if it's empty.
+///
+/// ## Arguments
+///
+/// * `value` - The value to check.
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+
+    match value.is_empty() {
+        true => None,
+        false => Some(value.to_string()),
+    }
+}
+
+/// Resolve a possibly relative URL against the URL of the page it was found
+/// on.
+///
+/// ## Arguments
+///
+/// * `page_url` - The URL of the page.
+/// * `url` - The URL to resolve.
+fn resolve_url(
+    page_url: &Url,
+    url: &str,
+) -> Option<String> {
+    page_url.join(url).ok().map(|url| url.to_string())
+}
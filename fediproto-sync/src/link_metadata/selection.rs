@@ -0,0 +1,203 @@
+use fediproto_sync_lib::config::{FediProtoSyncConfig, LinkCardPreference};
+use reqwest::Url;
+
+/// Policy for choosing which link in a post becomes the link card.
+#[derive(Debug, Clone)]
+pub struct LinkSelectionPolicy {
+    /// Which eligible link to prefer.
+    pub preference: LinkCardPreference,
+
+    /// The marker to put before a link to use it for the link card, if any.
+    pub override_marker: Option<String>,
+
+    /// Domains that should never be used for a link card.
+    pub blocked_domains: Vec<String>,
+
+    /// The hostname of the Mastodon server the posts are from.
+    pub instance_host: String,
+}
+
+impl LinkSelectionPolicy {
+    /// Create a new link selection policy from the app's config.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config for the app.
+    pub fn new(config: &FediProtoSyncConfig) -> Self {
+        let override_marker = match config.link_card_override_marker.trim().is_empty() {
+            true => None,
+            false => Some(config.link_card_override_marker.trim().to_string()),
+        };
+
+        let blocked_domains = config
+            .link_card_blocked_domains
+            .iter()
+            .map(|domain| domain.trim().trim_start_matches('.').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+
+        Self {
+            preference: config.link_card_preference.clone(),
+            override_marker,
+            blocked_domains,
+            instance_host: config.mastodon_server.trim().to_lowercase(),
+        }
+    }
+
+    /// Select the link to use for the link card of a Mastodon status.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status.
+    ///
+    /// ## Note
+    ///
+    /// Mentions, hashtags, profile links and links to the Mastodon server are
+    /// skipped. A link with the override marker directly before it is always
+    /// used. Links to blocked domains are never used.
+    pub fn select_link(
+        &self,
+        status: &megalodon::entities::Status,
+    ) -> Option<String> {
+        let html_document = dom_query::Document::fragment(status.content.as_str());
+
+        let mut eligible_links = Vec::new();
+
+        for node in html_document.select("a[href]").nodes() {
+            let href = match node.attr("href") {
+                Some(href) => href.trim().to_string(),
+                None => continue,
+            };
+
+            let url = match Url::parse(&href) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+                _ => continue,
+            };
+
+            if self.is_blocked_domain(&url) {
+                tracing::info!("Skipping link card for blocked domain: {}", href);
+                continue;
+            }
+
+            if self.has_override_marker(node) {
+                tracing::info!("Using link with override marker for link card: {}", href);
+                return Some(href);
+            }
+
+            if node.has_class("mention")
+                || node.has_class("hashtag")
+                || is_status_mention_or_tag(status, &href)
+                || is_profile_url(&url)
+                || self.is_instance_url(&url)
+            {
+                continue;
+            }
+
+            eligible_links.push(href);
+        }
+
+        match self.preference {
+            LinkCardPreference::First => eligible_links.into_iter().next(),
+            LinkCardPreference::Last => eligible_links.pop(),
+        }
+    }
+
+    /// Check if a link has the override marker directly before it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `node` - The `<a>` node of the link.
+    fn has_override_marker(
+        &self,
+        node: &dom_query::NodeRef,
+    ) -> bool {
+        let override_marker = match &self.override_marker {
+            Some(override_marker) => override_marker,
+            None => return false,
+        };
+
+        node.prev_sibling()
+            .filter(|prev_node| prev_node.is_text())
+            .map(|prev_node| {
+                prev_node
+                    .text()
+                    .trim_end()
+                    .ends_with(override_marker.as_str())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check if a URL is on a blocked domain or one of its subdomains.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to check.
+    fn is_blocked_domain(
+        &self,
+        url: &Url,
+    ) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+
+        self.blocked_domains
+            .iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+    }
+
+    /// Check if a URL is on the Mastodon server the posts are from.
+    ///
+    /// ## Arguments
+    ///
+    /// * `url` - The URL to check.
+    fn is_instance_url(
+        &self,
+        url: &Url,
+    ) -> bool {
+        url.host_str().unwrap_or_default().to_lowercase() == self.instance_host
+    }
+}
+
+/// Check if a link is one of the status' mentions or hashtags.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon status.
+/// * `href` - The link to check.
+fn is_status_mention_or_tag(
+    status: &megalodon::entities::Status,
+    href: &str,
+) -> bool {
+    let href = href.to_lowercase();
+
+    status
+        .mentions
+        .iter()
+        .any(|mention| mention.url.to_lowercase() == href)
+        || status.tags.iter().any(|tag| tag.url.to_lowercase() == href)
+}
+
+/// Check if a URL looks like a link to a fediverse profile.
+///
+/// ## Arguments
+///
+/// * `url` - The URL to check.
+///
+/// ## Note
+///
+/// Matches the `/@user` and `/users/user` paths used by Mastodon and most
+/// other fediverse software.
+fn is_profile_url(url: &Url) -> bool {
+    let path_segments = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<&str>>()
+        })
+        .unwrap_or_default();
+
+    match path_segments.as_slice() {
+        [user] => user.starts_with('@') && user.len() > 1,
+        ["users", _] | ["u", _] => true,
+        _ => false,
+    }
+}
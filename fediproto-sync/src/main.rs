@@ -29,7 +29,7 @@ async fn main() -> Result<()> {
     let env_vars_path = root_app_dir.join(format!("{}.env", environment_name.to_lowercase()));
 
     if env_vars_path.exists() {
-        let _ = dotenvy::from_path(env_vars_path)?;
+        dotenvy::from_path(env_vars_path)?;
     }
 
     let cli = Cli::parse();
@@ -58,5 +58,49 @@ async fn main() -> Result<()> {
         CliSubcommands::GenerateTokenEncryptionKey => {
             fediproto_sync::handle_generate_token_key_command()
         }
+
+        CliSubcommands::Backfill(backfill_args) => {
+            let options = (&backfill_args).into();
+            let account_pair_id = backfill_args.account_pair.clone();
+            let config: FediProtoSyncConfig = backfill_args.run_args.into();
+
+            fediproto_sync::handle_backfill_command(config, account_pair_id.as_deref(), options)
+                .await
+        }
+
+        CliSubcommands::ListPendingPosts(list_pending_posts_args) => {
+            fediproto_sync::handle_list_pending_posts_command(&list_pending_posts_args.database_url)
+        }
+
+        CliSubcommands::ListDeadLetters(list_dead_letters_args) => {
+            fediproto_sync::handle_list_dead_letters_command(
+                &list_dead_letters_args.database_url,
+                list_dead_letters_args.account_pair.as_deref(),
+            )
+        }
+
+        CliSubcommands::InspectDeadLetter(dead_letter_args) => {
+            fediproto_sync::handle_inspect_dead_letter_command(
+                &dead_letter_args.database_url,
+                dead_letter_args.account_pair.as_deref(),
+                &dead_letter_args.post_id,
+            )
+        }
+
+        CliSubcommands::RequeueDeadLetter(dead_letter_args) => {
+            fediproto_sync::handle_requeue_dead_letter_command(
+                &dead_letter_args.database_url,
+                dead_letter_args.account_pair.as_deref(),
+                &dead_letter_args.post_id,
+            )
+        }
+
+        CliSubcommands::DiscardDeadLetter(dead_letter_args) => {
+            fediproto_sync::handle_discard_dead_letter_command(
+                &dead_letter_args.database_url,
+                dead_letter_args.account_pair.as_deref(),
+                &dead_letter_args.post_id,
+            )
+        }
     }
 }
@@ -1,65 +1,478 @@
+use std::cmp::Ordering;
+
 use anyhow::Result;
 
+use fediproto_sync_lib::config::SourcePlatform;
+use serde::Deserialize;
+
+use crate::{
+    backfill::compare_post_ids,
+    rate_limit::MastodonRateLimiter,
+    rules::transform::TransformRule,
+    source_platform,
+    templates::{TemplateContext, TemplateSet},
+};
+
+/// The number of posts to get in each page of account statuses.
+const STATUSES_PAGE_SIZE: u32 = 40;
+
+/// The ID of a status returned by the Mastodon API.
+#[derive(Debug, Deserialize)]
+struct StatusId {
+    /// The ID of the status.
+    id: String,
+}
+
+/// Client for the Mastodon API requests that `Megalodon` doesn't have the
+/// options for.
+#[derive(Debug, Clone)]
+pub struct MastodonHttpClient {
+    /// The HTTP client to use for requests.
+    http_client: reqwest::Client,
+
+    /// The base URL of the Mastodon server.
+    base_url: String,
+
+    /// The access token for the Mastodon account.
+    access_token: String,
+}
+
+impl MastodonHttpClient {
+    /// Create a new instance of the `MastodonHttpClient` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `http_client` - The HTTP client to use for requests.
+    /// * `base_url` - The base URL of the Mastodon server, like
+    ///   `https://mastodon.social`.
+    /// * `access_token` - The access token for the Mastodon account.
+    pub fn new(
+        http_client: reqwest::Client,
+        base_url: &str,
+        access_token: &str,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+        }
+    }
+
+    /// Get the IDs of the posts of a Mastodon account made right after a post,
+    /// with `min_id`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account_id` - The Mastodon account ID to get the post IDs for.
+    /// * `min_id` - The ID of the post to get the posts after.
+    /// * `include_replies` - Whether to include replies to other accounts.
+    /// * `limit` - The maximum number of post IDs to get.
+    ///
+    /// ## Note
+    ///
+    /// Only the IDs are read, since `Megalodon` can't parse the statuses of
+    /// every server, and the IDs are returned newest first.
+    pub async fn get_account_status_ids_after(
+        &self,
+        account_id: &str,
+        min_id: &str,
+        include_replies: bool,
+        limit: u32,
+    ) -> Result<megalodon::response::Response<Vec<String>>, megalodon::error::Error> {
+        let url = format!("{}/api/v1/accounts/{}/statuses", self.base_url, account_id);
+
+        let response = self
+            .http_client
+            .get(&url)
+            .bearer_auth(&self.access_token)
+            .query(&[
+                ("min_id", min_id.to_string()),
+                ("limit", limit.to_string()),
+                ("pinned", "false".to_string()),
+                ("exclude_replies", (!include_replies).to_string()),
+                ("exclude_reblogs", "false".to_string()),
+            ])
+            .send()
+            .await
+            .map_err(|e| {
+                megalodon::error::Error::new_own(
+                    e.to_string(),
+                    megalodon::error::Kind::HTTPStatusError,
+                    Some(url.clone()),
+                    None,
+                    None,
+                )
+            })?;
+
+        let status = response.status();
+        let header = response.headers().clone();
+
+        if !status.is_success() {
+            return Err(megalodon::error::Error::new_own(
+                format!("The Mastodon server responded with '{}'.", status),
+                megalodon::error::Kind::HTTPStatusError,
+                Some(url),
+                Some(status.as_u16()),
+                Some(header),
+            ));
+        }
+
+        let status_ids = response.json::<Vec<StatusId>>().await.map_err(|e| {
+            megalodon::error::Error::new_own(
+                e.to_string(),
+                megalodon::error::Kind::ParseError,
+                Some(url.clone()),
+                Some(status.as_u16()),
+                None,
+            )
+        })?;
+
+        Ok(megalodon::response::Response::new(
+            status_ids
+                .into_iter()
+                .map(|status_id| status_id.id)
+                .collect(),
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default().to_string(),
+            header,
+        ))
+    }
+
+    /// Get the ID of the first post over the limit since the last synced
+    /// post, paging forward from it with `min_id`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `rate_limiter` - The rate limiter for the Mastodon server.
+    /// * `account_id` - The Mastodon account ID to get the posts for.
+    /// * `last_post_id` - The last post ID to page forward from.
+    /// * `include_replies` - Whether to include replies to other accounts.
+    /// * `max_posts` - The maximum number of posts to sync at once.
+    ///
+    /// ## Note
+    ///
+    /// Returns `None` if there are no more than `max_posts` posts since the
+    /// last post. Paging stops as soon as the post over the limit is found.
+    pub async fn get_first_post_id_over_limit(
+        &self,
+        rate_limiter: &MastodonRateLimiter,
+        account_id: &str,
+        last_post_id: &str,
+        include_replies: bool,
+        max_posts: usize,
+    ) -> Result<Option<String>, megalodon::error::Error> {
+        let mut post_ids = Vec::new();
+        let mut min_id = last_post_id.to_string();
+
+        while post_ids.len() <= max_posts {
+            let limit = (max_posts + 1 - post_ids.len()).min(STATUSES_PAGE_SIZE as usize);
+
+            let mut page_post_ids = rate_limiter
+                .send(self.get_account_status_ids_after(
+                    account_id,
+                    &min_id,
+                    include_replies,
+                    limit as u32,
+                ))
+                .await?
+                .json;
+
+            // The page is newest first, and some servers return the post it's
+            // paged from too.
+            page_post_ids.retain(|post_id| compare_post_ids(post_id, &min_id) == Ordering::Greater);
+            page_post_ids.sort_by(|a, b| compare_post_ids(a, b));
+
+            let is_last_page = page_post_ids.len() < limit;
+
+            match page_post_ids.last() {
+                Some(newest_post_id) => min_id = newest_post_id.clone(),
+                None => break,
+            }
+
+            post_ids.extend(page_post_ids);
+
+            if is_last_page {
+                break;
+            }
+
+            tracing::debug!(
+                "Found '{}' posts since the last post, paged forward to '{}'.",
+                post_ids.len(),
+                min_id
+            );
+        }
+
+        let first_post_id_over_limit = post_ids.get(max_posts).cloned();
+
+        if let Some(first_post_id_over_limit) = &first_post_id_over_limit {
+            tracing::warn!(
+                "Found more than '{}' posts since '{}'. Syncing the oldest '{}' now, the posts from '{}' on will be synced on the next sync.",
+                max_posts,
+                last_post_id,
+                max_posts,
+                first_post_id_over_limit
+            );
+        }
+
+        Ok(first_post_id_over_limit)
+    }
+}
+
 /// Extension trait for the Mastodon API.
 pub trait MastodonApiExtensions {
     #[allow(async_fn_in_trait)]
     async fn get_latest_posts(
         &self,
+        rate_limiter: &MastodonRateLimiter,
         account_id: &str,
         last_post_id: Option<String>,
+        first_post_id_over_limit: Option<String>,
+        include_unlisted: bool,
+        include_replies: bool,
+    ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error>;
+
+    #[allow(async_fn_in_trait)]
+    async fn get_posts_in_range(
+        &self,
+        rate_limiter: &MastodonRateLimiter,
+        account_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
         include_unlisted: bool,
+        include_replies: bool,
+    ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error>;
+
+    #[allow(async_fn_in_trait)]
+    async fn get_pinned_posts(
+        &self,
+        rate_limiter: &MastodonRateLimiter,
+        account_id: &str,
     ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error>;
 }
 
 impl MastodonApiExtensions for Box<dyn megalodon::Megalodon + Send + Sync> {
-    /// Get the latest posts from a Mastodon account.
+    /// Get the posts from a Mastodon account since the last synced post,
+    /// oldest first.
     ///
     /// ## Arguments
     ///
+    /// * `rate_limiter` - The rate limiter for the Mastodon server.
     /// * `account_id` - The Mastodon account ID to get the latest posts for.
     /// * `last_post_id` - The last post ID to get posts since.
+    /// * `first_post_id_over_limit` - The ID of the first post over the limit
+    ///   of posts to sync at once, from `get_first_post_id_over_limit`.
+    /// * `include_unlisted` - Whether to include unlisted posts.
+    /// * `include_replies` - Whether to include replies to other accounts.
+    ///
+    /// ## Note
+    ///
+    /// If there is no last post ID, only the latest post is returned. The
+    /// posts from the first post over the limit on aren't fetched, so the sync
+    /// cursor doesn't move past them before they're synced.
     async fn get_latest_posts(
         &self,
+        rate_limiter: &MastodonRateLimiter,
         account_id: &str,
         last_post_id: Option<String>,
+        first_post_id_over_limit: Option<String>,
         include_unlisted: bool,
+        include_replies: bool,
     ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error> {
-        let limit_value = match last_post_id {
-            Some(_) => None,
-            None => Some(1),
-        };
+        let mut latest_posts = Vec::new();
+        let mut max_id = first_post_id_over_limit;
+
+        // Page backwards from the first post over the limit, or the newest
+        // post, until the last post is reached.
+        loop {
+            let latest_statuses_options = megalodon::megalodon::GetAccountStatusesInputOptions {
+                limit: match last_post_id {
+                    Some(_) => Some(STATUSES_PAGE_SIZE),
+                    None => Some(1),
+                },
+                max_id: max_id.clone(),
+                since_id: last_post_id.clone(),
+                pinned: Some(false),
+                exclude_replies: Some(!include_replies),
+                exclude_reblogs: Some(false),
+                only_media: Some(false),
+                only_public: Some(true),
+            };
+
+            let statuses =
+                rate_limiter
+                    .send(self.get_account_statuses(
+                        account_id.to_string(),
+                        Some(&latest_statuses_options),
+                    ))
+                    .await?
+                    .json;
+
+            // Some servers ignore `since_id` when `max_id` is set, so stop at
+            // the last post, or at the first post older than it if it was
+            // deleted.
+            let is_after_last_post = |item: &megalodon::entities::Status| {
+                last_post_id.as_deref().is_none_or(|last_post_id| {
+                    compare_post_ids(&item.id, last_post_id) == Ordering::Greater
+                })
+            };
+
+            let is_last_post_reached = !statuses.iter().all(is_after_last_post);
+
+            let statuses = statuses
+                .into_iter()
+                .take_while(is_after_last_post)
+                .collect::<Vec<megalodon::entities::Status>>();
+
+            let oldest_status_id = match statuses.last() {
+                Some(oldest_status) => oldest_status.id.clone(),
+                None => break,
+            };
+
+            latest_posts.extend(statuses);
+
+            if last_post_id.is_none() || is_last_post_reached {
+                break;
+            }
+
+            tracing::debug!(
+                "Found '{}' posts since the last post, paged back to '{}'.",
+                latest_posts.len(),
+                oldest_status_id
+            );
+
+            max_id = Some(oldest_status_id);
+        }
+
+        let mut filtered_latest_posts = latest_posts
+            .into_iter()
+            .filter(|item| is_syncable_visibility(item, include_unlisted))
+            .collect::<Vec<megalodon::entities::Status>>();
+
+        filtered_latest_posts.reverse();
+
+        Ok(filtered_latest_posts)
+    }
+
+    /// Get the posts from a Mastodon account created within a date range,
+    /// oldest first.
+    ///
+    /// ## Arguments
+    ///
+    /// * `rate_limiter` - The rate limiter for the Mastodon server.
+    /// * `account_id` - The Mastodon account ID to get the posts for.
+    /// * `since` - The start of the date range.
+    /// * `until` - The end of the date range. Posts created at this time are
+    ///   not included.
+    /// * `include_unlisted` - Whether to include unlisted posts.
+    /// * `include_replies` - Whether to include replies to other accounts.
+    async fn get_posts_in_range(
+        &self,
+        rate_limiter: &MastodonRateLimiter,
+        account_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+        until: chrono::DateTime<chrono::Utc>,
+        include_unlisted: bool,
+        include_replies: bool,
+    ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error> {
+        let mut posts_in_range = Vec::new();
+        let mut max_id = None;
+
+        // Page backwards from the newest post until the start of the range is
+        // reached.
+        loop {
+            let statuses_options = megalodon::megalodon::GetAccountStatusesInputOptions {
+                limit: Some(STATUSES_PAGE_SIZE),
+                max_id: max_id.clone(),
+                since_id: None,
+                pinned: Some(false),
+                exclude_replies: Some(!include_replies),
+                exclude_reblogs: Some(false),
+                only_media: Some(false),
+                only_public: Some(true),
+            };
+
+            let statuses = rate_limiter
+                .send(self.get_account_statuses(account_id.to_string(), Some(&statuses_options)))
+                .await?
+                .json;
+
+            let oldest_status = match statuses.last() {
+                Some(oldest_status) => oldest_status.clone(),
+                None => break,
+            };
+
+            posts_in_range.extend(statuses.into_iter().filter(|item| {
+                item.created_at >= since
+                    && item.created_at < until
+                    && is_syncable_visibility(item, include_unlisted)
+            }));
+
+            tracing::debug!(
+                "Found '{}' posts in range, paged back to '{}'.",
+                posts_in_range.len(),
+                oldest_status.created_at
+            );
+
+            if oldest_status.created_at < since {
+                break;
+            }
+
+            max_id = Some(oldest_status.id.clone());
+        }
+
+        posts_in_range.reverse();
 
-        let latest_statuses_options = megalodon::megalodon::GetAccountStatusesInputOptions {
-            limit: limit_value,
+        Ok(posts_in_range)
+    }
+
+    /// Get the pinned posts of a Mastodon account, in the order they're shown
+    /// on the profile.
+    ///
+    /// ## Arguments
+    ///
+    /// * `rate_limiter` - The rate limiter for the Mastodon server.
+    /// * `account_id` - The Mastodon account ID to get the pinned posts for.
+    async fn get_pinned_posts(
+        &self,
+        rate_limiter: &MastodonRateLimiter,
+        account_id: &str,
+    ) -> Result<Vec<megalodon::entities::Status>, megalodon::error::Error> {
+        let pinned_statuses_options = megalodon::megalodon::GetAccountStatusesInputOptions {
+            limit: None,
             max_id: None,
-            since_id: last_post_id,
-            pinned: Some(false),
-            exclude_replies: Some(true),
+            since_id: None,
+            pinned: Some(true),
+            exclude_replies: Some(false),
             exclude_reblogs: Some(false),
             only_media: Some(false),
-            only_public: Some(true),
+            only_public: Some(false),
         };
 
-        let latest_posts = self
-            .get_account_statuses(account_id.to_string(), Some(&latest_statuses_options))
+        let pinned_posts = rate_limiter
+            .send(self.get_account_statuses(account_id.to_string(), Some(&pinned_statuses_options)))
             .await?;
 
-        let filtered_latest_posts = latest_posts
-            .json
-            .iter()
-            .filter(|&item| match include_unlisted {
-                true => {
-                    item.visibility == megalodon::entities::status::StatusVisibility::Public
-                        || item.visibility
-                            == megalodon::entities::status::StatusVisibility::Unlisted
-                }
+        Ok(pinned_posts.json)
+    }
+}
 
-                false => item.visibility == megalodon::entities::status::StatusVisibility::Public,
-            })
-            .cloned()
-            .collect();
+/// Check if the visibility of a Mastodon post allows it to be synced.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon post.
+/// * `include_unlisted` - Whether unlisted posts can be synced.
+pub fn is_syncable_visibility(
+    status: &megalodon::entities::Status,
+    include_unlisted: bool,
+) -> bool {
+    match include_unlisted {
+        true => {
+            status.visibility == megalodon::entities::status::StatusVisibility::Public
+                || status.visibility == megalodon::entities::status::StatusVisibility::Unlisted
+        }
 
-        Ok(filtered_latest_posts)
+        false => status.visibility == megalodon::entities::status::StatusVisibility::Public,
     }
 }
 
@@ -79,12 +492,19 @@ impl ParsedMastodonPost {
     /// ## Arguments
     ///
     /// * `status` - The Mastodon status to parse.
-    pub fn from_mastodon_status(status: &megalodon::entities::Status) -> Result<Self> {
+    /// * `source_platform` - The platform the status was posted on.
+    pub fn from_mastodon_status(
+        status: &megalodon::entities::Status,
+        source_platform: SourcePlatform,
+    ) -> Result<Self> {
         // Parse the HTML content of the status.
         let html_document = dom_query::Document::fragment(status.content.clone().as_str());
 
         let mastodon_status = status.clone();
-        let stripped_html = Self::convert_html_content_to_string(&html_document)?;
+        let stripped_html = Self::convert_html_content_to_string(
+            &html_document,
+            source_platform::keeps_line_breaks(source_platform),
+        )?;
         let found_links = Self::get_links(&html_document, &status.tags)?;
         let found_tags = Self::get_tags(&html_document, &status.tags)?;
 
@@ -96,14 +516,86 @@ impl ParsedMastodonPost {
         })
     }
 
+    /// Convert the HTML content of a Mastodon status to text, for matching it
+    /// against the post rules.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status to convert.
+    ///
+    /// ## Note
+    ///
+    /// Line breaks are kept for every platform, so the words on either side
+    /// of a line break aren't matched as one word.
+    pub fn content_to_text(status: &megalodon::entities::Status) -> Result<String> {
+        let html_document = dom_query::Document::fragment(status.content.as_str());
+
+        Self::convert_html_content_to_string(&html_document, true)
+    }
+
+    /// Apply the transform rules for a destination to the post content.
+    ///
+    /// ## Arguments
+    ///
+    /// * `transforms` - The transform rules, in the order they're applied.
+    /// * `destination` - The name of the destination the post is synced to.
+    ///
+    /// ## Note
+    ///
+    /// Tags and links removed from the content by the transforms are removed
+    /// from the found tags and links, so richtext facets are only generated
+    /// for the ones still in the content.
+    pub fn apply_transforms(
+        &mut self,
+        transforms: &[TransformRule],
+        destination: &str,
+    ) -> Result<Self> {
+        let applicable_transforms = transforms
+            .iter()
+            .filter(|transform| transform.applies_to(destination, &self.mastodon_status))
+            .collect::<Vec<&TransformRule>>();
+
+        if applicable_transforms.is_empty() {
+            return Ok(self.to_owned());
+        }
+
+        for transform in applicable_transforms {
+            tracing::debug!(
+                "Applying transform rule '{}' to post '{}'.",
+                transform.name,
+                self.mastodon_status.id
+            );
+
+            self.stripped_html = transform.operation.apply(
+                &self.stripped_html,
+                &self.mastodon_status,
+                &self.found_tags,
+            );
+        }
+
+        self.found_tags
+            .retain(|tag| self.stripped_html.contains(tag.as_str()));
+        self.found_links
+            .retain(|link| self.stripped_html.contains(link.as_str()));
+
+        Ok(self.to_owned())
+    }
+
     /// Truncate the post content to ensure it fits within the 300 character
     /// limit for BlueSky.
     ///
+    /// ## Arguments
+    ///
+    /// * `templates` - The templates for the ellipsis and read more strings.
+    ///
     /// ## Note
     ///
     /// If the current content is already less than or equal to 300 characters,
     /// this method will just return without modifying the content.
-    pub fn truncate_post_content(&mut self) -> Result<Self> {
+    pub fn truncate_post_content(
+        &mut self,
+        templates: &TemplateSet,
+    ) -> Result<Self> {
         // If the content is already less than or equal to 300 characters, we don't need
         // to truncate.
         if self.stripped_html.len() <= 300 {
@@ -112,15 +604,15 @@ impl ParsedMastodonPost {
 
         // Define the ellipsis and read more, with the URL to the post on Mastodon,
         // strings.
-        let ellipsis_string = "[...]";
-        let read_more_string = format!(
-            "\n\nRead more: {}",
-            self.mastodon_status.url.as_ref().unwrap()
-        );
+        let template_context = TemplateContext::from_mastodon_status(&self.mastodon_status);
+        let ellipsis_string = template_context.render(&templates.ellipsis);
+        let read_more_string = template_context.render(&templates.read_more);
 
         // Calculate the length of the truncated content after the ellipsis and read
         // more strings are added.
-        let cut_down_length = 300 - ellipsis_string.len() - read_more_string.len();
+        let cut_down_length = 300_usize
+            .saturating_sub(ellipsis_string.len())
+            .saturating_sub(read_more_string.len());
 
         // Truncate the content.
         let mut string_builder = Self::trim_post_string(&self.stripped_html, cut_down_length);
@@ -130,7 +622,7 @@ impl ParsedMastodonPost {
         let mut trimmed_tags = Vec::new();
 
         for tag in &self.found_tags {
-            if !string_builder.contains(&*tag) {
+            if !string_builder.contains(tag) {
                 trimmed_tags.push(tag.clone());
             }
         }
@@ -142,26 +634,30 @@ impl ParsedMastodonPost {
             trimmed_tag_string_builder.push_str(tag.as_str());
 
             if index < trimmed_tags.len() {
-                trimmed_tag_string_builder.push_str(" ");
+                trimmed_tag_string_builder.push(' ');
             }
         }
 
         // Calculate the final length of the content after the tags are added.
-        let final_cut_down_length = cut_down_length - trimmed_tag_string_builder.len();
+        let final_cut_down_length =
+            cut_down_length.saturating_sub(trimmed_tag_string_builder.len());
 
         // Truncate the content again to ensure the tags fit within the 300 character
         // limit.
         string_builder = Self::trim_post_string(&self.stripped_html, final_cut_down_length);
 
         // Add the ellipsis, read more, and tags to the content.
-        string_builder.push_str(ellipsis_string);
+        string_builder.push_str(ellipsis_string.as_str());
         string_builder.push_str(read_more_string.as_str());
         string_builder.push_str(trimmed_tag_string_builder.as_str());
 
         // Update the content with the truncated content and replace the found links
-        // with the link to the Mastodon post.
+        // with the link to the Mastodon post, if the read more template has it.
         self.stripped_html = string_builder;
-        self.found_links = vec![self.mastodon_status.url.as_ref().unwrap().to_string()];
+        self.found_links = match self.stripped_html.contains(&template_context.url) {
+            true => vec![template_context.url],
+            false => Vec::new(),
+        };
 
         Ok(self.to_owned())
     }
@@ -183,7 +679,7 @@ impl ParsedMastodonPost {
         let mut string_builder = String::new();
 
         for (index, character) in content.chars().enumerate() {
-            if index >= max_length - 1 {
+            if index + 1 >= max_length {
                 break;
             }
 
@@ -198,11 +694,45 @@ impl ParsedMastodonPost {
     /// ## Arguments
     ///
     /// * `document` - The HTML document to convert to a string.
-    fn convert_html_content_to_string(document: &dom_query::Document) -> Result<String> {
+    /// * `keep_line_breaks` - Whether to keep the line breaks in the content.
+    ///
+    /// ## Note
+    ///
+    /// Some platforms, like Pleroma and Friendica, don't wrap the content in
+    /// paragraphs, so if there are no paragraphs the whole document is
+    /// converted.
+    fn convert_html_content_to_string(
+        document: &dom_query::Document,
+        keep_line_breaks: bool,
+    ) -> Result<String> {
         let mut stripped_html = String::new();
 
-        for node in document.select("p").iter() {
-            stripped_html.push_str(format!("{}\n\n", node.text()).as_str());
+        // The line breaks are replaced in a copy of the document, so the links
+        // and tags are still found in the original.
+        let line_break_document;
+        let document = match keep_line_breaks {
+            true => {
+                line_break_document = document.clone();
+                line_break_document.select("br").replace_with_html("\n");
+
+                &line_break_document
+            }
+
+            false => document,
+        };
+
+        let paragraphs = document.select("p");
+
+        match paragraphs.is_empty() {
+            true => {
+                stripped_html.push_str(document.root().text().trim());
+            }
+
+            false => {
+                for node in paragraphs.iter() {
+                    stripped_html.push_str(format!("{}\n\n", node.text()).as_str());
+                }
+            }
         }
 
         stripped_html = stripped_html.trim_end().to_string();
@@ -218,17 +748,17 @@ impl ParsedMastodonPost {
     /// * `tags` - The tags to ignore when getting links.
     fn get_links(
         document: &dom_query::Document,
-        tags: &Vec<megalodon::entities::status::Tag>,
+        tags: &[megalodon::entities::status::Tag],
     ) -> Result<Vec<String>> {
         let mut links = Vec::new();
 
         for node in document.select("a").iter() {
-            let href = node.attr("href").unwrap().to_string();
+            let href = match node.attr("href") {
+                Some(href) => href.to_string(),
+                None => continue,
+            };
 
-            if tags
-                .iter()
-                .any(|tag| &tag.url.to_lowercase() == &href.to_lowercase())
-            {
+            if Self::is_tag_link(&node, &href, tags) {
                 tracing::info!("Ignoring tag link: {}", href);
                 continue;
             }
@@ -247,27 +777,62 @@ impl ParsedMastodonPost {
     /// * `tags` - The tags to get from the document.
     fn get_tags(
         document: &dom_query::Document,
-        tags: &Vec<megalodon::entities::status::Tag>,
+        tags: &[megalodon::entities::status::Tag],
     ) -> Result<Vec<String>> {
         let mut found_tags = Vec::new();
 
         for node in document.select("a").iter() {
-            let href = node.attr("href").unwrap().to_string();
-
-            // Check if the tag is in the list of tags to ignore.
-            // We have to compare the lowercase versions of the URLs because
-            // the Mastodon API returns the URLs in lowercase; whereas, the
-            // HTML content may have the URLs in mixed case. This ensures that
-            // the BlueSky post will be consistent with the Mastodon post's
-            // formatting.
-            if tags
-                .iter()
-                .any(|tag| &tag.url.to_lowercase() == &href.to_lowercase())
-            {
+            let href = match node.attr("href") {
+                Some(href) => href.to_string(),
+                None => continue,
+            };
+
+            if Self::is_tag_link(&node, &href, tags) {
                 found_tags.push(node.text().to_string());
             }
         }
 
         Ok(found_tags)
     }
+
+    /// Check if a link in the HTML content of a Mastodon post is a tag.
+    ///
+    /// ## Arguments
+    ///
+    /// * `node` - The link to check.
+    /// * `href` - The URL of the link.
+    /// * `tags` - The tags of the post.
+    ///
+    /// ## Note
+    ///
+    /// Platforms don't agree on the URLs for tags, so links marked as tags by
+    /// a `hashtag` class, a `tag` relation or a `data-tag` attribute are tags
+    /// too.
+    fn is_tag_link(
+        node: &dom_query::Selection,
+        href: &str,
+        tags: &[megalodon::entities::status::Tag],
+    ) -> bool {
+        // We have to compare the lowercase versions of the URLs because
+        // the Mastodon API returns the URLs in lowercase; whereas, the
+        // HTML content may have the URLs in mixed case. This ensures that
+        // the BlueSky post will be consistent with the Mastodon post's
+        // formatting.
+        if tags
+            .iter()
+            .any(|tag| tag.url.to_lowercase() == href.to_lowercase())
+        {
+            return true;
+        }
+
+        let has_hashtag_class = node
+            .attr("class")
+            .is_some_and(|class| class.split_whitespace().any(|class| class == "hashtag"));
+
+        let has_tag_rel = node
+            .attr("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|rel| rel == "tag"));
+
+        has_hashtag_class || has_tag_rel || node.has_attr("data-tag")
+    }
 }
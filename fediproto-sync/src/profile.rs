@@ -0,0 +1,661 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use atrium_api::{
+    agent::atp_agent::AtpAgent,
+    app, com,
+    types::{
+        TryFromUnknown, TryIntoUnknown,
+        string::{AtIdentifier, Cid, Did, Nsid, RecordKey},
+    },
+};
+use diesel::r2d2::{ConnectionManager, Pool};
+use fediproto_sync_db::{
+    AnyConnection,
+    models::{NewMastodonProfileSyncState, SyncedPostBlueSkyData},
+};
+use fediproto_sync_lib::{
+    config::FediProtoSyncConfig,
+    crypto::sha256_hex,
+    error::{AuthenticationSource, FediProtoSyncError},
+};
+use megalodon::entities::Account;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{
+    bsky_post_sync::BLUESKY_DESTINATION,
+    bsky_session::DbSessionStore,
+    core::SocialMediaClients,
+    img_utils::ImageAttachmentData,
+    mastodon::MastodonApiExtensions,
+    rate_limit::{RateLimitedClient, send_rate_limited},
+};
+
+/// The collection of the BlueSky profile record.
+pub const PROFILE_COLLECTION: &str = "app.bsky.actor.profile";
+
+/// The record key of the BlueSky profile record.
+pub const PROFILE_RECORD_KEY: &str = "self";
+
+/// The max length, in characters, of the display name on a BlueSky profile.
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+
+/// The max length, in characters, of the bio on a BlueSky profile.
+pub const MAX_DESCRIPTION_LENGTH: usize = 256;
+
+/// Regex for finding line breaks in HTML.
+static LINE_BREAK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<br\s*/?>").unwrap());
+
+/// A Mastodon profile converted for BlueSky.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertedProfile {
+    /// The display name.
+    pub display_name: String,
+
+    /// The bio, with the profile fields added to the end.
+    pub description: String,
+
+    /// The URL of the avatar, if one is set.
+    pub avatar_url: Option<String>,
+
+    /// The URL of the banner, if one is set.
+    pub banner_url: Option<String>,
+}
+
+impl ConvertedProfile {
+    /// Convert a Mastodon profile for BlueSky.
+    ///
+    /// ## Arguments
+    ///
+    /// * `account` - The Mastodon account to convert the profile of.
+    ///
+    /// ## Note
+    ///
+    /// Each profile field is added to the bio as a `name: value` line. The
+    /// display name and bio are truncated to fit BlueSky's limits.
+    pub fn from_mastodon_account(account: &Account) -> Self {
+        let mut description = html_to_profile_text(&account.note);
+
+        let field_lines = account
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.name, html_to_profile_text(&field.value)))
+            .collect::<Vec<String>>();
+
+        if !field_lines.is_empty() {
+            if !description.is_empty() {
+                description.push_str("\n\n");
+            }
+
+            description.push_str(&field_lines.join("\n"));
+        }
+
+        Self {
+            display_name: truncate_profile_text(
+                account.display_name.trim(),
+                MAX_DISPLAY_NAME_LENGTH,
+            ),
+            description: truncate_profile_text(&description, MAX_DESCRIPTION_LENGTH),
+            avatar_url: profile_image_url(&account.avatar),
+            banner_url: profile_image_url(&account.header),
+        }
+    }
+
+    /// Get the hash of the display name and bio.
+    pub fn text_hash(&self) -> String {
+        sha256_hex(format!("{}\0{}", self.display_name, self.description).as_bytes())
+    }
+
+    /// Get the hash of the avatar.
+    pub fn avatar_hash(&self) -> String {
+        sha256_hex(self.avatar_url.as_deref().unwrap_or_default().as_bytes())
+    }
+
+    /// Get the hash of the banner.
+    pub fn banner_hash(&self) -> String {
+        sha256_hex(self.banner_url.as_deref().unwrap_or_default().as_bytes())
+    }
+}
+
+/// Check if any part of the profile is synced.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+pub fn is_profile_sync_enabled(config: &FediProtoSyncConfig) -> bool {
+    config.sync_pinned_post || config.sync_profile
+}
+
+/// Sync the Mastodon profile to the BlueSky profile.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to sync the profile of.
+///
+/// ## Note
+///
+/// The BlueSky profile record is only written if something changed.
+pub async fn sync_profile(
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+) -> Result<()> {
+    let (profile_cid, mut profile) = get_profile_record(
+        &social_media_clients.bluesky_session.atp_agent,
+        &social_media_clients.bluesky_session.did,
+    )
+    .await?;
+
+    let mut is_profile_changed = false;
+    let mut new_profile_sync_state = None;
+
+    if config.sync_profile {
+        new_profile_sync_state = update_profile_details(
+            &mut profile,
+            config,
+            db_connection_pool,
+            social_media_clients.clone(),
+        )
+        .await?;
+
+        is_profile_changed |= new_profile_sync_state.is_some();
+    }
+
+    if config.sync_pinned_post {
+        is_profile_changed |= update_pinned_post(
+            &mut profile,
+            config,
+            db_connection_pool,
+            social_media_clients.clone(),
+            mastodon_account,
+        )
+        .await?;
+    }
+
+    if !is_profile_changed {
+        tracing::info!("BlueSky profile is up to date.");
+
+        return Ok(());
+    }
+
+    put_profile_record(
+        &social_media_clients.bluesky_session.atp_agent,
+        &social_media_clients.bluesky_session.did,
+        profile,
+        profile_cid,
+    )
+    .await?;
+
+    if let Some(new_profile_sync_state) = new_profile_sync_state {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::upsert_mastodon_profile_sync_state(
+            db_connection,
+            &new_profile_sync_state,
+        )?;
+    }
+
+    tracing::info!("Updated BlueSky profile.");
+
+    Ok(())
+}
+
+/// Update the display name, bio, avatar, and banner on the BlueSky profile
+/// from Mastodon.
+///
+/// ## Arguments
+///
+/// * `profile` - The BlueSky profile record to update.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+///
+/// ## Note
+///
+/// The Mastodon account is fetched again so changes made since the daemon
+/// started are picked up. Only the parts whose hash differs from the last sync
+/// are updated. Returns the new sync state to save once the profile is
+/// written, or `None` if nothing changed.
+async fn update_profile_details(
+    profile: &mut app::bsky::actor::profile::Record,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+) -> Result<Option<NewMastodonProfileSyncState>> {
+    let mastodon_account = social_media_clients
+        .mastodon_rate_limiter
+        .send(
+            social_media_clients
+                .mastodon_client
+                .verify_account_credentials(),
+        )
+        .await
+        .map_err(|_| FediProtoSyncError::AuthenticationError(AuthenticationSource::Mastodon))?
+        .json;
+
+    let converted_profile = ConvertedProfile::from_mastodon_account(&mastodon_account);
+    let text_hash = converted_profile.text_hash();
+    let avatar_hash = converted_profile.avatar_hash();
+    let banner_hash = converted_profile.banner_hash();
+
+    let profile_sync_state = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_mastodon_profile_sync_state(
+            db_connection,
+            &config.account_pair_id,
+        )?
+        .filter(|profile_sync_state| profile_sync_state.account_id == mastodon_account.id)
+    };
+
+    let is_text_changed = profile_sync_state
+        .as_ref()
+        .is_none_or(|profile_sync_state| profile_sync_state.text_hash != text_hash);
+    let is_avatar_changed = profile_sync_state
+        .as_ref()
+        .is_none_or(|profile_sync_state| profile_sync_state.avatar_hash != avatar_hash);
+    let is_banner_changed = profile_sync_state
+        .as_ref()
+        .is_none_or(|profile_sync_state| profile_sync_state.banner_hash != banner_hash);
+
+    if !is_text_changed && !is_avatar_changed && !is_banner_changed {
+        return Ok(None);
+    }
+
+    if is_text_changed {
+        tracing::info!("Updating the display name and bio on the BlueSky profile.");
+
+        profile.display_name = Some(converted_profile.display_name.clone())
+            .filter(|display_name| !display_name.is_empty());
+        profile.description = Some(converted_profile.description.clone())
+            .filter(|description| !description.is_empty());
+    }
+
+    if is_avatar_changed {
+        tracing::info!("Updating the avatar on the BlueSky profile.");
+
+        profile.avatar = match &converted_profile.avatar_url {
+            Some(avatar_url) => Some(
+                upload_profile_image(
+                    avatar_url,
+                    config,
+                    &social_media_clients.bluesky_session.atp_agent,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+    }
+
+    if is_banner_changed {
+        tracing::info!("Updating the banner on the BlueSky profile.");
+
+        profile.banner = match &converted_profile.banner_url {
+            Some(banner_url) => Some(
+                upload_profile_image(
+                    banner_url,
+                    config,
+                    &social_media_clients.bluesky_session.atp_agent,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+    }
+
+    Ok(Some(NewMastodonProfileSyncState::new(
+        &config.account_pair_id,
+        &mastodon_account.id,
+        &text_hash,
+        &avatar_hash,
+        &banner_hash,
+    )))
+}
+
+/// Download a Mastodon profile image and upload it to BlueSky.
+///
+/// ## Arguments
+///
+/// * `image_url` - The URL of the image.
+/// * `config` - The config for the app.
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+async fn upload_profile_image(
+    image_url: &str,
+    config: &FediProtoSyncConfig,
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+) -> Result<atrium_api::types::BlobRef> {
+    let http_client = crate::core::create_http_client(config)?;
+
+    let image_data = send_rate_limited(
+        AuthenticationSource::Mastodon,
+        http_client.get(image_url),
+        config.rate_limit_max_wait,
+    )
+    .await?
+    .bytes()
+    .await?;
+
+    let profile_image = ImageAttachmentData::new(image_data, image_url)?;
+
+    tracing::info!("Uploading '{}' bytes", profile_image.image_bytes.len());
+    let blob_item = atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .upload_blob(profile_image.image_bytes.into())
+        .await?
+        .blob
+        .clone();
+
+    Ok(blob_item)
+}
+
+/// Set the pinned post on the BlueSky profile to the copy of the first pinned
+/// post on Mastodon.
+///
+/// ## Arguments
+///
+/// * `profile` - The BlueSky profile record to update.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account to get the pinned post of.
+///
+/// ## Note
+///
+/// Returns `true` if the pinned post changed. The pin is cleared if nothing is
+/// pinned on Mastodon or the pinned post was never synced.
+async fn update_pinned_post(
+    profile: &mut app::bsky::actor::profile::Record,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+) -> Result<bool> {
+    let pinned_posts = social_media_clients
+        .mastodon_client
+        .get_pinned_posts(
+            &social_media_clients.mastodon_rate_limiter,
+            &mastodon_account.id,
+        )
+        .await?;
+
+    let synced_pinned_post = match pinned_posts.first() {
+        Some(pinned_post) => {
+            let db_connection = &mut db_connection_pool.get()?;
+
+            find_synced_pinned_post(db_connection, &config.account_pair_id, &pinned_post.id)?
+        }
+
+        None => None,
+    };
+
+    let current_pinned_post_uri = profile
+        .pinned_post
+        .as_ref()
+        .map(|pinned_post| pinned_post.uri.clone());
+    let synced_pinned_post_uri = synced_pinned_post
+        .as_ref()
+        .map(|synced_pinned_post| synced_pinned_post.bsky_post_uri.clone());
+
+    if current_pinned_post_uri == synced_pinned_post_uri {
+        return Ok(false);
+    }
+
+    profile.pinned_post = match synced_pinned_post {
+        Some(synced_pinned_post) => {
+            tracing::info!(
+                "Pinning '{}' to the BlueSky profile.",
+                synced_pinned_post.bsky_post_uri
+            );
+
+            Some(
+                com::atproto::repo::strong_ref::MainData {
+                    cid: Cid::from_str(&synced_pinned_post.bsky_post_cid)?,
+                    uri: synced_pinned_post.bsky_post_uri.clone(),
+                }
+                .into(),
+            )
+        }
+
+        None => {
+            tracing::info!("Removing the pinned post from the BlueSky profile.");
+
+            None
+        }
+    };
+
+    Ok(true)
+}
+
+/// Find the BlueSky copy of the post pinned on Mastodon.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection to use.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `pinned_post_id` - The ID of the post pinned on Mastodon.
+///
+/// ## Note
+///
+/// Returns `None` if the post was never synced. Database errors are returned,
+/// so a failed lookup doesn't clear the pinned post.
+pub fn find_synced_pinned_post(
+    db_connection: &mut AnyConnection,
+    account_pair_id: &str,
+    pinned_post_id: &str,
+) -> Result<Option<SyncedPostBlueSkyData>> {
+    let synced_pinned_post = fediproto_sync_db::operations::find_bluesky_data_by_mastodon_post_id(
+        db_connection,
+        account_pair_id,
+        BLUESKY_DESTINATION,
+        pinned_post_id,
+    )?;
+
+    if synced_pinned_post.is_none() {
+        tracing::info!(
+            "Pinned post '{}' was never synced to BlueSky.",
+            pinned_post_id
+        );
+    }
+
+    Ok(synced_pinned_post)
+}
+
+/// Get the profile record of a BlueSky account.
+///
+/// ## Arguments
+///
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `did` - The DID of the BlueSky account.
+///
+/// ## Note
+///
+/// Returns the CID of the record, if it exists, and the record. An empty
+/// record is returned if the account doesn't have one yet.
+pub async fn get_profile_record(
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    did: &Did,
+) -> Result<(Option<Cid>, app::bsky::actor::profile::Record)> {
+    let get_record_result = atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .get_record(
+            com::atproto::repo::get_record::ParametersData {
+                cid: None,
+                collection: profile_collection()?,
+                repo: AtIdentifier::Did(did.clone()),
+                rkey: profile_record_key()?,
+            }
+            .into(),
+        )
+        .await;
+
+    match get_record_result {
+        Ok(profile_record) => {
+            let profile =
+                app::bsky::actor::profile::Record::try_from_unknown(profile_record.value.clone())?;
+
+            Ok((profile_record.cid.clone(), profile))
+        }
+
+        Err(atrium_api::xrpc::Error::XrpcResponse(atrium_api::xrpc::error::XrpcError {
+            error:
+                Some(atrium_api::xrpc::error::XrpcErrorKind::Custom(
+                    com::atproto::repo::get_record::Error::RecordNotFound(_),
+                )),
+            ..
+        })) => {
+            tracing::info!("No BlueSky profile record found, creating a new one.");
+
+            Ok((
+                None,
+                app::bsky::actor::profile::RecordData {
+                    avatar: None,
+                    banner: None,
+                    created_at: None,
+                    description: None,
+                    display_name: None,
+                    joined_via_starter_pack: None,
+                    labels: None,
+                    pinned_post: None,
+                    pronouns: None,
+                    website: None,
+                }
+                .into(),
+            ))
+        }
+
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write the profile record of a BlueSky account.
+///
+/// ## Arguments
+///
+/// * `atp_client` - The client/agent for interacting with the AT Protocol.
+/// * `did` - The DID of the BlueSky account.
+/// * `profile` - The profile record to write.
+/// * `swap_cid` - The CID of the record being replaced, if any. The write
+///   fails if the record was changed since it was read.
+pub async fn put_profile_record(
+    atp_client: &AtpAgent<DbSessionStore, RateLimitedClient>,
+    did: &Did,
+    profile: app::bsky::actor::profile::Record,
+    swap_cid: Option<Cid>,
+) -> Result<()> {
+    atp_client
+        .api
+        .com
+        .atproto
+        .repo
+        .put_record(
+            com::atproto::repo::put_record::InputData {
+                collection: profile_collection()?,
+                record: profile.try_into_unknown()?,
+                repo: AtIdentifier::Did(did.clone()),
+                rkey: profile_record_key()?,
+                swap_commit: None,
+                swap_record: swap_cid,
+                validate: Some(true),
+            }
+            .into(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Get the NSID of the BlueSky profile record collection.
+fn profile_collection() -> Result<Nsid> {
+    Nsid::new(PROFILE_COLLECTION.to_string()).map_err(|_| {
+        anyhow::anyhow!(
+            "Error creating NSID for collection '{}'",
+            PROFILE_COLLECTION
+        )
+    })
+}
+
+/// Get the record key of the BlueSky profile record.
+fn profile_record_key() -> Result<RecordKey> {
+    RecordKey::new(PROFILE_RECORD_KEY.to_string())
+        .map_err(|_| anyhow::anyhow!("Error creating record key '{}'", PROFILE_RECORD_KEY))
+}
+
+/// Convert the HTML of a Mastodon bio or profile field to text.
+///
+/// ## Arguments
+///
+/// * `html` - The HTML to convert.
+///
+/// ## Note
+///
+/// Paragraphs are separated by a blank line and line breaks are kept.
+pub fn html_to_profile_text(html: &str) -> String {
+    let html = LINE_BREAK_REGEX.replace_all(html, "\n");
+    let document = dom_query::Document::fragment(html.as_ref());
+
+    let paragraphs = document.select("p");
+
+    match paragraphs.exists() {
+        true => paragraphs
+            .iter()
+            .map(|paragraph| paragraph.text().trim().to_string())
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        false => document.root().text().trim().to_string(),
+    }
+}
+
+/// Truncate profile text to a max length, ending it with an ellipsis if it's
+/// cut off.
+///
+/// ## Arguments
+///
+/// * `text` - The text to truncate.
+/// * `max_length` - The max length of the text, in characters.
+pub fn truncate_profile_text(
+    text: &str,
+    max_length: usize,
+) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+
+    let mut truncated_text = text
+        .chars()
+        .take(max_length.saturating_sub(1))
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+
+    truncated_text.push('…');
+
+    truncated_text
+}
+
+/// Get the URL of a Mastodon profile image, if one is set.
+///
+/// ## Arguments
+///
+/// * `image_url` - The URL of the avatar or header.
+///
+/// ## Note
+///
+/// Mastodon returns a `missing.png` placeholder for profiles without an avatar
+/// or header.
+fn profile_image_url(image_url: &str) -> Option<String> {
+    match image_url.is_empty() || image_url.ends_with("/missing.png") {
+        true => None,
+        false => Some(image_url.to_string()),
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use atrium_api::xrpc::{
+    HttpClient, XrpcClient,
+    http::{Request, Response},
+};
+use chrono::{DateTime, Utc};
+use fediproto_sync_lib::error::{AuthenticationSource, FediProtoSyncError};
+use once_cell::sync::Lazy;
+use reqwest::header::HeaderMap;
+
+use crate::sync_error::from_http_error;
+
+/// The rate limit governor shared by every client in the process.
+static RATE_LIMIT_GOVERNOR: Lazy<Arc<RateLimitGovernor>> =
+    Lazy::new(|| Arc::new(RateLimitGovernor::default()));
+
+/// How long to wait after a `429` response that doesn't say when to retry.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Reset values above this are UNIX timestamps instead of seconds to wait.
+const MIN_RESET_TIMESTAMP: i64 = 1_000_000_000;
+
+/// The metric for the number of requests allowed in a host's window.
+pub const RATE_LIMIT_LIMIT_METRIC: &str = "fediproto_sync_rate_limit_limit";
+
+/// The metric for the number of requests left in a host's window.
+pub const RATE_LIMIT_REMAINING_METRIC: &str = "fediproto_sync_rate_limit_remaining";
+
+/// The metric for the UNIX timestamp a host's window resets at.
+pub const RATE_LIMIT_RESET_METRIC: &str = "fediproto_sync_rate_limit_reset_timestamp_seconds";
+
+/// The rate limit budget of a host, from the headers of its last response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitBudget {
+    /// The number of requests allowed in the current window.
+    pub limit: Option<u64>,
+
+    /// The number of requests left in the current window.
+    pub remaining: Option<u64>,
+
+    /// The time the current window resets.
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitBudget {
+    /// Read the rate limit budget from the headers of a response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The HTTP status of the response.
+    /// * `headers` - The headers of the response.
+    /// * `now` - The current time.
+    ///
+    /// ## Note
+    ///
+    /// Reads the `RateLimit-*` headers sent by BlueSky PDSes, the
+    /// `X-RateLimit-*` headers sent by Mastodon and `Retry-After`. Returns
+    /// `None` if the response doesn't have any of them and isn't a `429`.
+    pub fn from_headers(
+        status: u16,
+        headers: &HeaderMap,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let header_value = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.get(*name))
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+
+        let limit = header_value(&["ratelimit-limit", "x-ratelimit-limit"])
+            .and_then(|value| value.parse::<u64>().ok());
+        let remaining = header_value(&["ratelimit-remaining", "x-ratelimit-remaining"])
+            .and_then(|value| value.parse::<u64>().ok());
+        let reset_at = header_value(&["ratelimit-reset", "x-ratelimit-reset"])
+            .and_then(|value| parse_reset(&value, now));
+        let retry_at =
+            header_value(&["retry-after"]).and_then(|value| parse_retry_after(&value, now));
+
+        if status != 429
+            && limit.is_none()
+            && remaining.is_none()
+            && reset_at.is_none()
+            && retry_at.is_none()
+        {
+            return None;
+        }
+
+        // A `429` means the budget is spent, whatever the headers say.
+        let (remaining, reset_at) = match status {
+            429 => (
+                Some(0),
+                retry_at
+                    .or(reset_at)
+                    .or_else(|| Some(now + DEFAULT_RETRY_AFTER)),
+            ),
+            _ => (remaining, retry_at.or(reset_at)),
+        };
+
+        Some(Self {
+            limit,
+            remaining,
+            reset_at,
+        })
+    }
+
+    /// Get how long to wait before the next request, if the budget is spent.
+    ///
+    /// ## Arguments
+    ///
+    /// * `now` - The current time.
+    pub fn delay(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        if self.remaining != Some(0) {
+            return None;
+        }
+
+        let reset_at = self.reset_at?;
+
+        (reset_at - now)
+            .to_std()
+            .ok()
+            .filter(|delay| !delay.is_zero())
+    }
+}
+
+/// Parse the time a rate limit window resets.
+///
+/// ## Arguments
+///
+/// * `value` - The value of the header, as a UNIX timestamp, the number of
+///   seconds to wait or an RFC 3339 date.
+/// * `now` - The current time.
+fn parse_reset(
+    value: &str,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match value.parse::<i64>() {
+        Ok(timestamp) if timestamp >= MIN_RESET_TIMESTAMP => DateTime::from_timestamp(timestamp, 0),
+        Ok(seconds) => Some(now + chrono::Duration::seconds(seconds.max(0))),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|reset_at| reset_at.with_timezone(&Utc)),
+    }
+}
+
+/// Parse the time a `Retry-After` header says to retry at.
+///
+/// ## Arguments
+///
+/// * `value` - The value of the header, as the number of seconds to wait or
+///   an HTTP date.
+/// * `now` - The current time.
+fn parse_retry_after(
+    value: &str,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match value.parse::<i64>() {
+        Ok(seconds) => Some(now + chrono::Duration::seconds(seconds.max(0))),
+        Err(_) => DateTime::parse_from_rfc2822(value)
+            .ok()
+            .map(|retry_at| retry_at.with_timezone(&Utc)),
+    }
+}
+
+/// Get the key a host's budget is tracked under.
+///
+/// ## Arguments
+///
+/// * `host` - The host requests are sent to.
+/// * `port` - The port requests are sent to, if it isn't the default port.
+pub fn host_key(
+    host: &str,
+    port: Option<u16>,
+) -> String {
+    match port {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// Tracks the rate limit budget of every host requests are sent to.
+#[derive(Debug, Default)]
+pub struct RateLimitGovernor {
+    /// The budget of each host.
+    budgets: Mutex<HashMap<String, RateLimitBudget>>,
+}
+
+impl RateLimitGovernor {
+    /// Get the rate limit governor shared by every client in the process.
+    pub fn global() -> Arc<Self> {
+        RATE_LIMIT_GOVERNOR.clone()
+    }
+
+    /// Update the budget of a host from a response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `host` - The key of the host the response is from, from `host_key`.
+    /// * `status` - The HTTP status of the response.
+    /// * `headers` - The headers of the response.
+    pub fn update(
+        &self,
+        host: &str,
+        status: u16,
+        headers: &HeaderMap,
+    ) {
+        let Some(budget) = RateLimitBudget::from_headers(status, headers, Utc::now()) else {
+            return;
+        };
+
+        tracing::debug!(
+            host = host,
+            limit = budget.limit,
+            remaining = budget.remaining,
+            reset_at = ?budget.reset_at,
+            "Updated rate limit budget."
+        );
+
+        record_budget_metrics(host, &budget);
+
+        if budget.remaining == Some(0) {
+            tracing::warn!(
+                "Rate limit for '{}' is exhausted until '{}'.",
+                host,
+                budget
+                    .reset_at
+                    .map(|reset_at| reset_at.to_rfc3339())
+                    .unwrap_or_else(|| "N/A".to_string())
+            );
+        }
+
+        self.budgets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(host.to_string(), budget);
+    }
+
+    /// Get the budget of a host.
+    ///
+    /// ## Arguments
+    ///
+    /// * `host` - The key of the host to get the budget of, from `host_key`.
+    pub fn budget(
+        &self,
+        host: &str,
+    ) -> Option<RateLimitBudget> {
+        self.budgets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(host)
+            .copied()
+    }
+
+    /// Get the budgets of every host, sorted by host.
+    pub fn budgets(&self) -> Vec<(String, RateLimitBudget)> {
+        let mut budgets = self
+            .budgets
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(host, budget)| (host.clone(), *budget))
+            .collect::<Vec<(String, RateLimitBudget)>>();
+
+        budgets.sort_by(|a, b| a.0.cmp(&b.0));
+
+        budgets
+    }
+
+    /// Wait for the budget of a host, if it's spent.
+    ///
+    /// ## Arguments
+    ///
+    /// * `host` - The key of the host to send a request to, from `host_key`.
+    /// * `max_wait` - The longest time to wait for the budget to reset.
+    ///
+    /// ## Note
+    ///
+    /// If the budget resets later than `max_wait`, the request is deferred by
+    /// returning an error, so the post is retried later instead of blocking
+    /// the sync.
+    pub async fn wait_for_budget(
+        &self,
+        host: &str,
+        max_wait: Duration,
+    ) -> Result<(), FediProtoSyncError> {
+        let delay = self
+            .budget(host)
+            .and_then(|budget| budget.delay(Utc::now()));
+
+        match delay {
+            None => Ok(()),
+
+            Some(delay) if delay <= max_wait => {
+                tracing::warn!(
+                    "Rate limit for '{}' is exhausted, waiting '{}' seconds.",
+                    host,
+                    delay.as_secs()
+                );
+
+                tokio::time::sleep(delay).await;
+
+                Ok(())
+            }
+
+            Some(delay) => Err(FediProtoSyncError::RateLimitError {
+                host: host.to_string(),
+                retry_after_seconds: delay.as_secs(),
+            }),
+        }
+    }
+}
+
+/// Record the rate limit budget of a host as metrics.
+///
+/// ## Arguments
+///
+/// * `host` - The key of the host the budget is for, from `host_key`.
+/// * `budget` - The budget of the host.
+///
+/// ## Note
+///
+/// The parts of the budget the host didn't send are left as they were.
+pub fn record_budget_metrics(
+    host: &str,
+    budget: &RateLimitBudget,
+) {
+    if let Some(limit) = budget.limit {
+        metrics::gauge!(RATE_LIMIT_LIMIT_METRIC, "host" => host.to_string()).set(limit as f64);
+    }
+
+    if let Some(remaining) = budget.remaining {
+        metrics::gauge!(RATE_LIMIT_REMAINING_METRIC, "host" => host.to_string())
+            .set(remaining as f64);
+    }
+
+    if let Some(reset_at) = budget.reset_at {
+        metrics::gauge!(RATE_LIMIT_RESET_METRIC, "host" => host.to_string())
+            .set(reset_at.timestamp() as f64);
+    }
+}
+
+/// Serve the metrics for Prometheus to scrape.
+///
+/// ## Arguments
+///
+/// * `metrics_address` - The address to serve the metrics on, like
+///   `0.0.0.0:9100`.
+pub fn serve_metrics(metrics_address: &str) -> anyhow::Result<()> {
+    let metrics_address = metrics_address
+        .parse::<std::net::SocketAddr>()
+        .with_context(|| format!("Invalid metrics address '{}'.", metrics_address))?;
+
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(metrics_address)
+        .install()
+        .context("Failed to serve the metrics.")?;
+
+    tracing::info!("Serving metrics on 'http://{}/metrics'.", metrics_address);
+
+    Ok(())
+}
+
+/// Log the rate limit budget of every host requests were sent to.
+pub fn log_rate_limit_budgets() {
+    for (host, budget) in RateLimitGovernor::global().budgets() {
+        tracing::info!(
+            host = host.as_str(),
+            limit = budget.limit,
+            remaining = budget.remaining,
+            reset_at = ?budget.reset_at,
+            "Rate limit budget for '{}': '{}' of '{}' requests left.",
+            host,
+            budget
+                .remaining
+                .map(|remaining| remaining.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            budget
+                .limit
+                .map(|limit| limit.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        );
+    }
+}
+
+/// Send a request, waiting for the rate limit budget of its host first.
+///
+/// ## Arguments
+///
+/// * `service` - The service the request is sent to.
+/// * `request` - The request to send.
+/// * `max_wait` - The longest time to wait for the budget to reset.
+///
+/// ## Note
+///
+/// Responses with an error status are returned as errors.
+pub async fn send_rate_limited(
+    service: AuthenticationSource,
+    request: reqwest::RequestBuilder,
+    max_wait: Duration,
+) -> Result<reqwest::Response, FediProtoSyncError> {
+    let (client, request) = request.build_split();
+    let request = request.map_err(|e| from_http_error(service.clone(), e))?;
+    let host = host_key(
+        request.url().host_str().unwrap_or_default(),
+        request.url().port(),
+    );
+
+    let governor = RateLimitGovernor::global();
+    governor.wait_for_budget(&host, max_wait).await?;
+
+    let response = client
+        .execute(request)
+        .await
+        .map_err(|e| from_http_error(service.clone(), e))?;
+
+    governor.update(&host, response.status().as_u16(), response.headers());
+
+    response
+        .error_for_status()
+        .map_err(|e| from_http_error(service, e))
+}
+
+/// Waits for the rate limit budget of a Mastodon server before the requests
+/// sent with the Mastodon client.
+#[derive(Debug, Clone)]
+pub struct MastodonRateLimiter {
+    /// The key of the Mastodon server, from `host_key`.
+    host: String,
+
+    /// The longest time to wait for the budget to reset.
+    max_wait: Duration,
+}
+
+impl MastodonRateLimiter {
+    /// Create a new instance of the `MastodonRateLimiter` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `mastodon_server` - The URL of the Mastodon server.
+    /// * `max_wait` - The longest time to wait for the budget to reset.
+    pub fn new(
+        mastodon_server: &str,
+        max_wait: Duration,
+    ) -> Self {
+        let host = match reqwest::Url::parse(mastodon_server) {
+            Ok(url) => host_key(url.host_str().unwrap_or_default(), url.port()),
+            Err(_) => mastodon_server.to_string(),
+        };
+
+        Self { host, max_wait }
+    }
+
+    /// Send a request with the Mastodon client, waiting for the budget of the
+    /// server first and updating it from the response.
+    ///
+    /// ## Arguments
+    ///
+    /// * `request` - The request to send.
+    ///
+    /// ## Note
+    ///
+    /// If the budget resets later than `max_wait`, the request isn't sent and
+    /// a `429` error is returned instead, so the post is retried later.
+    pub async fn send<T>(
+        &self,
+        request: impl Future<Output = Result<megalodon::response::Response<T>, megalodon::error::Error>>,
+    ) -> Result<megalodon::response::Response<T>, megalodon::error::Error> {
+        let governor = RateLimitGovernor::global();
+
+        if let Err(e) = governor.wait_for_budget(&self.host, self.max_wait).await {
+            return Err(megalodon::error::Error::new_own(
+                e.to_string(),
+                megalodon::error::Kind::HTTPStatusError,
+                None,
+                Some(429),
+                None,
+            ));
+        }
+
+        let response = request.await;
+
+        match &response {
+            Ok(response) => governor.update(&self.host, response.status, &response.header),
+
+            Err(megalodon::error::Error::OwnError(own_error)) => {
+                if let (Some(status), Some(header)) = (own_error.status, &own_error.header) {
+                    governor.update(&self.host, status, header);
+                }
+            }
+
+            Err(_) => {}
+        }
+
+        response
+    }
+}
+
+/// An XRPC client that waits for the rate limit budget of the host before
+/// every request.
+#[derive(Clone)]
+pub struct RateLimitedClient {
+    /// The base URI of the XRPC server.
+    base_uri: String,
+
+    /// The HTTP client to send the requests with.
+    http_client: reqwest::Client,
+
+    /// The longest time to wait for the budget to reset.
+    max_wait: Duration,
+}
+
+impl RateLimitedClient {
+    /// Create a new instance of the `RateLimitedClient` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `base_uri` - The base URI of the XRPC server.
+    /// * `http_client` - The HTTP client to send the requests with.
+    /// * `max_wait` - The longest time to wait for the budget to reset.
+    pub fn new(
+        base_uri: &str,
+        http_client: reqwest::Client,
+        max_wait: Duration,
+    ) -> Self {
+        Self {
+            base_uri: base_uri.to_string(),
+            http_client,
+            max_wait,
+        }
+    }
+}
+
+impl HttpClient for RateLimitedClient {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let host = host_key(
+            request.uri().host().unwrap_or_default(),
+            request.uri().port_u16(),
+        );
+
+        let governor = RateLimitGovernor::global();
+        governor.wait_for_budget(&host, self.max_wait).await?;
+
+        let response = self.http_client.execute(request.try_into()?).await?;
+
+        governor.update(&host, response.status().as_u16(), response.headers());
+
+        let mut response_builder = Response::builder().status(response.status());
+        for (name, value) in response.headers() {
+            response_builder = response_builder.header(name, value);
+        }
+
+        Ok(response_builder.body(response.bytes().await?.to_vec())?)
+    }
+}
+
+impl XrpcClient for RateLimitedClient {
+    fn base_uri(&self) -> String {
+        self.base_uri.clone()
+    }
+}
@@ -0,0 +1,200 @@
+use fediproto_sync_lib::config::{FediProtoSyncConfig, ReplyPolicy};
+
+/// The domain Bridgy Fed uses for the BlueSky handles of bridged fediverse
+/// accounts.
+pub const BRIDGY_FED_HANDLE_DOMAIN: &str = "ap.brid.gy";
+
+/// Filter for deciding which replies to other accounts are synced.
+#[derive(Debug, Clone)]
+pub struct ReplyFilter {
+    /// How replies to other accounts are synced.
+    pub policy: ReplyPolicy,
+
+    /// The hashtag that makes a reply eligible, without the leading `#`, if
+    /// any.
+    pub hashtag: Option<String>,
+}
+
+impl ReplyFilter {
+    /// Create a new reply filter from the app's config.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config for the app.
+    pub fn new(config: &FediProtoSyncConfig) -> Self {
+        let hashtag = config
+            .reply_hashtag
+            .as_deref()
+            .map(|hashtag| hashtag.trim().trim_start_matches('#').to_string())
+            .filter(|hashtag| !hashtag.is_empty());
+
+        Self {
+            policy: config.reply_policy.clone(),
+            hashtag,
+        }
+    }
+
+    /// Check if a status is allowed to sync by the reply policy.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status.
+    /// * `account_id` - The ID of the Mastodon account being synced.
+    ///
+    /// ## Note
+    ///
+    /// Statuses that aren't replies to other accounts are always allowed.
+    /// Replies to other accounts are only allowed if the policy isn't `skip`
+    /// and they mention no one or have the reply hashtag.
+    pub fn allows(
+        &self,
+        status: &megalodon::entities::Status,
+        account_id: &str,
+    ) -> bool {
+        if !is_reply_to_other_account(status, account_id) {
+            return true;
+        }
+
+        if self.policy == ReplyPolicy::Skip {
+            return false;
+        }
+
+        status.mentions.is_empty() || self.has_hashtag(status)
+    }
+
+    /// Check if a status has the reply hashtag.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status.
+    fn has_hashtag(
+        &self,
+        status: &megalodon::entities::Status,
+    ) -> bool {
+        let hashtag = match &self.hashtag {
+            Some(hashtag) => hashtag,
+            None => return false,
+        };
+
+        status
+            .tags
+            .iter()
+            .any(|tag| tag.name.eq_ignore_ascii_case(hashtag))
+    }
+}
+
+/// Check if a status is a reply to an account other than the one being
+/// synced.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon status.
+/// * `account_id` - The ID of the Mastodon account being synced.
+pub fn is_reply_to_other_account(
+    status: &megalodon::entities::Status,
+    account_id: &str,
+) -> bool {
+    if status.in_reply_to_id.is_none() {
+        return false;
+    }
+
+    status.in_reply_to_account_id.as_deref() != Some(account_id)
+}
+
+/// Get the Bridgy Fed handle on BlueSky for a fediverse account.
+///
+/// ## Arguments
+///
+/// * `acct` - The account, like `someone@mastodon.social`. Local accounts
+///   without a domain use `instance_host`.
+/// * `instance_host` - The hostname of the Mastodon server the posts are from.
+///
+/// ## Note
+///
+/// Bridgy Fed handles look like `someone.mastodon.social.ap.brid.gy`, with
+/// underscores replaced by hyphens since they aren't allowed in handles.
+pub fn bridged_handle(
+    acct: &str,
+    instance_host: &str,
+) -> String {
+    let (username, domain) = match acct.split_once('@') {
+        Some((username, domain)) => (username, domain),
+        None => (acct, instance_host),
+    };
+
+    format!(
+        "{}.{}.{}",
+        username.replace('_', "-"),
+        domain,
+        BRIDGY_FED_HANDLE_DOMAIN
+    )
+    .to_lowercase()
+}
+
+/// Check if a bridged BlueSky post is a copy of a Mastodon status.
+///
+/// ## Arguments
+///
+/// * `record` - The record of the BlueSky post, as JSON.
+/// * `status` - The Mastodon status.
+///
+/// ## Note
+///
+/// Bridgy Fed stores the URL of the original post in the `bridgyOriginalUrl`
+/// field of the posts it bridges.
+pub fn is_bridged_copy_of(
+    record: &serde_json::Value,
+    status: &megalodon::entities::Status,
+) -> bool {
+    let original_url = match record
+        .get("bridgyOriginalUrl")
+        .and_then(|value| value.as_str())
+    {
+        Some(original_url) => original_url,
+        None => return false,
+    };
+
+    original_url == status.uri || status.url.as_deref() == Some(original_url)
+}
+
+/// The ancestors of a reply that are needed to repair its thread.
+#[derive(Debug, Clone)]
+pub struct ThreadRepairPlan {
+    /// The ID of the nearest ancestor that was synced, if any.
+    pub nearest_synced_ancestor_id: Option<String>,
+
+    /// The ancestors after the nearest synced ancestor that weren't synced,
+    /// oldest first.
+    pub missing_ancestors: Vec<megalodon::entities::Status>,
+}
+
+impl ThreadRepairPlan {
+    /// Create a new thread repair plan from the ancestors of a reply.
+    ///
+    /// ## Arguments
+    ///
+    /// * `ancestors` - The ancestors of the reply, oldest first, as returned
+    ///   by Mastodon's status context.
+    /// * `is_synced` - Checks if a Mastodon post ID was synced.
+    pub fn new(
+        ancestors: &[megalodon::entities::Status],
+        mut is_synced: impl FnMut(&str) -> bool,
+    ) -> Self {
+        let nearest_synced_index = ancestors
+            .iter()
+            .rposition(|ancestor| is_synced(&ancestor.id));
+
+        let (nearest_synced_ancestor_id, missing_ancestors) = match nearest_synced_index {
+            Some(index) => (
+                Some(ancestors[index].id.clone()),
+                ancestors[index + 1..].to_vec(),
+            ),
+            None => (None, ancestors.to_vec()),
+        };
+
+        Self {
+            nearest_synced_ancestor_id,
+            missing_ancestors,
+        }
+    }
+}
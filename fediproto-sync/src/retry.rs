@@ -0,0 +1,284 @@
+use anyhow::Result;
+use fediproto_sync_db::{
+    AnyConnection,
+    models::{
+        MastodonPostRetryQueueItem, NewMastodonPostDeadLetter, NewMastodonPostRetryQueueItem,
+    },
+};
+use fediproto_sync_lib::{config::FediProtoSyncConfig, error::ErrorClass};
+
+use crate::{
+    bsky_post_sync::{BLUESKY_DESTINATION, parse_post_for_destination},
+    rules::PostRules,
+    sync_error::classify_error,
+};
+
+/// The policy for retrying posts that failed to sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times to try syncing a post.
+    pub max_attempts: u32,
+
+    /// The delay before the first retry.
+    pub initial_delay: std::time::Duration,
+
+    /// The longest delay to wait before a retry.
+    pub max_delay: std::time::Duration,
+}
+
+impl RetryPolicy {
+    /// Create the retry policy for an account pair.
+    ///
+    /// ## Arguments
+    ///
+    /// * `config` - The config for the account pair.
+    pub fn from_config(config: &FediProtoSyncConfig) -> Self {
+        Self {
+            max_attempts: config.retry_max_attempts,
+            initial_delay: config.retry_initial_delay,
+            max_delay: config.retry_max_delay,
+        }
+    }
+
+    /// Get the delay before the next retry, doubling after every failed
+    /// attempt.
+    ///
+    /// ## Arguments
+    ///
+    /// * `failed_attempts` - The number of times syncing the post has failed.
+    pub fn delay_after(
+        &self,
+        failed_attempts: u32,
+    ) -> std::time::Duration {
+        let exponent = failed_attempts.saturating_sub(1).min(31);
+
+        self.initial_delay
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_delay)
+    }
+
+    /// Check if a post has no attempts left and should be moved to the dead
+    /// letters.
+    ///
+    /// ## Arguments
+    ///
+    /// * `failed_attempts` - The number of times syncing the post has failed.
+    pub fn is_exhausted(
+        &self,
+        failed_attempts: u32,
+    ) -> bool {
+        failed_attempts >= self.max_attempts
+    }
+
+    /// Check if a post should be moved to the dead letters after a failed
+    /// attempt.
+    ///
+    /// ## Arguments
+    ///
+    /// * `failed_attempts` - The number of times syncing the post has failed.
+    /// * `failure_class` - The class of the error the last attempt failed
+    ///   with.
+    ///
+    /// ## Note
+    ///
+    /// Permanent failures won't go away by retrying, so they're moved right
+    /// away. Auth failures aren't the fault of the post, so they're retried
+    /// until the credentials are fixed.
+    pub fn should_dead_letter(
+        &self,
+        failed_attempts: u32,
+        failure_class: ErrorClass,
+    ) -> bool {
+        match failure_class {
+            ErrorClass::Transient => self.is_exhausted(failed_attempts),
+            ErrorClass::Permanent => true,
+            ErrorClass::Auth => false,
+        }
+    }
+
+    /// Get the time the next retry can be attempted.
+    ///
+    /// ## Arguments
+    ///
+    /// * `failed_attempts` - The number of times syncing the post has failed.
+    /// * `now` - The current time.
+    pub fn next_attempt_at(
+        &self,
+        failed_attempts: u32,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> chrono::NaiveDateTime {
+        let delay = chrono::Duration::from_std(self.delay_after(failed_attempts))
+            .unwrap_or(chrono::Duration::MAX);
+
+        now.checked_add_signed(delay)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+            .naive_utc()
+    }
+}
+
+/// Record a failed attempt to sync a post. The post is scheduled to be retried
+/// with a backoff, or moved to the dead letters once it's out of attempts or
+/// the error is permanent.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `db_connection` - The database connection to use.
+/// * `post_item` - The Mastodon post that failed to sync.
+/// * `retry_item` - The post's item in the retry queue, if it was a retry.
+/// * `error` - The error the post failed to sync with.
+/// * `post_rules` - The rules for processing posts.
+pub fn record_failed_attempt(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+    post_item: &megalodon::entities::Status,
+    retry_item: Option<&MastodonPostRetryQueueItem>,
+    error: &anyhow::Error,
+    post_rules: &PostRules,
+) -> Result<()> {
+    record_failure(
+        config,
+        db_connection,
+        &post_item.id,
+        retry_item,
+        error,
+        || {
+            parse_post_for_destination(
+                post_item,
+                config.source_platform,
+                post_rules,
+                BLUESKY_DESTINATION,
+            )
+            .map(|parsed_status| parsed_status.stripped_html)
+            .ok()
+        },
+    )
+}
+
+/// Record a failed attempt to fetch a post in the retry queue from Mastodon.
+/// The post goes through the same backoff and attempt limit as a post that
+/// failed to sync.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `db_connection` - The database connection to use.
+/// * `retry_item` - The post's item in the retry queue.
+/// * `error` - The error the post failed to be fetched with.
+pub fn record_failed_fetch(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+    retry_item: &MastodonPostRetryQueueItem,
+    error: &anyhow::Error,
+) -> Result<()> {
+    record_failure(
+        config,
+        db_connection,
+        &retry_item.id,
+        Some(retry_item),
+        error,
+        || None,
+    )
+}
+
+/// Schedule a post to be retried, or move it to the dead letters.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `db_connection` - The database connection to use.
+/// * `mastodon_post_id` - The ID of the Mastodon post that failed.
+/// * `retry_item` - The post's item in the retry queue, if it was a retry.
+/// * `error` - The error the post failed with.
+/// * `rendered_payload` - Renders the post for the dead letters.
+fn record_failure(
+    config: &FediProtoSyncConfig,
+    db_connection: &mut AnyConnection,
+    mastodon_post_id: &str,
+    retry_item: Option<&MastodonPostRetryQueueItem>,
+    error: &anyhow::Error,
+    rendered_payload: impl FnOnce() -> Option<String>,
+) -> Result<()> {
+    let retry_policy = RetryPolicy::from_config(config);
+    let failure_reason = format!("{:#}", error);
+    let failure_class = classify_error(error);
+
+    // The first attempt isn't counted as a retry.
+    let failed_attempts = match retry_item {
+        Some(retry_item) => retry_item.retry_count.max(0) as u32 + 2,
+        None => 1,
+    };
+
+    if failure_class == ErrorClass::Auth {
+        tracing::error!(
+            "Post '{}' failed to sync because of an auth error. Check the credentials of the account pair '{}'.",
+            mastodon_post_id,
+            config.account_pair_id
+        );
+    }
+
+    if retry_policy.should_dead_letter(failed_attempts, failure_class) {
+        let rendered_payload = rendered_payload();
+
+        let new_dead_letter = NewMastodonPostDeadLetter::new(
+            &config.account_pair_id,
+            mastodon_post_id,
+            &failure_reason,
+            failure_class,
+            rendered_payload.as_deref(),
+            failed_attempts as i32,
+        );
+
+        fediproto_sync_db::operations::insert_mastodon_post_dead_letter(
+            db_connection,
+            &new_dead_letter,
+        )?;
+
+        tracing::warn!(
+            "Post '{}' failed to sync '{}' times with a {} error, moved it to the dead letters.",
+            mastodon_post_id,
+            failed_attempts,
+            failure_class
+        );
+
+        return Ok(());
+    }
+
+    let next_attempt_at = retry_policy.next_attempt_at(failed_attempts, chrono::Utc::now());
+
+    match retry_item {
+        Some(retry_item) => {
+            fediproto_sync_db::operations::update_mastodon_post_retry_queue_item(
+                db_connection,
+                retry_item,
+                Some(&failure_reason),
+                failure_class,
+                next_attempt_at,
+            )?;
+        }
+
+        None => {
+            let new_retry_item = NewMastodonPostRetryQueueItem::new(
+                &config.account_pair_id,
+                mastodon_post_id,
+                &failure_reason,
+                failure_class,
+                next_attempt_at,
+            );
+
+            fediproto_sync_db::operations::insert_mastodon_post_retry_queue_item(
+                db_connection,
+                &new_retry_item,
+            )?;
+        }
+    }
+
+    tracing::info!(
+        "Post '{}' failed with a {} error, it will be retried at '{}'.",
+        mastodon_post_id,
+        failure_class,
+        next_attempt_at
+    );
+
+    Ok(())
+}
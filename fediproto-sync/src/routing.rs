@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use diesel::r2d2::{ConnectionManager, Pool};
+use fediproto_sync_db::AnyConnection;
+use fediproto_sync_lib::config::FediProtoSyncConfig;
+use megalodon::entities::Account;
+
+use crate::{
+    bsky_post_sync,
+    core::SocialMediaClients,
+    rules::{PostRules, route::RouteDecision},
+    sync_error::{classify_error, most_relevant_error},
+};
+
+/// Sync a Mastodon post to every BlueSky destination the route rules decide
+/// for it.
+///
+/// ## Arguments
+///
+/// * `post_item` - The Mastodon post to sync.
+/// * `config` - The config for the app.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+/// * `mastodon_account` - The Mastodon account that posted the status.
+/// * `post_rules` - The rules for processing posts.
+///
+/// ## Note
+///
+/// Destinations the post was already synced to are skipped, so a post that
+/// failed to sync to some destinations can be retried without posting it
+/// twice to the others. A failure for one destination doesn't stop the post
+/// from syncing to the rest, but an error is returned once they're done. The
+/// returned error is the one that matters most for retrying the post.
+pub(crate) async fn sync_post_to_destinations(
+    post_item: &megalodon::entities::Status,
+    config: &FediProtoSyncConfig,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+    mastodon_account: &Account,
+    post_rules: Arc<PostRules>,
+) -> Result<()> {
+    let route_decision = RouteDecision::evaluate(&post_rules.routes, post_item);
+
+    if let Some(rule_name) = &route_decision.rule_name {
+        tracing::info!(
+            "Post '{}' matched route rule '{}', syncing to '{}'.",
+            post_item.id,
+            rule_name,
+            route_decision.destinations.join(", ")
+        );
+    }
+
+    let synced_destinations = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_synced_mastodon_post_destinations(
+            db_connection,
+            &config.account_pair_id,
+            &post_item.id,
+        )?
+    };
+
+    let mut failed_destinations = Vec::new();
+
+    for destination in &route_decision.destinations {
+        if synced_destinations.contains(destination) {
+            tracing::info!(
+                "Post '{}' was already synced to '{}', skipping.",
+                post_item.id,
+                destination
+            );
+
+            continue;
+        }
+
+        let bluesky_session = match social_media_clients.bluesky_session_for(destination) {
+            Some(bluesky_session) => bluesky_session,
+            None => {
+                tracing::error!("Destination '{}' isn't configured.", destination);
+                failed_destinations.push((
+                    destination.clone(),
+                    anyhow::anyhow!("Destination '{}' isn't configured.", destination),
+                ));
+
+                continue;
+            }
+        };
+
+        let sync_config = bsky_post_sync::BlueSkyPostSyncConfig {
+            config: config.clone(),
+            destination: destination.clone(),
+            did: bluesky_session.did.clone(),
+            pds_service_endpoint: bluesky_session.pds_service_endpoint.clone(),
+            mastodon_account: mastodon_account.clone(),
+            mastodon_client: social_media_clients.mastodon_client.clone(),
+            mastodon_rate_limiter: social_media_clients.mastodon_rate_limiter.clone(),
+            db_connection_pool: db_connection_pool.clone(),
+            post_rules: post_rules.clone(),
+        };
+
+        let sync_result =
+            bsky_post_sync::sync_post(post_item, &bluesky_session.atp_agent, &sync_config).await;
+
+        if let Err(e) = sync_result {
+            tracing::error!(
+                "Failed to sync post '{}' to '{}' ({}): {:#?}",
+                post_item.id,
+                destination,
+                classify_error(&e),
+                e
+            );
+
+            failed_destinations.push((destination.clone(), e));
+        }
+    }
+
+    if !failed_destinations.is_empty() {
+        let (destinations, errors): (Vec<String>, Vec<anyhow::Error>) =
+            failed_destinations.into_iter().unzip();
+
+        let error = most_relevant_error(errors)
+            .unwrap_or_else(|| anyhow::anyhow!("Unknown error."))
+            .context(format!(
+                "Failed to sync post '{}' to '{}'.",
+                post_item.id,
+                destinations.join(", ")
+            ));
+
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Delete a Mastodon post from every BlueSky destination it was synced to.
+///
+/// ## Arguments
+///
+/// * `mastodon_post_id` - The ID of the deleted Mastodon post.
+/// * `account_pair_id` - The ID of the account pair the post was synced by.
+/// * `db_connection_pool` - The database connection pool.
+/// * `social_media_clients` - The clients for Mastodon and BlueSky.
+///
+/// ## Note
+///
+/// The records of the synced post are kept, so the post isn't synced again.
+pub(crate) async fn delete_post_from_destinations(
+    mastodon_post_id: &str,
+    account_pair_id: &str,
+    db_connection_pool: &Pool<ConnectionManager<AnyConnection>>,
+    social_media_clients: Arc<SocialMediaClients>,
+) -> Result<()> {
+    let synced_destinations = {
+        let db_connection = &mut db_connection_pool.get()?;
+
+        fediproto_sync_db::operations::get_synced_mastodon_post_destinations(
+            db_connection,
+            account_pair_id,
+            mastodon_post_id,
+        )?
+    };
+
+    let mut failed_destinations = Vec::new();
+
+    for destination in &synced_destinations {
+        let bluesky_session = match social_media_clients.bluesky_session_for(destination) {
+            Some(bluesky_session) => bluesky_session,
+            None => {
+                tracing::error!("Destination '{}' isn't configured.", destination);
+                failed_destinations.push(destination.clone());
+
+                continue;
+            }
+        };
+
+        // The connection is released before deleting the post, since the
+        // BlueSky session takes one of its own when it's refreshed.
+        let bluesky_data = {
+            let db_connection = &mut db_connection_pool.get()?;
+
+            fediproto_sync_db::operations::get_bluesky_data_by_mastodon_post_id(
+                db_connection,
+                account_pair_id,
+                destination,
+                mastodon_post_id,
+            )?
+        };
+
+        let delete_result = bsky_post_sync::delete_post(
+            &bluesky_session.atp_agent,
+            &bluesky_session.did,
+            &bluesky_data.bsky_post_uri,
+        )
+        .await;
+
+        match delete_result {
+            Ok(_) => {
+                tracing::info!(
+                    "Deleted post '{}' from '{}'.",
+                    mastodon_post_id,
+                    destination
+                );
+            }
+
+            Err(e) => {
+                tracing::error!(
+                    "Failed to delete post '{}' from '{}': {:#?}",
+                    mastodon_post_id,
+                    destination,
+                    e
+                );
+
+                failed_destinations.push(destination.clone());
+            }
+        }
+    }
+
+    if !failed_destinations.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Failed to delete post '{}' from '{}'.",
+            mastodon_post_id,
+            failed_destinations.join(", ")
+        ));
+    }
+
+    Ok(())
+}
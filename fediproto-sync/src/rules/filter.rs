@@ -0,0 +1,94 @@
+use megalodon::entities::Status;
+use serde::Deserialize;
+
+use super::matcher::StatusMatcher;
+
+/// The action to take for a post.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// Sync the post to BlueSky.
+    Sync,
+
+    /// Never sync the post to BlueSky.
+    Skip,
+
+    /// Don't sync the post yet and check it again on the next sync.
+    Hold,
+}
+
+impl FilterAction {
+    /// Get the name of the action, as it's stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sync => "sync",
+            Self::Skip => "skip",
+            Self::Hold => "hold",
+        }
+    }
+}
+
+impl std::fmt::Display for FilterAction {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A rule for deciding whether to sync, skip or hold posts.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FilterRule {
+    /// The name of the rule, used when logging decisions.
+    pub name: String,
+
+    /// The action to take for posts that match the rule.
+    pub action: FilterAction,
+
+    /// The conditions a post has to match.
+    #[serde(flatten)]
+    pub matcher: StatusMatcher,
+}
+
+/// The decision made for a post by the filter rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterDecision {
+    /// The action to take for the post.
+    pub action: FilterAction,
+
+    /// The name of the rule that matched, if any.
+    pub rule_name: Option<String>,
+}
+
+impl FilterDecision {
+    /// Decide what to do with a post using the filter rules.
+    ///
+    /// ## Arguments
+    ///
+    /// * `filters` - The filter rules, in order of priority.
+    /// * `status` - The Mastodon status to decide for.
+    ///
+    /// ## Note
+    ///
+    /// The first rule that matches decides the action. If no rules match, the
+    /// post is synced.
+    pub fn evaluate(
+        filters: &[FilterRule],
+        status: &Status,
+    ) -> Self {
+        let matching_rule = filters.iter().find(|filter| filter.matcher.matches(status));
+
+        match matching_rule {
+            Some(matching_rule) => Self {
+                action: matching_rule.action,
+                rule_name: Some(matching_rule.name.clone()),
+            },
+
+            None => Self {
+                action: FilterAction::Sync,
+                rule_name: None,
+            },
+        }
+    }
+}
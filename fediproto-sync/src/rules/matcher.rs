@@ -0,0 +1,191 @@
+use megalodon::entities::{Status, StatusVisibility};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::mastodon::ParsedMastodonPost;
+
+/// Conditions for matching a Mastodon status.
+///
+/// ## Note
+///
+/// Every condition that is set must match for the status to match. For
+/// conditions with a list of values, any of the values can match. A matcher
+/// without any conditions matches every status.
+///
+/// For boosts, the conditions on the content of the status (hashtags, text,
+/// media, language and content warning) are checked against the boosted
+/// status.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StatusMatcher {
+    /// Hashtags, with or without the leading `#`, the status has.
+    pub hashtags: Vec<String>,
+
+    /// A regular expression to match against the text of the status.
+    #[serde(deserialize_with = "super::deserialize_optional_regex")]
+    pub text_regex: Option<Regex>,
+
+    /// The visibilities the status can have.
+    pub visibility: Vec<StatusVisibility>,
+
+    /// Whether the status has media attachments.
+    pub has_media: Option<bool>,
+
+    /// The languages the status can be in, like `en` or `en-GB`.
+    pub languages: Vec<String>,
+
+    /// Whether the status has a content warning.
+    pub has_content_warning: Option<bool>,
+
+    /// The names of the applications the status can be posted from.
+    pub applications: Vec<String>,
+
+    /// Whether the status is a boost.
+    pub is_boost: Option<bool>,
+
+    /// The accounts, like `someone@mastodon.social`, the boosted status can be
+    /// from.
+    pub boost_authors: Vec<String>,
+}
+
+impl StatusMatcher {
+    /// Check if a Mastodon status matches all of the conditions.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status to check.
+    pub fn matches(
+        &self,
+        status: &Status,
+    ) -> bool {
+        let content_status = status.reblog.as_deref().unwrap_or(status);
+
+        if self
+            .is_boost
+            .is_some_and(|is_boost| status.reblog.is_some() != is_boost)
+        {
+            return false;
+        }
+
+        if !self.boost_authors.is_empty() {
+            let boost_author = match &status.reblog {
+                Some(reblog) => reblog.account.acct.as_str(),
+                None => return false,
+            };
+
+            if !self.boost_authors.iter().any(|author| {
+                author
+                    .trim_start_matches('@')
+                    .eq_ignore_ascii_case(boost_author)
+            }) {
+                return false;
+            }
+        }
+
+        if !self.visibility.is_empty() && !self.visibility.contains(&status.visibility) {
+            return false;
+        }
+
+        if !self.hashtags.is_empty()
+            && !content_status.tags.iter().any(|tag| {
+                self.hashtags.iter().any(|hashtag| {
+                    hashtag
+                        .trim_start_matches('#')
+                        .eq_ignore_ascii_case(&tag.name)
+                })
+            })
+        {
+            return false;
+        }
+
+        if self
+            .has_media
+            .is_some_and(|has_media| content_status.media_attachments.is_empty() == has_media)
+        {
+            return false;
+        }
+
+        if self.has_content_warning.is_some_and(|has_content_warning| {
+            content_status.spoiler_text.trim().is_empty() == has_content_warning
+        }) {
+            return false;
+        }
+
+        if !self.languages.is_empty() {
+            let status_language = content_status.language.as_deref().unwrap_or_default();
+
+            if !self
+                .languages
+                .iter()
+                .any(|language| language_matches(language, status_language))
+            {
+                return false;
+            }
+        }
+
+        if !self.applications.is_empty() {
+            let application_name = status
+                .application
+                .as_ref()
+                .map(|application| application.name.as_str())
+                .unwrap_or_default();
+
+            if !self
+                .applications
+                .iter()
+                .any(|application| application.eq_ignore_ascii_case(application_name))
+            {
+                return false;
+            }
+        }
+
+        if self
+            .text_regex
+            .as_ref()
+            .is_some_and(|text_regex| !text_regex.is_match(&status_text(content_status)))
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Check if the language of a status matches a language in a rule.
+///
+/// ## Arguments
+///
+/// * `language` - The language in the rule.
+/// * `status_language` - The language of the status.
+///
+/// ## Note
+///
+/// A language without a region, like `en`, matches any region of the
+/// language, like `en-GB`.
+fn language_matches(
+    language: &str,
+    status_language: &str,
+) -> bool {
+    if language.eq_ignore_ascii_case(status_language) {
+        return true;
+    }
+
+    match status_language.split_once('-') {
+        Some((primary_language, _)) => {
+            !language.contains('-') && language.eq_ignore_ascii_case(primary_language)
+        }
+        None => false,
+    }
+}
+
+/// Get the plain text of a status.
+///
+/// ## Arguments
+///
+/// * `status` - The Mastodon status.
+fn status_text(status: &Status) -> String {
+    match ParsedMastodonPost::content_to_text(status) {
+        Ok(text) => text,
+        Err(_) => status.content.clone(),
+    }
+}
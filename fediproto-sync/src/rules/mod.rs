@@ -0,0 +1,168 @@
+/// Rules for deciding whether to sync, skip or hold posts.
+pub mod filter;
+/// Matching Mastodon statuses against conditions in rules.
+pub mod matcher;
+/// Rules for deciding which BlueSky destinations posts are synced to.
+pub mod route;
+/// Rules for transforming the text of posts before they're synced.
+pub mod transform;
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use self::{
+    filter::FilterRule,
+    route::{BlueSkyDestination, RouteRule},
+    transform::TransformRule,
+};
+use crate::{bsky_post_sync::BLUESKY_DESTINATION, templates::PostTemplates};
+
+/// Rules for processing posts, loaded from the post rules file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PostRules {
+    /// Rules for deciding whether to sync, skip or hold posts.
+    #[serde(rename = "filter")]
+    pub filters: Vec<FilterRule>,
+
+    /// Rules for transforming the text of posts, applied in order.
+    #[serde(rename = "transform")]
+    pub transforms: Vec<TransformRule>,
+
+    /// Rules for deciding which BlueSky destinations posts are synced to.
+    #[serde(rename = "route")]
+    pub routes: Vec<RouteRule>,
+
+    /// The BlueSky accounts, other than the one for the account pair, that
+    /// posts can be routed to.
+    #[serde(rename = "destination")]
+    pub destinations: Vec<BlueSkyDestination>,
+
+    /// The templates for the strings added to synced posts.
+    pub templates: PostTemplates,
+}
+
+impl PostRules {
+    /// Load the post rules from a TOML file.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path to the post rules file, if any.
+    ///
+    /// ## Note
+    ///
+    /// If no path is provided, an empty set of rules is returned and every
+    /// post is synced.
+    pub fn load(path: Option<&std::path::Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+
+        let rules_file = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read post rules file '{}'.", path.display()))?;
+
+        let post_rules = Self::from_toml_str(&rules_file)
+            .with_context(|| format!("Failed to parse post rules file '{}'.", path.display()))?;
+
+        tracing::info!(
+            "Loaded '{}' filter rules, '{}' transform rules and '{}' route rules from '{}'.",
+            post_rules.filters.len(),
+            post_rules.transforms.len(),
+            post_rules.routes.len(),
+            path.display()
+        );
+
+        Ok(post_rules)
+    }
+
+    /// Parse the post rules from a TOML string.
+    ///
+    /// ## Arguments
+    ///
+    /// * `rules` - The TOML string to parse.
+    ///
+    /// ## Note
+    ///
+    /// Every destination needs a unique name, and `bluesky` is reserved for
+    /// the BlueSky account of the account pair. Route rules can only use
+    /// destinations that are defined.
+    pub fn from_toml_str(rules: &str) -> Result<Self> {
+        let post_rules = toml::from_str::<Self>(rules)?;
+
+        let mut destination_names = HashSet::from([BLUESKY_DESTINATION.to_string()]);
+
+        for destination in &post_rules.destinations {
+            if destination.name.trim().is_empty() {
+                return Err(anyhow::anyhow!("Destination names can't be empty."));
+            }
+
+            if !destination_names.insert(destination.name.to_lowercase()) {
+                return Err(anyhow::anyhow!(
+                    "Destination name '{}' is used more than once.",
+                    destination.name
+                ));
+            }
+        }
+
+        for route in &post_rules.routes {
+            if route.destinations.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Route rule '{}' has no destinations.",
+                    route.name
+                ));
+            }
+
+            let unknown_destination = route
+                .destinations
+                .iter()
+                .find(|destination| !destination_names.contains(&destination.to_lowercase()));
+
+            if let Some(unknown_destination) = unknown_destination {
+                return Err(anyhow::anyhow!(
+                    "Route rule '{}' uses the unknown destination '{}'.",
+                    route.name,
+                    unknown_destination
+                ));
+            }
+        }
+
+        Ok(post_rules)
+    }
+}
+
+/// Deserialize and compile a regular expression.
+///
+/// ## Arguments
+///
+/// * `deserializer` - The deserializer.
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize and compile an optional regular expression.
+///
+/// ## Arguments
+///
+/// * `deserializer` - The deserializer.
+fn deserialize_optional_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = Option::<String>::deserialize(deserializer)?;
+
+    match pattern {
+        Some(pattern) => Regex::new(&pattern)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
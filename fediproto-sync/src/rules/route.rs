@@ -0,0 +1,93 @@
+use megalodon::entities::Status;
+use serde::Deserialize;
+
+use super::matcher::StatusMatcher;
+use crate::bsky_post_sync::BLUESKY_DESTINATION;
+
+/// A BlueSky account, other than the one for the account pair, that posts
+/// can be routed to.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BlueSkyDestination {
+    /// The name of the destination, used by route and transform rules.
+    pub name: String,
+
+    /// The BlueSky PDS URL to connect to. If not set, the one for the account
+    /// pair is used.
+    pub bluesky_pds_server: Option<String>,
+
+    /// The BlueSky handle to use for authentication.
+    pub bluesky_handle: String,
+
+    /// The BlueSky app password to use for authentication.
+    pub bluesky_app_password: String,
+}
+
+/// A rule for deciding which BlueSky destinations a post is synced to.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RouteRule {
+    /// The name of the rule, used when logging decisions.
+    pub name: String,
+
+    /// The destinations to sync posts that match the rule to.
+    pub destinations: Vec<String>,
+
+    /// The conditions a post has to match.
+    #[serde(flatten)]
+    pub matcher: StatusMatcher,
+}
+
+/// The decision made for a post by the route rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDecision {
+    /// The destinations to sync the post to.
+    pub destinations: Vec<String>,
+
+    /// The name of the rule that matched, if any.
+    pub rule_name: Option<String>,
+}
+
+impl RouteDecision {
+    /// Decide which destinations to sync a post to using the route rules.
+    ///
+    /// ## Arguments
+    ///
+    /// * `routes` - The route rules, in order of priority.
+    /// * `status` - The Mastodon status to decide for.
+    ///
+    /// ## Note
+    ///
+    /// The first rule that matches decides the destinations. If no rules
+    /// match, the post is synced to the BlueSky account for the account pair.
+    /// Destination names are compared case-insensitively, so they're returned
+    /// in lowercase.
+    pub fn evaluate(
+        routes: &[RouteRule],
+        status: &Status,
+    ) -> Self {
+        let matching_rule = routes.iter().find(|route| route.matcher.matches(status));
+
+        match matching_rule {
+            Some(matching_rule) => {
+                let mut destinations: Vec<String> = Vec::new();
+
+                for destination in &matching_rule.destinations {
+                    let destination = destination.to_lowercase();
+
+                    if !destinations.contains(&destination) {
+                        destinations.push(destination);
+                    }
+                }
+
+                Self {
+                    destinations,
+                    rule_name: Some(matching_rule.name.clone()),
+                }
+            }
+
+            None => Self {
+                destinations: vec![BLUESKY_DESTINATION.to_string()],
+                rule_name: None,
+            },
+        }
+    }
+}
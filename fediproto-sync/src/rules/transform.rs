@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use megalodon::entities::Status;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::matcher::StatusMatcher;
+
+/// An operation for transforming the text of a post.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransformOperation {
+    /// Replace every match of a regular expression.
+    RegexReplace {
+        /// The regular expression to match.
+        #[serde(deserialize_with = "super::deserialize_regex")]
+        pattern: Regex,
+
+        /// The text to replace matches with. Capture groups can be referenced
+        /// with `$1` or `${name}`.
+        replacement: String,
+    },
+
+    /// Remove the lines at the end of the post that only contain hashtags.
+    StripTrailingHashtags,
+
+    /// Replace the shortcodes of custom emoji, like `:blobcat:`.
+    ReplaceCustomEmoji {
+        /// Replacements for specific shortcodes, without the colons.
+        #[serde(default)]
+        replacements: HashMap<String, String>,
+
+        /// The text to replace any other shortcodes with.
+        #[serde(default)]
+        fallback: String,
+    },
+
+    /// Add text to the end of the post.
+    Append {
+        /// The text to add.
+        text: String,
+    },
+}
+
+impl TransformOperation {
+    /// Apply the operation to the text of a post.
+    ///
+    /// ## Arguments
+    ///
+    /// * `text` - The text of the post.
+    /// * `status` - The Mastodon status the post is from.
+    /// * `tags` - The hashtags found in the post, including the leading `#`.
+    pub fn apply(
+        &self,
+        text: &str,
+        status: &Status,
+        tags: &[String],
+    ) -> String {
+        match self {
+            Self::RegexReplace {
+                pattern,
+                replacement,
+            } => pattern.replace_all(text, replacement.as_str()).to_string(),
+
+            Self::StripTrailingHashtags => strip_trailing_hashtags(text, tags),
+
+            Self::ReplaceCustomEmoji {
+                replacements,
+                fallback,
+            } => {
+                let mut transformed_text = text.to_string();
+
+                for emoji in &status.emojis {
+                    let replacement = replacements.get(&emoji.shortcode).unwrap_or(fallback);
+
+                    transformed_text = transformed_text
+                        .replace(&format!(":{}:", emoji.shortcode), replacement.as_str());
+                }
+
+                transformed_text
+            }
+
+            Self::Append {
+                text: appended_text,
+            } => format!("{}{}", text, appended_text),
+        }
+    }
+}
+
+/// A rule for transforming the text of posts before they're synced.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TransformRule {
+    /// The name of the rule, used when logging.
+    pub name: String,
+
+    /// The destinations the rule applies to. If empty, the rule applies to
+    /// every destination.
+    #[serde(default)]
+    pub destinations: Vec<String>,
+
+    /// The operation to apply.
+    #[serde(flatten)]
+    pub operation: TransformOperation,
+
+    /// The conditions a post has to match for the rule to apply.
+    #[serde(flatten)]
+    pub matcher: StatusMatcher,
+}
+
+impl TransformRule {
+    /// Check if the rule applies to a post being synced to a destination.
+    ///
+    /// ## Arguments
+    ///
+    /// * `destination` - The name of the destination the post is synced to.
+    /// * `status` - The Mastodon status the post is from.
+    pub fn applies_to(
+        &self,
+        destination: &str,
+        status: &Status,
+    ) -> bool {
+        let destination_matches = self.destinations.is_empty()
+            || self
+                .destinations
+                .iter()
+                .any(|rule_destination| rule_destination.eq_ignore_ascii_case(destination));
+
+        destination_matches && self.matcher.matches(status)
+    }
+}
+
+/// Remove the lines at the end of a post that only contain hashtags.
+///
+/// ## Arguments
+///
+/// * `text` - The text of the post.
+/// * `tags` - The hashtags found in the post, including the leading `#`.
+///
+/// ## Note
+///
+/// The first line is always kept, so a post that only has hashtags isn't
+/// left empty.
+fn strip_trailing_hashtags(
+    text: &str,
+    tags: &[String],
+) -> String {
+    let mut stripped_text = text.trim_end();
+
+    while let Some((remaining_text, last_line)) = stripped_text.rsplit_once('\n') {
+        let is_hashtag_line = last_line
+            .split_whitespace()
+            .all(|word| tags.iter().any(|tag| tag.eq_ignore_ascii_case(word)));
+
+        if !is_hashtag_line {
+            break;
+        }
+
+        stripped_text = remaining_text.trim_end();
+    }
+
+    stripped_text.to_string()
+}
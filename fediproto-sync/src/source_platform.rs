@@ -0,0 +1,235 @@
+use anyhow::Result;
+use fediproto_sync_lib::config::{FediProtoSyncConfig, SourcePlatform};
+use serde::Deserialize;
+
+use crate::core::create_http_client;
+
+/// The path of the nodeinfo discovery document.
+pub const NODEINFO_WELL_KNOWN_PATH: &str = "/.well-known/nodeinfo";
+
+/// The prefix of the relations for the nodeinfo 2.x schemas.
+const NODEINFO_2_SCHEMA_PREFIX: &str = "http://nodeinfo.diaspora.software/ns/schema/2.";
+
+/// The nodeinfo discovery document.
+#[derive(Deserialize, Debug)]
+struct NodeInfoLinks {
+    /// The links to the nodeinfo documents.
+    #[serde(default)]
+    links: Vec<NodeInfoLink>,
+}
+
+/// A link to a nodeinfo document.
+#[derive(Deserialize, Debug)]
+struct NodeInfoLink {
+    /// The schema of the nodeinfo document.
+    rel: String,
+
+    /// The URL of the nodeinfo document.
+    href: String,
+}
+
+/// A nodeinfo document.
+#[derive(Deserialize, Debug)]
+struct NodeInfo {
+    /// The software the server runs.
+    software: NodeInfoSoftware,
+}
+
+/// The software in a nodeinfo document.
+#[derive(Deserialize, Debug)]
+struct NodeInfoSoftware {
+    /// The name of the software.
+    name: String,
+}
+
+/// Client for detecting the platform a server runs with nodeinfo.
+pub struct NodeInfoClient {
+    /// The HTTP client to use for requests.
+    http_client: reqwest::Client,
+
+    /// The base URL of the server.
+    base_url: String,
+}
+
+impl NodeInfoClient {
+    /// Create a new instance of the `NodeInfoClient` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `http_client` - The HTTP client to use for requests.
+    /// * `base_url` - The base URL of the server, like `https://mastodon.social`.
+    pub fn new(
+        http_client: reqwest::Client,
+        base_url: &str,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Detect the platform the server runs.
+    ///
+    /// ## Note
+    ///
+    /// Returns `None` if the server runs software that isn't supported.
+    pub async fn detect_platform(&self) -> Result<Option<SourcePlatform>> {
+        let nodeinfo_links = self
+            .http_client
+            .get(format!("{}{}", self.base_url, NODEINFO_WELL_KNOWN_PATH))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<NodeInfoLinks>()
+            .await?;
+
+        // Use the newest 2.x schema the server has.
+        let nodeinfo_link = nodeinfo_links
+            .links
+            .iter()
+            .filter(|link| link.rel.starts_with(NODEINFO_2_SCHEMA_PREFIX))
+            .max_by(|a, b| a.rel.cmp(&b.rel))
+            .ok_or_else(|| anyhow::anyhow!("The server doesn't have a nodeinfo 2.x document."))?;
+
+        let nodeinfo = self
+            .http_client
+            .get(&nodeinfo_link.href)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<NodeInfo>()
+            .await?;
+
+        tracing::debug!(
+            "Server '{}' runs '{}'.",
+            self.base_url,
+            nodeinfo.software.name
+        );
+
+        Ok(platform_from_software_name(&nodeinfo.software.name))
+    }
+}
+
+/// Get the platform for the name of the software in a nodeinfo document.
+///
+/// ## Arguments
+///
+/// * `software_name` - The name of the software.
+///
+/// ## Note
+///
+/// Forks that are compatible with a platform, like Akkoma for Pleroma, are
+/// treated as that platform.
+pub fn platform_from_software_name(software_name: &str) -> Option<SourcePlatform> {
+    match software_name.to_lowercase().as_str() {
+        "mastodon" | "hometown" | "glitchsoc" => Some(SourcePlatform::Mastodon),
+        "pleroma" | "akkoma" => Some(SourcePlatform::Pleroma),
+        "friendica" => Some(SourcePlatform::Friendica),
+        "firefish" | "calckey" | "iceshrimp" => Some(SourcePlatform::Firefish),
+        "gotosocial" => Some(SourcePlatform::GoToSocial),
+        _ => None,
+    }
+}
+
+/// Resolve the platform of the source server in the config.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the app.
+///
+/// ## Note
+///
+/// If the platform is set to `auto`, it's detected with the server's
+/// nodeinfo. Mastodon is assumed if it can't be detected.
+pub async fn resolve_source_platform(config: &FediProtoSyncConfig) -> SourcePlatform {
+    if config.source_platform != SourcePlatform::Auto {
+        return config.source_platform;
+    }
+
+    let detect_result = match create_http_client(config) {
+        Ok(http_client) => {
+            NodeInfoClient::new(http_client, &format!("https://{}", config.mastodon_server))
+                .detect_platform()
+                .await
+        }
+        Err(e) => Err(e.into()),
+    };
+
+    match detect_result {
+        Ok(Some(source_platform)) => {
+            tracing::info!(
+                "Detected '{}' on '{}'.",
+                source_platform,
+                config.mastodon_server
+            );
+
+            source_platform
+        }
+
+        Ok(None) => {
+            tracing::warn!(
+                "'{}' runs a platform that isn't supported, assuming it's compatible with Mastodon.",
+                config.mastodon_server
+            );
+
+            SourcePlatform::Mastodon
+        }
+
+        Err(e) => {
+            tracing::warn!(
+                "Failed to detect the platform of '{}', assuming it's Mastodon: {:#?}",
+                config.mastodon_server,
+                e
+            );
+
+            SourcePlatform::Mastodon
+        }
+    }
+}
+
+/// Resolve the platform of the source server for each account pair.
+///
+/// ## Arguments
+///
+/// * `account_pair_configs` - The configs of the account pairs.
+pub async fn resolve_account_pair_source_platforms(
+    account_pair_configs: &mut [FediProtoSyncConfig]
+) {
+    for account_pair_config in account_pair_configs {
+        account_pair_config.source_platform = resolve_source_platform(account_pair_config).await;
+    }
+}
+
+/// Check if the line breaks in the content of posts from a platform are kept
+/// when the content is converted to text.
+///
+/// ## Arguments
+///
+/// * `source_platform` - The platform of the source server.
+///
+/// ## Note
+///
+/// Posts from Mastodon are converted to text the same way they were before
+/// other platforms were supported, so their text, length and truncation
+/// don't change.
+pub fn keeps_line_breaks(source_platform: SourcePlatform) -> bool {
+    !matches!(
+        source_platform,
+        SourcePlatform::Auto | SourcePlatform::Mastodon
+    )
+}
+
+/// Get the `megalodon` SNS for a platform.
+///
+/// ## Arguments
+///
+/// * `source_platform` - The platform of the source server.
+pub fn megalodon_sns(source_platform: SourcePlatform) -> megalodon::SNS {
+    match source_platform {
+        SourcePlatform::Auto | SourcePlatform::Mastodon => megalodon::SNS::Mastodon,
+        SourcePlatform::Pleroma => megalodon::SNS::Pleroma,
+        SourcePlatform::Friendica => megalodon::SNS::Friendica,
+        SourcePlatform::Firefish => megalodon::SNS::Firefish,
+        SourcePlatform::GoToSocial => megalodon::SNS::Gotosocial,
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use fediproto_sync_lib::config::{FediProtoSyncConfig, SourcePlatform};
+use megalodon::entities::Status;
+use tokio::sync::mpsc;
+
+use crate::core::create_http_client;
+
+/// The path of the user stream in the Mastodon streaming API.
+pub const USER_STREAM_PATH: &str = "/api/v1/streaming/user";
+
+/// The time to wait before the first reconnect to the streaming API.
+pub const MIN_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How long a connection has to stay up before the reconnect delay is reset.
+const STABLE_CONNECTION_DURATION: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long to wait for data from the stream before treating it as
+/// disconnected. Mastodon sends a heartbeat every 15 seconds or so.
+const STREAM_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
+/// An event sent by the Mastodon streaming API.
+#[derive(Debug, Clone)]
+pub enum StreamingEvent {
+    /// A status was posted.
+    Update(Box<Status>),
+
+    /// A status was edited.
+    StatusUpdate(Box<Status>),
+
+    /// A status was deleted.
+    Delete(String),
+}
+
+impl StreamingEvent {
+    /// Parse an event sent by the Mastodon streaming API.
+    ///
+    /// ## Arguments
+    ///
+    /// * `event` - The name of the event, like `update`.
+    /// * `data` - The data of the event.
+    ///
+    /// ## Note
+    ///
+    /// Returns `None` for events that aren't used for syncing, like
+    /// notifications.
+    pub fn parse(
+        event: &str,
+        data: &str,
+    ) -> Result<Option<Self>> {
+        match event {
+            "update" => Ok(Some(Self::Update(Box::new(serde_json::from_str(data)?)))),
+            "status.update" => Ok(Some(Self::StatusUpdate(Box::new(serde_json::from_str(
+                data,
+            )?)))),
+            "delete" => Ok(Some(Self::Delete(
+                data.trim().trim_matches('"').to_string(),
+            ))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A message sent by the stream task to the sync loop.
+#[derive(Debug, Clone)]
+pub enum StreamMessage {
+    /// The stream connected.
+    Connected,
+
+    /// The stream sent an event.
+    Event(StreamingEvent),
+
+    /// The stream disconnected. It reconnects after a delay.
+    Disconnected,
+}
+
+/// A server-sent event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSentEvent {
+    /// The name of the event.
+    pub event: String,
+
+    /// The data of the event.
+    pub data: String,
+}
+
+/// Parser for a stream of server-sent events.
+#[derive(Debug, Default)]
+pub struct ServerSentEventParser {
+    /// The bytes of the line that hasn't been completed yet.
+    buffer: Vec<u8>,
+
+    /// The name of the event being parsed.
+    event: Option<String>,
+
+    /// The data lines of the event being parsed.
+    data: Vec<String>,
+}
+
+impl ServerSentEventParser {
+    /// Add a chunk of the stream to the parser.
+    ///
+    /// ## Arguments
+    ///
+    /// * `chunk` - The chunk of the stream.
+    ///
+    /// ## Note
+    ///
+    /// Returns the events completed by the chunk. Comments, like the
+    /// heartbeats sent by Mastodon, are ignored.
+    pub fn push(
+        &mut self,
+        chunk: &[u8],
+    ) -> Vec<ServerSentEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+
+        while let Some(line_end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = self.buffer.drain(..=line_end).collect::<Vec<u8>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                let event = self.event.take();
+
+                if !self.data.is_empty() {
+                    events.push(ServerSentEvent {
+                        event: event.unwrap_or_else(|| "message".to_string()),
+                        data: self.data.join("\n"),
+                    });
+
+                    self.data.clear();
+                }
+
+                continue;
+            }
+
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+/// The delay before reconnecting to the streaming API, doubled after every
+/// failed attempt.
+#[derive(Debug, Clone)]
+pub struct ReconnectBackoff {
+    /// The delay before the first reconnect.
+    initial_delay: std::time::Duration,
+
+    /// The longest delay to wait.
+    max_delay: std::time::Duration,
+
+    /// The delay before the next reconnect.
+    next_delay: std::time::Duration,
+}
+
+impl ReconnectBackoff {
+    /// Create a new instance of the `ReconnectBackoff` struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `initial_delay` - The delay before the first reconnect.
+    /// * `max_delay` - The longest delay to wait.
+    pub fn new(
+        initial_delay: std::time::Duration,
+        max_delay: std::time::Duration,
+    ) -> Self {
+        let initial_delay = initial_delay.min(max_delay);
+
+        Self {
+            initial_delay,
+            max_delay,
+            next_delay: initial_delay,
+        }
+    }
+
+    /// Get the delay before the next reconnect, and double the one after it.
+    pub fn next_delay(&mut self) -> std::time::Duration {
+        let delay = self.next_delay;
+        self.next_delay = self.next_delay.saturating_mul(2).min(self.max_delay);
+
+        delay
+    }
+
+    /// Reset the delay after a connection stayed up.
+    pub fn reset(&mut self) {
+        self.next_delay = self.initial_delay;
+    }
+}
+
+/// A connection to the user stream of the Mastodon streaming API.
+pub struct MastodonStream {
+    /// The response the events are read from.
+    response: reqwest::Response,
+
+    /// The parser for the events.
+    parser: ServerSentEventParser,
+
+    /// The events that were parsed, but haven't been read yet.
+    events: VecDeque<ServerSentEvent>,
+}
+
+impl MastodonStream {
+    /// Connect to the user stream.
+    ///
+    /// ## Arguments
+    ///
+    /// * `http_client` - The HTTP client to use for the request.
+    /// * `base_url` - The base URL of the Mastodon server.
+    /// * `access_token` - The access token for the Mastodon account.
+    pub async fn connect(
+        http_client: &reqwest::Client,
+        base_url: &str,
+        access_token: &str,
+    ) -> Result<Self> {
+        let response = http_client
+            .get(format!(
+                "{}{}",
+                base_url.trim_end_matches('/'),
+                USER_STREAM_PATH
+            ))
+            .bearer_auth(access_token)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(Self {
+            response,
+            parser: ServerSentEventParser::default(),
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Read the next event used for syncing from the stream.
+    ///
+    /// ## Note
+    ///
+    /// Returns `None` when the server closes the stream.
+    pub async fn next_event(&mut self) -> Result<Option<StreamingEvent>> {
+        loop {
+            while let Some(server_sent_event) = self.events.pop_front() {
+                match StreamingEvent::parse(&server_sent_event.event, &server_sent_event.data) {
+                    Ok(Some(streaming_event)) => return Ok(Some(streaming_event)),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to parse '{}' event from the stream: {:#?}",
+                            server_sent_event.event,
+                            e
+                        );
+                    }
+                }
+            }
+
+            let chunk = tokio::time::timeout(STREAM_IDLE_TIMEOUT, self.response.chunk())
+                .await
+                .map_err(|_| anyhow::anyhow!("The stream stopped sending data."))??;
+
+            match chunk {
+                Some(chunk) => self.events.extend(self.parser.push(&chunk)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Check if the streaming API is used for an account pair.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+///
+/// ## Note
+///
+/// Only Mastodon serves the user stream as server-sent events, so other
+/// platforms fall back to polling.
+pub fn is_streaming_enabled(config: &FediProtoSyncConfig) -> bool {
+    if !config.mastodon_streaming {
+        return false;
+    }
+
+    if config.source_platform != SourcePlatform::Mastodon {
+        tracing::warn!(
+            "Streaming isn't supported for '{}', falling back to polling.",
+            config.source_platform
+        );
+
+        return false;
+    }
+
+    true
+}
+
+/// Spawn a task that keeps a connection to the user stream open, reconnecting
+/// with an exponential backoff when it drops.
+///
+/// ## Arguments
+///
+/// * `config` - The config for the account pair.
+/// * `access_token` - The access token for the Mastodon account.
+///
+/// ## Note
+///
+/// The task stops once the receiver is dropped.
+pub fn spawn_user_stream(
+    config: &FediProtoSyncConfig,
+    access_token: String,
+) -> Result<mpsc::UnboundedReceiver<StreamMessage>> {
+    let http_client = create_http_client(config)?;
+    let base_url = format!("https://{}", config.mastodon_server);
+    let max_reconnect_delay = config.streaming_max_reconnect_delay;
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut backoff = ReconnectBackoff::new(MIN_RECONNECT_DELAY, max_reconnect_delay);
+
+        loop {
+            match MastodonStream::connect(&http_client, &base_url, &access_token).await {
+                Ok(mut stream) => {
+                    tracing::info!("Connected to the Mastodon streaming API.");
+                    let connected_at = tokio::time::Instant::now();
+
+                    if sender.send(StreamMessage::Connected).is_err() {
+                        return;
+                    }
+
+                    loop {
+                        match stream.next_event().await {
+                            Ok(Some(streaming_event)) => {
+                                if sender.send(StreamMessage::Event(streaming_event)).is_err() {
+                                    return;
+                                }
+                            }
+
+                            Ok(None) => {
+                                tracing::warn!("The Mastodon streaming API closed the stream.");
+                                break;
+                            }
+
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Lost the connection to the Mastodon streaming API: {:#?}",
+                                    e
+                                );
+                                break;
+                            }
+                        }
+                    }
+
+                    if connected_at.elapsed() >= STABLE_CONNECTION_DURATION {
+                        backoff.reset();
+                    }
+
+                    if sender.send(StreamMessage::Disconnected).is_err() {
+                        return;
+                    }
+                }
+
+                Err(e) => {
+                    tracing::warn!("Failed to connect to the Mastodon streaming API: {:#?}", e);
+                }
+            }
+
+            let reconnect_delay = backoff.next_delay();
+
+            tracing::info!(
+                "Reconnecting to the Mastodon streaming API in '{}' seconds.",
+                reconnect_delay.as_secs()
+            );
+
+            tokio::time::sleep(reconnect_delay).await;
+        }
+    });
+
+    Ok(receiver)
+}
+
+/// Wait for the next message from the stream task.
+///
+/// ## Arguments
+///
+/// * `receiver` - The receiver for the stream task, if streaming is enabled.
+///
+/// ## Note
+///
+/// Never completes if streaming isn't enabled, so it can be used in
+/// `tokio::select!` next to the sync interval.
+pub async fn next_stream_message(
+    receiver: &mut Option<mpsc::UnboundedReceiver<StreamMessage>>
+) -> Option<StreamMessage> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use atrium_api::xrpc::{self, error::XrpcErrorKind};
+use fediproto_sync_lib::error::{AuthenticationSource, ErrorClass, FediProtoSyncError};
+
+/// Convert an error returned by the XRPC client to a `FediProtoSyncError`,
+/// keeping the HTTP status and the name of the XRPC error.
+///
+/// ## Arguments
+///
+/// * `error` - The error returned by the XRPC client.
+pub fn from_xrpc_error<E>(error: xrpc::Error<E>) -> FediProtoSyncError
+where
+    E: std::fmt::Debug + serde::Serialize,
+    xrpc::Error<E>: std::error::Error + Send + Sync + 'static,
+{
+    let (status, error_name, message) = match &error {
+        xrpc::Error::XrpcResponse(response_error) => {
+            let (error_name, message) = match &response_error.error {
+                Some(XrpcErrorKind::Undefined(error_body)) => {
+                    (error_body.error.clone(), error_body.message.clone())
+                }
+
+                // The errors defined by a lexicon are serialized like the
+                // error body they were parsed from, with `error` and
+                // `message` fields.
+                Some(XrpcErrorKind::Custom(custom_error)) => {
+                    let error_body = serde_json::to_value(custom_error).ok();
+                    let error_field = |field: &str| {
+                        error_body
+                            .as_ref()
+                            .and_then(|error_body| error_body.get(field))
+                            .and_then(|value| value.as_str())
+                            .map(|value| value.to_string())
+                    };
+
+                    (error_field("error"), error_field("message"))
+                }
+
+                None => (None, None),
+            };
+
+            (Some(response_error.status.as_u16()), error_name, message)
+        }
+
+        _ => (None, None, None),
+    };
+
+    FediProtoSyncError::XrpcError {
+        status,
+        error_name,
+        message,
+        source: Arc::new(error),
+    }
+}
+
+/// Convert an error returned by a HTTP client to a `FediProtoSyncError`,
+/// keeping the HTTP status.
+///
+/// ## Arguments
+///
+/// * `service` - The service the request was sent to.
+/// * `error` - The error returned by the HTTP client.
+pub fn from_http_error(
+    service: AuthenticationSource,
+    error: reqwest::Error,
+) -> FediProtoSyncError {
+    FediProtoSyncError::HttpError {
+        service,
+        status: error.status().map(|status| status.as_u16()),
+        source: Arc::new(error),
+    }
+}
+
+/// Classify an error, to decide if the action that failed should be tried
+/// again.
+///
+/// ## Arguments
+///
+/// * `error` - The error to classify.
+///
+/// ## Note
+///
+/// The first `FediProtoSyncError` in the chain of the error decides its
+/// class. Errors that can't be classified are treated as transient, so they're
+/// retried.
+pub fn classify_error(error: &anyhow::Error) -> ErrorClass {
+    for cause in error.chain() {
+        if let Some(sync_error) = cause.downcast_ref::<FediProtoSyncError>() {
+            return sync_error.class();
+        }
+
+        if let Some(http_error) = cause.downcast_ref::<reqwest::Error>() {
+            return ErrorClass::from_http_status(http_error.status().map(|status| status.as_u16()));
+        }
+    }
+
+    ErrorClass::Transient
+}
+
+/// Pick the error that matters most for retrying a post that failed to sync
+/// to more than one destination.
+///
+/// ## Arguments
+///
+/// * `errors` - The errors the post failed to sync with.
+///
+/// ## Note
+///
+/// Auth errors come first, then transient errors, so a post isn't given up on
+/// because one destination failed permanently while another can still be
+/// retried.
+pub fn most_relevant_error(errors: Vec<anyhow::Error>) -> Option<anyhow::Error> {
+    errors
+        .into_iter()
+        .max_by_key(|error| match classify_error(error) {
+            ErrorClass::Permanent => 0,
+            ErrorClass::Transient => 1,
+            ErrorClass::Auth => 2,
+        })
+}
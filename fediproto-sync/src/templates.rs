@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// The strings added to synced posts by FediProto Sync.
+///
+/// ## Note
+///
+/// Templates can use these placeholders:
+///
+/// * `{url}` - The URL of the post on Mastodon.
+/// * `{author}` - The username of the account that posted the post.
+/// * `{title}` - The title of the link card, for boosts.
+/// * `{language}` - The language of the post.
+///
+/// For `reply_title`, the placeholders are for the post being replied to.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct TemplateSet {
+    /// Added to the end of a truncated post.
+    pub read_more: String,
+
+    /// Added where a truncated post is cut off.
+    pub ellipsis: String,
+
+    /// The title of the link card for a boost.
+    pub boost_title: String,
+
+    /// The title of the link card to the post a reply is replying to.
+    pub reply_title: String,
+
+    /// The title of the link card for a video that can't be uploaded.
+    pub video_link_title: String,
+
+    /// The description of the link card for a video that can't be uploaded.
+    pub video_link_description: String,
+}
+
+impl Default for TemplateSet {
+    fn default() -> Self {
+        Self {
+            read_more: "\n\nRead more: {url}".to_string(),
+            ellipsis: "[...]".to_string(),
+            boost_title: "{title} / 🚀 Boost".to_string(),
+            reply_title: "↩️ Replying to @{author}".to_string(),
+            video_link_title: "View video on Mastodon".to_string(),
+            video_link_description: "Check out this video posted by @{author}!".to_string(),
+        }
+    }
+}
+
+/// Templates for a language that replace the default templates.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TemplateSetOverrides {
+    /// Added to the end of a truncated post.
+    pub read_more: Option<String>,
+
+    /// Added where a truncated post is cut off.
+    pub ellipsis: Option<String>,
+
+    /// The title of the link card for a boost.
+    pub boost_title: Option<String>,
+
+    /// The title of the link card to the post a reply is replying to.
+    pub reply_title: Option<String>,
+
+    /// The title of the link card for a video that can't be uploaded.
+    pub video_link_title: Option<String>,
+
+    /// The description of the link card for a video that can't be uploaded.
+    pub video_link_description: Option<String>,
+}
+
+/// The templates for synced posts, loaded from the post rules file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PostTemplates {
+    /// The templates used when there are none for the language of a post.
+    #[serde(flatten)]
+    pub default: TemplateSet,
+
+    /// Templates for specific languages, like `de` or `pt-BR`.
+    pub languages: HashMap<String, TemplateSetOverrides>,
+}
+
+impl PostTemplates {
+    /// Get the templates for the language of a post.
+    ///
+    /// ## Arguments
+    ///
+    /// * `language` - The language of the post, if known.
+    ///
+    /// ## Note
+    ///
+    /// Templates for a language with a region, like `pt-BR`, are used before
+    /// templates for the language without one, like `pt`. Any templates not
+    /// set for the language are taken from the defaults.
+    pub fn for_language(
+        &self,
+        language: Option<&str>,
+    ) -> TemplateSet {
+        let language = match language {
+            Some(language) => language,
+            None => return self.default.clone(),
+        };
+
+        let primary_language = language.split('-').next().unwrap_or(language);
+
+        let overrides = self
+            .find_overrides(language)
+            .or_else(|| self.find_overrides(primary_language));
+
+        match overrides {
+            Some(overrides) => TemplateSet {
+                read_more: overrides
+                    .read_more
+                    .clone()
+                    .unwrap_or_else(|| self.default.read_more.clone()),
+                ellipsis: overrides
+                    .ellipsis
+                    .clone()
+                    .unwrap_or_else(|| self.default.ellipsis.clone()),
+                boost_title: overrides
+                    .boost_title
+                    .clone()
+                    .unwrap_or_else(|| self.default.boost_title.clone()),
+                reply_title: overrides
+                    .reply_title
+                    .clone()
+                    .unwrap_or_else(|| self.default.reply_title.clone()),
+                video_link_title: overrides
+                    .video_link_title
+                    .clone()
+                    .unwrap_or_else(|| self.default.video_link_title.clone()),
+                video_link_description: overrides
+                    .video_link_description
+                    .clone()
+                    .unwrap_or_else(|| self.default.video_link_description.clone()),
+            },
+
+            None => self.default.clone(),
+        }
+    }
+
+    /// Find the templates for a language.
+    ///
+    /// ## Arguments
+    ///
+    /// * `language` - The language to find templates for.
+    fn find_overrides(
+        &self,
+        language: &str,
+    ) -> Option<&TemplateSetOverrides> {
+        self.languages
+            .iter()
+            .find(|(template_language, _)| template_language.eq_ignore_ascii_case(language))
+            .map(|(_, overrides)| overrides)
+    }
+}
+
+/// The values for the placeholders in templates.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    /// The URL of the post on Mastodon.
+    pub url: String,
+
+    /// The username of the account that posted the post.
+    pub author: String,
+
+    /// The title of the link card.
+    pub title: String,
+
+    /// The language of the post.
+    pub language: String,
+}
+
+impl TemplateContext {
+    /// Create the template context for a Mastodon status.
+    ///
+    /// ## Arguments
+    ///
+    /// * `status` - The Mastodon status.
+    pub fn from_mastodon_status(status: &megalodon::entities::Status) -> Self {
+        Self {
+            url: status.url.clone().unwrap_or_else(|| status.uri.clone()),
+            author: status.account.username.clone(),
+            title: String::new(),
+            language: status.language.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Set the title of the link card.
+    ///
+    /// ## Arguments
+    ///
+    /// * `title` - The title of the link card.
+    pub fn with_title(
+        mut self,
+        title: &str,
+    ) -> Self {
+        self.title = title.to_string();
+
+        self
+    }
+
+    /// Fill in the placeholders in a template.
+    ///
+    /// ## Arguments
+    ///
+    /// * `template` - The template to fill in.
+    ///
+    /// ## Note
+    ///
+    /// The template is filled in with a single pass, so placeholders in the
+    /// filled in values, like a link title containing `{url}`, are kept as
+    /// they are. Unknown placeholders are kept as well.
+    pub fn render(
+        &self,
+        template: &str,
+    ) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut remaining = template;
+
+        while let Some(start) = remaining.find('{') {
+            rendered.push_str(&remaining[..start]);
+            remaining = &remaining[start..];
+
+            let value = remaining.find('}').and_then(|end| {
+                let value = match &remaining[1..end] {
+                    "url" => &self.url,
+                    "author" => &self.author,
+                    "title" => &self.title,
+                    "language" => &self.language,
+                    _ => return None,
+                };
+
+                Some((value, end))
+            });
+
+            match value {
+                Some((value, end)) => {
+                    rendered.push_str(value);
+                    remaining = &remaining[end + 1..];
+                }
+                None => {
+                    rendered.push('{');
+                    remaining = &remaining[1..];
+                }
+            }
+        }
+
+        rendered.push_str(remaining);
+
+        rendered
+    }
+}
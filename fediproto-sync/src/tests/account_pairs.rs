@@ -0,0 +1,295 @@
+use diesel::Connection;
+use fediproto_sync_db::{
+    AnyConnection,
+    models::{
+        NewMastodonPost, NewMastodonPostDeadLetter, NewMastodonPostFilterDecision,
+        NewMastodonPostRetryQueueItem, NewSyncedPostBlueSkyData,
+    },
+};
+use fediproto_sync_lib::error::ErrorClass;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::{account_pairs::AccountPairs, bsky_post_sync::BLUESKY_DESTINATION};
+
+/// The ID of the post both account pairs have a record for.
+const SHARED_POST_ID: &str = "1";
+
+/// Create an in-memory database with the migrations applied.
+fn migrated_db() -> AnyConnection {
+    let mut db_connection = AnyConnection::establish(":memory:").unwrap();
+    fediproto_sync_db::core::run_migrations(&mut db_connection).unwrap();
+
+    db_connection
+}
+
+/// Tests to ensure account pairs are parsed with the values that aren't set
+/// left empty.
+#[rstest]
+fn account_pairs__parses_pairs() {
+    let account_pairs = AccountPairs::from_toml_str(
+        r#"
+        [[pair]]
+        id = "org"
+        mastodon_server = "mastodon.example"
+        bluesky_handle = "org.bsky.social"
+        bluesky_app_password = "app-password"
+        sync_interval_seconds = 600
+        post_rules_file = "org-rules.toml"
+
+        [[pair]]
+        id = "personal"
+        bluesky_handle = "someone.bsky.social"
+        bluesky_app_password = "app-password"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(account_pairs.pairs.len(), 2);
+
+    let org_pair = &account_pairs.pairs[0];
+    assert_eq!(org_pair.id, "org");
+    assert_eq!(
+        org_pair.mastodon_server.as_deref(),
+        Some("mastodon.example")
+    );
+    assert_eq!(org_pair.sync_interval_seconds, Some(600));
+    assert_eq!(
+        org_pair.post_rules_file.as_deref(),
+        Some(std::path::Path::new("org-rules.toml"))
+    );
+
+    let personal_pair = &account_pairs.pairs[1];
+    assert_eq!(personal_pair.id, "personal");
+    assert_eq!(personal_pair.mastodon_server, None);
+    assert_eq!(personal_pair.sync_interval_seconds, None);
+}
+
+/// Tests to ensure an empty file has no account pairs.
+#[rstest]
+fn account_pairs__parses_empty_file() {
+    let account_pairs = AccountPairs::from_toml_str("").unwrap();
+
+    assert!(account_pairs.pairs.is_empty());
+}
+
+/// Tests to ensure account pair IDs must be unique, not empty, and not the ID
+/// of the pair configured by the environment variables.
+#[rstest]
+#[case::duplicate(&["org", "org"])]
+#[case::reserved(&["default"])]
+#[case::empty(&[" "])]
+fn account_pairs__rejects_invalid_ids(#[case] ids: &[&str]) {
+    let pairs = ids
+        .iter()
+        .map(|id| {
+            format!(
+                "[[pair]]\nid = \"{}\"\nbluesky_handle = \"someone.bsky.social\"\nbluesky_app_password = \"app-password\"\n",
+                id
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    assert!(AccountPairs::from_toml_str(&pairs).is_err());
+}
+
+/// Tests to ensure a post synced by one account pair isn't treated as synced
+/// by another pair with the same post ID.
+#[rstest]
+fn account_pairs__scopes_synced_posts() {
+    let db_connection = &mut migrated_db();
+
+    let mut post_item = mastodon_status("<p>Hello!</p>");
+    post_item.id = SHARED_POST_ID.to_string();
+
+    fediproto_sync_db::operations::insert_new_synced_mastodon_post(
+        db_connection,
+        &NewMastodonPost::new("org", BLUESKY_DESTINATION, &post_item, None, None),
+        &NewSyncedPostBlueSkyData::new(
+            "org",
+            BLUESKY_DESTINATION,
+            SHARED_POST_ID,
+            "bafyreib2rxk3rh6kzwq",
+            "at://did:plc:org/app.bsky.feed.post/1",
+        ),
+    )
+    .unwrap();
+
+    assert!(
+        fediproto_sync_db::operations::check_synced_mastodon_post_exists(
+            db_connection,
+            "org",
+            BLUESKY_DESTINATION,
+            SHARED_POST_ID,
+        )
+    );
+    assert!(
+        !fediproto_sync_db::operations::check_synced_mastodon_post_exists(
+            db_connection,
+            "personal",
+            BLUESKY_DESTINATION,
+            SHARED_POST_ID,
+        )
+    );
+    assert!(
+        fediproto_sync_db::operations::get_synced_mastodon_post_destinations(
+            db_connection,
+            "personal",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_empty()
+    );
+    assert!(
+        fediproto_sync_db::operations::find_bluesky_data_by_mastodon_post_id(
+            db_connection,
+            "personal",
+            BLUESKY_DESTINATION,
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_none()
+    );
+}
+
+/// Tests to ensure the filter decisions of account pairs with the same post
+/// ID are kept and deleted separately.
+#[rstest]
+fn account_pairs__scopes_filter_decisions() {
+    let db_connection = &mut migrated_db();
+
+    for account_pair_id in ["org", "personal"] {
+        fediproto_sync_db::operations::upsert_mastodon_post_filter_decision(
+            db_connection,
+            &NewMastodonPostFilterDecision::new(account_pair_id, SHARED_POST_ID, "hold", None),
+        )
+        .unwrap();
+    }
+
+    fediproto_sync_db::operations::delete_mastodon_post_filter_decision(
+        db_connection,
+        "org",
+        SHARED_POST_ID,
+    )
+    .unwrap();
+
+    assert!(
+        fediproto_sync_db::operations::get_mastodon_post_filter_decision_by_post_id(
+            db_connection,
+            "org",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_none()
+    );
+    assert!(
+        fediproto_sync_db::operations::get_mastodon_post_filter_decision_by_post_id(
+            db_connection,
+            "personal",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_some()
+    );
+}
+
+/// Tests to ensure the retry queue items and dead letters of account pairs
+/// with the same post ID are kept and deleted separately.
+#[rstest]
+fn account_pairs__scopes_retries_and_dead_letters() {
+    let db_connection = &mut migrated_db();
+    let now = chrono::Utc::now().naive_utc();
+
+    for account_pair_id in ["org", "personal"] {
+        fediproto_sync_db::operations::insert_mastodon_post_retry_queue_item(
+            db_connection,
+            &NewMastodonPostRetryQueueItem::new(
+                account_pair_id,
+                SHARED_POST_ID,
+                "Timed out.",
+                ErrorClass::Transient,
+                now,
+            ),
+        )
+        .unwrap();
+    }
+
+    let org_retry_item =
+        fediproto_sync_db::operations::get_mastodon_post_retry_queue_item_by_post_id(
+            db_connection,
+            "org",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(org_retry_item.account_pair_id, "org");
+
+    fediproto_sync_db::operations::update_mastodon_post_retry_queue_item(
+        db_connection,
+        &org_retry_item,
+        None,
+        ErrorClass::Transient,
+        now,
+    )
+    .unwrap();
+
+    let personal_retry_item =
+        fediproto_sync_db::operations::get_mastodon_post_retry_queue_item_by_post_id(
+            db_connection,
+            "personal",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(personal_retry_item.retry_count, 0);
+
+    fediproto_sync_db::operations::insert_mastodon_post_dead_letter(
+        db_connection,
+        &NewMastodonPostDeadLetter::new(
+            "org",
+            SHARED_POST_ID,
+            "Invalid record.",
+            ErrorClass::Permanent,
+            None,
+            2,
+        ),
+    )
+    .unwrap();
+
+    assert!(
+        fediproto_sync_db::operations::get_mastodon_post_retry_queue_item_by_post_id(
+            db_connection,
+            "org",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_none()
+    );
+    assert_eq!(
+        fediproto_sync_db::operations::get_mastodon_post_retry_queue_items(
+            db_connection,
+            "personal",
+        )
+        .unwrap(),
+        vec![personal_retry_item]
+    );
+    assert_eq!(
+        fediproto_sync_db::operations::delete_mastodon_post_dead_letter(
+            db_connection,
+            "personal",
+            SHARED_POST_ID,
+        )
+        .unwrap(),
+        0
+    );
+    assert!(
+        fediproto_sync_db::operations::get_mastodon_post_dead_letter_by_post_id(
+            db_connection,
+            "org",
+            SHARED_POST_ID,
+        )
+        .unwrap()
+        .is_some()
+    );
+}
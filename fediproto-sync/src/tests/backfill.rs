@@ -0,0 +1,102 @@
+use std::{cmp::Ordering, time::Duration};
+
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::backfill::{BackfillOptions, compare_post_ids, posts_after_cursor};
+
+/// Create backfill options with an hourly limit.
+///
+/// ## Arguments
+///
+/// * `max_posts_per_hour` - The maximum number of posts to sync per hour.
+fn backfill_options(max_posts_per_hour: u32) -> BackfillOptions {
+    BackfillOptions {
+        since: chrono::DateTime::UNIX_EPOCH,
+        until: chrono::Utc::now(),
+        max_posts_per_hour,
+        dry_run: false,
+    }
+}
+
+/// Create a post with an ID.
+///
+/// ## Arguments
+///
+/// * `id` - The ID of the post.
+fn post(id: &str) -> megalodon::entities::Status {
+    let mut status = mastodon_status("<p>An old post.</p>");
+    status.id = id.to_string();
+
+    status
+}
+
+/// Tests to ensure posts are spaced out to stay under the hourly limit.
+#[rstest]
+#[case(0, Duration::ZERO)]
+#[case(1, Duration::from_secs(3600))]
+#[case(30, Duration::from_secs(120))]
+#[case(3600, Duration::from_secs(1))]
+fn backfill_options__post_interval(
+    #[case] max_posts_per_hour: u32,
+    #[case] expected: Duration,
+) {
+    assert_eq!(
+        backfill_options(max_posts_per_hour).post_interval(),
+        expected
+    );
+}
+
+/// Tests to ensure post IDs are compared numerically.
+#[rstest]
+#[case("99", "100", Ordering::Less)]
+#[case("113000000000000002", "113000000000000001", Ordering::Greater)]
+#[case("113000000000000001", "113000000000000001", Ordering::Equal)]
+fn compare_post_ids__compares_numerically(
+    #[case] a: &str,
+    #[case] b: &str,
+    #[case] expected: Ordering,
+) {
+    assert_eq!(compare_post_ids(a, b), expected);
+}
+
+/// Tests to ensure the IDs of platforms that don't use numeric IDs are
+/// compared by the time they were created.
+#[rstest]
+#[case::pleroma("AoTxAA4x2fSYr8gTDE", "AoTxAB1bBSxNc0yPVY", Ordering::Less)]
+#[case::gotosocial(
+    "01JBZ8Q4X6YV3T2M9K7N5R1WQC",
+    "01JBZ9A0ZB4M3Q6T8V2X5Y7N1D",
+    Ordering::Less
+)]
+#[case::firefish("9wbxq3k0d1hbz2kf", "9wbxq2h7c0gay1je", Ordering::Greater)]
+fn compare_post_ids__compares_platform_ids(
+    #[case] a: &str,
+    #[case] b: &str,
+    #[case] expected: Ordering,
+) {
+    assert_eq!(compare_post_ids(a, b), expected);
+}
+
+/// Tests to ensure a resumed backfill only includes the posts after the
+/// cursor.
+#[rstest]
+#[case::no_cursor(None, &["98", "99", "100", "101"])]
+#[case::resumed(Some("99"), &["100", "101"])]
+#[case::finished(Some("101"), &[])]
+fn posts_after_cursor__skips_processed_posts(
+    #[case] last_post_id: Option<&str>,
+    #[case] expected: &[&str],
+) {
+    let posts = vec![post("98"), post("99"), post("100"), post("101")];
+
+    let remaining_posts = posts_after_cursor(posts, last_post_id);
+
+    assert_eq!(
+        remaining_posts
+            .iter()
+            .map(|post| post.id.as_str())
+            .collect::<Vec<&str>>(),
+        expected
+    );
+}
@@ -0,0 +1,121 @@
+use atrium_api::com;
+use fediproto_sync_lib::error::{ErrorClass, FediProtoSyncError};
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::bsky_post_sync::{get_create_result, post_record_key};
+
+/// The URI of the created post in the `applyWrites` responses.
+const CREATED_POST_URI: &str = "at://did:plc:example/app.bsky.feed.post/3lcakpyaw22x2";
+
+/// Parse the response of an `applyWrites` request.
+///
+/// ## Arguments
+///
+/// * `response` - The JSON of the response.
+fn apply_writes_output(
+    response: serde_json::Value
+) -> com::atproto::repo::apply_writes::OutputData {
+    serde_json::from_value(response).expect("Failed to parse the applyWrites response")
+}
+
+/// Create a Mastodon status with an ID and creation time.
+///
+/// ## Arguments
+///
+/// * `id` - The ID of the status.
+/// * `created_at` - The time the status was created, in RFC 3339 format.
+fn status_with_id(
+    id: &str,
+    created_at: &str,
+) -> megalodon::entities::Status {
+    let mut status = mastodon_status("<p>Hello world!</p>");
+
+    status.id = id.to_string();
+    status.created_at = chrono::DateTime::parse_from_rfc3339(created_at)
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+
+    status
+}
+
+/// Tests to ensure the record key of a post is a TID derived from the status.
+#[rstest]
+#[case("113000000000000001", "2024-12-01T12:00:00Z", "3lcakpyaw22x2")]
+#[case("113000000000000002", "2024-12-01T12:00:00Z", "3lcakpyaw22yw")]
+#[case("113000000000000001", "2026-10-18T12:00:00Z", "3my5klkhg22x2")]
+fn post_record_key__is_derived_from_status(
+    #[case] id: &str,
+    #[case] created_at: &str,
+    #[case] expected_record_key: &str,
+) {
+    let record_key = post_record_key(&status_with_id(id, created_at)).unwrap();
+
+    assert_eq!(record_key.as_str(), expected_record_key);
+}
+
+/// Tests to ensure the record key of a post is the same every time, so a
+/// retry finds the post it already published.
+#[rstest]
+fn post_record_key__is_deterministic() {
+    let status = status_with_id("113000000000000001", "2024-12-01T12:00:00Z");
+
+    assert_eq!(
+        post_record_key(&status).unwrap().as_str(),
+        post_record_key(&status.clone()).unwrap().as_str()
+    );
+}
+
+/// Tests to ensure the record keys of posts sort by the time the posts were
+/// created.
+#[rstest]
+fn post_record_key__sorts_by_created_time() {
+    let older_key = post_record_key(&status_with_id(
+        "113000000000000009",
+        "2024-12-01T12:00:00Z",
+    ))
+    .unwrap();
+    let newer_key = post_record_key(&status_with_id(
+        "113000000000000001",
+        "2024-12-01T12:00:01Z",
+    ))
+    .unwrap();
+
+    assert!(older_key.as_str() < newer_key.as_str());
+}
+
+/// Tests to ensure the result of creating a post is read from the response.
+#[rstest]
+fn get_create_result__create_result() {
+    let apply_writes_result = apply_writes_output(serde_json::json!({
+        "results": [{
+            "$type": "com.atproto.repo.applyWrites#createResult",
+            "uri": CREATED_POST_URI,
+            "cid": "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm"
+        }]
+    }));
+
+    let create_result = get_create_result(&apply_writes_result).unwrap();
+
+    assert_eq!(create_result.uri, CREATED_POST_URI);
+}
+
+/// Tests to ensure an unexpected response is returned as a permanent error,
+/// instead of panicking.
+#[rstest]
+#[case::no_results(serde_json::json!({}))]
+#[case::empty_results(serde_json::json!({ "results": [] }))]
+#[case::delete_result(serde_json::json!({
+    "results": [{ "$type": "com.atproto.repo.applyWrites#deleteResult" }]
+}))]
+fn get_create_result__unexpected_response(#[case] response: serde_json::Value) {
+    let apply_writes_result = apply_writes_output(response);
+
+    let error = get_create_result(&apply_writes_result).unwrap_err();
+
+    assert!(matches!(
+        error,
+        FediProtoSyncError::UnexpectedResponseError { .. }
+    ));
+    assert_eq!(error.class(), ErrorClass::Permanent);
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rstest::*;
+
+use crate::bsky_session::*;
+
+/// Create an unsigned JWT with a payload.
+///
+/// ## Arguments
+///
+/// * `payload` - The JSON payload of the JWT.
+fn jwt(payload: &str) -> String {
+    let encode = |value: &str| {
+        openssl::base64::encode_block(value.as_bytes())
+            .replace('+', "-")
+            .replace('/', "_")
+            .trim_end_matches('=')
+            .to_string()
+    };
+
+    format!(
+        "{}.{}.signature",
+        encode(r#"{"typ":"at+jwt","alg":"ES256K"}"#),
+        encode(payload)
+    )
+}
+
+/// Parse a time in RFC 3339 format.
+///
+/// ## Arguments
+///
+/// * `time` - The time to parse.
+fn time(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// Tests to ensure the DID and expiry are read from the JWTs of a session.
+#[rstest]
+#[case::access_token(
+    r#"{"scope":"com.atproto.appPass","sub":"did:plc:ewvi7nxzyoun6zhxrhs64oiz","iat":1792317600,"exp":1792324800,"aud":"did:web:pds.example.com"}"#,
+    Some("did:plc:ewvi7nxzyoun6zhxrhs64oiz"),
+    Some("2026-10-18T12:00:00Z")
+)]
+#[case::no_expiry(
+    r#"{"sub":"did:plc:ewvi7nxzyoun6zhxrhs64oiz"}"#,
+    Some("did:plc:ewvi7nxzyoun6zhxrhs64oiz"),
+    None
+)]
+fn session_token_claims__from_jwt(
+    #[case] payload: &str,
+    #[case] expected_sub: Option<&str>,
+    #[case] expected_expires_at: Option<&str>,
+) {
+    let claims = SessionTokenClaims::from_jwt(&jwt(payload)).unwrap();
+
+    assert_eq!(claims.sub.as_deref(), expected_sub);
+    assert_eq!(claims.expires_at(), expected_expires_at.map(time));
+}
+
+/// Tests to ensure tokens that aren't JWTs aren't read.
+#[rstest]
+#[case("")]
+#[case("not-a-jwt")]
+#[case("header.bm90IGpzb24.signature")]
+fn session_token_claims__invalid_jwt(#[case] token: &str) {
+    assert_eq!(SessionTokenClaims::from_jwt(token), None);
+}
+
+/// Tests to ensure the sessions of different accounts are cached separately.
+#[rstest]
+#[case("user.bsky.social", "bluesky:user.bsky.social")]
+#[case("User.Example.com", "bluesky:user.example.com")]
+fn session_service_name__is_per_account(
+    #[case] bluesky_handle: &str,
+    #[case] expected: &str,
+) {
+    assert_eq!(session_service_name(bluesky_handle), expected);
+}
+
+/// Tests to ensure sessions are refreshed once their access token is close to
+/// expiring.
+#[rstest]
+#[case::expires_later(Some("2026-10-18T14:00:00Z"), false)]
+#[case::expires_within_margin(Some("2026-10-18T12:10:00Z"), true)]
+#[case::expired(Some("2026-10-18T11:00:00Z"), true)]
+#[case::unknown_expiry(None, false)]
+fn is_refresh_due__checks_expiry(
+    #[case] expires_at: Option<&str>,
+    #[case] expected: bool,
+) {
+    assert_eq!(
+        is_refresh_due(
+            expires_at.map(time),
+            time("2026-10-18T12:00:00Z"),
+            Duration::from_secs(15 * 60)
+        ),
+        expected
+    );
+}
@@ -0,0 +1,76 @@
+/// A Mastodon status to base test statuses on.
+const MASTODON_STATUS_JSON: &str = include_str!("fixtures/mastodon_status.json");
+
+/// A status from a Pleroma server.
+pub const PLEROMA_STATUS_JSON: &str = include_str!("fixtures/pleroma_status.json");
+
+/// A status from a GoToSocial server.
+pub const GOTOSOCIAL_STATUS_JSON: &str = include_str!("fixtures/gotosocial_status.json");
+
+/// A status from a Friendica server.
+pub const FRIENDICA_STATUS_JSON: &str = include_str!("fixtures/friendica_status.json");
+
+/// A status from a Firefish server.
+pub const FIREFISH_STATUS_JSON: &str = include_str!("fixtures/firefish_status.json");
+
+/// Create a Mastodon status with the given HTML content.
+///
+/// ## Arguments
+///
+/// * `content` - The HTML content of the status.
+pub fn mastodon_status(content: &str) -> megalodon::entities::Status {
+    let mut status = serde_json::from_str::<megalodon::entities::Status>(MASTODON_STATUS_JSON)
+        .expect("Failed to parse the Mastodon status fixture");
+
+    status.content = content.to_string();
+
+    status
+}
+
+/// Create the JSON of a Mastodon status, as it's returned by the Mastodon
+/// API.
+///
+/// ## Arguments
+///
+/// * `id` - The ID of the status.
+/// * `visibility` - The visibility of the status, like `public`.
+pub fn mastodon_status_json(
+    id: &str,
+    visibility: &str,
+) -> serde_json::Value {
+    let mut status = serde_json::from_str::<serde_json::Value>(MASTODON_STATUS_JSON)
+        .expect("Failed to parse the Mastodon status fixture");
+
+    status["id"] = serde_json::Value::from(id);
+    status["visibility"] = serde_json::Value::from(visibility);
+
+    status
+}
+
+/// Parse a status fixture from a source platform.
+///
+/// ## Arguments
+///
+/// * `status_json` - The JSON of the status.
+pub fn platform_status(status_json: &str) -> megalodon::entities::Status {
+    serde_json::from_str::<megalodon::entities::Status>(status_json)
+        .expect("Failed to parse the platform status fixture")
+}
+
+/// Create a mention of a Mastodon account.
+///
+/// ## Arguments
+///
+/// * `acct` - The account being mentioned, like `someone@mastodon.social`.
+/// * `url` - The URL of the account's profile.
+pub fn mastodon_mention(
+    acct: &str,
+    url: &str,
+) -> megalodon::entities::Mention {
+    megalodon::entities::Mention {
+        id: "110000000000000002".to_string(),
+        username: acct.split('@').next().unwrap_or_default().to_string(),
+        url: url.to_string(),
+        acct: acct.to_string(),
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use diesel::Connection;
+use fediproto_sync_db::{AnyConnection, models::NewMastodonPostFilterDecision};
+use rstest::*;
+
+use super::fixtures::mastodon_status_json;
+use crate::{core::fetch_held_post, rate_limit::MastodonRateLimiter, rules::filter::FilterAction};
+
+/// The ID of the account pair the post is held for.
+const ACCOUNT_PAIR_ID: &str = "account-pair";
+
+/// The ID of the held post.
+const HELD_POST_ID: &str = "113000000000000001";
+
+/// Create a database with a held post.
+fn db_with_held_post() -> AnyConnection {
+    let mut db_connection = AnyConnection::establish(":memory:").unwrap();
+    fediproto_sync_db::core::run_migrations(&mut db_connection).unwrap();
+
+    let new_decision = NewMastodonPostFilterDecision::new(
+        ACCOUNT_PAIR_ID,
+        HELD_POST_ID,
+        FilterAction::Hold.as_str(),
+        Some("hold-rule".to_string()),
+    );
+
+    fediproto_sync_db::operations::upsert_mastodon_post_filter_decision(
+        &mut db_connection,
+        &new_decision,
+    )
+    .unwrap();
+
+    db_connection
+}
+
+/// Get the IDs of the posts that are held.
+///
+/// ## Arguments
+///
+/// * `db_connection` - The database connection.
+fn held_post_ids(db_connection: &mut AnyConnection) -> Vec<String> {
+    fediproto_sync_db::operations::get_mastodon_post_filter_decisions_by_action(
+        db_connection,
+        ACCOUNT_PAIR_ID,
+        FilterAction::Hold.as_str(),
+    )
+    .unwrap()
+    .into_iter()
+    .map(|decision| decision.mastodon_post_id)
+    .collect()
+}
+
+/// Create a megalodon client for a mocked Mastodon server.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+fn mastodon_client(server: &mockito::Server) -> Box<dyn megalodon::Megalodon + Send + Sync> {
+    megalodon::generator(
+        megalodon::SNS::Mastodon,
+        server.url(),
+        Some("access-token".to_string()),
+        None,
+    )
+    .unwrap()
+}
+
+/// Tests to ensure a held post that was fetched is returned and stays held
+/// until its filter decision is made again.
+#[rstest]
+#[tokio::test]
+async fn fetch_held_post__fetched() {
+    let mut server = mockito::Server::new_async().await;
+    let status_mock = server
+        .mock("GET", "/api/v1/statuses/113000000000000001")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(mastodon_status_json(HELD_POST_ID, "public").to_string())
+        .create_async()
+        .await;
+
+    let db_connection = &mut db_with_held_post();
+    let mastodon_client = mastodon_client(&server);
+
+    let post_item = fetch_held_post(
+        db_connection,
+        mastodon_client.as_ref(),
+        &MastodonRateLimiter::new(&server.url(), Duration::from_secs(60)),
+        ACCOUNT_PAIR_ID,
+        HELD_POST_ID,
+    )
+    .await
+    .unwrap();
+
+    status_mock.assert_async().await;
+    assert_eq!(
+        post_item.map(|post_item| post_item.id).as_deref(),
+        Some(HELD_POST_ID)
+    );
+    assert_eq!(held_post_ids(db_connection), vec![HELD_POST_ID]);
+}
+
+/// Tests to ensure a held post is only dropped when it was deleted, and stays
+/// held when it couldn't be fetched for any other reason.
+#[rstest]
+#[case::not_found(404, false)]
+#[case::gone(410, false)]
+#[case::server_error(500, true)]
+#[case::rate_limited(429, true)]
+#[tokio::test]
+async fn fetch_held_post__fetch_failed(
+    #[case] status: usize,
+    #[case] still_held: bool,
+) {
+    // A `429` spends the budget of the server, so the server isn't shared
+    // with the other tests.
+    let mut server = mockito::Server::new_with_opts_async(mockito::ServerOpts::default()).await;
+    let status_mock = server
+        .mock("GET", "/api/v1/statuses/113000000000000001")
+        .with_status(status)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"error":"Request failed"}"#)
+        .create_async()
+        .await;
+
+    let db_connection = &mut db_with_held_post();
+    let mastodon_client = mastodon_client(&server);
+
+    let post_item = fetch_held_post(
+        db_connection,
+        mastodon_client.as_ref(),
+        &MastodonRateLimiter::new(&server.url(), Duration::from_secs(60)),
+        ACCOUNT_PAIR_ID,
+        HELD_POST_ID,
+    )
+    .await
+    .unwrap();
+
+    status_mock.assert_async().await;
+    assert!(post_item.is_none());
+    assert_eq!(held_post_ids(db_connection).is_empty(), !still_held);
+}
@@ -0,0 +1,456 @@
+use diesel::Connection;
+use fediproto_sync_db::{AnyConnection, models::NewLinkMetadataCacheEntry};
+use rstest::*;
+
+use crate::link_metadata::{
+    LinkMetadata, LinkMetadataProvider, card_matches_url,
+    cardyb::CardyBClient,
+    normalize_url,
+    opengraph::{HtmlMetadata, OpenGraphExtractor},
+};
+
+/// A page with OpenGraph and Twitter card tags.
+const OPENGRAPH_PAGE: &str = r#"<html>
+<head>
+<title>Page title</title>
+<meta property="og:title" content="OpenGraph title">
+<meta property="og:description" content="OpenGraph description">
+<meta property="og:image" content="/images/thumbnail.png">
+<meta property="og:url" content="https://example.com/article">
+<meta name="twitter:title" content="Twitter title">
+<meta name="twitter:image" content="https://example.com/twitter.png">
+</head>
+<body></body>
+</html>"#;
+
+/// A page with only Twitter card tags.
+const TWITTER_CARD_PAGE: &str = r#"<html>
+<head>
+<title>Page title</title>
+<meta name="twitter:title" content="Twitter title">
+<meta name="twitter:description" content="Twitter description">
+<meta name="twitter:image" content="https://cdn.example.com/twitter.png">
+</head>
+<body></body>
+</html>"#;
+
+/// A page with only the standard tags.
+const FALLBACK_PAGE: &str = r#"<html>
+<head>
+<title>  Page title  </title>
+<meta name="description" content="Meta description">
+<link rel="canonical" href="/canonical">
+</head>
+<body></body>
+</html>"#;
+
+/// Default timeout for requests in the tests.
+const TEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The URL of the page being parsed.
+#[fixture]
+fn page_url() -> reqwest::Url {
+    reqwest::Url::parse("https://example.com/posts/1").unwrap()
+}
+
+/// A preview card generated by Mastodon.
+#[fixture]
+fn status_card() -> megalodon::entities::Card {
+    megalodon::entities::Card {
+        url: "https://www.example.com/article/".to_string(),
+        title: "Article".to_string(),
+        description: "Description".to_string(),
+        r#type: megalodon::entities::card::CardType::Link,
+        image: Some("https://files.mastodon.example/cache/preview_cards/thumb.png".to_string()),
+        author_name: None,
+        author_url: None,
+        provider_name: "".to_string(),
+        provider_url: "".to_string(),
+        html: None,
+        width: None,
+        height: None,
+        embed_url: None,
+        blurhash: None,
+    }
+}
+
+/// Tests to ensure OpenGraph tags are preferred over Twitter card tags and
+/// relative URLs are resolved.
+#[rstest]
+fn html_metadata_parse__prefers_opengraph(page_url: reqwest::Url) {
+    let html_metadata = HtmlMetadata::parse(OPENGRAPH_PAGE, &page_url);
+
+    assert_eq!(html_metadata.title.as_deref(), Some("OpenGraph title"));
+    assert_eq!(
+        html_metadata.description.as_deref(),
+        Some("OpenGraph description")
+    );
+    assert_eq!(
+        html_metadata.image.as_deref(),
+        Some("https://example.com/images/thumbnail.png")
+    );
+    assert_eq!(
+        html_metadata.url.as_deref(),
+        Some("https://example.com/article")
+    );
+}
+
+/// Tests to ensure Twitter card tags are used when there are no OpenGraph
+/// tags.
+#[rstest]
+fn html_metadata_parse__falls_back_to_twitter_card(page_url: reqwest::Url) {
+    let html_metadata = HtmlMetadata::parse(TWITTER_CARD_PAGE, &page_url);
+
+    assert_eq!(html_metadata.title.as_deref(), Some("Twitter title"));
+    assert_eq!(
+        html_metadata.description.as_deref(),
+        Some("Twitter description")
+    );
+    assert_eq!(
+        html_metadata.image.as_deref(),
+        Some("https://cdn.example.com/twitter.png")
+    );
+}
+
+/// Tests to ensure the `<title>`, `<meta>` and `<link>` tags are used when
+/// there are no OpenGraph or Twitter card tags.
+#[rstest]
+fn html_metadata_parse__falls_back_to_standard_tags(page_url: reqwest::Url) {
+    let html_metadata = HtmlMetadata::parse(FALLBACK_PAGE, &page_url);
+
+    assert_eq!(html_metadata.title.as_deref(), Some("Page title"));
+    assert_eq!(
+        html_metadata.description.as_deref(),
+        Some("Meta description")
+    );
+    assert_eq!(html_metadata.image, None);
+    assert_eq!(
+        html_metadata.url.as_deref(),
+        Some("https://example.com/canonical")
+    );
+}
+
+/// Tests to ensure missing values fall back to the requested URL instead of
+/// failing.
+#[rstest]
+fn html_metadata_into_link_metadata__missing_values(page_url: reqwest::Url) {
+    let link_metadata = HtmlMetadata::parse("<html><head></head></html>", &page_url)
+        .into_link_metadata("https://example.com/posts/1");
+
+    assert_eq!(
+        link_metadata,
+        LinkMetadata {
+            url: "https://example.com/posts/1".to_string(),
+            title: "https://example.com/posts/1".to_string(),
+            description: "".to_string(),
+            image: None,
+        }
+    );
+}
+
+/// Tests to ensure the extractor falls back to the page's oEmbed endpoint
+/// for a missing thumbnail.
+#[rstest]
+#[tokio::test]
+async fn opengraph_extractor__uses_oembed_fallback() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_html = format!(
+        r#"<html><head><title>Video</title><link rel="alternate" type="application/json+oembed" href="{}/oembed"></head></html>"#,
+        server.url()
+    );
+
+    let page_mock = server
+        .mock("GET", "/video")
+        .with_header("content-type", "text/html; charset=utf-8")
+        .with_body(page_html)
+        .create_async()
+        .await;
+
+    let oembed_mock = server
+        .mock("GET", "/oembed")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"title": "oEmbed title", "thumbnail_url": "/thumb.jpg"}"#)
+        .create_async()
+        .await;
+
+    let extractor = OpenGraphExtractor::new(reqwest::Client::new(), TEST_TIMEOUT, 1_048_576);
+
+    let page_url = format!("{}/video", server.url());
+    let link_metadata = extractor.get_link_metadata(&page_url).await.unwrap();
+
+    page_mock.assert_async().await;
+    oembed_mock.assert_async().await;
+
+    assert_eq!(link_metadata.title, "Video");
+    assert_eq!(
+        link_metadata.image,
+        Some(format!("{}/thumb.jpg", server.url()))
+    );
+}
+
+/// Tests to ensure the extractor stops reading a page after the maximum size.
+#[rstest]
+#[tokio::test]
+async fn opengraph_extractor__stops_at_max_size() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_html = format!(
+        r#"<html><head><meta property="og:title" content="Early title"></head><body>{}<meta property="og:image" content="/late.png"></body></html>"#,
+        "a".repeat(4096)
+    );
+
+    server
+        .mock("GET", "/large")
+        .with_header("content-type", "text/html")
+        .with_body(page_html)
+        .create_async()
+        .await;
+
+    let extractor = OpenGraphExtractor::new(reqwest::Client::new(), TEST_TIMEOUT, 512);
+
+    let page_url = format!("{}/large", server.url());
+    let link_metadata = extractor.get_link_metadata(&page_url).await.unwrap();
+
+    assert_eq!(link_metadata.title, "Early title");
+    assert_eq!(link_metadata.image, None);
+}
+
+/// Tests to ensure a response from CardyB is converted to `LinkMetadata`.
+#[rstest]
+#[tokio::test]
+async fn cardyb_client__parses_response() {
+    let mut server = mockito::Server::new_async().await;
+
+    let cardyb_mock = server
+        .mock("GET", "/v1/extract")
+        .match_query(mockito::Matcher::UrlEncoded(
+            "url".to_string(),
+            "https://example.com/article".to_string(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"error": "", "likely_type": "html", "url": "https://example.com/article", "title": "Article", "description": "Description", "image": "https://cardyb.bsky.app/v1/image?url=thumb"}"#,
+        )
+        .create_async()
+        .await;
+
+    let client = CardyBClient::new(
+        reqwest::Client::new(),
+        &format!("{}/v1/extract", server.url()),
+        TEST_TIMEOUT,
+    );
+
+    let link_metadata = client
+        .get_link_metadata("https://example.com/article")
+        .await
+        .unwrap();
+
+    cardyb_mock.assert_async().await;
+
+    assert_eq!(
+        link_metadata,
+        LinkMetadata {
+            url: "https://example.com/article".to_string(),
+            title: "Article".to_string(),
+            description: "Description".to_string(),
+            image: Some("https://cardyb.bsky.app/v1/image?url=thumb".to_string()),
+        }
+    );
+}
+
+/// Tests to ensure a response from CardyB with missing fields doesn't fail.
+#[rstest]
+#[tokio::test]
+async fn cardyb_client__missing_fields() {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/v1/extract")
+        .match_query(mockito::Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"error": "", "likely_type": "html", "url": "", "title": "", "image": ""}"#)
+        .create_async()
+        .await;
+
+    let client = CardyBClient::new(
+        reqwest::Client::new(),
+        &format!("{}/v1/extract", server.url()),
+        TEST_TIMEOUT,
+    );
+
+    let link_metadata = client
+        .get_link_metadata("https://example.com/article")
+        .await
+        .unwrap();
+
+    assert_eq!(link_metadata.title, "https://example.com/article");
+    assert_eq!(link_metadata.description, "");
+    assert_eq!(link_metadata.image, None);
+}
+
+/// Tests to ensure an error from CardyB is returned as an error.
+#[rstest]
+#[tokio::test]
+async fn cardyb_client__error_response() {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/v1/extract")
+        .match_query(mockito::Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"error": "Unable to generate link preview"}"#)
+        .create_async()
+        .await;
+
+    let client = CardyBClient::new(
+        reqwest::Client::new(),
+        &format!("{}/v1/extract", server.url()),
+        TEST_TIMEOUT,
+    );
+
+    let link_metadata = client
+        .get_link_metadata("https://example.com/article")
+        .await;
+
+    assert!(link_metadata.is_err());
+}
+
+/// Tests to ensure a preview card from Mastodon is converted to
+/// `LinkMetadata`, with its image used as the thumbnail.
+#[rstest]
+fn link_metadata_from_card__uses_card_values(status_card: megalodon::entities::Card) {
+    let link_metadata = LinkMetadata::from(&status_card);
+
+    assert_eq!(
+        link_metadata,
+        LinkMetadata {
+            url: "https://www.example.com/article/".to_string(),
+            title: "Article".to_string(),
+            description: "Description".to_string(),
+            image: Some("https://files.mastodon.example/cache/preview_cards/thumb.png".to_string()),
+        }
+    );
+}
+
+/// Tests to ensure a preview card without a title or image still produces
+/// usable `LinkMetadata`.
+#[rstest]
+fn link_metadata_from_card__missing_values(mut status_card: megalodon::entities::Card) {
+    status_card.title = "".to_string();
+    status_card.image = Some("".to_string());
+
+    let link_metadata = LinkMetadata::from(&status_card);
+
+    assert_eq!(link_metadata.title, "https://www.example.com/article/");
+    assert_eq!(link_metadata.image, None);
+}
+
+/// Tests to ensure a preview card is matched to a link in a post.
+#[rstest]
+#[case("https://www.example.com/article/", true)]
+#[case("https://example.com/article", true)]
+#[case("http://example.com/article#comments", true)]
+#[case("https://example.com/other-article", false)]
+#[case("https://example.com/article?page=2", false)]
+#[case("https://mastodon.example/@user/1", false)]
+#[case("not a url", false)]
+fn card_matches_url__matches_loosely(
+    status_card: megalodon::entities::Card,
+    #[case] url: &str,
+    #[case] expected: bool,
+) {
+    assert_eq!(card_matches_url(&status_card, url), expected);
+}
+
+/// Tests to ensure URLs are normalized for the link metadata cache.
+#[rstest]
+#[case(
+    "https://Example.com:443/article#comments",
+    "https://example.com/article"
+)]
+#[case(
+    "https://example.com/article?utm_source=mastodon&utm_medium=social",
+    "https://example.com/article"
+)]
+#[case(
+    "https://example.com/article?id=1&fbclid=abc",
+    "https://example.com/article?id=1"
+)]
+#[case("  https://example.com/  ", "https://example.com/")]
+#[case("not a url", "not a url")]
+fn normalize_url__is_normalized(
+    #[case] url: &str,
+    #[case] expected: &str,
+) {
+    assert_eq!(normalize_url(url), expected);
+}
+
+/// Tests to ensure a blob ref can be stored in the link metadata cache and
+/// read back.
+#[rstest]
+fn blob_ref__roundtrips_as_json() {
+    let blob_json = r#"{"$type":"blob","ref":{"$link":"bafkreibme22gw2h7y2h7tg2fhqotaqjucnbc24deqo72b6mkl2egezxhvy"},"mimeType":"image/jpeg","size":1024}"#;
+
+    let blob_ref = serde_json::from_str::<atrium_api::types::BlobRef>(blob_json).unwrap();
+    let serialized_blob_ref = serde_json::to_string(&blob_ref).unwrap();
+
+    assert_eq!(
+        serde_json::from_str::<atrium_api::types::BlobRef>(&serialized_blob_ref).unwrap(),
+        blob_ref
+    );
+}
+
+/// Tests to ensure the same link is cached separately for each BlueSky
+/// account, so a thumbnail blob is only reused by the account it was uploaded
+/// to.
+#[rstest]
+fn link_metadata_cache__keyed_by_account() {
+    let db_connection = &mut AnyConnection::establish(":memory:").unwrap();
+    fediproto_sync_db::core::run_migrations(db_connection).unwrap();
+
+    let url = "https://example.com/article";
+    let ttl = std::time::Duration::from_secs(3600);
+
+    for (did, thumbnail_blob) in [
+        ("did:plc:first", r#"{"ref":"first"}"#),
+        ("did:plc:second", r#"{"ref":"second"}"#),
+    ] {
+        let new_entry = NewLinkMetadataCacheEntry::new(url, "Title", "", None, did, ttl)
+            .with_thumbnail_blob("hash", thumbnail_blob);
+
+        fediproto_sync_db::operations::upsert_link_metadata_cache_entry(db_connection, &new_entry)
+            .unwrap();
+    }
+
+    let first_entry = fediproto_sync_db::operations::get_link_metadata_cache_entry(
+        db_connection,
+        url,
+        "did:plc:first",
+    )
+    .unwrap()
+    .unwrap();
+    let second_entry = fediproto_sync_db::operations::get_link_metadata_cache_entry(
+        db_connection,
+        url,
+        "did:plc:second",
+    )
+    .unwrap()
+    .unwrap();
+    let other_entry = fediproto_sync_db::operations::get_link_metadata_cache_entry(
+        db_connection,
+        url,
+        "did:plc:other",
+    )
+    .unwrap();
+
+    assert_eq!(
+        first_entry.thumbnail_blob.as_deref(),
+        Some(r#"{"ref":"first"}"#)
+    );
+    assert_eq!(
+        second_entry.thumbnail_blob.as_deref(),
+        Some(r#"{"ref":"second"}"#)
+    );
+    assert!(other_entry.is_none());
+}
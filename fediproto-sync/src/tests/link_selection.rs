@@ -0,0 +1,168 @@
+use fediproto_sync_lib::config::LinkCardPreference;
+use rstest::*;
+
+use super::fixtures::{mastodon_mention, mastodon_status};
+use crate::link_metadata::selection::LinkSelectionPolicy;
+
+/// A link to an article, formatted the way Mastodon formats links.
+fn article_link(url: &str) -> String {
+    format!(
+        r#"<a href="{}" target="_blank" rel="nofollow noopener" translate="no"><span class="invisible">https://</span><span class="">{}</span><span class="invisible"></span></a>"#,
+        url,
+        url.trim_start_matches("https://")
+    )
+}
+
+/// A mention of an account, formatted the way Mastodon formats mentions.
+fn mention_link(
+    username: &str,
+    url: &str,
+) -> String {
+    format!(
+        r#"<span class="h-card" translate="no"><a href="{}" class="u-url mention">@<span>{}</span></a></span>"#,
+        url, username
+    )
+}
+
+/// The default link selection policy.
+#[fixture]
+fn policy() -> LinkSelectionPolicy {
+    LinkSelectionPolicy {
+        preference: LinkCardPreference::Last,
+        override_marker: Some("🔗".to_string()),
+        blocked_domains: vec!["blocked.example".to_string()],
+        instance_host: "mastodon.example".to_string(),
+    }
+}
+
+/// Tests to ensure the article is used for "via @someone" posts instead of
+/// the mention at the end.
+#[rstest]
+fn select_link__via_mention(policy: LinkSelectionPolicy) {
+    let mut status = mastodon_status(&format!(
+        "<p>Great read {} via {}</p>",
+        article_link("https://example.com/article"),
+        mention_link("someone", "https://mastodon.social/@someone")
+    ));
+    status.mentions = vec![mastodon_mention(
+        "someone@mastodon.social",
+        "https://mastodon.social/@someone",
+    )];
+
+    assert_eq!(
+        policy.select_link(&status),
+        Some("https://example.com/article".to_string())
+    );
+}
+
+/// Tests to ensure profile links and links to the Mastodon server are
+/// skipped, even when they are the first link in the post.
+#[rstest]
+#[case(LinkCardPreference::First)]
+#[case(LinkCardPreference::Last)]
+fn select_link__skips_profiles_and_instance_links(
+    mut policy: LinkSelectionPolicy,
+    #[case] preference: LinkCardPreference,
+) {
+    policy.preference = preference;
+
+    let status = mastodon_status(&format!(
+        "<p>{} said it best in {}: {} (also on {})</p>",
+        article_link("https://other.example/@someone"),
+        article_link("https://mastodon.example/@user/112000000000000001"),
+        article_link("https://example.com/article"),
+        article_link("https://pixelfed.example/users/someone")
+    ));
+
+    assert_eq!(
+        policy.select_link(&status),
+        Some("https://example.com/article".to_string())
+    );
+}
+
+/// Tests to ensure the configured preference is used when there are multiple
+/// eligible links.
+#[rstest]
+#[case(LinkCardPreference::First, "https://example.com/first")]
+#[case(LinkCardPreference::Last, "https://example.com/last")]
+fn select_link__uses_preference(
+    mut policy: LinkSelectionPolicy,
+    #[case] preference: LinkCardPreference,
+    #[case] expected: &str,
+) {
+    policy.preference = preference;
+
+    let status = mastodon_status(&format!(
+        "<p>Compare {} with {}</p>",
+        article_link("https://example.com/first"),
+        article_link("https://example.com/last")
+    ));
+
+    assert_eq!(policy.select_link(&status), Some(expected.to_string()));
+}
+
+/// Tests to ensure a link with the override marker before it is always used.
+#[rstest]
+fn select_link__uses_override_marker(policy: LinkSelectionPolicy) {
+    let status = mastodon_status(&format!(
+        "<p>Background: 🔗 {}</p><p>Source: {}</p>",
+        article_link("https://example.com/background"),
+        article_link("https://example.com/source")
+    ));
+
+    assert_eq!(
+        policy.select_link(&status),
+        Some("https://example.com/background".to_string())
+    );
+}
+
+/// Tests to ensure the override marker is ignored when it's disabled.
+#[rstest]
+fn select_link__override_marker_disabled(mut policy: LinkSelectionPolicy) {
+    policy.override_marker = None;
+
+    let status = mastodon_status(&format!(
+        "<p>Background: 🔗 {}</p><p>Source: {}</p>",
+        article_link("https://example.com/background"),
+        article_link("https://example.com/source")
+    ));
+
+    assert_eq!(
+        policy.select_link(&status),
+        Some("https://example.com/source".to_string())
+    );
+}
+
+/// Tests to ensure links to blocked domains, and their subdomains, are never
+/// used.
+#[rstest]
+#[case("https://blocked.example/article", None)]
+#[case("https://www.blocked.example/article", None)]
+#[case(
+    "https://notblocked.example/article",
+    Some("https://notblocked.example/article")
+)]
+fn select_link__skips_blocked_domains(
+    policy: LinkSelectionPolicy,
+    #[case] url: &str,
+    #[case] expected: Option<&str>,
+) {
+    let status = mastodon_status(&format!("<p>🔗 {}</p>", article_link(url)));
+
+    assert_eq!(
+        policy.select_link(&status),
+        expected.map(|url| url.to_string())
+    );
+}
+
+/// Tests to ensure no link is selected for posts with only mentions and
+/// hashtags.
+#[rstest]
+fn select_link__no_eligible_links(policy: LinkSelectionPolicy) {
+    let status = mastodon_status(&format!(
+        r##"<p>Thanks {}! <a href="https://mastodon.example/tags/rust" class="mention hashtag" rel="tag">#<span>rust</span></a></p>"##,
+        mention_link("someone", "https://mastodon.social/@someone")
+    ));
+
+    assert_eq!(policy.select_link(&status), None);
+}
@@ -0,0 +1,437 @@
+use std::time::Duration;
+
+use mockito::Matcher;
+use rstest::*;
+
+use super::fixtures::mastodon_status_json;
+use crate::{
+    mastodon::{MastodonApiExtensions, MastodonHttpClient},
+    rate_limit::MastodonRateLimiter,
+};
+
+/// The ID of the Mastodon account to get the posts of.
+const ACCOUNT_ID: &str = "110000000000000001";
+
+/// The path of the account statuses endpoint.
+const ACCOUNT_STATUSES_PATH: &str = "/api/v1/accounts/110000000000000001/statuses";
+
+/// Create a page of public statuses, as it's returned by the Mastodon API.
+///
+/// ## Arguments
+///
+/// * `ids` - The IDs of the statuses, newest first.
+fn statuses_page(ids: &[&str]) -> String {
+    let statuses = ids
+        .iter()
+        .map(|id| mastodon_status_json(id, "public"))
+        .collect::<Vec<serde_json::Value>>();
+
+    serde_json::Value::Array(statuses).to_string()
+}
+
+/// Create a megalodon client for a mocked Mastodon server.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+fn mastodon_client(server: &mockito::Server) -> Box<dyn megalodon::Megalodon + Send + Sync> {
+    megalodon::generator(
+        megalodon::SNS::Mastodon,
+        server.url(),
+        Some("access-token".to_string()),
+        None,
+    )
+    .unwrap()
+}
+
+/// Create a rate limiter for a mocked Mastodon server.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+fn rate_limiter(server: &mockito::Server) -> MastodonRateLimiter {
+    MastodonRateLimiter::new(&server.url(), Duration::from_secs(60))
+}
+
+/// Create a client for the Mastodon API requests megalodon can't send, for a
+/// mocked Mastodon server.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+fn http_client(server: &mockito::Server) -> MastodonHttpClient {
+    MastodonHttpClient::new(reqwest::Client::new(), &server.url(), "access-token")
+}
+
+/// Mock the page of statuses after a post, as it's returned for `min_id`.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+/// * `min_id` - The ID of the post the page is after.
+/// * `limit` - The number of statuses requested.
+/// * `ids` - The IDs of the statuses in the page, newest first.
+async fn mock_page_after(
+    server: &mut mockito::Server,
+    min_id: &str,
+    limit: usize,
+    ids: &[&str],
+) -> mockito::Mock {
+    server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("min_id".into(), min_id.into()),
+            Matcher::UrlEncoded("limit".into(), limit.to_string()),
+        ]))
+        .match_header("authorization", "Bearer access-token")
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(ids))
+        .expect(1)
+        .create_async()
+        .await
+}
+
+/// Get the latest posts from a mocked Mastodon server, like the sync does.
+///
+/// ## Arguments
+///
+/// * `server` - The mocked Mastodon server.
+/// * `last_post_id` - The last post ID to get posts since.
+/// * `max_posts` - The maximum number of posts to get.
+async fn get_latest_posts(
+    server: &mockito::Server,
+    last_post_id: Option<&str>,
+    max_posts: usize,
+) -> Vec<megalodon::entities::Status> {
+    let rate_limiter = rate_limiter(server);
+
+    let first_post_id_over_limit = match last_post_id {
+        Some(last_post_id) => http_client(server)
+            .get_first_post_id_over_limit(&rate_limiter, ACCOUNT_ID, last_post_id, false, max_posts)
+            .await
+            .unwrap(),
+        None => None,
+    };
+
+    mastodon_client(server)
+        .get_latest_posts(
+            &rate_limiter,
+            ACCOUNT_ID,
+            last_post_id.map(str::to_string),
+            first_post_id_over_limit,
+            false,
+            false,
+        )
+        .await
+        .unwrap()
+}
+
+/// Get the IDs of statuses.
+///
+/// ## Arguments
+///
+/// * `statuses` - The statuses to get the IDs of.
+fn status_ids(statuses: &[megalodon::entities::Status]) -> Vec<&str> {
+    statuses.iter().map(|status| status.id.as_str()).collect()
+}
+
+/// Tests to ensure every post since the last synced post is returned, oldest
+/// first, when they span more than one page.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__pages_back_to_last_post() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_after_mock =
+        mock_page_after(&mut server, "100", 40, &["105", "104", "103", "102", "101"]).await;
+
+    let second_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "100".into()),
+            Matcher::UrlEncoded("max_id".into(), "103".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["102", "101"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let last_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "100".into()),
+            Matcher::UrlEncoded("max_id".into(), "101".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body("[]")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let first_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded("since_id".into(), "100".into()))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["105", "104", "103"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("100"), 400).await;
+
+    assert_eq!(
+        status_ids(&latest_posts),
+        vec!["101", "102", "103", "104", "105"]
+    );
+
+    page_after_mock.assert_async().await;
+    first_page_mock.assert_async().await;
+    second_page_mock.assert_async().await;
+    last_page_mock.assert_async().await;
+}
+
+/// Tests to ensure paging stops at the first post older than the last synced
+/// post when it was deleted, even if the server ignores `since_id`.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__stops_when_last_post_was_deleted() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_after_mock = mock_page_after(&mut server, "100", 40, &["103", "102"]).await;
+
+    let page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded("since_id".into(), "100".into()))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["103", "102", "99", "98"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("100"), 400).await;
+
+    assert_eq!(status_ids(&latest_posts), vec!["102", "103"]);
+
+    page_after_mock.assert_async().await;
+    page_mock.assert_async().await;
+}
+
+/// Tests to ensure the IDs of platforms that don't use numeric IDs are sent
+/// back to the server as they are when paging back to the last synced post.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__pages_back_with_platform_ids() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_after_mock = mock_page_after(
+        &mut server,
+        "01JBZ8Q4X6YV3T2M9K7N5R1WQC",
+        40,
+        &[
+            "01JBZAB7D5F2H4K6M8P0R3T5VW",
+            "01JBZ9A0ZB4M3Q6T8V2X5Y7N1D",
+            "01JBZ8X3M2K8R5T1V7Y9B4N6QA",
+        ],
+    )
+    .await;
+
+    let second_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "01JBZ8Q4X6YV3T2M9K7N5R1WQC".into()),
+            Matcher::UrlEncoded("max_id".into(), "01JBZ9A0ZB4M3Q6T8V2X5Y7N1D".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&[
+            "01JBZ8X3M2K8R5T1V7Y9B4N6QA",
+            "01JBZ8Q4X6YV3T2M9K7N5R1WQC",
+        ]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let first_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded(
+            "since_id".into(),
+            "01JBZ8Q4X6YV3T2M9K7N5R1WQC".into(),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&[
+            "01JBZAB7D5F2H4K6M8P0R3T5VW",
+            "01JBZ9A0ZB4M3Q6T8V2X5Y7N1D",
+        ]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("01JBZ8Q4X6YV3T2M9K7N5R1WQC"), 400).await;
+
+    assert_eq!(
+        status_ids(&latest_posts),
+        vec![
+            "01JBZ8X3M2K8R5T1V7Y9B4N6QA",
+            "01JBZ9A0ZB4M3Q6T8V2X5Y7N1D",
+            "01JBZAB7D5F2H4K6M8P0R3T5VW"
+        ]
+    );
+
+    page_after_mock.assert_async().await;
+    first_page_mock.assert_async().await;
+    second_page_mock.assert_async().await;
+}
+
+/// Tests to ensure paging stops at the last synced post, even if the server
+/// returns posts older than it.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__stops_at_last_post() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_after_mock = mock_page_after(&mut server, "100", 40, &["103", "102"]).await;
+
+    let page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded("since_id".into(), "100".into()))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["103", "102", "100", "99"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("100"), 400).await;
+
+    assert_eq!(status_ids(&latest_posts), vec!["102", "103"]);
+
+    page_after_mock.assert_async().await;
+    page_mock.assert_async().await;
+}
+
+/// Tests to ensure only the oldest posts up to the limit are returned, so the
+/// newer posts are left for the next sync instead of being skipped, and the
+/// newer posts aren't fetched.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__respects_max_posts() {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_after_mock =
+        mock_page_after(&mut server, "100", 4, &["104", "103", "102", "101"]).await;
+
+    let page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "100".into()),
+            Matcher::UrlEncoded("max_id".into(), "104".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["103", "102", "101", "100"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let newest_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded("since_id".into(), "100".into()))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["105", "104"]))
+        .expect(0)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("100"), 3).await;
+
+    assert_eq!(status_ids(&latest_posts), vec!["101", "102", "103"]);
+
+    page_after_mock.assert_async().await;
+    page_mock.assert_async().await;
+    newest_page_mock.assert_async().await;
+}
+
+/// Tests to ensure paging forward from the last synced post continues over
+/// more than one page, and stops once the post over the limit is found.
+#[rstest]
+#[tokio::test]
+async fn get_latest_posts__pages_forward_to_max_posts() {
+    let mut server = mockito::Server::new_async().await;
+
+    let first_page_ids = (101..=140)
+        .rev()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>();
+    let first_page_ids = first_page_ids
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+
+    let first_page_after_mock = mock_page_after(&mut server, "100", 40, &first_page_ids).await;
+    let second_page_after_mock = mock_page_after(&mut server, "140", 2, &["142", "141"]).await;
+
+    let first_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "100".into()),
+            Matcher::UrlEncoded("max_id".into(), "142".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&[&["141"], &first_page_ids[..39]].concat()))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let second_page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("since_id".into(), "100".into()),
+            Matcher::UrlEncoded("max_id".into(), "102".into()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(statuses_page(&["101", "100"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, Some("100"), 41).await;
+
+    let expected_ids = (101..=141)
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>();
+
+    assert_eq!(status_ids(&latest_posts), expected_ids);
+
+    first_page_after_mock.assert_async().await;
+    second_page_after_mock.assert_async().await;
+    first_page_mock.assert_async().await;
+    second_page_mock.assert_async().await;
+}
+
+/// Tests to ensure only the latest post is returned when nothing was synced
+/// yet, and posts that can't be synced are filtered out.
+#[rstest]
+#[case::public("public", vec!["105"])]
+#[case::private("private", vec![])]
+#[tokio::test]
+async fn get_latest_posts__gets_latest_post_without_last_post(
+    #[case] visibility: &str,
+    #[case] expected_ids: Vec<&str>,
+) {
+    let mut server = mockito::Server::new_async().await;
+
+    let page_mock = server
+        .mock("GET", ACCOUNT_STATUSES_PATH)
+        .match_query(Matcher::UrlEncoded("limit".into(), "1".into()))
+        .with_header("content-type", "application/json")
+        .with_body(
+            serde_json::Value::Array(vec![mastodon_status_json("105", visibility)]).to_string(),
+        )
+        .expect(1)
+        .create_async()
+        .await;
+
+    let latest_posts = get_latest_posts(&server, None, 400).await;
+
+    assert_eq!(status_ids(&latest_posts), expected_ids);
+
+    page_mock.assert_async().await;
+}
@@ -0,0 +1,42 @@
+/// Tests for `fediproto_sync::account_pairs`.
+mod account_pairs;
+/// Tests for `fediproto_sync::backfill`.
+mod backfill;
+/// Tests for `fediproto_sync::bsky_post_sync`.
+mod bsky_post_sync;
+/// Tests for `fediproto_sync::bsky_session`.
+mod bsky_session;
+/// Shared fixtures for tests.
+mod fixtures;
+/// Tests for the held posts in `fediproto_sync::core`.
+mod held_posts;
+/// Tests for `fediproto_sync::link_metadata`.
+mod link_metadata;
+/// Tests for `fediproto_sync::link_metadata::selection`.
+mod link_selection;
+/// Tests for `fediproto_sync::mastodon`.
+mod mastodon;
+/// Tests for `fediproto_sync::profile`.
+mod profile;
+/// Tests for the publish delay in `fediproto_sync::core`.
+mod publish_delay;
+/// Tests for `fediproto_sync::rate_limit`.
+mod rate_limit;
+/// Tests for `fediproto_sync::replies`.
+mod replies;
+/// Tests for `fediproto_sync::retry`.
+mod retry;
+/// Tests for `fediproto_sync::rules::route`.
+mod route;
+/// Tests for `fediproto_sync::rules`.
+mod rules;
+/// Tests for `fediproto_sync::source_platform`.
+mod source_platform;
+/// Tests for `fediproto_sync::streaming`.
+mod streaming;
+/// Tests for `fediproto_sync::sync_error`.
+mod sync_error;
+/// Tests for `fediproto_sync::templates`.
+mod templates;
+/// Tests for `fediproto_sync::rules::transform`.
+mod transform;
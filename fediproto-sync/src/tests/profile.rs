@@ -0,0 +1,170 @@
+use diesel::Connection;
+use fediproto_sync_db::AnyConnection;
+use fediproto_sync_lib::config::DEFAULT_ACCOUNT_PAIR_ID;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::profile::{
+    ConvertedProfile, MAX_DESCRIPTION_LENGTH, find_synced_pinned_post, html_to_profile_text,
+    truncate_profile_text,
+};
+
+/// Create a Mastodon account with the given profile.
+///
+/// ## Arguments
+///
+/// * `note` - The HTML of the bio.
+/// * `fields` - The names and HTML values of the profile fields.
+fn mastodon_account(
+    note: &str,
+    fields: &[(&str, &str)],
+) -> megalodon::entities::Account {
+    let mut account = mastodon_status("").account;
+    account.note = note.to_string();
+    account.fields = fields
+        .iter()
+        .map(|(name, value)| {
+            serde_json::from_value::<megalodon::entities::Field>(serde_json::json!({
+                "name": name,
+                "value": value,
+                "verified_at": null
+            }))
+            .expect("Failed to parse the profile field")
+        })
+        .collect();
+
+    account
+}
+
+/// Tests to ensure bio HTML is converted to text with its paragraphs and line
+/// breaks kept.
+#[rstest]
+#[case::single_paragraph("<p>Hello &amp; welcome.</p>", "Hello & welcome.")]
+#[case::paragraphs("<p>First.</p><p>Second.</p>", "First.\n\nSecond.")]
+#[case::line_breaks(
+    "<p>Line one<br>Line two<br />Line three</p>",
+    "Line one\nLine two\nLine three"
+)]
+#[case::no_paragraphs("Just <a href=\"https://example.com\">a link</a>", "Just a link")]
+#[case::empty("", "")]
+fn html_to_profile_text__converts_html(
+    #[case] html: &str,
+    #[case] expected: &str,
+) {
+    assert_eq!(html_to_profile_text(html), expected);
+}
+
+/// Tests to ensure profile text is only truncated when it's too long.
+#[rstest]
+#[case::short("Short", 10, "Short")]
+#[case::exact("Exactly10!", 10, "Exactly10!")]
+#[case::long("This is too long", 10, "This is t…")]
+#[case::multi_byte("ééééé", 3, "éé…")]
+fn truncate_profile_text__truncates_long_text(
+    #[case] text: &str,
+    #[case] max_length: usize,
+    #[case] expected: &str,
+) {
+    assert_eq!(truncate_profile_text(text, max_length), expected);
+}
+
+/// Tests to ensure profile fields are added to the bio as lines.
+#[rstest]
+fn converted_profile__adds_fields_to_bio() {
+    let account = mastodon_account(
+        "<p>About me.</p>",
+        &[
+            (
+                "Website",
+                "<a href=\"https://example.com\" rel=\"me\">example.com</a>",
+            ),
+            ("Pronouns", "they/them"),
+        ],
+    );
+
+    let converted_profile = ConvertedProfile::from_mastodon_account(&account);
+
+    assert_eq!(
+        converted_profile.description,
+        "About me.\n\nWebsite: example.com\nPronouns: they/them"
+    );
+}
+
+/// Tests to ensure long bios are truncated to BlueSky's limit.
+#[rstest]
+fn converted_profile__truncates_bio() {
+    let account = mastodon_account(&format!("<p>{}</p>", "a".repeat(400)), &[]);
+
+    let converted_profile = ConvertedProfile::from_mastodon_account(&account);
+
+    assert_eq!(
+        converted_profile.description.chars().count(),
+        MAX_DESCRIPTION_LENGTH
+    );
+}
+
+/// Tests to ensure Mastodon's placeholder images aren't synced.
+#[rstest]
+fn converted_profile__ignores_missing_images() {
+    let mut account = mastodon_account("", &[]);
+
+    let converted_profile = ConvertedProfile::from_mastodon_account(&account);
+    assert_eq!(converted_profile.avatar_url, None);
+    assert_eq!(converted_profile.banner_url, None);
+
+    account.avatar = "https://mastodon.example/avatars/original/avatar.png".to_string();
+
+    let converted_profile = ConvertedProfile::from_mastodon_account(&account);
+    assert_eq!(
+        converted_profile.avatar_url.as_deref(),
+        Some("https://mastodon.example/avatars/original/avatar.png")
+    );
+}
+
+/// Tests to ensure the hashes only change when their part of the profile
+/// changes.
+#[rstest]
+fn converted_profile__hashes_track_changes() {
+    let account = mastodon_account("<p>About me.</p>", &[]);
+    let converted_profile = ConvertedProfile::from_mastodon_account(&account);
+
+    let mut changed_account = account.clone();
+    changed_account.note = "<p>About me, updated.</p>".to_string();
+    let changed_profile = ConvertedProfile::from_mastodon_account(&changed_account);
+
+    assert_ne!(converted_profile.text_hash(), changed_profile.text_hash());
+    assert_eq!(
+        converted_profile.avatar_hash(),
+        changed_profile.avatar_hash()
+    );
+    assert_eq!(
+        converted_profile.banner_hash(),
+        changed_profile.banner_hash()
+    );
+}
+
+/// Tests to ensure a pinned post that was never synced isn't found.
+#[rstest]
+fn find_synced_pinned_post__not_synced() {
+    let db_connection = &mut AnyConnection::establish(":memory:").unwrap();
+    fediproto_sync_db::core::run_migrations(db_connection).unwrap();
+
+    let synced_pinned_post =
+        find_synced_pinned_post(db_connection, DEFAULT_ACCOUNT_PAIR_ID, "113000000000000001")
+            .unwrap();
+
+    assert_eq!(synced_pinned_post, None);
+}
+
+/// Tests to ensure a failed database lookup is returned as an error, instead
+/// of clearing the pinned post like a post that was never synced.
+#[rstest]
+fn find_synced_pinned_post__returns_database_errors() {
+    // The tables don't exist without the migrations, so the lookup fails.
+    let db_connection = &mut AnyConnection::establish(":memory:").unwrap();
+
+    assert!(
+        find_synced_pinned_post(db_connection, DEFAULT_ACCOUNT_PAIR_ID, "113000000000000001")
+            .is_err()
+    );
+}
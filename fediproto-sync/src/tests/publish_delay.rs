@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::core::{is_deleted_status_error, is_within_publish_delay};
+
+/// Parse a date and time in the RFC 3339 format.
+///
+/// ## Arguments
+///
+/// * `value` - The date and time to parse.
+fn date_time(value: &str) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .unwrap()
+        .with_timezone(&chrono::Utc)
+}
+
+/// Tests to ensure posts are only held while they are younger than the publish
+/// delay. The test status was created at `2024-12-01T12:00:00Z`.
+#[rstest]
+#[case::disabled(0, "2024-12-01T12:00:00Z", false)]
+#[case::just_created(10, "2024-12-01T12:00:00Z", true)]
+#[case::within_delay(10, "2024-12-01T12:09:59Z", true)]
+#[case::delay_passed(10, "2024-12-01T12:10:00Z", false)]
+#[case::long_after(10, "2024-12-02T12:00:00Z", false)]
+fn is_within_publish_delay__checks_post_age(
+    #[case] delay_minutes: u64,
+    #[case] now: &str,
+    #[case] expected: bool,
+) {
+    let status = mastodon_status("<p>Hello!</p>");
+
+    let result = is_within_publish_delay(
+        &status,
+        Duration::from_secs(delay_minutes * 60),
+        date_time(now),
+    );
+
+    assert_eq!(result, expected);
+}
+
+/// Tests to ensure only missing statuses are treated as deleted.
+#[rstest]
+#[case::not_found(Some(404), true)]
+#[case::gone(Some(410), true)]
+#[case::server_error(Some(500), false)]
+#[case::rate_limited(Some(429), false)]
+#[case::no_status(None, false)]
+fn is_deleted_status_error__checks_status(
+    #[case] status: Option<u16>,
+    #[case] expected: bool,
+) {
+    let error = megalodon::error::Error::new_own(
+        "Request failed".to_string(),
+        megalodon::error::Kind::HTTPStatusError,
+        None,
+        status,
+        None,
+    );
+
+    assert_eq!(is_deleted_status_error(&error), expected);
+}
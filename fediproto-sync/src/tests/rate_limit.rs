@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use fediproto_sync_lib::error::{AuthenticationSource, FediProtoSyncError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use rstest::*;
+
+use super::fixtures::mastodon_status_json;
+use crate::rate_limit::*;
+
+/// Get the time the tests are run at.
+fn now() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2026-10-18T12:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// Create the headers of a response.
+///
+/// ## Arguments
+///
+/// * `headers` - The names and values of the headers.
+fn response_headers(headers: &[(&str, &str)]) -> HeaderMap {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_str(value).unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Tests to ensure the budget is read from the headers sent by BlueSky PDSes
+/// and Mastodon.
+#[rstest]
+#[case::bluesky(
+    &[("RateLimit-Limit", "5000"), ("RateLimit-Remaining", "4999"), ("RateLimit-Reset", "1792324800")],
+    Some(5000),
+    Some(4999),
+    Some("2026-10-18T12:00:00Z")
+)]
+#[case::mastodon(
+    &[("X-RateLimit-Limit", "300"), ("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset", "2026-10-18T12:05:00.000Z")],
+    Some(300),
+    Some(0),
+    Some("2026-10-18T12:05:00Z")
+)]
+#[case::reset_in_seconds(
+    &[("RateLimit-Remaining", "10"), ("RateLimit-Reset", "30")],
+    None,
+    Some(10),
+    Some("2026-10-18T12:00:30Z")
+)]
+fn rate_limit_budget__from_headers(
+    #[case] headers: &[(&str, &str)],
+    #[case] expected_limit: Option<u64>,
+    #[case] expected_remaining: Option<u64>,
+    #[case] expected_reset_at: Option<&str>,
+) {
+    let budget = RateLimitBudget::from_headers(200, &response_headers(headers), now()).unwrap();
+
+    assert_eq!(budget.limit, expected_limit);
+    assert_eq!(budget.remaining, expected_remaining);
+    assert_eq!(
+        budget.reset_at,
+        expected_reset_at.map(|reset_at| DateTime::parse_from_rfc3339(reset_at)
+            .unwrap()
+            .with_timezone(&Utc))
+    );
+}
+
+/// Tests to ensure a `429` spends the budget until the time in `Retry-After`,
+/// or a default time if it's missing.
+#[rstest]
+#[case::seconds(&[("Retry-After", "120")], "2026-10-18T12:02:00Z")]
+#[case::http_date(&[("Retry-After", "Sun, 18 Oct 2026 12:10:00 GMT")], "2026-10-18T12:10:00Z")]
+#[case::missing(&[], "2026-10-18T12:01:00Z")]
+fn rate_limit_budget__too_many_requests(
+    #[case] headers: &[(&str, &str)],
+    #[case] expected_reset_at: &str,
+) {
+    let budget = RateLimitBudget::from_headers(429, &response_headers(headers), now()).unwrap();
+
+    assert_eq!(budget.remaining, Some(0));
+    assert_eq!(
+        budget.reset_at,
+        Some(
+            DateTime::parse_from_rfc3339(expected_reset_at)
+                .unwrap()
+                .with_timezone(&Utc)
+        )
+    );
+}
+
+/// Tests to ensure responses without rate limit headers don't change the
+/// budget.
+#[rstest]
+fn rate_limit_budget__no_headers() {
+    assert_eq!(
+        RateLimitBudget::from_headers(200, &HeaderMap::new(), now()),
+        None
+    );
+}
+
+/// Tests to ensure requests only wait while the budget is spent.
+#[rstest]
+#[case(Some(0), Some(30), Some(Duration::from_secs(30)))]
+#[case(Some(0), Some(-30), None)]
+#[case(Some(1), Some(30), None)]
+#[case(None, Some(30), None)]
+#[case(Some(0), None, None)]
+fn rate_limit_budget__delay(
+    #[case] remaining: Option<u64>,
+    #[case] reset_in_seconds: Option<i64>,
+    #[case] expected_delay: Option<Duration>,
+) {
+    let budget = RateLimitBudget {
+        limit: Some(100),
+        remaining,
+        reset_at: reset_in_seconds.map(|seconds| now() + chrono::Duration::seconds(seconds)),
+    };
+
+    assert_eq!(budget.delay(now()), expected_delay);
+}
+
+/// Tests to ensure requests are deferred when the budget resets later than
+/// the longest time to wait.
+#[rstest]
+#[tokio::test]
+async fn rate_limit_governor__defers_when_budget_is_spent() {
+    let governor = RateLimitGovernor::default();
+
+    governor.update(
+        "pds.example.com",
+        429,
+        &response_headers(&[("Retry-After", "3600")]),
+    );
+
+    let wait_result = governor
+        .wait_for_budget("pds.example.com", Duration::from_secs(60))
+        .await;
+
+    match wait_result {
+        Err(FediProtoSyncError::RateLimitError {
+            host,
+            retry_after_seconds,
+        }) => {
+            assert_eq!(host, "pds.example.com");
+            assert!(retry_after_seconds > 3500);
+        }
+
+        _ => panic!("Expected a rate limit error, got '{:?}'", wait_result),
+    }
+
+    // Other hosts have their own budget.
+    assert!(
+        governor
+            .wait_for_budget("other.example.com", Duration::from_secs(60))
+            .await
+            .is_ok()
+    );
+}
+
+/// Tests to ensure requests aren't sent again after a `429` until the budget
+/// resets.
+#[rstest]
+#[tokio::test]
+async fn send_rate_limited__defers_after_too_many_requests() {
+    // The budget of the server stays spent, so the server isn't shared with
+    // the other tests.
+    let mut server = mockito::Server::new_with_opts_async(mockito::ServerOpts::default()).await;
+
+    let too_many_requests_mock = server
+        .mock("GET", "/media/image.png")
+        .with_status(429)
+        .with_header("retry-after", "3600")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let http_client = reqwest::Client::new();
+    let url = format!("{}/media/image.png", server.url());
+
+    let first_result = send_rate_limited(
+        AuthenticationSource::Mastodon,
+        http_client.get(&url),
+        Duration::from_secs(60),
+    )
+    .await;
+
+    assert!(matches!(
+        first_result,
+        Err(FediProtoSyncError::HttpError {
+            status: Some(429),
+            ..
+        })
+    ));
+
+    let second_result = send_rate_limited(
+        AuthenticationSource::Mastodon,
+        http_client.get(&url),
+        Duration::from_secs(60),
+    )
+    .await;
+
+    assert!(matches!(
+        second_result,
+        Err(FediProtoSyncError::RateLimitError { .. })
+    ));
+
+    too_many_requests_mock.assert_async().await;
+}
+
+/// Tests to ensure Mastodon requests aren't sent again once the budget of the
+/// Mastodon server is spent, until it resets.
+#[rstest]
+#[tokio::test]
+async fn mastodon_rate_limiter__defers_when_budget_spent() {
+    // The budget of the server stays spent, so the server isn't shared with
+    // the other tests.
+    let mut server = mockito::Server::new_with_opts_async(mockito::ServerOpts::default()).await;
+
+    let status_mock = server
+        .mock("GET", "/api/v1/statuses/113000000000000001")
+        .with_header("content-type", "application/json")
+        .with_header("x-ratelimit-limit", "300")
+        .with_header("x-ratelimit-remaining", "0")
+        .with_header("x-ratelimit-reset", "3600")
+        .with_body(mastodon_status_json("113000000000000001", "public").to_string())
+        .expect(1)
+        .create_async()
+        .await;
+
+    let mastodon_client = megalodon::generator(
+        megalodon::SNS::Mastodon,
+        server.url(),
+        Some("access-token".to_string()),
+        None,
+    )
+    .unwrap();
+    let rate_limiter = MastodonRateLimiter::new(&server.url(), Duration::from_secs(60));
+
+    let first_result = rate_limiter
+        .send(mastodon_client.get_status("113000000000000001".to_string()))
+        .await;
+
+    assert!(first_result.is_ok());
+
+    let second_result = rate_limiter
+        .send(mastodon_client.get_status("113000000000000001".to_string()))
+        .await;
+
+    match second_result {
+        Err(megalodon::error::Error::OwnError(own_error)) => {
+            assert_eq!(own_error.status, Some(429))
+        }
+        _ => panic!("Expected a rate limit error, got '{:?}'", second_result),
+    }
+
+    status_mock.assert_async().await;
+}
+
+/// Tests to ensure the budget of a host is recorded as metrics when it's
+/// updated.
+#[rstest]
+fn rate_limit_governor__records_budget_metrics() {
+    let recorder = metrics_util::debugging::DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        RateLimitGovernor::default().update(
+            "metrics.example.com",
+            200,
+            &response_headers(&[
+                ("RateLimit-Limit", "3000"),
+                ("RateLimit-Remaining", "2999"),
+                ("RateLimit-Reset", "1792324800"),
+            ]),
+        );
+    });
+
+    let gauges = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let host = key
+                .key()
+                .labels()
+                .find(|label| label.key() == "host")
+                .map(|label| label.value().to_string());
+
+            (key.key().name().to_string(), host, value)
+        })
+        .collect::<Vec<_>>();
+
+    for (metric, expected_value) in [
+        (RATE_LIMIT_LIMIT_METRIC, 3000.0),
+        (RATE_LIMIT_REMAINING_METRIC, 2999.0),
+        (RATE_LIMIT_RESET_METRIC, 1792324800.0),
+    ] {
+        assert!(
+            gauges.iter().any(|(name, host, value)| {
+                name == metric
+                    && host.as_deref() == Some("metrics.example.com")
+                    && *value == metrics_util::debugging::DebugValue::Gauge(expected_value.into())
+            }),
+            "Expected '{}' to be '{}', got '{:?}'",
+            metric,
+            expected_value,
+            gauges
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use fediproto_sync_lib::config::ReplyPolicy;
+use rstest::*;
+
+use super::fixtures::{mastodon_mention, mastodon_status};
+use crate::replies::{ReplyFilter, ThreadRepairPlan, bridged_handle, is_bridged_copy_of};
+
+/// The ID of the account the test status was posted by.
+const ACCOUNT_ID: &str = "110000000000000001";
+
+/// Create a reply to another account.
+///
+/// ## Arguments
+///
+/// * `mentions_someone` - Whether the reply mentions the account it replies to.
+/// * `tags` - The names of the hashtags on the reply.
+fn reply_to_other(
+    mentions_someone: bool,
+    tags: &[&str],
+) -> megalodon::entities::Status {
+    let mut status = mastodon_status("<p>A reply.</p>");
+    status.in_reply_to_id = Some("113000000000000000".to_string());
+    status.in_reply_to_account_id = Some("110000000000000099".to_string());
+
+    if mentions_someone {
+        status.mentions = vec![mastodon_mention(
+            "someone@mastodon.social",
+            "https://mastodon.social/@someone",
+        )];
+    }
+
+    status.tags = tags
+        .iter()
+        .map(|tag| megalodon::entities::status::Tag {
+            name: tag.to_string(),
+            url: format!("https://mastodon.example/tags/{}", tag),
+        })
+        .collect();
+
+    status
+}
+
+/// Create a reply filter.
+///
+/// ## Arguments
+///
+/// * `policy` - How replies to other accounts are synced.
+fn reply_filter(policy: ReplyPolicy) -> ReplyFilter {
+    ReplyFilter {
+        policy,
+        hashtag: Some("crosspost".to_string()),
+    }
+}
+
+/// Tests to ensure posts that aren't replies to other accounts are always
+/// allowed.
+#[rstest]
+#[case(ReplyPolicy::Skip)]
+#[case(ReplyPolicy::Standalone)]
+fn reply_filter__allows_non_replies(#[case] policy: ReplyPolicy) {
+    let filter = reply_filter(policy);
+
+    let status = mastodon_status("<p>Not a reply.</p>");
+    assert!(filter.allows(&status, ACCOUNT_ID));
+
+    let mut self_reply = mastodon_status("<p>A thread.</p>");
+    self_reply.in_reply_to_id = Some("113000000000000000".to_string());
+    self_reply.in_reply_to_account_id = Some(ACCOUNT_ID.to_string());
+    assert!(filter.allows(&self_reply, ACCOUNT_ID));
+}
+
+/// Tests to ensure only replies that mention no one, or have the reply
+/// hashtag, are allowed.
+#[rstest]
+#[case::skip_policy(ReplyPolicy::Skip, false, &[], false)]
+#[case::no_mentions(ReplyPolicy::Standalone, false, &[], true)]
+#[case::mentions(ReplyPolicy::Standalone, true, &[], false)]
+#[case::mentions_with_hashtag(ReplyPolicy::Quote, true, &["CrossPost"], true)]
+#[case::mentions_with_other_hashtag(ReplyPolicy::Quote, true, &["other"], false)]
+fn reply_filter__checks_eligibility(
+    #[case] policy: ReplyPolicy,
+    #[case] mentions_someone: bool,
+    #[case] tags: &[&str],
+    #[case] expected: bool,
+) {
+    let filter = reply_filter(policy);
+    let status = reply_to_other(mentions_someone, tags);
+
+    assert_eq!(filter.allows(&status, ACCOUNT_ID), expected);
+}
+
+/// Tests to ensure Bridgy Fed handles are generated for local and remote
+/// accounts.
+#[rstest]
+#[case("someone@mastodon.social", "someone.mastodon.social.ap.brid.gy")]
+#[case("Some_One@Example.com", "some-one.example.com.ap.brid.gy")]
+#[case("local", "local.mastodon.example.ap.brid.gy")]
+fn bridged_handle__formats_handle(
+    #[case] acct: &str,
+    #[case] expected: &str,
+) {
+    assert_eq!(bridged_handle(acct, "mastodon.example"), expected);
+}
+
+/// Tests to ensure bridged posts are matched by their original URL.
+#[rstest]
+fn is_bridged_copy_of__matches_original_url() {
+    let status = mastodon_status("<p>Hello!</p>");
+
+    let bridged_record = serde_json::json!({
+        "text": "Hello!",
+        "bridgyOriginalUrl": "https://mastodon.example/users/user/statuses/113000000000000001"
+    });
+    let other_record = serde_json::json!({
+        "text": "Hello!",
+        "bridgyOriginalUrl": "https://mastodon.example/users/user/statuses/113000000000000002"
+    });
+    let unbridged_record = serde_json::json!({ "text": "Hello!" });
+
+    assert!(is_bridged_copy_of(&bridged_record, &status));
+    assert!(!is_bridged_copy_of(&other_record, &status));
+    assert!(!is_bridged_copy_of(&unbridged_record, &status));
+}
+
+/// Create an ancestor in a thread.
+///
+/// ## Arguments
+///
+/// * `id` - The ID of the post.
+fn ancestor(id: &str) -> megalodon::entities::Status {
+    let mut status = mastodon_status("<p>Part of a thread.</p>");
+    status.id = id.to_string();
+
+    status
+}
+
+/// Tests to ensure the nearest synced ancestor is found and only the
+/// ancestors after it are missing.
+#[rstest]
+#[case::nearest_is_parent(&["1", "3"], Some("3"), &[])]
+#[case::gap_after_synced(&["1"], Some("1"), &["2", "3"])]
+#[case::newest_synced_wins(&["1", "2"], Some("2"), &["3"])]
+#[case::none_synced(&[], None, &["1", "2", "3"])]
+fn thread_repair_plan__finds_nearest_synced_ancestor(
+    #[case] synced_ids: &[&str],
+    #[case] expected_nearest: Option<&str>,
+    #[case] expected_missing: &[&str],
+) {
+    let ancestors = vec![ancestor("1"), ancestor("2"), ancestor("3")];
+
+    let plan = ThreadRepairPlan::new(&ancestors, |post_id| synced_ids.contains(&post_id));
+
+    assert_eq!(plan.nearest_synced_ancestor_id.as_deref(), expected_nearest);
+    assert_eq!(
+        plan.missing_ancestors
+            .iter()
+            .map(|ancestor| ancestor.id.as_str())
+            .collect::<Vec<&str>>(),
+        expected_missing
+    );
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use fediproto_sync_lib::error::ErrorClass;
+use rstest::*;
+
+use crate::retry::RetryPolicy;
+
+/// Create a retry policy.
+///
+/// ## Arguments
+///
+/// * `max_attempts` - The maximum number of times to try syncing a post.
+fn retry_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        initial_delay: Duration::from_secs(300),
+        max_delay: Duration::from_secs(3600),
+    }
+}
+
+/// Tests to ensure the delay doubles after every failed attempt, up to the
+/// maximum delay.
+#[rstest]
+#[case(0, Duration::from_secs(300))]
+#[case(1, Duration::from_secs(300))]
+#[case(2, Duration::from_secs(600))]
+#[case(3, Duration::from_secs(1200))]
+#[case(4, Duration::from_secs(2400))]
+#[case(5, Duration::from_secs(3600))]
+#[case(u32::MAX, Duration::from_secs(3600))]
+fn retry_policy__delay_after(
+    #[case] failed_attempts: u32,
+    #[case] expected_delay: Duration,
+) {
+    assert_eq!(retry_policy(8).delay_after(failed_attempts), expected_delay);
+}
+
+/// Tests to ensure posts are out of attempts once they've failed the maximum
+/// number of times.
+#[rstest]
+#[case(1, 3, false)]
+#[case(2, 3, false)]
+#[case(3, 3, true)]
+#[case(4, 3, true)]
+#[case(1, 1, true)]
+fn retry_policy__is_exhausted(
+    #[case] failed_attempts: u32,
+    #[case] max_attempts: u32,
+    #[case] expected: bool,
+) {
+    assert_eq!(
+        retry_policy(max_attempts).is_exhausted(failed_attempts),
+        expected
+    );
+}
+
+/// Tests to ensure the next attempt is scheduled after the delay.
+#[rstest]
+fn retry_policy__next_attempt_at() {
+    let now = chrono::DateTime::parse_from_rfc3339("2026-10-18T12:00:00Z")
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+
+    assert_eq!(
+        retry_policy(8).next_attempt_at(2, now),
+        (now + chrono::Duration::seconds(600)).naive_utc()
+    );
+}
+
+/// Tests to ensure posts are moved to the dead letters by the class of the
+/// error they failed with.
+#[rstest]
+#[case(1, ErrorClass::Transient, false)]
+#[case(3, ErrorClass::Transient, true)]
+#[case(1, ErrorClass::Permanent, true)]
+#[case(1, ErrorClass::Auth, false)]
+#[case(10, ErrorClass::Auth, false)]
+fn retry_policy__should_dead_letter(
+    #[case] failed_attempts: u32,
+    #[case] failure_class: ErrorClass,
+    #[case] expected: bool,
+) {
+    assert_eq!(
+        retry_policy(3).should_dead_letter(failed_attempts, failure_class),
+        expected
+    );
+}
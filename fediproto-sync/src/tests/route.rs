@@ -0,0 +1,138 @@
+use megalodon::entities::StatusVisibility;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::rules::{PostRules, route::RouteDecision};
+
+/// A rules file with destinations and route rules for them.
+const ROUTES_FILE: &str = r##"
+[[destination]]
+name = "product"
+bluesky_handle = "product.bsky.social"
+bluesky_app_password = "app-password"
+
+[[destination]]
+name = "Personal"
+bluesky_pds_server = "pds.example"
+bluesky_handle = "someone.example"
+bluesky_app_password = "app-password"
+
+[[route]]
+name = "releases"
+destinations = ["product", "personal", "PRODUCT"]
+hashtags = ["#release"]
+
+[[route]]
+name = "photos"
+destinations = ["personal"]
+has_media = true
+
+[[route]]
+name = "german"
+destinations = ["bluesky", "product"]
+languages = ["de"]
+visibility = ["public"]
+"##;
+
+/// The rules parsed from `ROUTES_FILE`.
+#[fixture]
+fn post_rules() -> PostRules {
+    PostRules::from_toml_str(ROUTES_FILE).unwrap()
+}
+
+/// Tests to ensure destinations and route rules are parsed in order.
+#[rstest]
+fn post_rules_from_toml_str__parses_routes(post_rules: PostRules) {
+    let destination_names = post_rules
+        .destinations
+        .iter()
+        .map(|destination| destination.name.as_str())
+        .collect::<Vec<&str>>();
+    let route_names = post_rules
+        .routes
+        .iter()
+        .map(|route| route.name.as_str())
+        .collect::<Vec<&str>>();
+
+    assert_eq!(destination_names, vec!["product", "Personal"]);
+    assert_eq!(route_names, vec!["releases", "photos", "german"]);
+    assert_eq!(post_rules.destinations[0].bluesky_pds_server, None);
+    assert_eq!(
+        post_rules.destinations[1].bluesky_pds_server.as_deref(),
+        Some("pds.example")
+    );
+}
+
+/// Tests to ensure destinations need unique names that aren't reserved, and
+/// route rules can only use destinations that are defined.
+#[rstest]
+#[case::unknown_destination("[[route]]\nname = \"bad\"\ndestinations = [\"product\"]")]
+#[case::no_destinations("[[route]]\nname = \"bad\"\ndestinations = []")]
+#[case::reserved_name(
+    "[[destination]]\nname = \"BlueSky\"\nbluesky_handle = \"a.bsky.social\"\nbluesky_app_password = \"app-password\""
+)]
+#[case::duplicate_name(
+    "[[destination]]\nname = \"product\"\nbluesky_handle = \"a.bsky.social\"\nbluesky_app_password = \"app-password\"\n\n[[destination]]\nname = \"Product\"\nbluesky_handle = \"b.bsky.social\"\nbluesky_app_password = \"app-password\""
+)]
+fn post_rules_from_toml_str__invalid_routes(#[case] rules: &str) {
+    assert!(PostRules::from_toml_str(rules).is_err());
+}
+
+/// Tests to ensure posts that don't match a route rule are synced to the
+/// BlueSky account of the account pair.
+#[rstest]
+fn route_decision_evaluate__no_match_syncs_to_account_pair(post_rules: PostRules) {
+    let decision =
+        RouteDecision::evaluate(&post_rules.routes, &mastodon_status("<p>Hello world!</p>"));
+
+    assert_eq!(
+        decision,
+        RouteDecision {
+            destinations: vec!["bluesky".to_string()],
+            rule_name: None,
+        }
+    );
+}
+
+/// Tests to ensure the first matching rule decides the destinations, without
+/// any duplicates.
+#[rstest]
+fn route_decision_evaluate__first_match_wins(post_rules: PostRules) {
+    // Matches both the "releases" and "german" rules.
+    let mut status = mastodon_status("<p>Version 2.0 ist da! #release</p>");
+    status.language = Some("de".to_string());
+    status.tags = vec![megalodon::entities::status::Tag {
+        name: "Release".to_string(),
+        url: "https://mastodon.example/tags/release".to_string(),
+    }];
+
+    let decision = RouteDecision::evaluate(&post_rules.routes, &status);
+
+    assert_eq!(decision.rule_name.as_deref(), Some("releases"));
+    assert_eq!(
+        decision.destinations,
+        vec!["product".to_string(), "personal".to_string()]
+    );
+}
+
+/// Tests to ensure each rule decides the destinations for the posts it
+/// matches.
+#[rstest]
+#[case(Some("de"), StatusVisibility::Public, Some("german"))]
+#[case(Some("de-AT"), StatusVisibility::Public, Some("german"))]
+#[case(Some("de"), StatusVisibility::Unlisted, None)]
+#[case(Some("en"), StatusVisibility::Public, None)]
+fn route_decision_evaluate__matches_rules(
+    post_rules: PostRules,
+    #[case] language: Option<&str>,
+    #[case] visibility: StatusVisibility,
+    #[case] expected_rule_name: Option<&str>,
+) {
+    let mut status = mastodon_status("<p>Hallo Welt!</p>");
+    status.language = language.map(|language| language.to_string());
+    status.visibility = visibility;
+
+    let decision = RouteDecision::evaluate(&post_rules.routes, &status);
+
+    assert_eq!(decision.rule_name.as_deref(), expected_rule_name);
+}
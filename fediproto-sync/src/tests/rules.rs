@@ -0,0 +1,285 @@
+use megalodon::entities::StatusVisibility;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::rules::{
+    PostRules,
+    filter::{FilterAction, FilterDecision},
+    matcher::StatusMatcher,
+};
+
+/// A rules file with a rule for each action.
+const RULES_FILE: &str = r##"
+[[filter]]
+name = "no-bsky"
+action = "skip"
+hashtags = ["#nobsky"]
+
+[[filter]]
+name = "drafts"
+action = "hold"
+text_regex = "(?i)\\bdraft\\b"
+
+[[filter]]
+name = "photos"
+action = "sync"
+has_media = true
+
+[[filter]]
+name = "everything-else-unlisted"
+action = "skip"
+visibility = ["unlisted"]
+"##;
+
+/// The rules parsed from `RULES_FILE`.
+#[fixture]
+fn post_rules() -> PostRules {
+    PostRules::from_toml_str(RULES_FILE).unwrap()
+}
+
+/// Create a hashtag for a status.
+///
+/// ## Arguments
+///
+/// * `name` - The name of the hashtag, without the leading `#`.
+fn tag(name: &str) -> megalodon::entities::status::Tag {
+    megalodon::entities::status::Tag {
+        name: name.to_string(),
+        url: format!("https://mastodon.example/tags/{}", name),
+    }
+}
+
+/// Create a boost of a status from another account.
+///
+/// ## Arguments
+///
+/// * `acct` - The account of the boosted status.
+fn boost_from(acct: &str) -> megalodon::entities::Status {
+    let mut boosted_status = mastodon_status("<p>Boosted post</p>");
+    boosted_status.account.acct = acct.to_string();
+
+    let mut status = mastodon_status("");
+    status.reblog = Some(Box::new(boosted_status));
+
+    status
+}
+
+/// Tests to ensure the rules file is parsed in order.
+#[rstest]
+fn post_rules_from_toml_str__parses_rules(post_rules: PostRules) {
+    let rule_names = post_rules
+        .filters
+        .iter()
+        .map(|filter| filter.name.as_str())
+        .collect::<Vec<&str>>();
+
+    assert_eq!(
+        rule_names,
+        vec!["no-bsky", "drafts", "photos", "everything-else-unlisted"]
+    );
+    assert_eq!(post_rules.filters[0].action, FilterAction::Skip);
+    assert_eq!(
+        post_rules.filters[3].matcher.visibility,
+        vec![StatusVisibility::Unlisted]
+    );
+}
+
+/// Tests to ensure an invalid regular expression or action is an error.
+#[rstest]
+#[case("[[filter]]\nname = \"bad\"\naction = \"skip\"\ntext_regex = \"(\"")]
+#[case("[[filter]]\nname = \"bad\"\naction = \"delete\"")]
+fn post_rules_from_toml_str__invalid_rules(#[case] rules: &str) {
+    assert!(PostRules::from_toml_str(rules).is_err());
+}
+
+/// Tests to ensure an empty rules file syncs every post.
+#[rstest]
+fn filter_decision_evaluate__no_rules_syncs() {
+    let post_rules = PostRules::from_toml_str("").unwrap();
+
+    let decision =
+        FilterDecision::evaluate(&post_rules.filters, &mastodon_status("<p>Hello world!</p>"));
+
+    assert_eq!(
+        decision,
+        FilterDecision {
+            action: FilterAction::Sync,
+            rule_name: None,
+        }
+    );
+}
+
+/// Tests to ensure the first matching rule decides the action.
+#[rstest]
+fn filter_decision_evaluate__first_match_wins(post_rules: PostRules) {
+    // Matches both the "no-bsky" and "drafts" rules.
+    let mut status = mastodon_status("<p>A draft post #NoBsky</p>");
+    status.tags = vec![tag("NoBsky")];
+
+    let decision = FilterDecision::evaluate(&post_rules.filters, &status);
+
+    assert_eq!(decision.action, FilterAction::Skip);
+    assert_eq!(decision.rule_name.as_deref(), Some("no-bsky"));
+}
+
+/// Tests to ensure each rule decides the action for the posts it matches.
+#[rstest]
+#[case(
+    "<p>This is a draft</p>",
+    false,
+    StatusVisibility::Public,
+    FilterAction::Hold,
+    Some("drafts")
+)]
+#[case(
+    "<p>Drafting a post</p>",
+    false,
+    StatusVisibility::Public,
+    FilterAction::Sync,
+    None
+)]
+#[case(
+    "<p>A photo</p>",
+    true,
+    StatusVisibility::Unlisted,
+    FilterAction::Sync,
+    Some("photos")
+)]
+#[case(
+    "<p>Quiet post</p>",
+    false,
+    StatusVisibility::Unlisted,
+    FilterAction::Skip,
+    Some("everything-else-unlisted")
+)]
+fn filter_decision_evaluate__matches_rules(
+    post_rules: PostRules,
+    #[case] content: &str,
+    #[case] has_media: bool,
+    #[case] visibility: StatusVisibility,
+    #[case] expected_action: FilterAction,
+    #[case] expected_rule_name: Option<&str>,
+) {
+    let mut status = mastodon_status(content);
+    status.visibility = visibility;
+
+    if has_media {
+        status.media_attachments = vec![
+            serde_json::from_str(
+                r#"{"id": "1", "type": "image", "url": "https://files.mastodon.example/1.png", "remote_url": null, "preview_url": null, "text_url": null, "meta": null, "description": null, "blurhash": null}"#,
+            )
+            .unwrap(),
+        ];
+    }
+
+    let decision = FilterDecision::evaluate(&post_rules.filters, &status);
+
+    assert_eq!(decision.action, expected_action);
+    assert_eq!(decision.rule_name.as_deref(), expected_rule_name);
+}
+
+/// Tests to ensure languages match with and without a region.
+#[rstest]
+#[case(vec!["en"], Some("en"), true)]
+#[case(vec!["en"], Some("en-GB"), true)]
+#[case(vec!["en-GB"], Some("en"), false)]
+#[case(vec!["de", "fr"], Some("en"), false)]
+#[case(vec!["en"], None, false)]
+fn status_matcher_matches__languages(
+    #[case] languages: Vec<&str>,
+    #[case] status_language: Option<&str>,
+    #[case] expected: bool,
+) {
+    let matcher = StatusMatcher {
+        languages: languages
+            .iter()
+            .map(|language| language.to_string())
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut status = mastodon_status("<p>Hello world!</p>");
+    status.language = status_language.map(|language| language.to_string());
+
+    assert_eq!(matcher.matches(&status), expected);
+}
+
+/// Tests to ensure content warnings are matched.
+#[rstest]
+#[case(Some(true), "Spoilers", true)]
+#[case(Some(true), "", false)]
+#[case(Some(false), "", true)]
+#[case(None, "Spoilers", true)]
+fn status_matcher_matches__content_warning(
+    #[case] has_content_warning: Option<bool>,
+    #[case] spoiler_text: &str,
+    #[case] expected: bool,
+) {
+    let matcher = StatusMatcher {
+        has_content_warning,
+        ..Default::default()
+    };
+
+    let mut status = mastodon_status("<p>Hello world!</p>");
+    status.spoiler_text = spoiler_text.to_string();
+
+    assert_eq!(matcher.matches(&status), expected);
+}
+
+/// Tests to ensure the posting application is matched.
+#[rstest]
+#[case(Some("Ivory"), true)]
+#[case(Some("ivory"), true)]
+#[case(Some("Web"), false)]
+#[case(None, false)]
+fn status_matcher_matches__applications(
+    #[case] application_name: Option<&str>,
+    #[case] expected: bool,
+) {
+    let matcher = StatusMatcher {
+        applications: vec!["Ivory".to_string()],
+        ..Default::default()
+    };
+
+    let mut status = mastodon_status("<p>Hello world!</p>");
+    status.application =
+        application_name.map(|application_name| megalodon::entities::Application {
+            name: application_name.to_string(),
+            website: None,
+            vapid_key: None,
+        });
+
+    assert_eq!(matcher.matches(&status), expected);
+}
+
+/// Tests to ensure boosts are matched by the author of the boosted post.
+#[rstest]
+#[case(boost_from("someone@mastodon.social"), true)]
+#[case(boost_from("Someone@Mastodon.Social"), true)]
+#[case(boost_from("other@mastodon.social"), false)]
+#[case(mastodon_status("<p>Not a boost</p>"), false)]
+fn status_matcher_matches__boost_authors(
+    #[case] status: megalodon::entities::Status,
+    #[case] expected: bool,
+) {
+    let matcher = StatusMatcher {
+        boost_authors: vec!["@someone@mastodon.social".to_string()],
+        ..Default::default()
+    };
+
+    assert_eq!(matcher.matches(&status), expected);
+}
+
+/// Tests to ensure boosts are matched against the content of the boosted
+/// post.
+#[rstest]
+fn status_matcher_matches__boost_content() {
+    let matcher = StatusMatcher {
+        is_boost: Some(true),
+        text_regex: Some(regex::Regex::new("Boosted").unwrap()),
+        ..Default::default()
+    };
+
+    assert!(matcher.matches(&boost_from("someone@mastodon.social")));
+    assert!(!matcher.matches(&mastodon_status("<p>Boosted</p>")));
+}
@@ -0,0 +1,252 @@
+use fediproto_sync_lib::config::SourcePlatform;
+use rstest::*;
+
+use super::fixtures::{
+    FIREFISH_STATUS_JSON, FRIENDICA_STATUS_JSON, GOTOSOCIAL_STATUS_JSON, PLEROMA_STATUS_JSON,
+    mastodon_status, platform_status,
+};
+use crate::{
+    account_pairs::AccountPairs,
+    mastodon::ParsedMastodonPost,
+    source_platform::{
+        NodeInfoClient, keeps_line_breaks, megalodon_sns, platform_from_software_name,
+    },
+};
+
+/// Tests to ensure the software names in nodeinfo documents are mapped to
+/// platforms, including compatible forks.
+#[rstest]
+#[case("mastodon", Some(SourcePlatform::Mastodon))]
+#[case("hometown", Some(SourcePlatform::Mastodon))]
+#[case("Pleroma", Some(SourcePlatform::Pleroma))]
+#[case("akkoma", Some(SourcePlatform::Pleroma))]
+#[case("friendica", Some(SourcePlatform::Friendica))]
+#[case("firefish", Some(SourcePlatform::Firefish))]
+#[case("iceshrimp", Some(SourcePlatform::Firefish))]
+#[case("gotosocial", Some(SourcePlatform::GoToSocial))]
+#[case("misskey", None)]
+fn platform_from_software_name__maps_names(
+    #[case] software_name: &str,
+    #[case] expected_platform: Option<SourcePlatform>,
+) {
+    assert_eq!(
+        platform_from_software_name(software_name),
+        expected_platform
+    );
+}
+
+/// Tests to ensure `auto` uses the Mastodon API until it's resolved.
+#[rstest]
+fn megalodon_sns__uses_mastodon_for_auto() {
+    assert!(matches!(
+        megalodon_sns(SourcePlatform::Auto),
+        megalodon::SNS::Mastodon
+    ));
+    assert!(matches!(
+        megalodon_sns(SourcePlatform::GoToSocial),
+        megalodon::SNS::Gotosocial
+    ));
+}
+
+/// Tests to ensure the platform is detected with the newest nodeinfo 2.x
+/// document.
+#[rstest]
+#[tokio::test]
+async fn nodeinfo_client__detects_platform() {
+    let mut server = mockito::Server::new_async().await;
+
+    let nodeinfo_links = format!(
+        r#"{{"links": [
+            {{"rel": "http://nodeinfo.diaspora.software/ns/schema/2.0", "href": "{0}/nodeinfo/2.0"}},
+            {{"rel": "http://nodeinfo.diaspora.software/ns/schema/2.1", "href": "{0}/nodeinfo/2.1"}}
+        ]}}"#,
+        server.url()
+    );
+
+    server
+        .mock("GET", "/.well-known/nodeinfo")
+        .with_header("content-type", "application/json")
+        .with_body(nodeinfo_links)
+        .create_async()
+        .await;
+
+    let nodeinfo_mock = server
+        .mock("GET", "/nodeinfo/2.1")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"version": "2.1", "software": {"name": "akkoma", "version": "3.13.2"}}"#)
+        .create_async()
+        .await;
+
+    let nodeinfo_client = NodeInfoClient::new(reqwest::Client::new(), &server.url());
+    let source_platform = nodeinfo_client.detect_platform().await.unwrap();
+
+    nodeinfo_mock.assert_async().await;
+    assert_eq!(source_platform, Some(SourcePlatform::Pleroma));
+}
+
+/// Tests to ensure detection fails when the server has no nodeinfo.
+#[rstest]
+#[tokio::test]
+async fn nodeinfo_client__fails_without_nodeinfo() {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", "/.well-known/nodeinfo")
+        .with_status(404)
+        .create_async()
+        .await;
+
+    let nodeinfo_client = NodeInfoClient::new(reqwest::Client::new(), &server.url());
+
+    assert!(nodeinfo_client.detect_platform().await.is_err());
+}
+
+/// Tests to ensure the status shape of each platform is parsed into the
+/// same text, tags and links.
+#[rstest]
+#[case::pleroma(
+    PLEROMA_STATUS_JSON,
+    SourcePlatform::Pleroma,
+    "Hello from Pleroma!",
+    "#rust"
+)]
+#[case::gotosocial(
+    GOTOSOCIAL_STATUS_JSON,
+    SourcePlatform::GoToSocial,
+    "Hello from GoToSocial!",
+    "#Rust"
+)]
+#[case::friendica(
+    FRIENDICA_STATUS_JSON,
+    SourcePlatform::Friendica,
+    "Hello from Friendica!",
+    "rust"
+)]
+#[case::firefish(
+    FIREFISH_STATUS_JSON,
+    SourcePlatform::Firefish,
+    "Hello from Firefish!",
+    "#rust"
+)]
+fn parsed_mastodon_post__parses_platform_statuses(
+    #[case] status_json: &str,
+    #[case] source_platform: SourcePlatform,
+    #[case] expected_first_line: &str,
+    #[case] expected_tag: &str,
+) {
+    let status = platform_status(status_json);
+    let parsed_status = ParsedMastodonPost::from_mastodon_status(&status, source_platform).unwrap();
+
+    let mut lines = parsed_status.stripped_html.lines();
+    assert_eq!(lines.next(), Some(expected_first_line));
+    assert_eq!(lines.next(), Some("Read https://example.com/article"));
+    assert!(parsed_status.stripped_html.ends_with(expected_tag));
+
+    assert_eq!(
+        parsed_status.found_links,
+        vec!["https://example.com/article"]
+    );
+    assert_eq!(parsed_status.found_tags, vec![expected_tag]);
+}
+
+/// Tests to ensure the IDs of statuses from platforms that don't use numeric
+/// IDs are kept as they are.
+#[rstest]
+#[case::pleroma(PLEROMA_STATUS_JSON, "AoTxAA4x2fSYr8gTDE")]
+#[case::gotosocial(GOTOSOCIAL_STATUS_JSON, "01JBZ8Q4X6YV3T2M9K7N5R1WQC")]
+#[case::firefish(FIREFISH_STATUS_JSON, "9wbxq3k0d1hbz2kf")]
+fn platform_status__keeps_non_numeric_ids(
+    #[case] status_json: &str,
+    #[case] expected_id: &str,
+) {
+    let status = platform_status(status_json);
+
+    assert_eq!(status.id, expected_id);
+    assert!(status.id.parse::<i64>().is_err());
+}
+
+/// Tests to ensure Mastodon statuses are converted to text the same way they
+/// were before other platforms were supported.
+#[rstest]
+#[case::paragraphs(
+    "<p>First paragraph</p><p>Second paragraph</p>",
+    "First paragraph\n\nSecond paragraph"
+)]
+#[case::line_breaks(
+    "<p>First paragraph<br>Second line</p><p>Second paragraph</p>",
+    "First paragraphSecond line\n\nSecond paragraph"
+)]
+#[case::no_paragraphs("Just text<br>on two lines", "Just texton two lines")]
+fn parsed_mastodon_post__converts_mastodon_statuses_as_before(
+    #[case] content: &str,
+    #[case] expected_text: &str,
+) {
+    for source_platform in [SourcePlatform::Mastodon, SourcePlatform::Auto] {
+        let status = mastodon_status(content);
+        let parsed_status =
+            ParsedMastodonPost::from_mastodon_status(&status, source_platform).unwrap();
+
+        assert_eq!(parsed_status.stripped_html, expected_text);
+    }
+}
+
+/// Tests to ensure line breaks are only kept for the platforms that need
+/// them.
+#[rstest]
+#[case(SourcePlatform::Auto, false)]
+#[case(SourcePlatform::Mastodon, false)]
+#[case(SourcePlatform::Pleroma, true)]
+#[case(SourcePlatform::Friendica, true)]
+#[case(SourcePlatform::Firefish, true)]
+#[case(SourcePlatform::GoToSocial, true)]
+fn keeps_line_breaks__by_platform(
+    #[case] source_platform: SourcePlatform,
+    #[case] expected: bool,
+) {
+    assert_eq!(keeps_line_breaks(source_platform), expected);
+}
+
+/// Tests to ensure the text of a status used to match the post rules keeps
+/// its line breaks.
+#[rstest]
+fn parsed_mastodon_post__content_to_text_keeps_line_breaks() {
+    let status = mastodon_status("<p>First line<br>Second line</p>");
+
+    assert_eq!(
+        ParsedMastodonPost::content_to_text(&status).unwrap(),
+        "First line\nSecond line"
+    );
+}
+
+/// Tests to ensure account pairs can set the source platform.
+#[rstest]
+fn account_pairs__parses_source_platform() {
+    let account_pairs = AccountPairs::from_toml_str(
+        r#"
+        [[pair]]
+        id = "gts"
+        source_platform = "GoToSocial"
+        mastodon_server = "gotosocial.example"
+        bluesky_handle = "someone.bsky.social"
+        bluesky_app_password = "app-password"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        account_pairs.pairs[0].source_platform,
+        Some(SourcePlatform::GoToSocial)
+    );
+
+    let invalid_pairs = AccountPairs::from_toml_str(
+        r#"
+        [[pair]]
+        id = "invalid"
+        source_platform = "myspace"
+        bluesky_handle = "someone.bsky.social"
+        bluesky_app_password = "app-password"
+        "#,
+    );
+
+    assert!(invalid_pairs.is_err());
+}
@@ -0,0 +1,174 @@
+use rstest::*;
+
+use crate::{
+    bsky_post_sync::parse_post_uri,
+    streaming::{
+        MastodonStream, ReconnectBackoff, ServerSentEvent, ServerSentEventParser, StreamingEvent,
+        USER_STREAM_PATH,
+    },
+};
+
+/// A Mastodon status to send in the stream.
+const MASTODON_STATUS_JSON: &str = include_str!("fixtures/mastodon_status.json");
+
+/// Create an event as it's sent by the streaming API.
+///
+/// ## Arguments
+///
+/// * `event` - The name of the event.
+/// * `data` - The data of the event.
+fn server_sent_event(
+    event: &str,
+    data: &str,
+) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data.replace('\n', ""))
+}
+
+/// Tests to ensure events are parsed even when they're split across chunks,
+/// and heartbeats are ignored.
+#[rstest]
+fn server_sent_event_parser__parses_split_events() {
+    let mut parser = ServerSentEventParser::default();
+
+    assert!(parser.push(b":thump\n\nevent: del").is_empty());
+    assert!(
+        parser
+            .push(b"ete\r\ndata: 113000000000000001\r\n")
+            .is_empty()
+    );
+
+    let events = parser.push(b"\r\nevent: update\ndata: {\"id\":\ndata: 1}\n\n");
+
+    assert_eq!(
+        events,
+        vec![
+            ServerSentEvent {
+                event: "delete".to_string(),
+                data: "113000000000000001".to_string(),
+            },
+            ServerSentEvent {
+                event: "update".to_string(),
+                data: "{\"id\":\n1}".to_string(),
+            },
+        ]
+    );
+}
+
+/// Tests to ensure the events used for syncing are parsed, and other events
+/// are ignored.
+#[rstest]
+fn streaming_event__parses_events() {
+    let update_event = StreamingEvent::parse("update", MASTODON_STATUS_JSON).unwrap();
+    assert!(matches!(
+        update_event,
+        Some(StreamingEvent::Update(status)) if status.id == "113000000000000001"
+    ));
+
+    let status_update_event = StreamingEvent::parse("status.update", MASTODON_STATUS_JSON).unwrap();
+    assert!(matches!(
+        status_update_event,
+        Some(StreamingEvent::StatusUpdate(_))
+    ));
+
+    let delete_event = StreamingEvent::parse("delete", "113000000000000001").unwrap();
+    assert!(matches!(
+        delete_event,
+        Some(StreamingEvent::Delete(mastodon_post_id)) if mastodon_post_id == "113000000000000001"
+    ));
+
+    let notification_event = StreamingEvent::parse("notification", "{}").unwrap();
+    assert!(notification_event.is_none());
+
+    assert!(StreamingEvent::parse("update", "not json").is_err());
+}
+
+/// Tests to ensure the reconnect delay doubles up to the max, and is reset
+/// after a stable connection.
+#[rstest]
+fn reconnect_backoff__doubles_until_max() {
+    let mut backoff = ReconnectBackoff::new(
+        std::time::Duration::from_secs(1),
+        std::time::Duration::from_secs(5),
+    );
+
+    let delays = (0..5)
+        .map(|_| backoff.next_delay().as_secs())
+        .collect::<Vec<u64>>();
+
+    assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+    backoff.reset();
+    assert_eq!(backoff.next_delay().as_secs(), 1);
+}
+
+/// Tests to ensure the events sent by the user stream are read, and the end
+/// of the stream is reported.
+#[rstest]
+#[tokio::test]
+async fn mastodon_stream__reads_events() {
+    let mut server = mockito::Server::new_async().await;
+
+    let stream_body = format!(
+        ":)\n\n{}{}{}",
+        server_sent_event("notification", "{}"),
+        server_sent_event("update", MASTODON_STATUS_JSON),
+        server_sent_event("delete", "113000000000000001")
+    );
+
+    let stream_mock = server
+        .mock("GET", USER_STREAM_PATH)
+        .match_header("authorization", "Bearer access-token")
+        .with_header("content-type", "text/event-stream")
+        .with_body(stream_body)
+        .create_async()
+        .await;
+
+    let mut stream =
+        MastodonStream::connect(&reqwest::Client::new(), &server.url(), "access-token")
+            .await
+            .unwrap();
+
+    assert!(matches!(
+        stream.next_event().await.unwrap(),
+        Some(StreamingEvent::Update(_))
+    ));
+    assert!(matches!(
+        stream.next_event().await.unwrap(),
+        Some(StreamingEvent::Delete(_))
+    ));
+    assert!(stream.next_event().await.unwrap().is_none());
+
+    stream_mock.assert_async().await;
+}
+
+/// Tests to ensure connecting fails when the server rejects the token.
+#[rstest]
+#[tokio::test]
+async fn mastodon_stream__fails_when_unauthorized() {
+    let mut server = mockito::Server::new_async().await;
+
+    server
+        .mock("GET", USER_STREAM_PATH)
+        .with_status(401)
+        .create_async()
+        .await;
+
+    let connect_result =
+        MastodonStream::connect(&reqwest::Client::new(), &server.url(), "access-token").await;
+
+    assert!(connect_result.is_err());
+}
+
+/// Tests to ensure the post deleted for a `delete` event is found from the
+/// URI of the BlueSky post.
+#[rstest]
+fn parse_post_uri__gets_collection_and_record_key() {
+    let (collection, rkey) =
+        parse_post_uri("at://did:plc:abcdefghijklmnop/app.bsky.feed.post/3lbtjdh2rwc2a").unwrap();
+
+    assert_eq!(collection.as_str(), "app.bsky.feed.post");
+    assert_eq!(rkey.as_str(), "3lbtjdh2rwc2a");
+
+    assert!(parse_post_uri("https://bsky.app/profile/someone/post/3lbtjdh2rwc2a").is_err());
+    assert!(parse_post_uri("at://did:plc:abcdefghijklmnop/app.bsky.feed.post").is_err());
+}
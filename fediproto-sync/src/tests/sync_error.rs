@@ -0,0 +1,116 @@
+use atrium_api::{
+    com,
+    xrpc::{
+        self,
+        error::{ErrorResponseBody, XrpcError, XrpcErrorKind},
+        http::StatusCode,
+    },
+};
+use fediproto_sync_lib::error::{AuthenticationSource, ErrorClass, FediProtoSyncError};
+use rstest::*;
+
+use crate::sync_error::*;
+
+/// Tests to ensure XRPC errors keep the status and the name of the error.
+#[rstest]
+fn from_xrpc_error__undefined_error() {
+    let error = from_xrpc_error(
+        xrpc::Error::<com::atproto::repo::apply_writes::Error>::XrpcResponse(XrpcError {
+            status: StatusCode::BAD_REQUEST,
+            error: Some(XrpcErrorKind::Undefined(ErrorResponseBody {
+                error: Some("InvalidRequest".to_string()),
+                message: Some("Record is invalid".to_string()),
+            })),
+        }),
+    );
+
+    match &error {
+        FediProtoSyncError::XrpcError {
+            status,
+            error_name,
+            message,
+            ..
+        } => {
+            assert_eq!(*status, Some(400));
+            assert_eq!(error_name.as_deref(), Some("InvalidRequest"));
+            assert_eq!(message.as_deref(), Some("Record is invalid"));
+        }
+
+        _ => panic!("Expected an XRPC error, got '{:?}'", error),
+    }
+
+    assert_eq!(error.class(), ErrorClass::Permanent);
+}
+
+/// Tests to ensure the name and message of errors defined by a lexicon are
+/// kept.
+#[rstest]
+fn from_xrpc_error__custom_error() {
+    let error = from_xrpc_error(xrpc::Error::XrpcResponse(XrpcError {
+        status: StatusCode::BAD_REQUEST,
+        error: Some(XrpcErrorKind::Custom(
+            com::atproto::repo::get_record::Error::RecordNotFound(Some(
+                "Could not locate record".to_string(),
+            )),
+        )),
+    }));
+
+    match &error {
+        FediProtoSyncError::XrpcError {
+            error_name,
+            message,
+            ..
+        } => {
+            assert_eq!(error_name.as_deref(), Some("RecordNotFound"));
+            assert_eq!(message.as_deref(), Some("Could not locate record"));
+        }
+
+        _ => panic!("Expected an XRPC error, got '{:?}'", error),
+    }
+}
+
+/// Tests to ensure errors are classified by the first `FediProtoSyncError` in
+/// their chain, and unknown errors are transient.
+#[rstest]
+#[case(
+    anyhow::Error::from(FediProtoSyncError::AuthenticationError(AuthenticationSource::BlueSky))
+        .context("Failed to sync post."),
+    ErrorClass::Auth
+)]
+#[case(
+    anyhow::Error::from(FediProtoSyncError::ImageCompressionError),
+    ErrorClass::Permanent
+)]
+#[case(anyhow::anyhow!("Something went wrong."), ErrorClass::Transient)]
+fn classify_error__uses_error_chain(
+    #[case] error: anyhow::Error,
+    #[case] expected_class: ErrorClass,
+) {
+    assert_eq!(classify_error(&error), expected_class);
+}
+
+/// Tests to ensure the error that matters most for retrying a post is picked.
+#[rstest]
+#[case(vec![ErrorClass::Permanent, ErrorClass::Transient], Some(ErrorClass::Transient))]
+#[case(vec![ErrorClass::Transient, ErrorClass::Auth], Some(ErrorClass::Auth))]
+#[case(vec![ErrorClass::Permanent], Some(ErrorClass::Permanent))]
+#[case(vec![], None)]
+fn most_relevant_error__prefers_retryable_errors(
+    #[case] error_classes: Vec<ErrorClass>,
+    #[case] expected_class: Option<ErrorClass>,
+) {
+    let errors = error_classes
+        .into_iter()
+        .map(|error_class| match error_class {
+            ErrorClass::Transient => anyhow::anyhow!("Something went wrong."),
+            ErrorClass::Permanent => FediProtoSyncError::ImageCompressionError.into(),
+            ErrorClass::Auth => {
+                FediProtoSyncError::AuthenticationError(AuthenticationSource::BlueSky).into()
+            }
+        })
+        .collect::<Vec<anyhow::Error>>();
+
+    let error = most_relevant_error(errors);
+
+    assert_eq!(error.as_ref().map(classify_error), expected_class);
+}
@@ -0,0 +1,172 @@
+use fediproto_sync_lib::config::SourcePlatform;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::{
+    mastodon::ParsedMastodonPost,
+    rules::PostRules,
+    templates::{PostTemplates, TemplateContext, TemplateSet},
+};
+
+/// Templates with a German and a Brazilian Portuguese template set.
+#[fixture]
+fn post_templates() -> PostTemplates {
+    PostRules::from_toml_str(
+        r#"
+[templates]
+boost_title = "{title} (boosted)"
+
+[templates.languages.de]
+read_more = "\n\nWeiterlesen: {url}"
+boost_title = "{title} / 🚀 Geteilt"
+video_link_title = "Video auf Mastodon ansehen"
+video_link_description = "Schau dir dieses Video von @{author} an!"
+
+[templates.languages.pt]
+read_more = "\n\nLeia mais: {url}"
+
+[templates.languages.pt-BR]
+read_more = "\n\nLeia mais ({language}): {url}"
+"#,
+    )
+    .unwrap()
+    .templates
+}
+
+/// Tests to ensure the built-in templates are used when none are configured.
+#[rstest]
+fn post_templates__defaults() {
+    let post_templates = PostRules::from_toml_str("").unwrap().templates;
+
+    assert_eq!(
+        post_templates.for_language(Some("en")),
+        TemplateSet::default()
+    );
+    assert_eq!(
+        TemplateSet::default().read_more,
+        "\n\nRead more: {url}".to_string()
+    );
+}
+
+/// Tests to ensure the templates for the language of a post are used, and
+/// fall back to the defaults.
+#[rstest]
+#[case(Some("de"), "\n\nWeiterlesen: {url}", "{title} / 🚀 Geteilt")]
+#[case(Some("DE-at"), "\n\nWeiterlesen: {url}", "{title} / 🚀 Geteilt")]
+#[case(Some("pt"), "\n\nLeia mais: {url}", "{title} (boosted)")]
+#[case(
+    Some("pt-BR"),
+    "\n\nLeia mais ({language}): {url}",
+    "{title} (boosted)"
+)]
+#[case(Some("pt-PT"), "\n\nLeia mais: {url}", "{title} (boosted)")]
+#[case(Some("fr"), "\n\nRead more: {url}", "{title} (boosted)")]
+#[case(None, "\n\nRead more: {url}", "{title} (boosted)")]
+fn post_templates_for_language__fallbacks(
+    post_templates: PostTemplates,
+    #[case] language: Option<&str>,
+    #[case] expected_read_more: &str,
+    #[case] expected_boost_title: &str,
+) {
+    let templates = post_templates.for_language(language);
+
+    assert_eq!(templates.read_more, expected_read_more);
+    assert_eq!(templates.boost_title, expected_boost_title);
+    assert_eq!(templates.ellipsis, "[...]");
+}
+
+/// Tests to ensure the placeholders in templates are filled in.
+#[rstest]
+fn template_context_render__placeholders(post_templates: PostTemplates) {
+    let mut status = mastodon_status("<p>Hallo Welt!</p>");
+    status.language = Some("de".to_string());
+
+    let templates = post_templates.for_language(status.language.as_deref());
+    let template_context = TemplateContext::from_mastodon_status(&status).with_title("Ein Artikel");
+
+    assert_eq!(
+        template_context.render(&templates.read_more),
+        "\n\nWeiterlesen: https://mastodon.example/@user/113000000000000001"
+    );
+    assert_eq!(
+        template_context.render(&templates.boost_title),
+        "Ein Artikel / 🚀 Geteilt"
+    );
+    assert_eq!(
+        template_context.render(&templates.video_link_description),
+        "Schau dir dieses Video von @user an!"
+    );
+    assert_eq!(template_context.render("{language}"), "de");
+}
+
+/// Tests to ensure placeholders in the filled in values aren't filled in
+/// again.
+#[rstest]
+#[case("{title} (boosted)", "Why {author} links to {url} (boosted)")]
+#[case("{title} by {author}", "Why {author} links to {url} by user")]
+#[case("{unknown} {title", "{unknown} {title")]
+#[case("{{title}}", "{Why {author} links to {url}}")]
+fn template_context_render__single_pass(
+    #[case] template: &str,
+    #[case] expected: &str,
+) {
+    let status = mastodon_status("<p>Hello world!</p>");
+    let template_context =
+        TemplateContext::from_mastodon_status(&status).with_title("Why {author} links to {url}");
+
+    assert_eq!(template_context.render(template), expected);
+}
+
+/// Tests to ensure truncated posts use the templates for their language and
+/// still fit on BlueSky.
+#[rstest]
+fn truncate_post_content__uses_templates(post_templates: PostTemplates) {
+    let mut status = mastodon_status(&format!("<p>{}</p>", "Sehr lange Nachricht. ".repeat(20)));
+    status.language = Some("de".to_string());
+
+    let templates = post_templates.for_language(status.language.as_deref());
+
+    let truncated_status =
+        ParsedMastodonPost::from_mastodon_status(&status, SourcePlatform::Mastodon)
+            .unwrap()
+            .truncate_post_content(&templates)
+            .unwrap();
+
+    assert!(truncated_status.stripped_html.len() <= 300);
+    assert!(
+        truncated_status
+            .stripped_html
+            .contains("[...]\n\nWeiterlesen: https://mastodon.example/@user/113000000000000001")
+    );
+    assert_eq!(
+        truncated_status.found_links,
+        vec!["https://mastodon.example/@user/113000000000000001"]
+    );
+}
+
+/// Tests to ensure a read more template without the URL doesn't leave a
+/// link to generate a facet for.
+#[rstest]
+fn truncate_post_content__read_more_without_url() {
+    let templates = TemplateSet {
+        read_more: " (more on Mastodon)".to_string(),
+        ellipsis: "…".to_string(),
+        ..Default::default()
+    };
+
+    let status = mastodon_status(&format!("<p>{}</p>", "A very long post. ".repeat(20)));
+
+    let truncated_status =
+        ParsedMastodonPost::from_mastodon_status(&status, SourcePlatform::Mastodon)
+            .unwrap()
+            .truncate_post_content(&templates)
+            .unwrap();
+
+    assert!(truncated_status.stripped_html.len() <= 300);
+    assert!(
+        truncated_status
+            .stripped_html
+            .contains("… (more on Mastodon)")
+    );
+    assert!(truncated_status.found_links.is_empty());
+}
@@ -0,0 +1,310 @@
+use fediproto_sync_lib::config::SourcePlatform;
+use rstest::*;
+
+use super::fixtures::mastodon_status;
+use crate::{
+    mastodon::ParsedMastodonPost,
+    rules::{PostRules, transform::TransformOperation},
+};
+
+/// A hashtag, formatted the way Mastodon formats hashtags.
+fn hashtag_link(name: &str) -> String {
+    format!(
+        r##"<a href="https://mastodon.example/tags/{}" class="mention hashtag" rel="tag">#<span>{}</span></a>"##,
+        name.to_lowercase(),
+        name
+    )
+}
+
+/// A link to an article, formatted the way Mastodon formats links.
+fn article_link(url: &str) -> String {
+    format!(
+        r#"<a href="{}" target="_blank" rel="nofollow noopener" translate="no"><span class="invisible">https://</span><span class="">{}</span><span class="invisible"></span></a>"#,
+        url,
+        url.trim_start_matches("https://")
+    )
+}
+
+/// Parse a Mastodon status with hashtags.
+///
+/// ## Arguments
+///
+/// * `content` - The HTML content of the status.
+/// * `tags` - The names of the hashtags in the content.
+fn parsed_status(
+    content: &str,
+    tags: &[&str],
+) -> ParsedMastodonPost {
+    let mut status = mastodon_status(content);
+    status.tags = tags
+        .iter()
+        .map(|tag| megalodon::entities::status::Tag {
+            name: tag.to_lowercase(),
+            url: format!("https://mastodon.example/tags/{}", tag.to_lowercase()),
+        })
+        .collect();
+
+    ParsedMastodonPost::from_mastodon_status(&status, SourcePlatform::Mastodon).unwrap()
+}
+
+/// Tests to ensure transform rules are parsed with their operations.
+#[rstest]
+fn post_rules_from_toml_str__parses_transforms() {
+    let post_rules = PostRules::from_toml_str(
+        r#"
+[[transform]]
+name = "toots"
+op = "regex_replace"
+pattern = "(?i)\\btoot(s?)\\b"
+replacement = "post$1"
+
+[[transform]]
+name = "hashtags"
+op = "strip_trailing_hashtags"
+destinations = ["bluesky"]
+
+[[transform]]
+name = "footer"
+op = "append"
+text = "\n\n(via Mastodon)"
+is_boost = false
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(post_rules.transforms.len(), 3);
+    assert!(matches!(
+        post_rules.transforms[0].operation,
+        TransformOperation::RegexReplace { .. }
+    ));
+    assert!(matches!(
+        post_rules.transforms[1].operation,
+        TransformOperation::StripTrailingHashtags
+    ));
+    assert_eq!(post_rules.transforms[1].destinations, vec!["bluesky"]);
+    assert_eq!(post_rules.transforms[2].matcher.is_boost, Some(false));
+}
+
+/// Tests to ensure invalid transform rules are an error.
+#[rstest]
+#[case(
+    "[[transform]]\nname = \"bad\"\nop = \"regex_replace\"\npattern = \"(\"\nreplacement = \"\""
+)]
+#[case("[[transform]]\nname = \"bad\"\nop = \"regex_replace\"\nreplacement = \"\"")]
+#[case("[[transform]]\nname = \"bad\"\nop = \"uppercase\"")]
+fn post_rules_from_toml_str__invalid_transforms(#[case] rules: &str) {
+    assert!(PostRules::from_toml_str(rules).is_err());
+}
+
+/// Tests to ensure regular expressions are replaced, including capture
+/// groups.
+#[rstest]
+fn apply_transforms__regex_replace() {
+    let post_rules = PostRules::from_toml_str(
+        r#"
+[[transform]]
+name = "toots"
+op = "regex_replace"
+pattern = "\\btoot(s?)\\b"
+replacement = "post$1"
+"#,
+    )
+    .unwrap();
+
+    let transformed_status = parsed_status("<p>My first toot, of many toots</p>", &[])
+        .apply_transforms(&post_rules.transforms, "bluesky")
+        .unwrap();
+
+    assert_eq!(
+        transformed_status.stripped_html,
+        "My first post, of many posts"
+    );
+}
+
+/// Tests to ensure only the hashtag lines at the end of a post are removed
+/// and the removed hashtags don't get facets.
+#[rstest]
+fn apply_transforms__strip_trailing_hashtags() {
+    let post_rules = PostRules::from_toml_str(
+        "[[transform]]\nname = \"hashtags\"\nop = \"strip_trailing_hashtags\"",
+    )
+    .unwrap();
+
+    let transformed_status = parsed_status(
+        &format!(
+            "<p>Learning {} today</p><p>{} {}</p>",
+            hashtag_link("Rust"),
+            hashtag_link("Programming"),
+            hashtag_link("100DaysOfCode")
+        ),
+        &["Rust", "Programming", "100DaysOfCode"],
+    )
+    .apply_transforms(&post_rules.transforms, "bluesky")
+    .unwrap();
+
+    assert_eq!(transformed_status.stripped_html, "Learning #Rust today");
+    assert_eq!(transformed_status.found_tags, vec!["#Rust"]);
+}
+
+/// Tests to ensure a post that only has hashtags isn't left empty.
+#[rstest]
+fn apply_transforms__strip_trailing_hashtags_only_hashtags() {
+    let post_rules = PostRules::from_toml_str(
+        "[[transform]]\nname = \"hashtags\"\nop = \"strip_trailing_hashtags\"",
+    )
+    .unwrap();
+
+    let transformed_status = parsed_status(
+        &format!("<p>{} {}</p>", hashtag_link("Rust"), hashtag_link("Art")),
+        &["Rust", "Art"],
+    )
+    .apply_transforms(&post_rules.transforms, "bluesky")
+    .unwrap();
+
+    assert_eq!(transformed_status.stripped_html, "#Rust #Art");
+    assert_eq!(transformed_status.found_tags, vec!["#Rust", "#Art"]);
+}
+
+/// Tests to ensure custom emoji shortcodes are replaced, and only for the
+/// custom emoji used in the post.
+#[rstest]
+fn apply_transforms__replace_custom_emoji() {
+    let post_rules = PostRules::from_toml_str(
+        r#"
+[[transform]]
+name = "emoji"
+op = "replace_custom_emoji"
+fallback = "🙂"
+
+[transform.replacements]
+blobcat = "🐱"
+"#,
+    )
+    .unwrap();
+
+    let mut status = mastodon_status("<p>Hello :blobcat: :wave_custom: at 10:30:00</p>");
+    status.emojis = ["blobcat", "wave_custom"]
+        .iter()
+        .map(|shortcode| megalodon::entities::Emoji {
+            shortcode: shortcode.to_string(),
+            static_url: format!("https://files.mastodon.example/emoji/{}.png", shortcode),
+            url: format!("https://files.mastodon.example/emoji/{}.png", shortcode),
+            visible_in_picker: true,
+            category: None,
+        })
+        .collect();
+
+    let transformed_status =
+        ParsedMastodonPost::from_mastodon_status(&status, SourcePlatform::Mastodon)
+            .unwrap()
+            .apply_transforms(&post_rules.transforms, "bluesky")
+            .unwrap();
+
+    assert_eq!(transformed_status.stripped_html, "Hello 🐱 🙂 at 10:30:00");
+}
+
+/// Tests to ensure rules only apply to their destinations and to the posts
+/// they match.
+#[rstest]
+#[case("<p>Hello world!</p>", "bluesky", "Hello world!\n\n(via Mastodon)")]
+#[case("<p>Hello world!</p>", "other", "Hello world!")]
+#[case("<p>Hello world! #nofooter</p>", "bluesky", "Hello world! #nofooter")]
+fn apply_transforms__destinations_and_conditions(
+    #[case] content: &str,
+    #[case] destination: &str,
+    #[case] expected: &str,
+) {
+    let post_rules = PostRules::from_toml_str(
+        r#"
+[[transform]]
+name = "footer"
+op = "append"
+text = "\n\n(via Mastodon)"
+destinations = ["BlueSky"]
+text_regex = "^[^#]*$"
+"#,
+    )
+    .unwrap();
+
+    let transformed_status = parsed_status(content, &[])
+        .apply_transforms(&post_rules.transforms, destination)
+        .unwrap();
+
+    assert_eq!(transformed_status.stripped_html, expected);
+}
+
+/// Tests to ensure the found tags and links still match the content after
+/// transforms change it, so richtext facets point at the right text.
+#[rstest]
+fn apply_transforms__keeps_facets_consistent() {
+    let post_rules = PostRules::from_toml_str(
+        r#"
+[[transform]]
+name = "shorten"
+op = "regex_replace"
+pattern = "Check out this article: "
+replacement = "📰 "
+
+[[transform]]
+name = "drop-tracking-links"
+op = "regex_replace"
+pattern = "https://tracker\\.example/\\S*"
+replacement = ""
+
+[[transform]]
+name = "hashtags"
+op = "strip_trailing_hashtags"
+
+[[transform]]
+name = "footer"
+op = "append"
+text = "\n\n(via Mastodon)"
+"#,
+    )
+    .unwrap();
+
+    let transformed_status = parsed_status(
+        &format!(
+            "<p>Check out this article: {} {} about {}</p><p>{}</p>",
+            article_link("https://example.com/article"),
+            article_link("https://tracker.example/click"),
+            hashtag_link("Rust"),
+            hashtag_link("News")
+        ),
+        &["Rust", "News"],
+    )
+    .apply_transforms(&post_rules.transforms, "bluesky")
+    .unwrap()
+    .truncate_post_content(&post_rules.templates.default)
+    .unwrap();
+
+    assert_eq!(
+        transformed_status.stripped_html,
+        "📰 https://example.com/article  about #Rust\n\n(via Mastodon)"
+    );
+    assert_eq!(
+        transformed_status.found_links,
+        vec!["https://example.com/article"]
+    );
+    assert_eq!(transformed_status.found_tags, vec!["#Rust"]);
+
+    // The facets are generated from the positions of the found tags and
+    // links in the content.
+    let link_index = transformed_status
+        .stripped_html
+        .find(&transformed_status.found_links[0])
+        .unwrap();
+    let tag_index = transformed_status
+        .stripped_html
+        .find(&transformed_status.found_tags[0])
+        .unwrap();
+
+    assert_eq!(
+        &transformed_status.stripped_html[link_index..link_index + 27],
+        "https://example.com/article"
+    );
+    assert_eq!(
+        &transformed_status.stripped_html[tag_index..tag_index + 5],
+        "#Rust"
+    );
+}